@@ -0,0 +1,30 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rjq::JsonData;
+use serde_json::Value;
+
+/// Builtins that can loop forever or allocate without bound. Until query
+/// execution gets a timeout these are excluded so the fuzzer only reports
+/// panics and stack overflows.
+const UNBOUNDED: &[&str] = &[
+    "range", "repeat", "recurse", "while", "until", "limit", "def",
+];
+
+// Input layout: `<query> NUL <json document>`; a document that does not parse
+// is replaced with `null`.
+fuzz_target!(|data: &[u8]| {
+    let (query, document) = match data.iter().position(|&b| b == 0) {
+        Some(split) => (&data[..split], &data[split + 1..]),
+        None => (data, &[][..]),
+    };
+    let query = String::from_utf8_lossy(query);
+    if UNBOUNDED.iter().any(|name| query.contains(name)) {
+        return;
+    }
+    let document: Value = serde_json::from_slice(document).unwrap_or(Value::Null);
+
+    if let Ok(result) = JsonData::new(document).execute_query(&query) {
+        let _ = result.format_pretty();
+    }
+});
@@ -0,0 +1,176 @@
+use super::{App, BrowserState};
+use crate::source::DataSource;
+use crate::source::browser::{self, DirEntry};
+use std::path::{Path, PathBuf};
+
+impl App {
+    pub fn browser(&self) -> Option<&BrowserState> {
+        self.state.browser.as_ref()
+    }
+
+    /// Opens the file browser in the directory of the current file, or closes it
+    pub fn toggle_browser(&mut self) {
+        if self.state.browser.take().is_some() {
+            return;
+        }
+        let dir = self
+            .source_path
+            .as_deref()
+            .filter(|path| !crate::source::http::is_url(path))
+            .and_then(|path| Path::new(path).parent())
+            .filter(|parent| !parent.as_os_str().is_empty())
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+        self.browse(dir);
+    }
+
+    /// Entries of the browsed directory matching the search, best first
+    pub fn browser_entries(&self) -> Vec<&DirEntry> {
+        self.state
+            .browser
+            .as_ref()
+            .map(|state| browser::filter(&state.entries, &state.search))
+            .unwrap_or_default()
+    }
+
+    pub fn browser_search_push(&mut self, c: char) {
+        if let Some(state) = &mut self.state.browser {
+            state.search.push(c);
+            state.selected = 0;
+        }
+    }
+
+    /// Deletes a search character, or goes up a directory when there are none
+    pub fn browser_search_pop(&mut self) {
+        let Some(state) = &mut self.state.browser else {
+            return;
+        };
+        if state.search.pop().is_some() {
+            state.selected = 0;
+        } else {
+            let parent = state.dir.join("..");
+            self.browse(parent);
+        }
+    }
+
+    pub fn move_browser(&mut self, forward: bool) {
+        let count = self.browser_entries().len();
+        if let Some(state) = &mut self.state.browser {
+            state.selected = if forward {
+                (state.selected + 1).min(count.saturating_sub(1))
+            } else {
+                state.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Path of the selected entry
+    pub fn browser_selection(&self) -> Option<PathBuf> {
+        let state = self.state.browser.as_ref()?;
+        let entries = self.browser_entries();
+        let entry = entries.get(state.selected.min(entries.len().checked_sub(1)?))?;
+        Some(state.dir.join(&entry.name))
+    }
+
+    /// First lines of the selected file, `None` for a directory
+    pub fn browser_preview(&self, lines: usize) -> Option<String> {
+        let path = self.browser_selection().filter(|path| path.is_file())?;
+        Some(browser::preview(&path, lines).unwrap_or_else(|e| e.to_string()))
+    }
+
+    /// Enters the selected directory, or loads the selected file into the active tab
+    ///
+    /// The file's format comes from its extension, and reload reads it again.
+    pub fn browser_open(&mut self) {
+        let Some(path) = self.browser_selection() else {
+            return;
+        };
+        if path.is_dir() {
+            self.browse(path);
+            return;
+        }
+
+        let path = path.display().to_string();
+        // A format forced on the command line was meant for the files named there
+        self.load_options.format = None;
+        let mut warnings = Vec::new();
+        match DataSource::File(path.clone()).load(&self.load_options, &mut warnings) {
+            Ok(data) => {
+                self.data.replace_with(data);
+                self.set_source_path(Some(path.clone()));
+                self.data_changed();
+                self.state.browser = None;
+                self.state.set_status_message(format!("Opened {}", path));
+            }
+            Err(e) => self
+                .state
+                .set_status_message(format!("Cannot open {}: {}", path, e)),
+        }
+        for warning in warnings {
+            self.state.push_warning(warning);
+        }
+    }
+
+    /// Shows `dir` in the browser, keeping the current directory if it cannot be read
+    fn browse(&mut self, dir: PathBuf) {
+        let dir = dir.canonicalize().unwrap_or(dir);
+        match browser::list(&dir) {
+            Ok(entries) => {
+                self.state.browser = Some(BrowserState {
+                    dir,
+                    entries,
+                    search: String::new(),
+                    selected: 0,
+                })
+            }
+            Err(e) => {
+                self.state
+                    .set_status_message(format!("Cannot read {}: {}", dir.display(), e))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+
+    #[test]
+    fn test_browse_and_open_a_file() {
+        let dir = std::env::temp_dir().join(format!("rjq_app_browser_{}", std::process::id()));
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("start.json"), "{}").unwrap();
+        fs::write(dir.join("sub/data.yaml"), "name: rjq\n").unwrap();
+
+        let mut app = App::new(json!({}));
+        app.set_source_path(Some(dir.join("start.json").display().to_string()));
+        app.set_input(".name");
+        app.toggle_browser();
+        assert!(app.browser().is_some());
+
+        "sub".chars().for_each(|c| app.browser_search_push(c));
+        assert_eq!(app.browser_entries().len(), 1);
+        app.browser_open();
+        assert!(app.browser().unwrap().dir.ends_with("sub"));
+        assert_eq!(app.browser_preview(5).as_deref(), None);
+        app.move_browser(true);
+        assert_eq!(app.browser_preview(5).as_deref(), Some("name: rjq"));
+
+        app.browser_open();
+        assert!(app.browser().is_none());
+        assert_eq!(app.current_values().unwrap(), vec![json!("rjq")]);
+        assert!(
+            app.command_export()
+                .rjq_command()
+                .ends_with("sub/data.yaml")
+        );
+
+        // Backspace on an empty search goes up a directory
+        app.toggle_browser();
+        app.browser_search_pop();
+        assert_eq!(app.browser().unwrap().dir, dir);
+        fs::remove_dir_all(&dir).ok();
+    }
+}
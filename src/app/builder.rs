@@ -1,6 +1,7 @@
 use super::{AppConfig, AppState, ContentGenerator};
 use crate::query::JsonData;
 use crate::query::{CachedQueryExecutor, InMemoryQueryCache, JaqQueryExecutor, QueryExecutor};
+use crate::render::RenderTree;
 use crate::ui::{DefaultEventHandler, EventHandler};
 
 pub struct AppBuilder<Q, E>
@@ -86,15 +87,11 @@ pub struct EnhancedApp<Q: QueryExecutor, E: EventHandler> {
 impl<Q: QueryExecutor, E: EventHandler> ContentGenerator for EnhancedApp<Q, E> {
     fn generate_current_content(&self) -> String {
         match self.execute_current_query() {
-            Ok(result) => result.format_pretty(),
-            Err(_) => {
-                if self.state.input.is_empty() {
-                    serde_json::to_string_pretty(self.data.get())
-                        .unwrap_or_else(|_| "Error formatting JSON".to_string())
-                } else {
-                    "".to_string()
-                }
+            Ok(result) => RenderTree::from_result(&result).to_text(),
+            Err(_) if self.state.input.is_empty() => {
+                RenderTree::from_value(self.data.get()).to_text()
             }
+            Err(_) => String::new(),
         }
     }
 
@@ -125,6 +122,30 @@ impl<Q: QueryExecutor, E: EventHandler> EnhancedApp<Q, E> {
         &self.data
     }
 
+    /// Hits, misses and size of the results cache added with
+    /// [`AppBuilder::with_cache`]
+    pub fn cache_stats(&self) -> Option<crate::query::CacheStats> {
+        self.query_executor.cache_stats()
+    }
+
+    /// Replaces the document and drops results cached for the old one
+    pub fn replace_data(&mut self, value: serde_json::Value) {
+        self.data.replace(value);
+        self.query_executor.invalidate();
+        self.state.reset_scroll();
+    }
+
+    /// Deep-merges `patch` into the document and drops cached results
+    pub fn merge_data(&mut self, patch: serde_json::Value) {
+        self.data.merge(patch);
+        self.query_executor.invalidate();
+        self.state.reset_scroll();
+    }
+
+    pub fn warnings(&self) -> &[crate::app::AppWarning] {
+        &self.state.warnings
+    }
+
     pub fn set_exit(&mut self, exit: bool) {
         self.state.set_exit(exit);
     }
@@ -162,20 +183,14 @@ impl<Q: QueryExecutor, E: EventHandler> EnhancedApp<Q, E> {
     // 強化されたクエリ実行メソッド（依存性注入されたExecutorを使用）
     pub fn execute_current_query(&self) -> crate::Result<crate::query::QueryResult> {
         if self.state.input.is_empty() {
-            return Err(crate::app::error::AppError::QueryCompile(
-                "Empty query".to_string(),
-            ));
+            return Err(crate::app::error::AppError::query_compile("Empty query"));
         }
 
-        let results = self
-            .query_executor
-            .execute(self.data.get(), &self.state.input)?;
-
-        Ok(match results.len() {
-            0 => crate::query::QueryResult::Empty,
-            1 => crate::query::QueryResult::Single(results.into_iter().next().unwrap()),
-            _ => crate::query::QueryResult::Multiple(results),
-        })
+        crate::query::QueryResult::from_run(self.query_executor.execute_fingerprinted(
+            self.data.get(),
+            self.data.fingerprint(),
+            &self.state.input,
+        ))
     }
 
     // イベント処理メソッド（依存性注入されたEventHandlerを使用）
@@ -184,30 +199,41 @@ impl<Q: QueryExecutor, E: EventHandler> EnhancedApp<Q, E> {
         self.update_with_action(action);
     }
 
+    /// Handles any terminal event through the injected handler
+    pub fn handle_event(&mut self, event: crossterm::event::Event) {
+        let action = self.event_handler.handle_event(event);
+        self.update_with_action(action);
+    }
+
     fn update_with_action(&mut self, action: crate::ui::Action) {
+        use crate::ui::{Action, EditAction, SystemAction, ViewAction};
         match action {
-            crate::ui::Action::Quit => self.set_exit(true),
-            crate::ui::Action::Input(c) => {
+            Action::System(SystemAction::Quit) => self.set_exit(true),
+            Action::Edit(EditAction::Input(c)) => {
                 self.push_char(c);
                 self.reset_scroll();
             }
-            crate::ui::Action::Backspace => {
+            Action::Edit(EditAction::Paste(text)) => {
+                text.chars().for_each(|c| self.push_char(c));
+                self.reset_scroll();
+            }
+            Action::Edit(EditAction::Backspace) => {
                 if !self.input().is_empty() {
                     self.pop_char();
                 }
                 self.reset_scroll();
             }
-            crate::ui::Action::Clear => {
+            Action::Edit(EditAction::Submit) => {
                 self.clear_input();
                 self.reset_scroll();
             }
-            crate::ui::Action::ScrollUp => self.scroll_up(),
-            crate::ui::Action::ScrollDown => self.scroll_down(),
-            crate::ui::Action::Tab => {
-                // TAB completion logic would need to be implemented here
-                // For now, this is a no-op as the builder pattern doesn't support suggestions
+            Action::View(ViewAction::ScrollUp) => self.scroll_up(),
+            Action::View(ViewAction::ScrollDown) => self.scroll_down(),
+            Action::View(ViewAction::DismissWarning) => self.state.dismiss_warning(),
+            Action::Edit(_) | Action::View(_) | Action::System(_) => {
+                // Replay, export, bookmarks, tabs, pinning, sliders and completion are only supported by the interactive App
             }
-            crate::ui::Action::None => {}
+            Action::None => {}
         }
     }
 }
@@ -245,4 +271,34 @@ mod tests {
         assert_eq!(app.input(), "");
         assert_eq!(app.data().get(), &json!({"name": "test"}));
     }
+
+    #[test]
+    fn test_replace_data_with_cache() {
+        let mut app = AppBuilder::new(json!({"name": "old"})).with_cache().build();
+        app.push_char('.');
+        assert_eq!(
+            app.execute_current_query().unwrap().format_pretty(),
+            "{\n  \"name\": \"old\"\n}"
+        );
+
+        app.replace_data(json!({"name": "new"}));
+        assert_eq!(app.data().generation(), 1);
+        let stats = app.cache_stats().unwrap();
+        assert_eq!((stats.entries, stats.invalidations), (0, 1));
+        assert!(
+            app.execute_current_query()
+                .unwrap()
+                .format_pretty()
+                .contains("new")
+        );
+
+        app.merge_data(json!({"extra": true}));
+        assert_eq!(app.cache_stats().unwrap().invalidations, 2);
+        assert!(
+            app.execute_current_query()
+                .unwrap()
+                .format_pretty()
+                .contains("extra")
+        );
+    }
 }
@@ -0,0 +1,153 @@
+use super::App;
+use crate::query::SizeIndex;
+use crate::render::chart::{column_numbers, counts, numbers};
+use crate::render::{BarMapping, ChartKind, Counts};
+
+impl App {
+    /// Chart the numeric result is drawn as, if any
+    pub fn chart(&self) -> Option<ChartKind> {
+        self.state.chart
+    }
+
+    /// Numbers to chart and what they are: the selected column in the table
+    /// view, otherwise the result when it is a list of numbers
+    pub fn chart_data(&self) -> Option<(String, Vec<f64>)> {
+        if self.state.table_mode {
+            let (table, layout) = self.current_table()?;
+            let last = layout.visible().count().saturating_sub(1);
+            let column = layout.visible().nth(self.state.table_column.min(last))?;
+            return column_numbers(&table, &column.key).map(|data| (column.key.clone(), data));
+        }
+        let values = self.current_values().ok()?;
+        let label = if self.query().trim().is_empty() {
+            "."
+        } else {
+            self.query().trim()
+        };
+        numbers(&values).map(|data| (label.to_string(), data))
+    }
+
+    /// Keys the bar chart takes labels and values from; `None` detects them
+    pub fn set_bar_mapping(&mut self, mapping: Option<BarMapping>) {
+        self.bar_mapping = mapping;
+    }
+
+    /// Labelled values of the current result for the bar chart
+    pub fn bar_data(&self) -> Option<Counts> {
+        let values = self.current_values().ok()?;
+        counts(&values, self.bar_mapping.as_ref())
+    }
+
+    fn chart_applies(&self, kind: ChartKind) -> bool {
+        match kind {
+            ChartKind::Histogram | ChartKind::Sparkline => self.chart_data().is_some(),
+            ChartKind::Bar => self.bar_data().is_some(),
+        }
+    }
+
+    /// Steps through the charts that fit the result, then back to the usual view
+    pub fn cycle_chart(&mut self) {
+        let start = self
+            .state
+            .chart
+            .and_then(|kind| ChartKind::ALL.iter().position(|&k| k == kind))
+            .map_or(0, |i| i + 1);
+        let next = ChartKind::ALL[start..]
+            .iter()
+            .copied()
+            .find(|&kind| self.chart_applies(kind));
+        if next.is_none() && self.state.chart.is_none() {
+            self.state
+                .set_status_message("Charts need numbers, a numeric column or labelled counts");
+        }
+        self.state.chart = next;
+    }
+}
+
+impl App {
+    pub fn size_view(&self) -> bool {
+        self.state.size_view
+    }
+
+    /// Shows which subtrees of the document take up the most space, or hides it
+    pub fn toggle_size_view(&mut self) {
+        self.state.size_view = !self.state.size_view;
+        self.state.reset_scroll();
+    }
+
+    /// Sizes of the whole document's subtrees, whatever the query
+    pub fn size_index(&self) -> &SizeIndex {
+        self.size_index
+            .get_or_init(|| SizeIndex::build(self.data.get()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_chart_data_from_result_or_column() {
+        let mut app = App::new(json!({"items": [{"id": "a", "size": 3}, {"id": "b", "size": 5}]}));
+        app.cycle_chart();
+        assert_eq!(app.chart(), None);
+        assert!(app.status_message().is_some());
+
+        app.set_input(".items[].size");
+        assert_eq!(
+            app.chart_data(),
+            Some((".items[].size".to_string(), vec![3.0, 5.0]))
+        );
+        app.cycle_chart();
+        assert_eq!(app.chart(), Some(ChartKind::Histogram));
+        app.cycle_chart();
+        assert_eq!(app.chart(), Some(ChartKind::Sparkline));
+        // No bar chart for plain numbers
+        app.cycle_chart();
+        assert_eq!(app.chart(), None);
+
+        // In the table view the selected column is charted
+        app.set_input(".items");
+        app.toggle_table();
+        assert_eq!(app.chart_data(), None);
+        app.select_table_column(true);
+        assert_eq!(app.chart_data(), Some(("size".to_string(), vec![3.0, 5.0])));
+    }
+
+    #[test]
+    fn test_size_index_follows_the_data() {
+        let mut app = App::new(json!({"a": [1, 2, 3], "b": 1}));
+        assert_eq!(app.size_index().root.children[0].path, ".a");
+        app.replace_data(json!({"a": 1, "b": "a long string"}));
+        assert_eq!(app.size_index().root.children[0].path, ".b");
+    }
+
+    #[test]
+    fn test_bar_chart_of_counts() {
+        let mut app = App::new(json!([
+            {"code": 500, "path": "/a"},
+            {"code": 404, "path": "/b"},
+            {"code": 500, "path": "/c"}
+        ]));
+        app.set_input("group_by(.code) | map({code: .[0].code, n: length})");
+        app.cycle_chart();
+        assert_eq!(app.chart(), Some(ChartKind::Bar));
+        let counts = app.bar_data().unwrap();
+        assert_eq!(
+            (counts.label.as_str(), counts.value.as_str()),
+            ("code", "n")
+        );
+        assert_eq!(
+            counts.entries,
+            vec![("500".to_string(), 2.0), ("404".to_string(), 1.0)]
+        );
+
+        app.set_bar_mapping(Some(BarMapping {
+            label: "n".to_string(),
+            value: "code".to_string(),
+        }));
+        let counts = app.bar_data().unwrap();
+        assert_eq!(counts.entries[0], ("2".to_string(), 500.0));
+    }
+}
@@ -1,7 +1,10 @@
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct AppConfig {
     pub prompt: &'static str,
     pub visible_height: usize,
+    /// Insert the matching closer when typing `(`, `[`, `{` or `"`
+    pub auto_pairs: bool,
 }
 
 impl Default for AppConfig {
@@ -9,6 +12,7 @@ impl Default for AppConfig {
         Self {
             prompt: "query > ",
             visible_height: 20,
+            auto_pairs: true,
         }
     }
 }
@@ -18,6 +22,7 @@ impl AppConfig {
         Self {
             prompt,
             visible_height: 20,
+            auto_pairs: true,
         }
     }
 
@@ -25,6 +30,7 @@ impl AppConfig {
         Self {
             prompt: "query > ",
             visible_height,
+            auto_pairs: true,
         }
     }
 
@@ -32,6 +38,7 @@ impl AppConfig {
         Self {
             prompt,
             visible_height,
+            auto_pairs: true,
         }
     }
 }
@@ -45,6 +52,7 @@ mod tests {
         let config = AppConfig::default();
         assert_eq!(config.prompt, "query > ");
         assert_eq!(config.visible_height, 20);
+        assert!(config.auto_pairs);
     }
 
     #[test]
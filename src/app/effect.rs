@@ -0,0 +1,298 @@
+use super::{App, AppWarning};
+use crate::export::{ExportRun, write_html};
+use crate::render::chart::format_bytes;
+use serde_json::Value;
+
+/// Side effect requested by an update
+///
+/// `update` only changes in-memory state and describes IO as effects, which
+/// the event loop then performs with [`App::run_effects`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Effect {
+    /// Append the query and its result to the tee log, if one is open, and
+    /// the query to the usage log
+    LogQuery(String),
+    /// Write `contents` to `path`, then show `message` in the status line
+    WriteFile {
+        path: String,
+        contents: String,
+        message: String,
+    },
+    /// Stream `values` as an HTML page titled `title` to `path` in the
+    /// background, with its progress in the status line
+    ExportHtml {
+        path: String,
+        title: String,
+        values: Vec<Value>,
+    },
+    /// Read the active tab's file or URL again
+    Reload,
+}
+
+impl App {
+    pub fn run_effects(&mut self, effects: impl IntoIterator<Item = Effect>) {
+        for effect in effects {
+            self.run_effect(effect);
+        }
+    }
+
+    /// Performs one effect; failures become warnings
+    pub fn run_effect(&mut self, effect: Effect) {
+        match effect {
+            Effect::LogQuery(query) => {
+                self.log_query(&query);
+                self.log_usage(&query);
+            }
+            Effect::WriteFile {
+                path,
+                contents,
+                message,
+            } => match std::fs::write(&path, contents) {
+                Ok(()) => self.state.set_status_message(message),
+                Err(e) => self
+                    .state
+                    .push_warning(AppWarning::LogWriteFailed(format!("{}: {}", path, e))),
+            },
+            Effect::ExportHtml {
+                path,
+                title,
+                values,
+            } => self.start_export(&path, move |out| write_html(out, &title, &values)),
+            Effect::Reload => self.reload(),
+        }
+    }
+
+    fn start_export<F>(&mut self, path: &str, write: F)
+    where
+        F: FnOnce(&mut dyn std::io::Write) -> std::io::Result<()> + Send + 'static,
+    {
+        if let Some(export) = &self.export {
+            let message = format!("Still exporting to {}", export.path());
+            self.state.set_status_message(message);
+            return;
+        }
+        match ExportRun::spawn(path, write) {
+            Ok(export) => self.export = Some(export),
+            Err(e) => self
+                .state
+                .push_warning(AppWarning::LogWriteFailed(format!("{}: {}", path, e))),
+        }
+    }
+
+    /// (path, bytes written) while an export runs
+    pub fn export_progress(&self) -> Option<(&str, usize)> {
+        self.export
+            .as_ref()
+            .map(|export| (export.path(), export.written()))
+    }
+
+    /// Reports a finished export; `true` when one finished
+    pub fn poll_export(&mut self) -> bool {
+        let Some(result) = self.export.as_ref().and_then(ExportRun::try_finish) else {
+            return false;
+        };
+        let export = self.export.take().expect("finished above");
+        match result {
+            Ok(()) => self.state.set_status_message(format!(
+                "Exported result to {} ({})",
+                export.path(),
+                format_bytes(export.written())
+            )),
+            Err(e) => self.state.push_warning(AppWarning::LogWriteFailed(format!(
+                "{}: {}",
+                export.path(),
+                e
+            ))),
+        }
+        true
+    }
+
+    /// Writes `query` and its result to the tee log, if enabled
+    fn log_query(&mut self, query: &str) {
+        if self.tee_log.is_none() {
+            return;
+        }
+        let result = match self.data.execute_query_with(&self.executor, query) {
+            Ok(result) => result.format_pretty(),
+            Err(e) => format!("Error: {}", e),
+        };
+        let Some(log) = self.tee_log.as_mut() else {
+            return;
+        };
+        match log.record(query, &result) {
+            Ok(Some((shown, total))) => self.state.push_warning(AppWarning::TruncatedOutput {
+                shown,
+                total: Some(total),
+            }),
+            Ok(None) => {}
+            Err(e) => self
+                .state
+                .push_warning(AppWarning::LogWriteFailed(e.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_write_file_effect() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_effect_write_{}.txt", std::process::id()));
+        let mut app = App::new(json!(null));
+        app.run_effect(Effect::WriteFile {
+            path: path.display().to_string(),
+            contents: "hello".to_string(),
+            message: "Wrote it".to_string(),
+        });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        assert_eq!(app.status_message(), Some("Wrote it"));
+        std::fs::remove_file(&path).ok();
+
+        app.run_effect(Effect::WriteFile {
+            path: "/nonexistent-dir/out.txt".to_string(),
+            contents: String::new(),
+            message: String::new(),
+        });
+        assert!(matches!(
+            app.warnings().last(),
+            Some(AppWarning::LogWriteFailed(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_html_effect_streams_in_background() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_effect_export_{}.html", std::process::id()));
+        let mut app = App::new(json!(null));
+        app.run_effect(Effect::ExportHtml {
+            path: path.display().to_string(),
+            title: ".a".to_string(),
+            values: vec![json!({"a": [1, 2]})],
+        });
+        assert!(app.export_progress().is_some());
+        while !app.poll_export() {
+            std::thread::sleep(std::time::Duration::from_millis(1));
+        }
+        assert!(app.export_progress().is_none());
+        let html = std::fs::read_to_string(&path).unwrap();
+        assert!(html.ends_with("</html>\n"));
+        assert!(
+            app.status_message()
+                .unwrap()
+                .starts_with("Exported result to")
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_reload_effect() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_effect_reload_{}.yaml", std::process::id()));
+        std::fs::write(&path, "a: 1\n").unwrap();
+        let mut app = App::new(json!({"a": 0}));
+        app.set_source_path(Some(path.display().to_string()));
+        app.set_input(".a");
+
+        app.run_effect(Effect::Reload);
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+        assert_eq!(app.input(), ".a");
+
+        std::fs::remove_file(&path).ok();
+        app.run_effect(Effect::Reload);
+        assert!(app.status_message().unwrap().starts_with("Reload failed"));
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+
+        app.set_source_path(None);
+        app.run_effect(Effect::Reload);
+        assert!(
+            app.status_message()
+                .unwrap()
+                .starts_with("stdin cannot be read again")
+        );
+    }
+
+    #[test]
+    fn test_command_export_carries_session_options() {
+        let mut app = App::new(json!([{"id": 1}]));
+        app.set_variables(vec![("min".to_string(), json!(1))]);
+        app.set_load_options(crate::source::LoadOptions {
+            slurp: true,
+            ..Default::default()
+        });
+        app.set_source_path(Some("a.json".to_string()));
+        app.set_input(".[][] | select(.id >= $min)".to_string());
+        assert_eq!(
+            app.command_export().jq_command(),
+            "jq -s --argjson min 1 '.[][] | select(.id >= $min)' a.json"
+        );
+        assert_eq!(
+            app.command_export().rjq_command(),
+            "rjq -s --argjson min 1 -q '.[][] | select(.id >= $min)' a.json"
+        );
+    }
+
+    #[test]
+    fn test_command_export_converts_yaml_named_input() {
+        let mut app = App::new(json!({"items": [1]}));
+        app.set_source_path(Some("data.yaml".to_string()));
+        app.set_input(".items[]".to_string());
+        assert_eq!(
+            app.command_export().jq_command(),
+            "rjq -q . data.yaml | jq '.items[]'"
+        );
+        assert_eq!(
+            app.command_export().rjq_command(),
+            "rjq -q '.items[]' data.yaml"
+        );
+    }
+
+    #[test]
+    fn test_reload_from_stdin_offers_a_snapshot() {
+        let mut app = App::from_data(crate::JsonData::from_inputs(vec![json!(1), json!(2)]));
+        app.run_effect(Effect::Reload);
+        assert!(app.command_export().input_path.is_none());
+
+        // Asking again saves the inputs and switches the source to the file
+        app.run_effect(Effect::Reload);
+        let path = app.command_export().input_path.unwrap();
+        assert!(path.ends_with(".ndjson"), "{}", path);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "1\n2\n");
+
+        app.run_effect(Effect::Reload);
+        assert!(app.data().is_stream());
+        assert_eq!(
+            app.status_message(),
+            Some(format!("Reloaded {}", path).as_str())
+        );
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_snapshot_does_not_reuse_an_existing_file() {
+        let taken = std::env::temp_dir().join(format!("rjq-stdin-{}.json", std::process::id()));
+        std::fs::write(&taken, "planted").unwrap();
+
+        let mut app = App::from_data(crate::JsonData::new(json!({"a": 1})));
+        app.snapshot_stdin();
+        let path = app.command_export().input_path.unwrap();
+        let planted = std::fs::read_to_string(&taken).unwrap();
+        let saved = std::fs::read_to_string(&path).unwrap();
+        #[cfg(unix)]
+        let mode = {
+            use std::os::unix::fs::PermissionsExt;
+            std::fs::metadata(&path).unwrap().permissions().mode() & 0o777
+        };
+        std::fs::remove_file(&taken).ok();
+        std::fs::remove_file(&path).ok();
+
+        assert_ne!(std::path::Path::new(&path), taken);
+        assert_eq!(planted, "planted");
+        assert_eq!(saved, "{\n  \"a\": 1\n}");
+        #[cfg(unix)]
+        assert_eq!(mode, 0o600);
+    }
+}
@@ -1,13 +1,323 @@
+use std::ops::Range;
+use std::path::Path;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum AppError {
     #[error("JSON parsing error: {0}")]
     JsonParse(#[from] serde_json::Error),
-    #[error("Query compilation error: {0}")]
-    QueryCompile(String),
+    /// Input in a format other than JSON could not be parsed
+    #[error("{format} parsing error: {message}")]
+    InputParse {
+        format: &'static str,
+        message: String,
+    },
+    #[error("Query compilation error: {message}")]
+    QueryCompile {
+        message: String,
+        /// Byte range of the offending part of the query, when known
+        span: Option<Range<usize>>,
+    },
     #[error("Query execution error: {0}")]
     QueryExecution(String),
+    /// The query raised an error while running, e.g. `error("boom")` or
+    /// indexing a number
+    #[error("Query runtime error: {message}")]
+    QueryRuntime {
+        message: String,
+        /// Outputs produced before the error
+        partial: Vec<serde_json::Value>,
+    },
+    #[error("File not found: {0}")]
+    FileNotFound(String),
+    /// A URL could not be fetched, or did not return data
+    #[error("Failed to fetch {url}: {message}")]
+    Fetch { url: String, message: String },
+    #[error("Config error in {path}: {message}")]
+    Config { path: String, message: String },
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 }
+
+impl AppError {
+    /// Creates a compile error without position information
+    pub fn query_compile(message: impl Into<String>) -> Self {
+        AppError::QueryCompile {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    /// Stable identifier of the error kind for machine-readable output
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::JsonParse(_) => "json_parse",
+            AppError::InputParse { .. } => "input_parse",
+            AppError::QueryCompile { .. } => "query_compile",
+            AppError::QueryExecution(_) => "query_execution",
+            AppError::QueryRuntime { .. } => "query_runtime",
+            AppError::FileNotFound(_) => "file_not_found",
+            AppError::Fetch { .. } => "fetch",
+            AppError::Config { .. } => "config",
+            AppError::Io(_) => "io",
+        }
+    }
+
+    /// Byte range in the query that caused the error, if known
+    pub fn span(&self) -> Option<Range<usize>> {
+        match self {
+            AppError::QueryCompile { span, .. } => span.clone(),
+            _ => None,
+        }
+    }
+
+    /// Returns an actionable suggestion for resolving the error, if one is known.
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            AppError::JsonParse(e) => json_parse_hint(e),
+            AppError::QueryCompile { message, .. } => query_compile_hint(message),
+            AppError::FileNotFound(path) => file_not_found_hint(path),
+            AppError::Fetch { message, .. } => fetch_hint(message),
+            AppError::Config { message, .. } if message.contains("is not set") => Some(
+                "export the variable before starting rjq, or write `$${` for a literal `${`"
+                    .to_string(),
+            ),
+            AppError::InputParse { .. }
+            | AppError::QueryExecution(_)
+            | AppError::QueryRuntime { .. }
+            | AppError::Config { .. }
+            | AppError::Io(_) => None,
+        }
+    }
+}
+
+fn json_parse_hint(error: &serde_json::Error) -> Option<String> {
+    if error.to_string().starts_with("trailing characters") {
+        return Some(format!(
+            "input appears to contain multiple JSON documents (second one starts at line {}); try -s to read them into one array, or --ndjson if there is one per line",
+            error.line()
+        ));
+    }
+    if error.is_eof() {
+        return Some("input ended unexpectedly; the JSON document may be truncated".to_string());
+    }
+    if error.to_string().starts_with("trailing comma") {
+        return Some("try --lenient to accept trailing commas and comments".to_string());
+    }
+    None
+}
+
+fn query_compile_hint(message: &str) -> Option<String> {
+    const UNCLOSED: [(&str, &str); 4] = [
+        ("unclosed `[`", "`[` is never closed; check bracket balance"),
+        (
+            "unclosed `(`",
+            "`(` is never closed; check parenthesis balance",
+        ),
+        ("unclosed `{`", "`{` is never closed; check brace balance"),
+        (
+            "unterminated string",
+            "string literal is never terminated; add a closing `\"`",
+        ),
+    ];
+    // Several problems are joined in order, so the first one named wins
+    if let Some((_, hint)) = UNCLOSED
+        .iter()
+        .filter_map(|(needle, hint)| Some((message.find(needle)?, hint)))
+        .min_by_key(|(position, _)| *position)
+    {
+        return Some(hint.to_string());
+    }
+
+    for closer in ["]", ")", "}"] {
+        if message.contains(&format!("unexpected `{}`", closer)) {
+            return Some(format!(
+                "unexpected `{}`; check bracket balance near it",
+                closer
+            ));
+        }
+    }
+
+    if message.starts_with("expected term, found the end of the query") {
+        return Some(
+            "query is incomplete; add a filter after the last operator or `|`".to_string(),
+        );
+    }
+
+    if message.starts_with("undefined filter") {
+        return Some(
+            "unknown filter or wrong number of arguments; check the spelling and arity".to_string(),
+        );
+    }
+
+    None
+}
+
+fn fetch_hint(message: &str) -> Option<String> {
+    if message.contains("timed out") {
+        return Some(
+            "the server is slow to answer; allow longer with --fetch-timeout SECS".to_string(),
+        );
+    }
+    if message.contains("text/html") {
+        return Some(
+            "the server sent a web page; check that the URL points at the data, not a page about it"
+                .to_string(),
+        );
+    }
+    None
+}
+
+fn file_not_found_hint(path: &str) -> Option<String> {
+    let path = Path::new(path);
+    let file_name = path.file_name()?.to_string_lossy().to_string();
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+
+    let mut similar: Vec<String> = std::fs::read_dir(dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|candidate| is_similar_name(&file_name, candidate))
+        .collect();
+    if similar.is_empty() {
+        return None;
+    }
+    similar.sort();
+
+    let shown: Vec<String> = similar
+        .iter()
+        .map(|name| dir.join(name).to_string_lossy().to_string())
+        .collect();
+    Some(format!("did you mean: {}?", shown.join(", ")))
+}
+
+fn is_similar_name(target: &str, candidate: &str) -> bool {
+    let threshold = (target.chars().count() / 4).max(2);
+    edit_distance(&target.to_lowercase(), &candidate.to_lowercase()) <= threshold
+}
+
+/// Levenshtein distance between `a` and `b`
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b_chars: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b_chars.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b_chars.len() + 1];
+        for (j, cb) in b_chars.iter().enumerate() {
+            let cost = if ca == *cb { 0 } else { 1 };
+            current[j + 1] = (previous[j] + cost)
+                .min(previous[j + 1] + 1)
+                .min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b_chars.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{JaqQueryExecutor, QueryExecutor};
+    use serde_json::json;
+
+    fn compile_error(query: &str) -> AppError {
+        JaqQueryExecutor
+            .execute(&json!({}), query)
+            .expect_err("query should fail to compile")
+    }
+
+    #[test]
+    fn test_hint_for_unclosed_bracket() {
+        let hint = compile_error(".a[").hint().unwrap();
+        assert!(hint.contains("bracket balance"));
+    }
+
+    #[test]
+    fn test_hint_for_unexpected_closer() {
+        let hint = compile_error(".a]").hint().unwrap();
+        assert!(hint.contains("`]`"));
+    }
+
+    #[test]
+    fn test_hint_for_unknown_filter() {
+        let hint = compile_error("nosuchfilter").hint().unwrap();
+        assert!(hint.contains("unknown filter"));
+    }
+
+    #[test]
+    fn test_hint_for_multiple_documents() {
+        let error: AppError = serde_json::from_str::<serde_json::Value>("{\"a\":1}\n{\"a\":2}")
+            .unwrap_err()
+            .into();
+        let hint = error.hint().unwrap();
+        assert!(hint.contains("line 2"));
+        assert!(hint.contains("-s "));
+        assert!(hint.contains("--ndjson"));
+    }
+
+    #[test]
+    fn test_hint_for_trailing_comma() {
+        let error: AppError = serde_json::from_str::<serde_json::Value>("[1, 2,]")
+            .unwrap_err()
+            .into();
+        assert!(error.hint().unwrap().contains("--lenient"));
+    }
+
+    #[test]
+    fn test_hint_for_missing_file() {
+        let dir = std::env::temp_dir().join(format!("rjq_hint_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("sample.json"), "{}").unwrap();
+
+        let missing = dir.join("sampel.json");
+        let hint = AppError::FileNotFound(missing.to_string_lossy().to_string()).hint();
+
+        std::fs::remove_dir_all(&dir).ok();
+        assert!(hint.unwrap().contains("sample.json"));
+    }
+
+    #[test]
+    fn test_compile_error_span() {
+        let error = compile_error(".a | nosuchfilter");
+        assert_eq!(error.code(), "query_compile");
+        assert_eq!(error.span(), Some(5..17));
+
+        let error = compile_error(".a]");
+        assert_eq!(error.span(), Some(2..3));
+
+        // An unclosed delimiter points at where it opens
+        let error = compile_error("map(.a");
+        assert_eq!(error.span(), Some(3..4));
+    }
+
+    #[test]
+    fn test_compile_error_messages() {
+        let message = |query: &str| match compile_error(query) {
+            AppError::QueryCompile { message, .. } => message,
+            other => panic!("expected a compile error, got {:?}", other),
+        };
+        assert_eq!(message(".a | ]"), "unexpected `]`");
+        assert_eq!(message("length(1)"), "undefined filter `length/1`");
+        assert_eq!(message(".a | $x"), "undefined variable `$x`");
+        assert_eq!(
+            message("if . then 1"),
+            "expected else or end, found the end of the query"
+        );
+        assert_eq!(
+            message("select(.a == \"b"),
+            "unterminated string; unclosed `(`"
+        );
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("same", "same"), 0);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
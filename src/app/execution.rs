@@ -0,0 +1,609 @@
+//! When queries run
+//!
+//! Re-running the query on every keystroke is what makes exploring small
+//! documents feel instant, and what makes typing into a large one stall. The
+//! [`ExecutionPolicy`] decides whether an edit runs at once, after the typing
+//! pauses or only on Enter; until then the previous result stays on screen.
+
+use super::{App, AppError, AppWarning, CachedResult};
+use crate::query::QueryRun;
+use crate::query::cost::{ExpensiveConstruct, find_expensive};
+use serde::Deserialize;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Documents at least this large ask before running expensive constructs such as `..`
+pub const EXPENSIVE_LIMIT: u64 = 16 * 1024 * 1024;
+
+/// Queries taking at least this long are reported as slow
+pub const SLOW_QUERY_THRESHOLD: Duration = Duration::from_millis(500);
+
+/// Background queries still running after this long are given up
+pub const DEFAULT_QUERY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Pause after the last keystroke before a debounced query runs
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Documents smaller than this run the query on every keystroke
+pub const IMMEDIATE_LIMIT: u64 = 1024 * 1024;
+
+/// Documents smaller than this run the query once typing pauses; larger ones wait for Enter
+pub const DEBOUNCE_LIMIT: u64 = 64 * 1024 * 1024;
+
+/// When edits run the query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExecutionPolicy {
+    /// Every edit runs the query
+    #[default]
+    Immediate,
+    /// The query runs once no key was typed for this long, or on Enter
+    Debounced(Duration),
+    /// The query only runs on Enter
+    Manual,
+}
+
+impl ExecutionPolicy {
+    /// The policy suited to a document of `bytes`
+    pub fn for_size(bytes: u64, debounce: Duration) -> Self {
+        if bytes < IMMEDIATE_LIMIT {
+            ExecutionPolicy::Immediate
+        } else if bytes < DEBOUNCE_LIMIT {
+            ExecutionPolicy::Debounced(debounce)
+        } else {
+            ExecutionPolicy::Manual
+        }
+    }
+
+    /// The next policy when toggled at runtime
+    fn next(self, debounce: Duration) -> Self {
+        match self {
+            ExecutionPolicy::Immediate => ExecutionPolicy::Debounced(debounce),
+            ExecutionPolicy::Debounced(_) => ExecutionPolicy::Manual,
+            ExecutionPolicy::Manual => ExecutionPolicy::Immediate,
+        }
+    }
+}
+
+impl fmt::Display for ExecutionPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionPolicy::Immediate => write!(f, "immediate"),
+            ExecutionPolicy::Debounced(delay) => write!(f, "debounced {}ms", delay.as_millis()),
+            ExecutionPolicy::Manual => write!(f, "manual"),
+        }
+    }
+}
+
+/// Policy names accepted in the `[execution]` config section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Chosen from the document's size with [`ExecutionPolicy::for_size`]
+    #[default]
+    Auto,
+    Immediate,
+    Debounced,
+    Manual,
+}
+
+impl ExecutionMode {
+    pub fn policy(self, document_bytes: u64, debounce: Duration) -> ExecutionPolicy {
+        match self {
+            ExecutionMode::Auto => ExecutionPolicy::for_size(document_bytes, debounce),
+            ExecutionMode::Immediate => ExecutionPolicy::Immediate,
+            ExecutionMode::Debounced => ExecutionPolicy::Debounced(debounce),
+            ExecutionMode::Manual => ExecutionPolicy::Manual,
+        }
+    }
+}
+
+impl App {
+    pub fn execution_policy(&self) -> ExecutionPolicy {
+        self.execution
+    }
+
+    /// Changes when edits run the query; an edit still waiting runs at once
+    /// when switching to [`ExecutionPolicy::Immediate`]
+    pub fn set_execution_policy(&mut self, policy: ExecutionPolicy) {
+        if let ExecutionPolicy::Debounced(delay) = policy {
+            self.debounce = delay;
+        }
+        self.execution = policy;
+        if policy == ExecutionPolicy::Immediate {
+            self.run_query();
+        }
+    }
+
+    /// Switches to the next policy: immediate, debounced, manual
+    pub fn cycle_execution_policy(&mut self) {
+        self.set_execution_policy(self.execution.next(self.debounce));
+        self.state
+            .set_status_message(format!("Queries run: {}", self.execution));
+    }
+
+    /// The query whose result is shown: the input, the last query run while
+    /// an edit waits for the execution policy, or the last one finished while
+    /// a newer one runs in the background
+    pub fn query(&self) -> &str {
+        self.shown.as_deref().unwrap_or_else(|| self.target_query())
+    }
+
+    /// The query the result should follow once it has run
+    fn target_query(&self) -> &str {
+        self.state.executed.as_deref().unwrap_or(&self.state.input)
+    }
+
+    /// Whether the input was edited since the shown result was computed
+    pub fn query_pending(&self) -> bool {
+        self.state.executed.is_some() || self.shown.is_some()
+    }
+
+    /// Runs the input now, whatever the policy
+    pub fn run_query(&mut self) {
+        self.state.executed = None;
+        self.state.edited_at = None;
+    }
+
+    /// Called before every keystroke that edits the input: the result of the
+    /// query shown so far stays unless the policy runs every edit
+    pub(super) fn hold_query(&mut self) {
+        if self.execution == ExecutionPolicy::Immediate {
+            // A cancelled query is only held until the next edit
+            self.run_query();
+            return;
+        }
+        if self.state.executed.is_none() {
+            self.state.executed = Some(self.state.input.clone());
+        }
+        self.state.edited_at = Some(Instant::now());
+    }
+
+    /// Time until a debounced edit runs, if one is waiting
+    pub fn time_until_execution(&self) -> Option<Duration> {
+        let ExecutionPolicy::Debounced(delay) = self.execution else {
+            return None;
+        };
+        self.state.executed.as_ref()?;
+        let waited = self.state.edited_at.map_or(delay, |at| at.elapsed());
+        Some(delay.saturating_sub(waited))
+    }
+
+    /// Runs a debounced edit once typing has paused long enough; `true` when it did
+    pub fn run_due_query(&mut self) -> bool {
+        match self.time_until_execution() {
+            Some(remaining) if remaining.is_zero() => {
+                self.run_query();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Runs queries on a worker thread from now on; until one finishes, the
+    /// previous result stays shown and [`App::poll_query`] picks it up
+    pub fn enable_background_execution(&mut self) {
+        self.background_queries = true;
+    }
+
+    /// Gives up background queries still running after `timeout`; `None`
+    /// lets them run until they finish or are cancelled
+    pub fn set_query_timeout(&mut self, timeout: Option<Duration>) {
+        self.query_timeout = timeout;
+    }
+
+    /// The query running in the background and how long it has taken so far
+    pub fn running_query(&self) -> Option<(&str, Duration)> {
+        self.running
+            .as_ref()
+            .map(|run| (run.query(), run.elapsed()))
+    }
+
+    /// Asks for confirmation before running a query that is slow on large
+    /// inputs, e.g. `..`; the previous result stays shown until then
+    pub fn set_confirm_expensive(&mut self, confirm: bool) {
+        self.confirm_expensive = confirm;
+    }
+
+    /// Why the query waits for Enter or F5 before it runs, if it does
+    pub fn unconfirmed_query(&self) -> Option<ExpensiveConstruct> {
+        let query = self.target_query();
+        if !self.confirm_expensive || self.confirmed.as_deref() == Some(query) {
+            return None;
+        }
+        find_expensive(query)
+    }
+
+    /// Runs the input now, even if it is expensive
+    pub fn confirm_query(&mut self) {
+        self.run_query();
+        self.confirmed = Some(self.state.input.clone());
+    }
+
+    /// Picks up a finished background query, holds back an expensive one
+    /// until it is confirmed, or starts one once the query changed; `true`
+    /// when the view needs redrawing
+    ///
+    /// Without a result to keep showing, e.g. right after the data changed,
+    /// the query starts in the background all the same and the result area
+    /// waits for it, see [`App::awaiting_result`].
+    pub fn poll_query(&mut self) -> bool {
+        self.note_result();
+        let stuck = QueryRun::stuck();
+        if stuck > self.stuck_reported {
+            self.state.push_warning(AppWarning::StuckQueries(stuck));
+        }
+        self.stuck_reported = stuck;
+        let generation = self.data.generation();
+        if let Some(result) = self.running.as_ref().and_then(QueryRun::try_finish) {
+            let run = self.running.take().expect("finished above");
+            // Outputs cut short by an error would hide it from longer queries
+            match &result {
+                Ok(result) if result.error().is_none() => {
+                    let stored = self.prefixes.get_mut().store(
+                        run.query(),
+                        run.generation(),
+                        result.values().to_vec(),
+                    );
+                    if !stored {
+                        self.state.push_warning(AppWarning::CacheDisabled(
+                            "every stored result is pinned; unpin one in the cache inspector (F12)"
+                                .to_string(),
+                        ));
+                    }
+                }
+                Ok(result) => self.state.push_warning(AppWarning::TruncatedOutput {
+                    shown: result.values().len(),
+                    total: None,
+                }),
+                Err(_) => {}
+            }
+            if run.elapsed() >= SLOW_QUERY_THRESHOLD {
+                self.state.push_warning(AppWarning::SlowQuery {
+                    query: run.query().to_string(),
+                    elapsed: run.elapsed(),
+                });
+            }
+            *self.result.get_mut() = Some(CachedResult {
+                query: run.query().to_string(),
+                generation: run.generation(),
+                result,
+                elapsed: run.elapsed(),
+            });
+            self.shown = None;
+            return true;
+        }
+        if let Some(timeout) = self.query_timeout
+            && self
+                .running
+                .as_ref()
+                .is_some_and(|run| run.elapsed() >= timeout)
+        {
+            self.time_out_query(timeout);
+            return true;
+        }
+
+        let target = self.target_query().to_string();
+        let shown = match &*self.result.borrow() {
+            Some(cached) if cached.generation == generation => Some(cached.query.clone()),
+            _ => None,
+        };
+        let Some(shown) = shown else {
+            if self.background_queries && !target.is_empty() && self.unconfirmed_query().is_none() {
+                self.start_query(&target, generation);
+            }
+            return false;
+        };
+        if shown == target {
+            self.running = None;
+            self.shown = None;
+            return false;
+        }
+        if self.unconfirmed_query().is_some() {
+            self.running = None;
+            self.shown = Some(shown);
+            return false;
+        }
+        if !self.background_queries {
+            self.shown = None;
+            return false;
+        }
+        self.start_query(&target, generation);
+        self.shown = Some(shown);
+        false
+    }
+
+    /// Starts `query` on the worker thread unless it already runs on this
+    /// generation of the data
+    fn start_query(&mut self, query: &str, generation: u64) {
+        if self
+            .running
+            .as_ref()
+            .is_some_and(|run| run.query() == query && run.generation() == generation)
+        {
+            return;
+        }
+        self.running = Some(QueryRun::spawn(
+            &self.data,
+            &self.executor,
+            self.prefixes.get_mut(),
+            query,
+        ));
+    }
+
+    /// Whether the result area waits for the background query, having no
+    /// earlier result to show meanwhile
+    pub fn awaiting_result(&self) -> bool {
+        self.shown.is_none() && self.running.is_some()
+    }
+
+    /// Abandons the query running in the background: the previous result
+    /// stays until the next edit, and the query is remembered as slow
+    pub fn cancel_query(&mut self) {
+        let Some(run) = self.running.take() else {
+            return;
+        };
+        self.state.edited_at = None;
+        self.state.set_status_message(format!(
+            "Cancelled after {:.1}s: {}",
+            run.elapsed().as_secs_f64(),
+            run.query()
+        ));
+        match self.shown.take() {
+            Some(shown) => {
+                self.state.executed = Some(shown);
+                self.state
+                    .query_history
+                    .record_slow_query(run.query().to_string());
+            }
+            // Without a previous result the cancellation is shown in its place
+            None => self.give_up(run, "cancelled".to_string()),
+        }
+    }
+
+    /// Gives up the running query, showing a timeout error as its result so
+    /// it is not started again until it is edited
+    fn time_out_query(&mut self, timeout: Duration) {
+        if let Some(run) = self.running.take() {
+            let message = format!("timed out after {:.1}s", timeout.as_secs_f64());
+            self.give_up(run, message);
+        }
+    }
+
+    /// Remembers `run` as slow and shows `message` as its result
+    fn give_up(&mut self, run: QueryRun, message: String) {
+        self.state
+            .query_history
+            .record_slow_query(run.query().to_string());
+        *self.result.get_mut() = Some(CachedResult {
+            query: run.query().to_string(),
+            generation: run.generation(),
+            result: Err(AppError::QueryExecution(message)),
+            elapsed: run.elapsed(),
+        });
+        self.shown = None;
+    }
+
+    /// Whether `query` was cancelled for taking too long before
+    pub fn is_slow_query(&self, query: &str) -> bool {
+        self.state
+            .query_history
+            .entry(query)
+            .is_some_and(|entry| entry.slow)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ContentGenerator;
+    use serde_json::json;
+
+    #[test]
+    fn test_policy_for_size() {
+        assert_eq!(
+            ExecutionPolicy::for_size(10, DEFAULT_DEBOUNCE),
+            ExecutionPolicy::Immediate
+        );
+        assert_eq!(
+            ExecutionPolicy::for_size(IMMEDIATE_LIMIT, DEFAULT_DEBOUNCE),
+            ExecutionPolicy::Debounced(DEFAULT_DEBOUNCE)
+        );
+        assert_eq!(
+            ExecutionPolicy::for_size(DEBOUNCE_LIMIT, DEFAULT_DEBOUNCE),
+            ExecutionPolicy::Manual
+        );
+        assert_eq!(
+            ExecutionMode::Immediate.policy(DEBOUNCE_LIMIT, DEFAULT_DEBOUNCE),
+            ExecutionPolicy::Immediate
+        );
+    }
+
+    #[test]
+    fn test_manual_policy_keeps_result_until_run() {
+        let mut app = App::new(json!({"a": 1, "b": 2}));
+        app.set_execution_policy(ExecutionPolicy::Manual);
+        for c in ".a".chars() {
+            app.type_char(c);
+        }
+        assert!(app.query_pending());
+        assert_eq!(app.query(), "");
+        assert_eq!(app.current_values().unwrap(), vec![json!({"a": 1, "b": 2})]);
+        assert!(!app.run_due_query());
+
+        app.run_query();
+        assert!(!app.query_pending());
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+
+        app.delete_char();
+        app.type_char('b');
+        assert_eq!(app.query(), ".a");
+        app.cycle_execution_policy();
+        assert_eq!(app.execution_policy(), ExecutionPolicy::Immediate);
+        assert_eq!(app.current_values().unwrap(), vec![json!(2)]);
+    }
+
+    #[test]
+    fn test_debounced_policy_runs_after_a_pause() {
+        let mut app = App::new(json!({"a": 1}));
+        app.set_execution_policy(ExecutionPolicy::Debounced(Duration::from_secs(3600)));
+        app.type_char('.');
+        assert!(app.time_until_execution().unwrap() > Duration::from_secs(60));
+        assert!(!app.run_due_query());
+        assert_eq!(app.query(), "");
+
+        app.set_execution_policy(ExecutionPolicy::Debounced(Duration::ZERO));
+        assert_eq!(app.time_until_execution(), Some(Duration::ZERO));
+        assert!(app.run_due_query());
+        assert_eq!(app.query(), ".");
+        assert_eq!(app.time_until_execution(), None);
+    }
+
+    /// Polls until the background query finished
+    fn finish(app: &mut App) {
+        while app.running_query().is_some() {
+            std::thread::sleep(Duration::from_millis(1));
+            app.poll_query();
+        }
+    }
+
+    #[test]
+    fn test_background_query_keeps_previous_result() {
+        let mut app = App::new(json!({"a": 1, "b": 2}));
+        app.enable_background_execution();
+        app.set_input(".a".to_string());
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+
+        app.set_input(".b".to_string());
+        assert!(!app.poll_query());
+        assert_eq!(app.running_query().map(|(query, _)| query), Some(".b"));
+        assert!(app.query_pending());
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+
+        finish(&mut app);
+        assert!(!app.query_pending());
+        assert_eq!(app.query(), ".b");
+        assert_eq!(app.current_values().unwrap(), vec![json!(2)]);
+    }
+
+    #[test]
+    fn test_background_runtime_error_survives_longer_query() {
+        let mut app = App::new(json!({"items": [{"id": 1}, {"id": 2}]}));
+        app.enable_background_execution();
+        app.set_input(".".to_string());
+        app.current_values().unwrap();
+        let failing = ".items[] | if .id == 2 then error(\"x\") else . end";
+        app.set_input(failing.to_string());
+        app.poll_query();
+        finish(&mut app);
+        assert_eq!(app.runtime_error(), Some((1, "x".to_string())));
+
+        app.set_input(format!("{} | .id", failing));
+        app.poll_query();
+        finish(&mut app);
+        assert_eq!(app.runtime_error(), Some((1, "x".to_string())));
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+    }
+
+    #[test]
+    fn test_first_query_runs_in_background() {
+        let mut app = App::new(json!({"a": 1}));
+        app.enable_background_execution();
+        app.set_input(".a".to_string());
+        assert!(!app.poll_query());
+        assert!(app.awaiting_result());
+        assert_eq!(app.get_total_lines(), 0);
+
+        finish(&mut app);
+        assert!(!app.awaiting_result());
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+    }
+
+    #[test]
+    fn test_cancelled_first_query_shows_cancellation() {
+        let mut app = App::new(json!(1));
+        app.enable_background_execution();
+        app.set_input("repeat(.)".to_string());
+        app.poll_query();
+        assert!(app.awaiting_result());
+
+        app.cancel_query();
+        assert!(!app.awaiting_result());
+        assert!(app.is_slow_query("repeat(.)"));
+        assert!(matches!(
+            app.current_values(),
+            Err(AppError::QueryExecution(message)) if message == "cancelled"
+        ));
+        // Not started again until edited
+        assert!(!app.poll_query());
+        assert!(app.running_query().is_none());
+    }
+
+    #[test]
+    fn test_cancelled_query_is_recorded_as_slow() {
+        let mut app = App::new(json!({"a": 1, "b": 2}));
+        app.enable_background_execution();
+        app.set_input(".a".to_string());
+        app.current_values().unwrap();
+        app.set_input(".b".to_string());
+        app.poll_query();
+
+        app.cancel_query();
+        assert!(app.running_query().is_none());
+        assert!(app.is_slow_query(".b"));
+        assert_eq!(app.query(), ".a");
+        assert!(!app.poll_query());
+        assert_eq!(app.current_values().unwrap(), vec![json!(1)]);
+
+        // The next edit runs again
+        app.delete_char();
+        app.type_char('b');
+        app.poll_query();
+        finish(&mut app);
+        assert_eq!(app.current_values().unwrap(), vec![json!(2)]);
+    }
+
+    #[test]
+    fn test_endless_query_times_out() {
+        let mut app = App::new(json!(1));
+        app.enable_background_execution();
+        app.set_query_timeout(Some(Duration::from_millis(100)));
+        app.set_input(".".to_string());
+        app.current_values().unwrap();
+        app.set_input("repeat(.)".to_string());
+        app.poll_query();
+        assert!(app.running_query().is_some());
+
+        finish(&mut app);
+        assert!(app.is_slow_query("repeat(.)"));
+        assert_eq!(app.query(), "repeat(.)");
+        assert!(matches!(
+            app.current_values(),
+            Err(AppError::QueryExecution(message)) if message == "timed out after 0.1s"
+        ));
+        // Not started again until edited
+        assert!(!app.poll_query());
+        assert!(app.running_query().is_none());
+    }
+
+    #[test]
+    fn test_expensive_query_waits_for_confirmation() {
+        let mut app = App::new(json!({"a": {"b": 1}}));
+        app.set_confirm_expensive(true);
+        app.set_input(".a".to_string());
+        app.current_values().unwrap();
+
+        app.set_input("[..] | length".to_string());
+        app.poll_query();
+        assert_eq!(
+            app.unconfirmed_query(),
+            Some(ExpensiveConstruct::RecursiveDescent)
+        );
+        assert_eq!(app.query(), ".a");
+        assert_eq!(app.current_values().unwrap(), vec![json!({"b": 1})]);
+
+        app.confirm_query();
+        assert_eq!(app.unconfirmed_query(), None);
+        app.poll_query();
+        assert_eq!(app.current_values().unwrap(), vec![json!(3)]);
+    }
+}
@@ -0,0 +1,212 @@
+use super::App;
+use super::state::FrequencyState;
+use crate::query::QueryExecutor;
+use crate::render::{Counts, PathSegment, jq_path};
+use serde_json::Value;
+
+impl App {
+    /// Value counts of a field shown in the frequency panel while it is open
+    pub fn frequency(&self) -> Option<&FrequencyState> {
+        self.state.frequency.as_ref()
+    }
+
+    /// Counts how often each value of the focused field occurs across the
+    /// rows of the result, or closes the panel
+    ///
+    /// The field is the selected column in the table view, otherwise the
+    /// first field of an array element from the top of the result view on.
+    pub fn toggle_frequency(&mut self) {
+        if self.state.frequency.take().is_some() {
+            return;
+        }
+        let Some((rows, array, field)) = self.focused_field() else {
+            self.state
+                .set_status_message("No field of an array element in view to count");
+            return;
+        };
+        let jq = frequency_jq(&field);
+        let counts = match self.executor.execute(&rows, &jq).as_deref() {
+            Ok([Value::Array(groups)]) => groups
+                .iter()
+                .filter_map(|group| {
+                    let label = match group.get("key")? {
+                        Value::String(s) => s.clone(),
+                        other => other.to_string(),
+                    };
+                    Some((label, group.get("count")?.as_f64()?))
+                })
+                .collect(),
+            _ => {
+                self.state
+                    .set_status_message(format!("Could not count the values of {}", field));
+                return;
+            }
+        };
+        self.state.frequency = Some(FrequencyState {
+            query: self.frequency_query(&array, &jq),
+            counts: Counts::new(&field, "count", counts),
+        });
+    }
+
+    /// Replaces the query with the one computing the counts and closes the panel
+    pub fn insert_frequency_query(&mut self) {
+        if let Some(frequency) = self.state.frequency.take() {
+            self.set_input(frequency.query);
+        }
+    }
+
+    /// The rows holding the focused field as one array, with the jq paths of
+    /// that array within the result and of the field within a row
+    fn focused_field(&self) -> Option<(Value, String, String)> {
+        if self.state.table_mode {
+            let (table, layout) = self.current_table()?;
+            let last = layout.visible().count().saturating_sub(1);
+            let column = layout.visible().nth(self.state.table_column.min(last))?;
+            let field = jq_path(&[PathSegment::Key(column.key.clone())]);
+            let rows = table.rows.into_iter().map(Value::Object).collect();
+            return Some((Value::Array(rows), ".".to_string(), field));
+        }
+        let (array, field) = {
+            let tree = self.document()?;
+            tree.lines()
+                .iter()
+                .skip(self.state.scroll_offset)
+                .take(self.config.visible_height.max(1))
+                .find_map(|line| {
+                    let (array, field) = split_at_element(&tree.node(line.node)?.path)?;
+                    Some((array.to_vec(), field.to_vec()))
+                })?
+        };
+        let values = self.current_values().ok()?;
+        // Several outputs are shown as one array
+        let result = match <[Value; 1]>::try_from(values) {
+            Ok([value]) => value,
+            Err(values) => Value::Array(values),
+        };
+        let rows = array
+            .iter()
+            .try_fold(&result, |value, segment| match segment {
+                PathSegment::Key(key) => value.get(key),
+                PathSegment::Index(index) => value.get(index),
+            })?;
+        Some((rows.clone(), jq_path(&array), jq_path(&field)))
+    }
+
+    /// `jq` run on the array at `array` in the current view, as one program
+    /// on the input
+    fn frequency_query(&self, array: &str, jq: &str) -> String {
+        let query = self.query().trim();
+        let single = matches!(self.current_values().ok().as_deref(), Some([_]));
+        let result = match self.table_export_query() {
+            Some(table) => table,
+            None if query.is_empty() => ".".to_string(),
+            None if single => query.to_string(),
+            None => format!("[{}]", query),
+        };
+        [result.as_str(), array, jq]
+            .into_iter()
+            .filter(|part| *part != ".")
+            .collect::<Vec<_>>()
+            .join(" | ")
+    }
+}
+
+/// jq turning an array of rows into the count of each value of `field`,
+/// most frequent first
+fn frequency_jq(field: &str) -> String {
+    format!(
+        "group_by({0}) | map({{key: .[0]{0}, count: length}}) | sort_by(-.count)",
+        field
+    )
+}
+
+/// Splits the path of a node inside an array element into the path of the
+/// array and the field of the element holding the node, e.g.
+/// `.users[0].address.city` into `.users` and `.address`
+///
+/// `None` for nodes outside array elements and for the elements themselves.
+fn split_at_element(path: &[PathSegment]) -> Option<(&[PathSegment], &[PathSegment])> {
+    let index = path.iter().enumerate().rev().position(|(i, segment)| {
+        matches!(segment, PathSegment::Index(_))
+            && matches!(path.get(i + 1), Some(PathSegment::Key(_)))
+    })?;
+    let index = path.len() - 1 - index;
+    let field = &path[index + 1..];
+    let end = field
+        .iter()
+        .position(|segment| matches!(segment, PathSegment::Index(_)))
+        .unwrap_or(field.len());
+    Some((&path[..index], &field[..end.max(1)]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn requests() -> Value {
+        json!({"requests": [
+            {"path": "/a", "status": 200},
+            {"path": "/b", "status": 500},
+            {"path": "/c", "status": 200}
+        ]})
+    }
+
+    #[test]
+    fn test_counts_field_in_view() {
+        let mut app = App::new(requests());
+        app.toggle_frequency();
+        let frequency = app.frequency().unwrap();
+        assert_eq!(frequency.counts.label, ".path");
+        assert_eq!(
+            frequency.query,
+            ".requests | group_by(.path) | map({key: .[0].path, count: length}) | sort_by(-.count)"
+        );
+
+        app.toggle_frequency();
+        app.set_input(".requests[] | .status");
+        app.toggle_frequency();
+        assert!(app.frequency().is_none());
+        assert_eq!(
+            app.status_message(),
+            Some("No field of an array element in view to count")
+        );
+    }
+
+    #[test]
+    fn test_counts_selected_column_and_inserts_query() {
+        let mut app = App::new(requests());
+        app.set_input(".requests[]");
+        app.toggle_table();
+        app.select_table_column(true);
+        app.toggle_frequency();
+        let counts = &app.frequency().unwrap().counts;
+        assert_eq!(
+            counts.entries,
+            vec![("200".to_string(), 2.0), ("500".to_string(), 1.0)]
+        );
+
+        app.insert_frequency_query();
+        assert!(app.frequency().is_none());
+        assert_eq!(
+            app.current_values().unwrap(),
+            vec![json!([{"key": 200, "count": 2}, {"key": 500, "count": 1}])]
+        );
+    }
+
+    #[test]
+    fn test_split_at_element() {
+        let path = [
+            PathSegment::Key("users".to_string()),
+            PathSegment::Index(0),
+            PathSegment::Key("tags".to_string()),
+            PathSegment::Index(1),
+        ];
+        let (array, field) = split_at_element(&path).unwrap();
+        assert_eq!(
+            (jq_path(array), jq_path(field)),
+            (".users".into(), ".tags".into())
+        );
+        assert!(split_at_element(&path[..2]).is_none());
+    }
+}
@@ -0,0 +1,172 @@
+use super::App;
+use super::state::ValueInspectorState;
+use crate::query::{CacheStats, PrefixCacheStats, PrefixEntry, QueryExecutor};
+use crate::ui::inspector::{decode_base64, looks_like_base64, preview_len};
+
+impl App {
+    /// Selected entry of the cache inspector while it is open
+    pub fn cache_inspector(&self) -> Option<usize> {
+        self.state.cache_inspector
+    }
+
+    /// Opens the debug view of the stored query outputs, or closes it
+    pub fn toggle_cache_inspector(&mut self) {
+        self.state.cache_inspector = match self.state.cache_inspector {
+            Some(_) => None,
+            None => Some(0),
+        };
+    }
+
+    /// Stored query outputs, oldest first
+    pub fn cache_entries(&self) -> Vec<PrefixEntry> {
+        self.prefixes.borrow().entries()
+    }
+
+    pub fn cache_stats(&self) -> PrefixCacheStats {
+        self.prefixes.borrow().stats()
+    }
+
+    /// Statistics of the executor's results cache, when it keeps one
+    pub fn query_cache_stats(&self) -> Option<CacheStats> {
+        self.executor.cache_stats()
+    }
+
+    pub fn move_cache_inspector(&mut self, forward: bool) {
+        let count = self.prefixes.get_mut().len();
+        if let Some(selected) = &mut self.state.cache_inspector {
+            *selected = if forward {
+                (*selected + 1).min(count.saturating_sub(1))
+            } else {
+                selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Keeps the selected entry from being evicted by newer queries, or lets it go
+    pub fn pin_cache_entry(&mut self) {
+        if let Some(selected) = self.state.cache_inspector {
+            self.prefixes.get_mut().toggle_pin(selected);
+        }
+    }
+
+    pub fn remove_cache_entry(&mut self) {
+        let Some(selected) = self.state.cache_inspector else {
+            return;
+        };
+        let prefixes = self.prefixes.get_mut();
+        prefixes.remove(selected);
+        self.state.cache_inspector = Some(selected.min(prefixes.len().saturating_sub(1)));
+    }
+
+    /// Forgets every entry that is not pinned
+    pub fn clear_cache(&mut self) {
+        self.prefixes.get_mut().clear_unpinned();
+        self.state.cache_inspector = self.state.cache_inspector.map(|_| 0);
+    }
+
+    /// The decoded value shown in the value inspector while it is open
+    pub fn value_inspector(&self) -> Option<&ValueInspectorState> {
+        self.state.value_inspector.as_ref()
+    }
+
+    /// Decodes the first base64 string from the top of the result view on,
+    /// or closes the inspector
+    pub fn toggle_value_inspector(&mut self) {
+        if self.state.value_inspector.take().is_some() {
+            return;
+        }
+        let first = self.state.scroll_offset;
+        let height = self.config.visible_height.max(1);
+        let found = self.document().and_then(|tree| {
+            tree.lines()
+                .iter()
+                .skip(first)
+                .take(height)
+                .find_map(|line| {
+                    let serde_json::Value::String(text) = tree.scalar(line.node)? else {
+                        return None;
+                    };
+                    looks_like_base64(&text).then(|| ValueInspectorState {
+                        path: tree.path_string(line.node),
+                        bytes: decode_base64(&text).unwrap_or_default(),
+                        scroll: 0,
+                    })
+                })
+        });
+        match found {
+            Some(inspector) => self.state.value_inspector = Some(inspector),
+            None => self
+                .state
+                .set_status_message("No base64 value in view to inspect"),
+        }
+    }
+
+    /// Scrolls the inspector's preview by `lines`, up or down
+    pub fn scroll_value_inspector(&mut self, down: bool, lines: usize) {
+        if let Some(inspector) = &mut self.state.value_inspector {
+            let last = preview_len(&inspector.bytes).saturating_sub(1);
+            inspector.scroll = if down {
+                (inspector.scroll + lines).min(last)
+            } else {
+                inspector.scroll.saturating_sub(lines)
+            };
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_value_inspector_decodes_base64_in_view() {
+        let mut app = App::new(json!({
+            "name": "report",
+            "data": "bGluZSBvbmUKbGluZSB0d28KbGluZSB0aHJlZQo="
+        }));
+        app.toggle_value_inspector();
+        let inspector = app.value_inspector().unwrap();
+        assert_eq!(inspector.path, ".data");
+        assert_eq!(inspector.bytes, b"line one\nline two\nline three\n");
+
+        app.scroll_value_inspector(true, 10);
+        assert_eq!(app.value_inspector().unwrap().scroll, 2);
+        app.toggle_value_inspector();
+        assert!(app.value_inspector().is_none());
+
+        let mut app = App::new(json!({"name": "report"}));
+        app.toggle_value_inspector();
+        assert!(app.value_inspector().is_none());
+        assert!(app.status_message().is_some());
+    }
+
+    #[test]
+    fn test_inspect_pin_and_clear_cache() {
+        let mut app = App::new(json!({"a": {"b": [1, 2]}}));
+        for query in [".a", ".a | .b", ".a | .b | length"] {
+            app.set_input(query);
+            app.current_values().unwrap();
+        }
+        let queries = |app: &App| -> Vec<String> {
+            app.cache_entries().into_iter().map(|e| e.query).collect()
+        };
+        assert_eq!(queries(&app), [".a", ".a | .b", ".a | .b | length"]);
+        assert_eq!(app.cache_stats().hits, 2);
+        assert_eq!(app.cache_entries()[0].hits, 1);
+
+        app.toggle_cache_inspector();
+        app.move_cache_inspector(true);
+        app.pin_cache_entry();
+        app.move_cache_inspector(true);
+        app.remove_cache_entry();
+        assert_eq!(app.cache_inspector(), Some(1));
+        assert!(app.cache_entries()[1].pinned);
+
+        app.clear_cache();
+        assert_eq!(queries(&app), [".a | .b"]);
+        assert_eq!(app.cache_inspector(), Some(0));
+        app.toggle_cache_inspector();
+        assert_eq!(app.cache_inspector(), None);
+    }
+}
@@ -0,0 +1,175 @@
+use super::App;
+use super::state::JoinState;
+use crate::query::{JoinSpec, object_arrays};
+
+/// Parts of a join in the order the dialog shows them
+pub const JOIN_PARTS: [&str; 4] = ["left array", "left key", "right array", "right key"];
+
+impl App {
+    /// The join being built while the dialog is open
+    pub fn join(&self) -> Option<&JoinState> {
+        self.state.join.as_ref()
+    }
+
+    /// Opens the dialog building a join of two arrays of the document, or
+    /// closes it
+    ///
+    /// The arrays start as the first two arrays of objects found.
+    pub fn toggle_join(&mut self) {
+        if self.state.join.take().is_some() {
+            return;
+        }
+        let arrays = object_arrays(self.data.get());
+        let Some(first) = arrays.first() else {
+            self.state
+                .set_status_message("Joins need arrays of objects in the document");
+            return;
+        };
+        let spec = JoinSpec {
+            left: first.path.clone(),
+            right: arrays.get(1).unwrap_or(first).path.clone(),
+            ..Default::default()
+        };
+        self.state.join = Some(JoinState {
+            spec,
+            focus: 1,
+            arrays,
+        });
+    }
+
+    pub fn join_push(&mut self, c: char) {
+        if let Some(join) = &mut self.state.join {
+            part_mut(&mut join.spec, join.focus).push(c);
+        }
+    }
+
+    pub fn join_pop(&mut self) {
+        if let Some(join) = &mut self.state.join {
+            part_mut(&mut join.spec, join.focus).pop();
+        }
+    }
+
+    /// Moves to the next or previous part of the join
+    pub fn move_join_focus(&mut self, down: bool) {
+        if let Some(join) = &mut self.state.join {
+            join.focus = if down {
+                (join.focus + 1).min(JOIN_PARTS.len() - 1)
+            } else {
+                join.focus.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Values offered for the focused part: the arrays of objects for an
+    /// array, the keys of its elements for a key
+    pub fn join_candidates(&self) -> Vec<&str> {
+        let Some(join) = &self.state.join else {
+            return Vec::new();
+        };
+        let keys_of = |path: &str| {
+            join.arrays
+                .iter()
+                .find(|array| array.path == path.trim())
+                .map(|array| array.keys.iter().map(String::as_str).collect())
+                .unwrap_or_default()
+        };
+        match join.focus {
+            0 | 2 => join
+                .arrays
+                .iter()
+                .map(|array| array.path.as_str())
+                .collect(),
+            1 => keys_of(&join.spec.left),
+            _ => keys_of(&join.spec.right),
+        }
+    }
+
+    /// Replaces the focused part with the candidate after its current value
+    pub fn complete_join_part(&mut self) {
+        let candidates: Vec<String> = self
+            .join_candidates()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let Some(join) = &mut self.state.join else {
+            return;
+        };
+        let part = part_mut(&mut join.spec, join.focus);
+        let next = candidates
+            .iter()
+            .position(|candidate| candidate == part)
+            .map_or(0, |i| (i + 1) % candidates.len().max(1));
+        if let Some(candidate) = candidates.get(next) {
+            *part = candidate.clone();
+        }
+    }
+
+    /// Replaces the query with the join and closes the dialog once every
+    /// part is given
+    pub fn apply_join(&mut self) {
+        let Some(join) = &self.state.join else {
+            return;
+        };
+        if !join.spec.is_complete() {
+            self.state
+                .set_status_message("Give both arrays and the key of each to join them");
+            return;
+        }
+        let jq = join.spec.to_jq();
+        self.state.join = None;
+        self.set_input(jq);
+    }
+}
+
+fn part_mut(spec: &mut JoinSpec, focus: usize) -> &mut String {
+    match focus {
+        0 => &mut spec.left,
+        1 => &mut spec.left_key,
+        2 => &mut spec.right,
+        _ => &mut spec.right_key,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_join_dialog_builds_query() {
+        let mut app = App::new(json!({
+            "orders": [{"id": 10, "user_id": 1}],
+            "users": [{"id": 1, "name": "ann"}]
+        }));
+        app.toggle_join();
+        assert_eq!(app.join().unwrap().spec.left, ".orders");
+        assert_eq!(app.join().unwrap().spec.right, ".users");
+        assert_eq!(app.join_candidates(), vec!["id", "user_id"]);
+
+        app.complete_join_part();
+        app.complete_join_part();
+        app.apply_join();
+        assert!(app.join().is_some());
+        assert_eq!(
+            app.status_message(),
+            Some("Give both arrays and the key of each to join them")
+        );
+
+        app.move_join_focus(true);
+        app.move_join_focus(true);
+        "id".chars().for_each(|c| app.join_push(c));
+        app.apply_join();
+        assert!(app.join().is_none());
+        assert_eq!(
+            app.current_values().unwrap(),
+            vec![json!([{"id": 1, "user_id": 1, "name": "ann"}])]
+        );
+    }
+
+    #[test]
+    fn test_join_needs_object_arrays() {
+        let mut app = App::new(json!({"tags": ["a"]}));
+        app.toggle_join();
+        assert!(app.join().is_none());
+    }
+}
@@ -0,0 +1,186 @@
+use super::{App, AppWarning};
+use crate::query::Indexer;
+use crate::source::follow::Follower;
+use crate::source::watch::FileWatcher;
+
+impl App {
+    /// Reloads the input whenever `watcher` sees it change
+    pub fn set_watcher(&mut self, watcher: FileWatcher) {
+        self.watcher = Some(watcher);
+    }
+
+    /// Appends the records `follower` receives to the document, which
+    /// becomes a stream of inputs
+    pub fn set_follower(&mut self, follower: Follower) {
+        self.follower = Some(follower);
+    }
+
+    /// Rebuilds the key index on a background thread after the data
+    /// changes; until it is done, suggestions use the previous index
+    pub fn enable_background_indexing(&mut self) {
+        self.indexer = Some(Indexer::spawn(self.key_index.clone()));
+    }
+
+    /// Whether the input can still change without a key press, so the event
+    /// loop has to look for changes while it waits
+    pub fn has_live_input(&self) -> bool {
+        self.watcher.is_some()
+            || self.follower.as_ref().is_some_and(|f| !f.is_closed())
+            || self.indexer.as_ref().is_some_and(Indexer::is_pending)
+    }
+
+    /// Generation of the data the key index describes; behind
+    /// [`JsonData::generation`](crate::JsonData::generation) while it is rebuilt
+    pub fn index_generation(&self) -> u64 {
+        self.key_index.generation()
+    }
+
+    /// Records received with `--follow` so far, and whether stdin is still open
+    pub fn follow_progress(&self) -> Option<(usize, bool)> {
+        self.follower.as_ref().map(|f| (f.count(), !f.is_closed()))
+    }
+
+    /// Picks up changes to the watched file and newly arrived records;
+    /// `true` when the view needs redrawing
+    pub fn poll_live_input(&mut self) -> bool {
+        let reloaded = self.reload_if_changed();
+        let received = self.receive_followed();
+        let indexed = self.receive_index();
+        reloaded || received || indexed
+    }
+
+    /// Swaps in the key index finished on the background thread; `true`
+    /// when there was one
+    pub fn receive_index(&mut self) -> bool {
+        let Some(index) = self.indexer.as_mut().and_then(Indexer::receive) else {
+            return false;
+        };
+        self.key_index = index;
+        true
+    }
+
+    /// Reloads the watched file if it changed since the last check; `true`
+    /// when it did
+    ///
+    /// Changes are ignored while another file is open, e.g. from the file browser.
+    pub fn reload_if_changed(&mut self) -> bool {
+        let Some(watcher) = &self.watcher else {
+            return false;
+        };
+        if !watcher.changed() {
+            return false;
+        }
+        let watched = self
+            .source_path
+            .as_deref()
+            .and_then(|path| std::fs::canonicalize(path).ok());
+        if watched.as_deref() != Some(watcher.path()) {
+            return false;
+        }
+        self.reload();
+        true
+    }
+
+    /// Appends the records that arrived on stdin since the last call; `true`
+    /// when anything changed
+    ///
+    /// The query re-runs on the grown document while the scroll position
+    /// stays. Records wait in the channel while another file is open.
+    pub fn receive_followed(&mut self) -> bool {
+        if self.source_path.is_some() {
+            return false;
+        }
+        let Some(follower) = &mut self.follower else {
+            return false;
+        };
+        let was_open = !follower.is_closed();
+        let received = follower.receive();
+        let closed = was_open && follower.is_closed();
+        let changed = closed || !received.records.is_empty() || !received.invalid.is_empty();
+        for (line, message) in &received.invalid {
+            self.state.push_warning(AppWarning::InvalidRecord {
+                line: *line,
+                message: message.clone(),
+            });
+        }
+        if !received.records.is_empty() {
+            // Only the new records need indexing
+            match &mut self.indexer {
+                Some(indexer) => {
+                    let records = received.records.clone();
+                    self.data.append(received.records);
+                    indexer.extend(records, self.data.generation());
+                }
+                None => {
+                    self.key_index.extend(&received.records);
+                    self.data.append(received.records);
+                    self.key_index.set_generation(self.data.generation());
+                }
+            }
+            self.invalidate_derived();
+            self.emit_data_reloaded();
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn test_watched_file_reloads_when_it_changes() {
+        let dir = std::env::temp_dir().join(format!("rjq_effect_watch_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("data.json");
+        std::fs::write(&path, "{\"a\": 1}").unwrap();
+        let mut app = App::new(json!({"a": 1}));
+        app.set_source_path(Some(path.display().to_string()));
+        app.set_watcher(FileWatcher::new(&path).unwrap());
+        app.set_input(".a");
+        assert!(!app.reload_if_changed());
+
+        std::fs::write(&path, "{\"a\": 2}").unwrap();
+        let started = Instant::now();
+        while !app.reload_if_changed() {
+            assert!(started.elapsed().as_secs() < 5, "change not noticed");
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(app.current_values().unwrap(), vec![json!(2)]);
+        assert_eq!(app.input(), ".a");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_followed_records_grow_the_document() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        let mut app = App::from_data(crate::JsonData::from_inputs(Vec::new()));
+        app.set_follower(Follower::spawn(BufReader::new(reader)));
+        app.enable_background_indexing();
+        app.set_input(".n");
+        assert!(app.has_live_input());
+        assert_eq!(app.follow_progress(), Some((0, true)));
+
+        writeln!(writer, "{{\"n\": 1}}\nnot json\n{{\"n\": 2}}").unwrap();
+        drop(writer);
+        let started = Instant::now();
+        while app.has_live_input() {
+            assert!(started.elapsed().as_secs() < 5, "stdin not closed");
+            app.poll_live_input();
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert_eq!(app.current_values().unwrap(), vec![json!(1), json!(2)]);
+        assert_eq!(app.follow_progress(), Some((2, false)));
+        // The loop also waited for the new records to be indexed
+        assert_eq!(app.index_generation(), app.data().generation());
+        assert!(app.key_index.contains("n"));
+        assert_eq!(
+            app.warnings()[0].to_string(),
+            "skipped stdin line 2: expected ident at line 1 column 2"
+        );
+    }
+}
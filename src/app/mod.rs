@@ -1,16 +1,84 @@
+mod browser;
 pub mod builder;
+mod chart;
 pub mod config;
+pub mod effect;
 pub mod error;
-pub mod state;
+pub mod execution;
+mod frequency;
+mod inspector;
+mod join;
+mod live;
+mod row_filter;
+mod sql;
+pub(crate) mod state;
+mod stats;
+pub mod subscription;
+mod table;
+pub mod warning;
+mod workspace;
 
-use crate::query::JsonData;
+use crate::export::{CommandExport, ExportRun, InputFlags};
+use crate::history::{Fix, RankingStrategy, Redactor, UsageLog};
+use crate::query::distinct::{distinct_strings, open_string_comparison};
+use crate::query::docs::{self, BuiltinDoc};
+use crate::query::format::format_query;
+use crate::query::lexer::{IncrementalTokenizer, QueryToken, QueryTokenKind, tokenize_query};
+use crate::query::literals::{adjust_number, numeric_literals};
+use crate::query::pipeline::stage_ranges;
+use crate::query::{
+    FileSummary, Indexer, JaqQueryExecutor, JsonData, KeyIndex, LazyDocument, PrefixCache,
+    PreludeExecutor, QueryExecutor, QueryResult, QueryRun, SizeIndex, Workspace,
+};
+use crate::render::{
+    BarMapping, HighlightRules, NumberFormats, RenderTree, RowCount, RowFilter, RuleStyle,
+};
+use crate::session::{Replay, SessionStore, TeeLog};
+use crate::source::follow::Follower;
+use crate::source::watch::FileWatcher;
+use crate::source::{DataSource, LoadOptions};
 pub use builder::{AppBuilder, EnhancedApp};
 pub use config::AppConfig;
+pub use effect::Effect;
 pub use error::AppError;
-pub use state::AppState;
+pub use execution::ExecutionPolicy;
+pub use join::JOIN_PARTS;
+use state::AppState;
+pub use state::{
+    BrowserState, FrequencyState, JoinState, ManualState, PinnedResult, TabState,
+    ValueInspectorState,
+};
+use std::borrow::Cow;
+use std::cell::{Cell, OnceCell, Ref, RefCell};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use subscription::Subscribers;
+pub use subscription::{AppEvent, SubscriptionId};
+use table::CachedRowStyles;
+pub use warning::AppWarning;
+
+/// File written by the export command
+pub const DEFAULT_EXPORT_PATH: &str = "rjq-export.sh";
+/// File written by the HTML export command
+pub const DEFAULT_HTML_EXPORT_PATH: &str = "rjq-export.html";
+
+/// Width the compact layout fits the result to until the view is drawn
+const DEFAULT_RESULT_WIDTH: usize = 80;
+
+mod sealed {
+    /// Keeps [`ContentGenerator`](super::ContentGenerator) implementable only inside the crate
+    pub trait Sealed {}
+
+    impl Sealed for super::App {}
+    impl<Q: crate::QueryExecutor, E: crate::EventHandler> Sealed for super::EnhancedApp<Q, E> {}
+}
 
 /// コンテンツ生成のための共通トレイト
-pub trait ContentGenerator {
+pub trait ContentGenerator: sealed::Sealed {
     /// 現在のコンテンツを生成する
     fn generate_current_content(&self) -> String;
 
@@ -18,39 +86,263 @@ pub trait ContentGenerator {
     fn get_total_lines(&self) -> usize;
 }
 
+/// Marks where the cursor goes in an alias expansion
+const ALIAS_CURSOR: &str = "$0";
+
+/// Most next-stage suggestions offered after a pipe
+const MAX_STAGE_CANDIDATES: usize = 8;
+
+/// Most field values offered inside a compared string literal
+const MAX_VALUE_CANDIDATES: usize = 100;
+
+/// Contents of the completion popup
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    /// Byte offset in the input where the completed text begins
+    pub start: usize,
+    pub candidates: Vec<String>,
+    /// Candidates are inserted as a JSON string literal, replacing the open quote
+    pub quoted: bool,
+}
+
 #[derive(Debug)]
 pub struct App {
     config: AppConfig,
     state: AppState,
     data: JsonData,
+    tee_log: Option<TeeLog>,
+    usage_log: Option<UsageLog>,
+    replay: Option<Replay>,
+    source_path: Option<String>,
+    executor: PreludeExecutor<JaqQueryExecutor>,
+    bookmarks: Vec<(String, String)>,
+    bookmark_index: usize,
+    aliases: BTreeMap<String, String>,
+    tokenizer: IncrementalTokenizer,
+    /// Keys present in `data`, used to rank history suggestions
+    key_index: KeyIndex,
+    /// Named filters applied to results for viewing only
+    display_filters: Vec<(String, String)>,
+    /// How numbers are shown, by the key they are under
+    number_formats: NumberFormats,
+    /// Styles of values under matching keys
+    highlight_rules: HighlightRules,
+    /// jq program styling table rows, giving each row the index of its style
+    row_rules: Option<(String, Vec<RuleStyle>)>,
+    /// Styles of the table's rows, computed again when the rows change
+    row_style_cache: RefCell<Option<CachedRowStyles>>,
+    /// Result of the current query, run again when the query or data changes
+    result: RefCell<Option<CachedResult>>,
+    /// Outputs of recent queries, which a query extending one of them starts from
+    prefixes: RefCell<PrefixCache>,
+    /// Result tree of the current query, rebuilt when the query or data changes
+    document: RefCell<Option<CachedDocument>>,
+    /// Strings at the field compared in the query, collected again when the data changes
+    distinct_cache: RefCell<Option<CachedDistinct>>,
+    /// Table layouts remembered between sessions
+    session_store: SessionStore,
+    /// Keys the bar chart reads labels and values from, instead of guessing
+    bar_mapping: Option<BarMapping>,
+    /// Sizes of the document's subtrees, built the first time they are shown
+    size_index: OnceCell<SizeIndex>,
+    /// Columns of the result view when it was last drawn
+    result_width: Cell<usize>,
+    /// Every loaded file once more than one is open; empty for a single file
+    sources: Vec<Source>,
+    /// How files and URLs are read again on reload
+    load_options: LoadOptions,
+    /// Files every query also runs against, from `--glob`
+    workspace: Option<Workspace>,
+    /// Per-file summary of the query it was built for
+    workspace_summary: RefCell<Option<(String, Vec<FileSummary>)>>,
+    /// Reloads the input when it changes, from `--watch`
+    watcher: Option<FileWatcher>,
+    /// Records still arriving on stdin, from `--follow`
+    follower: Option<Follower>,
+    /// Rebuilds `key_index` off the event loop once the data changes
+    indexer: Option<Indexer>,
+    /// Callbacks told about queries, data changes and errors
+    subscribers: RefCell<Subscribers>,
+    /// Whether edits run the query at once, after a pause or on Enter
+    execution: ExecutionPolicy,
+    /// Pause used when the policy is toggled to debounced
+    debounce: Duration,
+    /// Queries run on a worker thread instead of while drawing
+    background_queries: bool,
+    /// Query computing on the worker thread
+    running: Option<QueryRun>,
+    /// Background queries still running after this long are given up
+    query_timeout: Option<Duration>,
+    /// Export streaming to a file in the background
+    export: Option<ExportRun>,
+    /// Query whose result stays shown while `running` computes a newer one,
+    /// or while an expensive query waits to be confirmed
+    shown: Option<String>,
+    /// Ask before running queries that are slow on large inputs
+    confirm_expensive: bool,
+    /// Expensive query the user chose to run anyway
+    confirmed: Option<String>,
+    /// Stuck background queries already warned about
+    stuck_reported: usize,
 }
 
-impl ContentGenerator for App {
-    fn generate_current_content(&self) -> String {
-        match self.execute_current_query() {
-            Ok(result) => result.format_pretty(),
-            Err(_) => {
-                if self.input().is_empty() {
-                    serde_json::to_string_pretty(self.data.get())
-                        .unwrap_or_else(|_| "Error formatting JSON".to_string())
-                } else {
-                    "".to_string()
-                }
+/// One loaded file
+#[derive(Debug)]
+struct Source {
+    /// `None` for stdin
+    path: Option<String>,
+    /// `None` while the active tab queries it; its data is then `App::data`
+    data: Option<JsonData>,
+}
+
+/// Result of a query and the query that produced it
+#[derive(Debug)]
+struct CachedResult {
+    query: String,
+    generation: u64,
+    result: crate::Result<QueryResult>,
+    /// How long the query took to run
+    elapsed: Duration,
+}
+
+/// Distinct strings found at a field path of the data
+#[derive(Debug)]
+struct CachedDistinct {
+    generation: u64,
+    path: Vec<String>,
+    values: Vec<String>,
+}
+
+/// Result tree and the query that produced it
+#[derive(Debug)]
+struct CachedDocument {
+    query: String,
+    generation: u64,
+    display_filter: Option<usize>,
+    row_filter: String,
+    /// Rows left by the row filter while it is in effect
+    row_count: Option<RowCount>,
+    /// `None` when the query failed
+    tree: Option<RenderTree>,
+    /// Why the display filter could not be applied; the tree then shows the raw result
+    display_error: Option<String>,
+}
+
+/// Names tried for a stdin snapshot before giving up
+const SNAPSHOT_ATTEMPTS: usize = 16;
+
+/// Creates a new file, readable by the user only, for a stdin snapshot
+///
+/// The temporary directory is shared, so a file already there under the
+/// name is never written to; a name with a random suffix is tried instead.
+fn create_snapshot_file(extension: &str) -> std::io::Result<(PathBuf, File)> {
+    use std::hash::BuildHasher;
+    let dir = std::env::temp_dir();
+    let mut name = format!("rjq-stdin-{}", std::process::id());
+    for attempt in 0..SNAPSHOT_ATTEMPTS {
+        let path = dir.join(format!("{}.{}", name, extension));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        match options.open(&path) {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                let suffix = std::hash::RandomState::new().hash_one(attempt);
+                name = format!("rjq-stdin-{}-{:016x}", std::process::id(), suffix);
             }
+            Err(e) => return Err(e),
         }
     }
+    Err(std::io::Error::new(
+        std::io::ErrorKind::AlreadyExists,
+        format!("no free file name in {}", dir.display()),
+    ))
+}
+
+impl ContentGenerator for App {
+    fn generate_current_content(&self) -> String {
+        self.document()
+            .map(|tree| tree.to_text())
+            .unwrap_or_default()
+    }
 
     fn get_total_lines(&self) -> usize {
-        self.generate_current_content().lines().count()
+        // Nothing to scroll until the background query delivers
+        if self.awaiting_result() {
+            return 0;
+        }
+        if self.state.size_view {
+            return self.size_index().lines().len();
+        }
+        // The table view has a header row above one line per record, and the
+        // quick filter line while it is used
+        if let Some((table, _)) = self
+            .state
+            .table_mode
+            .then(|| self.current_table())
+            .flatten()
+        {
+            let filter_line =
+                self.state.table_filter_editing || !self.state.table_query.filter.is_empty();
+            return table.rows.len() + 1 + filter_line as usize;
+        }
+        self.document().map_or(0, |tree| tree.line_count())
     }
 }
 
 impl App {
     pub fn new(json_value: serde_json::Value) -> Self {
+        Self::from_data(JsonData::new(json_value))
+    }
+
+    /// App over already loaded data, e.g. a stream of NDJSON inputs
+    pub fn from_data(data: JsonData) -> Self {
         Self {
             config: AppConfig::default(),
             state: AppState::default(),
-            data: JsonData::new(json_value),
+            key_index: KeyIndex::for_data(&data),
+            data,
+            tee_log: None,
+            usage_log: None,
+            replay: None,
+            source_path: None,
+            executor: PreludeExecutor::default(),
+            bookmarks: Vec::new(),
+            bookmark_index: 0,
+            aliases: BTreeMap::new(),
+            tokenizer: IncrementalTokenizer::new(),
+            display_filters: Vec::new(),
+            number_formats: NumberFormats::default(),
+            highlight_rules: HighlightRules::default(),
+            row_rules: None,
+            row_style_cache: RefCell::new(None),
+            result: RefCell::new(None),
+            prefixes: RefCell::default(),
+            document: RefCell::new(None),
+            distinct_cache: RefCell::new(None),
+            session_store: SessionStore::default(),
+            bar_mapping: None,
+            size_index: OnceCell::new(),
+            result_width: Cell::new(DEFAULT_RESULT_WIDTH),
+            sources: Vec::new(),
+            load_options: LoadOptions::default(),
+            workspace: None,
+            workspace_summary: RefCell::new(None),
+            watcher: None,
+            follower: None,
+            indexer: None,
+            subscribers: RefCell::default(),
+            execution: ExecutionPolicy::default(),
+            debounce: execution::DEFAULT_DEBOUNCE,
+            background_queries: false,
+            running: None,
+            query_timeout: Some(execution::DEFAULT_QUERY_TIMEOUT),
+            export: None,
+            shown: None,
+            confirm_expensive: false,
+            confirmed: None,
+            stuck_reported: 0,
         }
     }
 
@@ -58,8 +350,583 @@ impl App {
         Self {
             config,
             state: AppState::default(),
+            key_index: KeyIndex::build(&json_value),
             data: JsonData::new(json_value),
+            tee_log: None,
+            usage_log: None,
+            replay: None,
+            source_path: None,
+            executor: PreludeExecutor::default(),
+            bookmarks: Vec::new(),
+            bookmark_index: 0,
+            aliases: BTreeMap::new(),
+            tokenizer: IncrementalTokenizer::new(),
+            display_filters: Vec::new(),
+            number_formats: NumberFormats::default(),
+            highlight_rules: HighlightRules::default(),
+            row_rules: None,
+            row_style_cache: RefCell::new(None),
+            result: RefCell::new(None),
+            prefixes: RefCell::default(),
+            document: RefCell::new(None),
+            distinct_cache: RefCell::new(None),
+            session_store: SessionStore::default(),
+            bar_mapping: None,
+            size_index: OnceCell::new(),
+            result_width: Cell::new(DEFAULT_RESULT_WIDTH),
+            sources: Vec::new(),
+            load_options: LoadOptions::default(),
+            workspace: None,
+            workspace_summary: RefCell::new(None),
+            watcher: None,
+            follower: None,
+            indexer: None,
+            subscribers: RefCell::default(),
+            execution: ExecutionPolicy::default(),
+            debounce: execution::DEFAULT_DEBOUNCE,
+            background_queries: false,
+            running: None,
+            query_timeout: Some(execution::DEFAULT_QUERY_TIMEOUT),
+            export: None,
+            shown: None,
+            confirm_expensive: false,
+            confirmed: None,
+            stuck_reported: 0,
+        }
+    }
+
+    /// Mirrors every executed query and its result to `log`
+    pub fn set_tee_log(&mut self, log: TeeLog) {
+        self.tee_log = Some(log);
+    }
+
+    /// Appends every query run with Enter to `log`, see [`UsageLog`]
+    pub fn set_usage_log(&mut self, log: UsageLog) {
+        self.usage_log = Some(log);
+    }
+
+    /// Masks or drops secrets before queries reach the history, the tee log
+    /// and the usage log
+    pub fn set_redactor(&mut self, redactor: Redactor) {
+        if let Some(log) = self.tee_log.as_mut() {
+            log.set_redactor(redactor.clone());
+        }
+        if let Some(log) = self.usage_log.as_mut() {
+            log.set_redactor(redactor.clone());
+        }
+        self.state.query_history.set_redactor(redactor);
+    }
+
+    /// Changes how history suggestions are ranked
+    pub fn set_ranking(&mut self, ranking: Box<dyn RankingStrategy>) {
+        self.state.query_history.set_ranking(ranking);
+    }
+
+    /// Records the file the data was loaded from (`None` for stdin)
+    pub fn set_source_path(&mut self, path: Option<String>) {
+        if let Some(source) = self.sources.get_mut(self.state.source) {
+            source.path = path.clone();
+        }
+        self.source_path = path;
+    }
+
+    /// Format and CSV settings the input was read with, reused by [`App::reload`]
+    pub fn set_load_options(&mut self, options: LoadOptions) {
+        self.load_options = options;
+    }
+
+    /// Reads the active tab's file or URL again, keeping the query
+    pub fn reload(&mut self) {
+        let Some(path) = self.source_path.clone() else {
+            if self.state.stdin_snapshot_offered {
+                self.snapshot_stdin();
+            } else {
+                self.state.stdin_snapshot_offered = true;
+                self.state.set_status_message(
+                    "stdin cannot be read again; Ctrl+R again saves it to a file to reload from",
+                );
+            }
+            return;
+        };
+        let mut warnings = Vec::new();
+        match DataSource::from_arg(&path).load(&self.load_options, &mut warnings) {
+            Ok(data) => {
+                self.data.replace_with(data);
+                self.data_changed();
+                self.state.set_status_message(format!("Reloaded {}", path));
+            }
+            Err(e) => {
+                self.emit_error(&e);
+                self.state
+                    .set_status_message(format!("Reload failed: {}", e))
+            }
+        }
+        for warning in warnings {
+            self.state.push_warning(warning);
+        }
+    }
+
+    /// Saves the data read from stdin to a temp file and reads from that
+    /// file from now on, so reload works
+    ///
+    /// The file holds the parsed data as JSON (NDJSON for a stream), whatever
+    /// format stdin was in.
+    pub fn snapshot_stdin(&mut self) {
+        let (extension, contents) = if self.data.is_stream() {
+            let lines: Vec<String> = self.data.inputs().iter().map(|v| v.to_string()).collect();
+            ("ndjson", lines.join("\n") + "\n")
+        } else {
+            let json = serde_json::to_string_pretty(self.data.get()).unwrap_or_default();
+            ("json", json)
+        };
+        let written = create_snapshot_file(extension)
+            .and_then(|(path, mut file)| file.write_all(contents.as_bytes()).map(|()| path));
+        match written {
+            Ok(path) => {
+                // The snapshot is JSON even when stdin was read with --format
+                self.load_options.format = None;
+                self.set_source_path(Some(path.display().to_string()));
+                self.state.set_status_message(format!(
+                    "Saved stdin to {}; Ctrl+R now reloads it",
+                    path.display()
+                ));
+            }
+            Err(e) => self
+                .state
+                .push_warning(AppWarning::LogWriteFailed(format!("stdin snapshot: {}", e))),
+        }
+    }
+
+    /// Makes jq definitions available to every query
+    pub fn set_prelude(&mut self, prelude: Option<String>) {
+        self.executor.set_prelude(prelude);
+        self.executor_changed();
+    }
+
+    /// Binds each value to `$name` in every query, e.g. from `--rawfile`
+    pub fn set_variables(&mut self, variables: Vec<(String, serde_json::Value)>) {
+        self.executor.set_variables(variables);
+        self.executor_changed();
+    }
+
+    /// Drops the results computed before the prelude or variables changed
+    fn executor_changed(&mut self) {
+        *self.result.get_mut() = None;
+        self.prefixes.get_mut().clear();
+        *self.document.get_mut() = None;
+        self.running = None;
+        self.shown = None;
+        *self.workspace_summary.get_mut() = None;
+    }
+
+    /// Named queries cycled into the input line with [`App::next_bookmark`]
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<(String, String)>) {
+        self.bookmarks = bookmarks;
+        self.bookmark_index = 0;
+    }
+
+    /// Abbreviations expanded by [`expand_alias`](Self::expand_alias)
+    pub fn set_aliases(&mut self, aliases: BTreeMap<String, String>) {
+        self.aliases = aliases;
+    }
+
+    /// Replaces the identifier before the cursor with its alias, if it has one
+    ///
+    /// The cursor moves to the expansion's `$0` marker, or its end. Words
+    /// inside string literals or after a `.` are never expanded.
+    pub fn expand_alias(&mut self) -> bool {
+        let cursor = self.state.cursor;
+        let input = &self.state.input;
+        let Some(token) = tokenize_query(input)
+            .into_iter()
+            .find(|token| token.end == cursor && token.kind == QueryTokenKind::Ident)
+        else {
+            return false;
+        };
+        let Some(expansion) = self.aliases.get(token.text(input)) else {
+            return false;
+        };
+        let (expansion, offset) = match expansion.find(ALIAS_CURSOR) {
+            Some(at) => (expansion.replacen(ALIAS_CURSOR, "", 1), at),
+            None => (expansion.clone(), expansion.len()),
+        };
+        let mut input = input.clone();
+        input.replace_range(token.start..token.end, &expansion);
+        self.hold_query();
+        self.state.set_input(input);
+        self.state.cursor = token.start + offset;
+        true
+    }
+
+    /// Replays recorded queries step by step
+    pub fn set_replay(&mut self, replay: Replay) {
+        self.replay = Some(replay);
+    }
+
+    // データの更新
+    /// Replaces the whole document, e.g. after the input file changed
+    pub fn replace_data(&mut self, value: serde_json::Value) {
+        self.data.replace(value);
+        self.data_changed();
+    }
+
+    /// Deep-merges `patch` into the document
+    pub fn merge_data(&mut self, patch: serde_json::Value) {
+        self.data.merge(patch);
+        self.data_changed();
+    }
+
+    // 結果ツリー
+    /// Result tree of the current query, passed through the active display
+    /// filter; the whole document while the input is empty and `None` when the
+    /// query fails
+    ///
+    /// Built once per query, data generation and display filter, so fold and
+    /// highlight state lasts until one of them changes.
+    pub fn document(&self) -> Option<Ref<'_, RenderTree>> {
+        self.document_for(None)
+    }
+
+    /// Like [`document`](Self::document), reusing `result` of the current query
+    /// instead of running it again when the tree is stale
+    pub(crate) fn document_for(
+        &self,
+        result: Option<&crate::Result<QueryResult>>,
+    ) -> Option<Ref<'_, RenderTree>> {
+        let generation = self.data.generation();
+        let (current, executed) = match &*self.document.borrow() {
+            Some(cached) => {
+                let executed = cached.generation == generation && cached.query == self.query();
+                (
+                    executed
+                        && cached.display_filter == self.state.display_filter
+                        && cached.row_filter == self.state.row_filter,
+                    executed,
+                )
+            }
+            None => (false, false),
+        };
+        if !current {
+            let cached = match result {
+                Some(result) => self.build_document(result),
+                None => {
+                    let result = self.current_result();
+                    if !executed {
+                        self.emit_query_result(&result);
+                    }
+                    self.build_document(&result)
+                }
+            };
+            if let (Some(result), false) = (result, executed) {
+                self.emit_query_result(result);
+            }
+            *self.document.borrow_mut() = Some(cached);
+        }
+        Ref::filter_map(self.document.borrow(), |cached| {
+            cached.as_ref().and_then(|cached| cached.tree.as_ref())
+        })
+        .ok()
+    }
+
+    fn build_document(&self, result: &crate::Result<QueryResult>) -> CachedDocument {
+        // A document too large to parse at once is outlined until a query needs all of it
+        let outline = self
+            .data
+            .unparsed()
+            .filter(|_| self.query().is_empty())
+            .map(LazyDocument::outline);
+        let whole = || outline.as_ref().unwrap_or_else(|| self.data.get());
+        let values = match result {
+            Ok(result) => Some(result.values()),
+            Err(_) if self.query().is_empty() => Some(std::slice::from_ref(whole())),
+            Err(_) => None,
+        };
+        let unfiltered = || match result {
+            Ok(result) => RenderTree::from_result(result),
+            Err(_) => RenderTree::from_value(whole()),
+        };
+        let mut display_error = None;
+        let mut row_count = None;
+        let row_filter = RowFilter::parse(&self.state.row_filter).ok().flatten();
+        let tree = values.map(|values| {
+            let displayed = self.apply_display_filter(values).unwrap_or_else(|e| {
+                display_error = Some(e.to_string());
+                None
+            });
+            match (displayed, &row_filter) {
+                (displayed, Some(row_filter)) => {
+                    let values = match displayed {
+                        Some(displayed) => displayed.into_values(),
+                        None => values.to_vec(),
+                    };
+                    let (rows, count) = row_filter.apply(values);
+                    row_count = Some(count);
+                    RenderTree::from_result(&QueryResult::from_values(rows))
+                }
+                (Some(displayed), None) => RenderTree::from_result(&displayed),
+                (None, None) => unfiltered(),
+            }
+        });
+        let tree = tree.map(|mut tree| {
+            if !self.number_formats.is_empty() {
+                tree.format_numbers(&self.number_formats);
+            }
+            if !self.highlight_rules.is_empty() {
+                tree.apply_highlight_rules(&self.highlight_rules);
+            }
+            if self.state.compact_layout {
+                tree.set_line_width(Some(self.result_width.get()));
+            }
+            tree
+        });
+        CachedDocument {
+            query: self.query().to_string(),
+            generation: self.data.generation(),
+            display_filter: self.state.display_filter,
+            row_filter: self.state.row_filter.clone(),
+            row_count,
+            tree,
+            display_error,
+        }
+    }
+
+    /// Runs the active display filter on every output, like `query | filter`
+    ///
+    /// The outputs are the inputs of a single run, so the filter is compiled
+    /// and started once however many there are.
+    fn apply_display_filter(
+        &self,
+        values: &[serde_json::Value],
+    ) -> crate::Result<Option<QueryResult>> {
+        let Some((_, filter)) = self.active_display_filter() else {
+            return Ok(None);
+        };
+        let outputs = self.executor.execute_inputs(values, filter)?;
+        Ok(Some(QueryResult::from_values(outputs)))
+    }
+
+    // 表示フィルタ
+    /// Named filters that can be switched on with [`App::cycle_display_filter`]
+    pub fn set_display_filters(&mut self, filters: Vec<(String, String)>) {
+        self.display_filters = filters;
+        self.state.display_filter = None;
+    }
+
+    /// Turns on the display filter named `name`, or adds `name` as a new filter
+    pub fn activate_display_filter(&mut self, name: &str) {
+        let index = match self.display_filters.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.display_filters
+                    .push((name.to_string(), name.to_string()));
+                self.display_filters.len() - 1
+            }
+        };
+        self.state.display_filter = Some(index);
+    }
+
+    /// (name, filter) of the display filter in effect
+    pub fn active_display_filter(&self) -> Option<(&str, &str)> {
+        self.state
+            .display_filter
+            .and_then(|i| self.display_filters.get(i))
+            .map(|(name, filter)| (name.as_str(), filter.as_str()))
+    }
+
+    /// Why the active display filter failed on the current result, if it did
+    pub fn display_filter_error(&self) -> Option<String> {
+        drop(self.document());
+        self.document
+            .borrow()
+            .as_ref()
+            .and_then(|cached| cached.display_error.clone())
+    }
+
+    /// Switches to the next display filter, turning filtering off after the last one
+    pub fn cycle_display_filter(&mut self) {
+        if self.display_filters.is_empty() {
+            self.state
+                .set_status_message("No display filters defined in config");
+            return;
+        }
+        self.state.display_filter = match self.state.display_filter {
+            None => Some(0),
+            Some(i) if i + 1 < self.display_filters.len() => Some(i + 1),
+            Some(_) => None,
+        };
+        self.state.reset_scroll();
+    }
+
+    /// Rules showing numbers under matching keys as sizes, seconds or
+    /// fixed decimals, without changing the data or the query's output
+    pub fn set_number_formats(&mut self, formats: NumberFormats) {
+        self.number_formats = formats;
+        if let Some(tree) = self
+            .document
+            .get_mut()
+            .as_mut()
+            .and_then(|cached| cached.tree.as_mut())
+        {
+            tree.format_numbers(&self.number_formats);
+        }
+    }
+
+    /// Rules coloring, dimming or emboldening values by their key and value
+    pub fn set_highlight_rules(&mut self, rules: HighlightRules) {
+        self.highlight_rules = rules;
+        if let Some(tree) = self
+            .document
+            .get_mut()
+            .as_mut()
+            .and_then(|cached| cached.tree.as_mut())
+        {
+            tree.apply_highlight_rules(&self.highlight_rules);
+        }
+    }
+
+    fn with_document_mut<R>(&mut self, f: impl FnOnce(&mut RenderTree) -> R) -> Option<R> {
+        drop(self.document());
+        self.document
+            .get_mut()
+            .as_mut()
+            .and_then(|cached| cached.tree.as_mut())
+            .map(f)
+    }
+
+    /// Lays the result out for a view `width` columns wide; only the compact
+    /// layout depends on the width
+    pub(crate) fn fit_document(&self, width: usize) {
+        self.result_width.set(width);
+        let wanted = self.state.compact_layout.then_some(width);
+        if let Some(tree) = self
+            .document
+            .borrow_mut()
+            .as_mut()
+            .and_then(|cached| cached.tree.as_mut())
+            .filter(|tree| tree.line_width() != wanted)
+        {
+            tree.set_line_width(wanted);
+        }
+    }
+
+    pub fn compact_layout(&self) -> bool {
+        self.state.compact_layout
+    }
+
+    /// Fits paging and the scroll position to a terminal `height` rows high
+    ///
+    /// The result view gets what the query line and the tab bar leave; a
+    /// shrunk view keeps the scroll position within the result.
+    pub fn resize(&mut self, height: u16) {
+        let tab_bar = usize::from(self.tab_queries().len() > 1);
+        self.config.visible_height = usize::from(height).saturating_sub(1 + tab_bar).max(1);
+        let max_scroll = self
+            .get_total_lines()
+            .saturating_sub(self.config.visible_height);
+        self.state.scroll_offset = self.state.scroll_offset.min(max_scroll);
+    }
+
+    /// Keeps containers that fit the view's width on one line, or gives
+    /// every value a line of its own again
+    pub fn set_compact_layout(&mut self, enabled: bool) {
+        self.state.compact_layout = enabled;
+        let width = enabled.then(|| self.result_width.get());
+        if let Some(tree) = self
+            .document
+            .get_mut()
+            .as_mut()
+            .and_then(|cached| cached.tree.as_mut())
+        {
+            tree.set_line_width(width);
+        }
+        // The result can get shorter than the scroll position
+        let max_scroll = self
+            .get_total_lines()
+            .saturating_sub(self.config.visible_height);
+        self.state.scroll_offset = self.state.scroll_offset.min(max_scroll);
+    }
+
+    pub fn toggle_compact_layout(&mut self) {
+        self.set_compact_layout(!self.state.compact_layout);
+        self.state.set_status_message(if self.state.compact_layout {
+            "Compact layout: short arrays and objects stay on one line"
+        } else {
+            "Compact layout off"
+        });
+    }
+
+    /// Folds or unfolds the innermost container on the top visible line
+    pub fn toggle_fold(&mut self) {
+        let line = self.state.scroll_offset;
+        let toggled = self.with_document_mut(|tree| tree.toggle_fold_at_line(line));
+        if toggled.flatten().is_none() {
+            self.state.set_status_message("Nothing to fold here");
+        }
+        // Folding can shorten the result below the scroll position
+        let max_scroll = self
+            .get_total_lines()
+            .saturating_sub(self.config.visible_height);
+        self.state.scroll_offset = self.state.scroll_offset.min(max_scroll);
+    }
+
+    /// Scrolls to the value at jq path `path`, unfolding what hides it
+    pub fn goto_path(&mut self, path: &str) -> bool {
+        let line = self.with_document_mut(|tree| {
+            let id = tree.find_path(path)?;
+            tree.reveal(id)
+        });
+        match line.flatten() {
+            Some(line) => {
+                self.state.scroll_offset = line;
+                true
+            }
+            None => {
+                self.state
+                    .set_status_message(format!("No value at {}", path.trim()));
+                false
+            }
+        }
+    }
+
+    /// Highlights values whose key or content contains `needle`, returning how many matched
+    pub fn highlight_matches(&mut self, needle: &str) -> usize {
+        self.with_document_mut(|tree| {
+            let matches = tree.search(needle);
+            tree.highlight(&matches);
+            matches.len()
+        })
+        .unwrap_or(0)
+    }
+
+    fn data_changed(&mut self) {
+        self.rebuild_indexes();
+        self.emit_data_reloaded();
+        self.state.picker_index = 0;
+        self.state.reset_scroll();
+    }
+
+    /// Rebuilds everything derived from the document
+    fn rebuild_indexes(&mut self) {
+        match &mut self.indexer {
+            // Sharing a lazily parsed document would parse all of it
+            Some(indexer) if self.data.unparsed().is_none() => {
+                indexer.rebuild(self.data.shared(), self.data.generation())
+            }
+            _ => self.key_index = KeyIndex::for_data(&self.data),
         }
+        self.invalidate_derived();
+    }
+
+    /// Drops the caches built from the document; the key index is left alone
+    fn invalidate_derived(&mut self) {
+        self.size_index = OnceCell::new();
+        self.executor.invalidate();
+        *self.result.get_mut() = None;
+        self.prefixes.get_mut().clear();
+        self.running = None;
+        self.shown = None;
+        // Another file can have the same generation, so the cached tree goes too
+        *self.document.get_mut() = None;
     }
 
     // 状態アクセサ
@@ -83,6 +950,14 @@ impl App {
         &self.data
     }
 
+    pub fn warnings(&self) -> &[AppWarning] {
+        &self.state.warnings
+    }
+
+    pub fn status_message(&self) -> Option<&str> {
+        self.state.status_message.as_deref()
+    }
+
     pub fn scroll_offset(&self) -> usize {
         self.state.scroll_offset
     }
@@ -94,6 +969,13 @@ impl App {
 
     pub fn clear_input(&mut self) {
         self.state.clear_input();
+        self.run_query();
+    }
+
+    /// Replaces the input and runs it, whatever the execution policy
+    pub fn set_input(&mut self, input: impl Into<String>) {
+        self.state.set_input(input);
+        self.run_query();
     }
 
     pub fn push_char(&mut self, c: char) {
@@ -104,23 +986,585 @@ impl App {
         self.state.pop_char();
     }
 
+    /// Types `c` at the cursor, auto-closing brackets when enabled
+    pub fn type_char(&mut self, c: char) {
+        self.hold_query();
+        if self.config.auto_pairs {
+            self.state.insert_with_pairs(c);
+        } else {
+            self.state.push_char(c);
+        }
+    }
+
+    /// Inserts pasted text at the cursor; line breaks and tabs become spaces
+    pub fn paste(&mut self, text: &str) {
+        self.hold_query();
+        for c in text.chars() {
+            self.state
+                .push_char(if c.is_whitespace() { ' ' } else { c });
+        }
+    }
+
+    /// Backspace, removing an empty bracket pair at once when auto-closing is enabled
+    pub fn delete_char(&mut self) {
+        self.hold_query();
+        if self.config.auto_pairs {
+            self.state.delete_with_pairs();
+        } else {
+            self.state.pop_char();
+        }
+    }
+
+    pub fn set_auto_pairs(&mut self, enabled: bool) {
+        self.config.auto_pairs = enabled;
+    }
+
+    /// Byte offset of the cursor in the input
+    pub fn cursor(&self) -> usize {
+        self.state.cursor
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        self.state.move_cursor_left();
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        self.state.move_cursor_right();
+    }
+
     pub fn scroll_up(&mut self) {
-        self.state.scroll_up();
+        self.scroll_up_by(1);
     }
 
     pub fn scroll_down(&mut self) {
+        self.scroll_down_by(1);
+    }
+
+    pub fn scroll_up_by(&mut self, lines: usize) {
+        for _ in 0..lines {
+            self.state.scroll_up();
+        }
+    }
+
+    /// Scrolls `lines` down, running the query only once to find the bottom
+    pub fn scroll_down_by(&mut self, lines: usize) {
         let total_lines = self.get_total_lines();
         let visible_height = self.config.visible_height;
-        self.state.scroll_down_bounded(total_lines, visible_height);
+        for _ in 0..lines {
+            self.state.scroll_down_bounded(total_lines, visible_height);
+        }
+    }
+
+    /// Lines moved by Page Up and Page Down
+    pub fn page_height(&self) -> usize {
+        self.config.visible_height.max(1)
     }
 
     pub fn reset_scroll(&mut self) {
         self.state.reset_scroll();
     }
 
+    pub fn push_warning(&mut self, warning: AppWarning) {
+        self.state.push_warning(warning);
+    }
+
+    pub fn dismiss_warning(&mut self) {
+        self.state.dismiss_warning();
+    }
+
+    pub fn clear_status_message(&mut self) {
+        self.state.status_message = None;
+    }
+
+    pub fn pinned(&self) -> Option<&PinnedResult> {
+        self.state.pinned.as_ref()
+    }
+
+    /// Freezes the current result into the pinned pane, or unpins it if one is shown
+    pub fn toggle_pin(&mut self) {
+        if self.state.pinned.take().is_some() {
+            self.state.set_status_message("Unpinned result");
+            return;
+        }
+        let result = if self.query().is_empty() {
+            Ok(QueryResult::Single(self.data.get().clone()))
+        } else {
+            self.execute_current_query()
+        };
+        match result {
+            Ok(result) => {
+                let query = self.command_export().query;
+                self.state
+                    .set_status_message(format!("Pinned result of `{}`", query));
+                self.state.pinned = Some(PinnedResult {
+                    query,
+                    document: RenderTree::from_result(&result),
+                    result,
+                    scroll_offset: 0,
+                });
+            }
+            Err(e) => self
+                .state
+                .set_status_message(format!("Nothing to pin: {}", e)),
+        }
+    }
+
+    pub fn sync_scroll(&self) -> bool {
+        self.state.sync_scroll
+    }
+
+    /// Toggles whether the pinned pane follows the live pane's scrolling
+    pub fn toggle_sync_scroll(&mut self) {
+        self.state.sync_scroll = !self.state.sync_scroll;
+        let label = if self.state.sync_scroll { "on" } else { "off" };
+        self.state
+            .set_status_message(format!("Synchronized scrolling {}", label));
+    }
+
+    /// Line the pinned pane starts at, following the live pane when synchronized
+    pub fn pinned_scroll_offset(&self) -> usize {
+        let Some(pinned) = &self.state.pinned else {
+            return 0;
+        };
+        if !self.state.sync_scroll {
+            return pinned.scroll_offset;
+        }
+        let max_scroll = pinned
+            .document
+            .line_count()
+            .saturating_sub(self.config.visible_height);
+        self.state.scroll_offset.min(max_scroll)
+    }
+
+    pub fn scroll_pinned_up(&mut self) {
+        if let Some(pinned) = self.state.pinned.as_mut() {
+            pinned.scroll_offset = pinned.scroll_offset.saturating_sub(1);
+        }
+    }
+
+    pub fn scroll_pinned_down(&mut self) {
+        let visible_height = self.config.visible_height;
+        if let Some(pinned) = self.state.pinned.as_mut() {
+            let max_scroll = pinned.document.line_count().saturating_sub(visible_height);
+            pinned.scroll_offset = (pinned.scroll_offset + 1).min(max_scroll);
+        }
+    }
+
+    /// Brings the query tokens up to date with the input, re-lexing only from the edit point
+    pub fn refresh_query_tokens(&mut self) {
+        self.tokenizer.update(&self.state.input);
+    }
+
+    /// Tokens of the current input
+    pub fn query_tokens(&self) -> Cow<'_, [QueryToken]> {
+        match self.tokenizer.tokens_for(&self.state.input) {
+            Some(tokens) => Cow::Borrowed(tokens),
+            None => Cow::Owned(tokenize_query(&self.state.input)),
+        }
+    }
+
+    /// Rewrites the query with canonical spacing
+    pub fn format_query(&mut self) {
+        let formatted = format_query(&self.state.input);
+        if formatted != self.state.input {
+            self.hold_query();
+            self.state.set_input(formatted);
+        }
+    }
+
+    // スライダーモード
+    pub fn slider_active(&self) -> bool {
+        self.state.slider.is_some()
+    }
+
+    /// Byte range of the literal selected in slider mode
+    pub fn slider_literal(&self) -> Option<Range<usize>> {
+        let index = self.state.slider?;
+        numeric_literals(&self.state.input).into_iter().nth(index)
+    }
+
+    // ドキュメントポップアップ
+    /// Builtin whose name the cursor is on or just after
+    pub fn doc_under_cursor(&self) -> Option<(Range<usize>, &'static BuiltinDoc)> {
+        let cursor = self.state.cursor;
+        tokenize_query(&self.state.input)
+            .into_iter()
+            .filter(|token| token.start <= cursor && cursor <= token.end)
+            .filter(|token| {
+                matches!(
+                    token.kind,
+                    QueryTokenKind::Ident | QueryTokenKind::Keyword | QueryTokenKind::Format
+                )
+            })
+            .find_map(|token| {
+                docs::lookup(token.text(&self.state.input)).map(|doc| (token.start..token.end, doc))
+            })
+    }
+
+    /// The documentation popup's contents while it is open
+    pub fn doc_popup(&self) -> Option<(Range<usize>, &'static BuiltinDoc)> {
+        self.state
+            .show_doc
+            .then(|| self.doc_under_cursor())
+            .flatten()
+    }
+
+    pub fn toggle_doc(&mut self) {
+        if self.state.show_doc {
+            self.state.show_doc = false;
+        } else if self.doc_under_cursor().is_some() {
+            self.state.show_doc = true;
+        } else {
+            self.state
+                .set_status_message("No documented builtin under the cursor");
+        }
+    }
+
+    pub fn close_doc(&mut self) {
+        self.state.show_doc = false;
+    }
+
+    // マニュアル
+    pub fn manual(&self) -> Option<&ManualState> {
+        self.state.manual.as_ref()
+    }
+
+    pub fn toggle_manual(&mut self) {
+        self.state.manual = match self.state.manual {
+            Some(_) => None,
+            None => Some(ManualState::default()),
+        };
+    }
+
+    /// Builtins matching the manual's search, grouped by category
+    pub fn manual_entries(&self) -> Vec<&'static BuiltinDoc> {
+        self.state
+            .manual
+            .as_ref()
+            .map(|manual| docs::search(&manual.search))
+            .unwrap_or_default()
+    }
+
+    pub fn manual_search_push(&mut self, c: char) {
+        if let Some(manual) = &mut self.state.manual {
+            manual.search.push(c);
+            manual.selected = 0;
+        }
+    }
+
+    pub fn manual_search_pop(&mut self) {
+        if let Some(manual) = &mut self.state.manual {
+            manual.search.pop();
+            manual.selected = 0;
+        }
+    }
+
+    pub fn move_manual(&mut self, forward: bool) {
+        let count = self.manual_entries().len();
+        if let Some(manual) = &mut self.state.manual {
+            manual.selected = if forward {
+                (manual.selected + 1).min(count.saturating_sub(1))
+            } else {
+                manual.selected.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Enters slider mode on the last numeric literal of the query, or leaves it
+    pub fn toggle_slider(&mut self) {
+        if self.state.slider.take().is_some() {
+            return;
+        }
+        match numeric_literals(&self.state.input).len() {
+            0 => self
+                .state
+                .set_status_message("No numeric literal in the query"),
+            count => self.state.slider = Some(count - 1),
+        }
+    }
+
+    /// Selects the next (or previous) numeric literal
+    pub fn select_literal(&mut self, forward: bool) {
+        let Some(index) = self.state.slider else {
+            return;
+        };
+        let count = numeric_literals(&self.state.input).len();
+        if count == 0 {
+            return;
+        }
+        self.state.slider = Some(if forward {
+            (index + 1) % count
+        } else {
+            (index + count - 1) % count
+        });
+    }
+
+    /// Rewrites the selected literal in place, adding `steps` units of its last digit
+    pub fn adjust_literal(&mut self, steps: i64) {
+        let Some(range) = self.slider_literal() else {
+            return;
+        };
+        if let Some(value) = adjust_number(&self.state.input[range.clone()], steps) {
+            let mut input = self.state.input.clone();
+            input.replace_range(range, &value);
+            self.hold_query();
+            self.state.set_input(input);
+            self.state.reset_scroll();
+        }
+    }
+
+    // 補完ポップアップ
+    /// Candidates for the completion popup and where they are inserted
+    ///
+    /// Inside a string literal compared with a field (`.status == "ac`) these
+    /// are the field's distinct values; after a top-level pipe they are the
+    /// stages that followed the previous one in the query history.
+    pub fn completion(&self) -> Option<Completion> {
+        let input = &self.state.input;
+        if let Some(context) = open_string_comparison(input) {
+            let candidates = self
+                .distinct_values(&context.path)
+                .iter()
+                .filter(|value| value.starts_with(&context.prefix) && **value != context.prefix)
+                .take(MAX_VALUE_CANDIDATES)
+                .cloned()
+                .collect();
+            return Some(Completion {
+                start: context.start,
+                candidates,
+                quoted: true,
+            });
+        }
+
+        if self.state.cursor != input.len() {
+            return None;
+        }
+        let ranges = stage_ranges(input);
+        let [.., previous, last] = ranges.as_slice() else {
+            return None;
+        };
+        let partial = input[last.clone()].trim_start();
+        let candidates = self
+            .state
+            .query_history
+            .next_stages(&input[previous.clone()])
+            .into_iter()
+            .filter(|stage| stage.starts_with(partial) && stage != partial)
+            .take(MAX_STAGE_CANDIDATES)
+            .collect();
+        Some(Completion {
+            start: input.len() - partial.len(),
+            candidates,
+            quoted: false,
+        })
+    }
+
+    /// Distinct strings at `path` in the data, collected once per data
+    /// generation and path rather than on every frame and key
+    fn distinct_values(&self, path: &[String]) -> Ref<'_, [String]> {
+        let generation = self.data.generation();
+        let current = matches!(
+            &*self.distinct_cache.borrow(),
+            Some(cached) if cached.generation == generation && cached.path == path
+        );
+        if !current {
+            *self.distinct_cache.borrow_mut() = Some(CachedDistinct {
+                generation,
+                path: path.to_vec(),
+                values: distinct_strings(self.data.get(), path),
+            });
+        }
+        Ref::map(self.distinct_cache.borrow(), |cached| {
+            cached
+                .as_ref()
+                .map_or(&[][..], |cached| cached.values.as_slice())
+        })
+    }
+
+    pub fn picker_candidates(&self) -> Vec<String> {
+        self.completion()
+            .map(|completion| completion.candidates)
+            .unwrap_or_default()
+    }
+
+    pub fn reset_picker(&mut self) {
+        self.state.picker_index = 0;
+    }
+
+    pub fn picker_index(&self) -> usize {
+        self.state.picker_index
+    }
+
+    pub fn move_picker(&mut self, forward: bool) {
+        let count = self.picker_candidates().len();
+        if count == 0 {
+            return;
+        }
+        let index = self.state.picker_index.min(count - 1);
+        self.state.picker_index = if forward {
+            (index + 1) % count
+        } else {
+            (index + count - 1) % count
+        };
+    }
+
+    /// Inserts the selected candidate, replacing what was typed of it
+    pub fn accept_picker(&mut self) -> bool {
+        let Some(completion) = self.completion() else {
+            return false;
+        };
+        let candidates = &completion.candidates;
+        let Some(value) = candidates.get(
+            self.state
+                .picker_index
+                .min(candidates.len().saturating_sub(1)),
+        ) else {
+            return false;
+        };
+        let input = if completion.quoted {
+            // JSON string escaping matches jq's string literal syntax
+            let quoted = serde_json::Value::String(value.clone()).to_string();
+            format!("{}{}", &self.state.input[..completion.start - 1], quoted)
+        } else {
+            let before = &self.state.input[..completion.start];
+            let separator = if before.ends_with(char::is_whitespace) {
+                ""
+            } else {
+                " "
+            };
+            format!("{}{}{}", before, separator, value)
+        };
+        self.hold_query();
+        self.state.set_input(input);
+        self.state.picker_index = 0;
+        self.state.reset_scroll();
+        true
+    }
+
+    // タブ操作
+    pub fn new_tab(&mut self) {
+        self.state.new_tab();
+    }
+
+    pub fn switch_tab(&mut self, forward: bool) {
+        let previous = self.state.source;
+        self.state.switch_tab(forward);
+        if self.state.source != previous {
+            self.activate_source(previous);
+        }
+    }
+
+    /// Loads another file into a tab of its own after the existing tabs
+    pub fn open_document(&mut self, path: Option<String>, data: JsonData) {
+        if self.sources.is_empty() {
+            self.sources.push(Source {
+                path: self.source_path.clone(),
+                data: None,
+            });
+        }
+        self.sources.push(Source {
+            path,
+            data: Some(data),
+        });
+        self.state.open_tab(self.sources.len() - 1);
+    }
+
+    /// File name of each tab's document, `None` while only one file is loaded
+    pub fn tab_sources(&self) -> Vec<Option<&str>> {
+        self.state
+            .tab_sources()
+            .into_iter()
+            .map(|index| {
+                let path = self.sources.get(index)?.path.as_deref();
+                Some(path.map_or("stdin", |path| {
+                    Path::new(path)
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .unwrap_or(path)
+                }))
+            })
+            .collect()
+    }
+
+    /// Swaps in the active tab's file, parking the one tab `previous` queried
+    fn activate_source(&mut self, previous: usize) {
+        let source = &mut self.sources[self.state.source];
+        let data = source
+            .data
+            .take()
+            .expect("only the active source lends out its data");
+        self.source_path = source.path.clone();
+        self.sources[previous].data = Some(std::mem::replace(&mut self.data, data));
+        self.rebuild_indexes();
+    }
+
+    pub fn tab_queries(&self) -> Vec<&str> {
+        self.state.tab_queries()
+    }
+
+    pub fn active_tab(&self) -> usize {
+        self.state.active_tab
+    }
+
     // クエリ実行（計算結果を返すのみ、状態には保存しない）
     pub fn execute_current_query(&self) -> crate::Result<crate::query::QueryResult> {
-        self.data.execute_query(&self.state.input)
+        self.prefixes
+            .borrow()
+            .execute(&self.data, &self.executor, self.query())
+    }
+
+    /// Result of the current query, run once per query and data generation
+    /// so drawing a frame or reading the values does not run it again
+    pub(crate) fn current_result(&self) -> Ref<'_, crate::Result<QueryResult>> {
+        let generation = self.data.generation();
+        let current = matches!(
+            &*self.result.borrow(),
+            Some(cached) if cached.generation == generation && cached.query == self.query()
+        );
+        if !current {
+            let started = std::time::Instant::now();
+            let result = self.execute_current_query();
+            let elapsed = started.elapsed();
+            // Outputs cut short by an error would hide it from longer queries
+            if let Ok(result) = &result
+                && result.error().is_none()
+            {
+                // A full cache is reported by the background path, which can warn
+                let _ = self.prefixes.borrow_mut().store(
+                    self.query(),
+                    generation,
+                    result.values().to_vec(),
+                );
+            }
+            *self.result.borrow_mut() = Some(CachedResult {
+                query: self.query().to_string(),
+                generation,
+                result,
+                elapsed,
+            });
+        }
+        Ref::map(self.result.borrow(), |cached| {
+            &cached.as_ref().expect("cached above").result
+        })
+    }
+
+    /// Replaces the input with the next bookmarked query
+    pub fn next_bookmark(&mut self) {
+        if self.bookmarks.is_empty() {
+            self.state
+                .set_status_message("No bookmarks defined in config");
+            return;
+        }
+        let (name, query) = self.bookmarks[self.bookmark_index].clone();
+        self.set_input(query);
+        self.state.reset_scroll();
+        self.state.set_status_message(format!(
+            "Bookmark {}/{}: {}",
+            self.bookmark_index + 1,
+            self.bookmarks.len(),
+            name
+        ));
+        self.bookmark_index = (self.bookmark_index + 1) % self.bookmarks.len();
     }
 
     // 候補機能
@@ -129,18 +1573,144 @@ impl App {
             return None;
         }
 
-        let suggestions = self
-            .state
-            .query_history
-            .get_suggestions(&self.state.input, 1);
+        let suggestions =
+            self.state
+                .query_history
+                .get_suggestions_with(&self.state.input, 1, |query| {
+                    self.key_index.relevance(query)
+                });
         suggestions.first().map(|s| s.text.clone())
     }
 
     pub fn apply_suggestion(&mut self, suggestion: String) {
-        self.state.input = suggestion;
+        self.hold_query();
+        self.state.set_input(suggestion);
     }
 
     pub fn record_query(&mut self, query: String) {
+        self.note_result();
         self.state.query_history.record_query(query);
     }
+
+    /// Tells the history whether the result on screen failed, so a later
+    /// recorded query can be remembered as the fix of a failure
+    pub(super) fn note_result(&mut self) {
+        let generation = self.data.generation();
+        if let Some(cached) = self.result.get_mut()
+            && cached.generation == generation
+        {
+            let error = cached.result.as_ref().err().map(ToString::to_string);
+            self.state
+                .query_history
+                .note_result(&cached.query, error.as_deref());
+        }
+    }
+
+    /// An earlier fix of a failure like the current query's, if any
+    pub fn suggested_fix(&self) -> Option<Fix> {
+        let result = self.current_result();
+        let error = result.as_ref().err()?;
+        self.state
+            .query_history
+            .suggest_fix(self.query(), &error.to_string())
+            .cloned()
+    }
+
+    /// The current exploration as reproducible command lines, including the
+    /// sort and filter of the table view
+    pub fn command_export(&self) -> CommandExport {
+        let query = match self.table_export_query() {
+            Some(query) => query,
+            None if self.query().is_empty() => ".".to_string(),
+            None => self.query().to_string(),
+        };
+        CommandExport {
+            prelude: self.executor.prelude().map(str::to_string),
+            variables: self.executor.variables().to_vec(),
+            input: InputFlags::from_options(&self.load_options, self.source_path.as_deref()),
+            ..CommandExport::new(query, self.source_path.clone())
+        }
+    }
+
+    /// Writes the current query as a shell script to [`DEFAULT_EXPORT_PATH`]
+    pub fn export_command(&self) -> Effect {
+        let export = self.command_export();
+        Effect::WriteFile {
+            path: DEFAULT_EXPORT_PATH.to_string(),
+            contents: export.shell_script(),
+            message: format!(
+                "Exported `{}` to {}",
+                export.jq_command(),
+                DEFAULT_EXPORT_PATH
+            ),
+        }
+    }
+
+    /// Values currently displayed: the query results, or the whole document for an empty query
+    pub fn current_values(&self) -> crate::Result<Vec<serde_json::Value>> {
+        if self.query().is_empty() {
+            return Ok(vec![self.data.get().clone()]);
+        }
+        match &*self.current_result() {
+            Ok(result) => Ok(result.values().to_vec()),
+            // A timed-out query must not run again without its timeout
+            Err(AppError::QueryExecution(message)) => {
+                Err(AppError::QueryExecution(message.clone()))
+            }
+            // Errors cannot be cloned, so a failed query runs again for its own
+            Err(_) => self.execute_current_query().map(QueryResult::into_values),
+        }
+    }
+
+    /// The runtime error that stopped the current query after some outputs,
+    /// with how many it produced
+    pub fn runtime_error(&self) -> Option<(usize, String)> {
+        match &*self.current_result() {
+            Ok(result) => result
+                .error()
+                .map(|error| (result.values().len(), error.to_string())),
+            Err(_) => None,
+        }
+    }
+
+    /// Writes the current result as highlighted HTML to [`DEFAULT_HTML_EXPORT_PATH`]
+    pub fn export_html(&mut self) -> Option<Effect> {
+        let values = match self.current_values() {
+            Ok(values) => values,
+            Err(e) => {
+                self.state
+                    .set_status_message(format!("Nothing to export: {}", e));
+                return None;
+            }
+        };
+        let title = self.command_export().query;
+        Some(Effect::ExportHtml {
+            path: DEFAULT_HTML_EXPORT_PATH.to_string(),
+            title,
+            values,
+        })
+    }
+
+    /// Loads the next replayed query into the input line
+    pub fn advance_replay(&mut self) -> Option<Effect> {
+        let query = self
+            .replay
+            .as_mut()
+            .and_then(|r| r.next_query())?
+            .to_string();
+        self.set_input(query.clone());
+        self.state.reset_scroll();
+        self.record_query(query.clone());
+        Some(Effect::LogQuery(query))
+    }
+
+    /// (replayed, total) when a replay is active
+    pub fn replay_progress(&self) -> Option<(usize, usize)> {
+        self.replay.as_ref().map(Replay::progress)
+    }
+
+    /// Time until the next automatic replay step, if any
+    pub fn time_until_replay_step(&self) -> Option<Duration> {
+        self.replay.as_ref().and_then(Replay::time_until_next)
+    }
 }
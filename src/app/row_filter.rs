@@ -0,0 +1,82 @@
+use super::App;
+use crate::render::{RowCount, RowFilter};
+
+impl App {
+    /// Whether typing edits the row filter of the JSON view
+    pub fn row_filter_editing(&self) -> bool {
+        self.state.row_filter_editing
+    }
+
+    /// Text of the row filter, empty when every row is shown
+    pub fn row_filter(&self) -> &str {
+        &self.state.row_filter
+    }
+
+    /// Starts or stops editing the row filter, keeping what was typed
+    ///
+    /// The filter narrows the rows already computed, so typing never runs
+    /// the query again.
+    pub fn toggle_row_filter(&mut self) {
+        self.state.row_filter_editing = !self.state.row_filter_editing;
+    }
+
+    pub fn row_filter_push(&mut self, c: char) {
+        self.state.row_filter.push(c);
+        self.state.reset_scroll();
+    }
+
+    pub fn row_filter_pop(&mut self) {
+        self.state.row_filter.pop();
+        self.state.reset_scroll();
+    }
+
+    /// Clears the row filter and stops editing it
+    pub fn clear_row_filter(&mut self) {
+        self.state.row_filter.clear();
+        self.state.row_filter_editing = false;
+        self.state.reset_scroll();
+    }
+
+    /// Why the row filter's regular expression is invalid, if it is
+    pub fn row_filter_error(&self) -> Option<String> {
+        RowFilter::parse(&self.state.row_filter).err()
+    }
+
+    /// Rows shown out of all rows of the current result while the row
+    /// filter is in effect
+    pub fn row_count(&self) -> Option<RowCount> {
+        drop(self.document());
+        self.document
+            .borrow()
+            .as_ref()
+            .and_then(|cached| cached.row_count)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_row_filter_narrows_without_changing_the_query() {
+        let mut app = App::new(json!({"logs": [
+            {"level": "info", "msg": "started"},
+            {"level": "error", "msg": "disk full"},
+            {"level": "warn", "msg": "slow disk"}
+        ]}));
+        app.set_input(".logs");
+        app.toggle_row_filter();
+        "disk".chars().for_each(|c| app.row_filter_push(c));
+        assert_eq!(app.query(), ".logs");
+        assert_eq!(app.row_count(), Some(RowCount { shown: 2, total: 3 }));
+        let text = app.document().unwrap().to_text();
+        assert!(text.contains("disk full") && !text.contains("started"));
+
+        app.clear_row_filter();
+        "/(/".chars().for_each(|c| app.row_filter_push(c));
+        assert!(app.row_filter_error().is_some());
+        // An invalid pattern keeps every row
+        assert_eq!(app.row_count(), None);
+    }
+}
@@ -0,0 +1,92 @@
+use super::App;
+use crate::query::sql_to_jq;
+
+impl App {
+    /// SQL typed in the SQL mode while it is on
+    pub fn sql(&self) -> Option<&str> {
+        self.state.sql.as_deref()
+    }
+
+    /// Starts typing SQL instead of jq, or goes back to jq
+    ///
+    /// Each edit that translates replaces the query with its jq, so the
+    /// result follows the SQL and the query line shows how it is written in
+    /// jq. Leaving the mode keeps that query.
+    pub fn toggle_sql(&mut self) {
+        self.state.sql = match self.state.sql {
+            Some(_) => None,
+            None => Some(String::new()),
+        };
+    }
+
+    pub fn sql_push(&mut self, c: char) {
+        if let Some(sql) = &mut self.state.sql {
+            sql.push(c);
+            self.translate_sql();
+        }
+    }
+
+    pub fn sql_pop(&mut self) {
+        if let Some(sql) = &mut self.state.sql {
+            sql.pop();
+            self.translate_sql();
+        }
+    }
+
+    /// Why the SQL does not translate, while it is being typed
+    pub fn sql_error(&self) -> Option<String> {
+        let sql = self.state.sql.as_deref()?;
+        if sql.trim().is_empty() {
+            return None;
+        }
+        sql_to_jq(sql).err()
+    }
+
+    fn translate_sql(&mut self) {
+        let Some(Ok(jq)) = self.state.sql.as_deref().map(sql_to_jq) else {
+            return;
+        };
+        if jq != self.state.input {
+            self.set_input(jq);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_sql_mode_translates_as_typed() {
+        let mut app = App::new(json!({"users": [
+            {"name": "ann", "age": 31},
+            {"name": "bob", "age": 22}
+        ]}));
+        app.set_input(".users");
+        app.toggle_sql();
+        "SELECT name FROM users WHERE age >"
+            .chars()
+            .for_each(|c| app.sql_push(c));
+        assert_eq!(app.sql_error().as_deref(), Some("expected a column"));
+        // The query stays at the last SQL that translated
+        assert_eq!(
+            app.input(),
+            "[.users[] | select(.age)] | map({name: .name})"
+        );
+
+        " 30".chars().for_each(|c| app.sql_push(c));
+        assert!(app.sql_error().is_none());
+        assert_eq!(
+            app.current_values().unwrap(),
+            vec![json!([{"name": "ann"}])]
+        );
+
+        app.toggle_sql();
+        assert!(app.sql().is_none());
+        assert_eq!(
+            app.input(),
+            "[.users[] | select(.age > 30)] | map({name: .name})"
+        );
+    }
+}
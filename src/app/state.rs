@@ -1,13 +1,174 @@
 use super::error::AppError;
-use crate::history::QueryHistory;
+use super::warning::AppWarning;
+use crate::history::{QueryHistory, UsageStats};
+use crate::query::literals::open_string_start;
+use crate::query::{JoinSpec, ObjectArray, QueryResult};
+use crate::render::{ChartKind, Counts, RenderTree, TableQuery};
+use crate::source::browser::DirEntry;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Bracket pairs closed automatically while typing
+const PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')];
+
+/// Query state kept by each tab
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TabState {
+    pub input: String,
+    pub cursor: usize,
+    pub scroll_offset: usize,
+    /// Index of the loaded file the tab queries
+    pub source: usize,
+    /// View the tab's result is shown in; all off is the JSON tree
+    pub table_mode: bool,
+    pub chart: Option<ChartKind>,
+    pub size_view: bool,
+    /// Selected column of the table view
+    pub table_column: usize,
+    /// Index of the tab's display filter among the named ones
+    pub display_filter: Option<usize>,
+    pub row_filter: String,
+    pub table_query: TableQuery,
+}
+
+/// Search state of the builtin manual
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ManualState {
+    pub search: String,
+    /// Index into the entries matching `search`
+    pub selected: usize,
+}
+
+/// Value open in the value inspector
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueInspectorState {
+    /// Path of the string in the result, e.g. `.files[0].data`
+    pub path: String,
+    /// The string, base64-decoded
+    pub bytes: Vec<u8>,
+    /// First preview line shown
+    pub scroll: usize,
+}
+
+/// State of the panel counting the values of a field
+#[derive(Debug, Clone, PartialEq)]
+pub struct FrequencyState {
+    /// The query computing the counts from the input, inserted with Enter
+    pub query: String,
+    /// Count of each value of the field, largest first
+    pub counts: Counts,
+}
+
+/// State of the dialog building a join expression
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct JoinState {
+    pub spec: JoinSpec,
+    /// Part being edited: left array, left key, right array, right key
+    pub focus: usize,
+    /// Arrays of objects in the document, offered for the parts
+    pub arrays: Vec<ObjectArray>,
+}
+
+/// State of the browser picking an input file
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BrowserState {
+    pub dir: PathBuf,
+    /// Entries of `dir`, unfiltered
+    pub entries: Vec<DirEntry>,
+    pub search: String,
+    /// Index into the entries matching `search`
+    pub selected: usize,
+}
+
+/// Snapshot of a result pinned for comparison
+#[derive(Debug)]
+pub struct PinnedResult {
+    pub query: String,
+    pub result: QueryResult,
+    pub document: RenderTree,
+    /// Own scroll position, used while scrolling is not synchronized
+    pub scroll_offset: usize,
+}
 
 #[derive(Debug, Default)]
 pub struct AppState {
     pub input: String,
+    /// Byte offset of the cursor in `input`
+    pub cursor: usize,
     pub exit: bool,
     pub last_error: Option<AppError>,
     pub scroll_offset: usize,
     pub query_history: QueryHistory,
+    pub warnings: Vec<AppWarning>,
+    /// Transient informational message shown in the status area
+    pub status_message: Option<String>,
+    /// Saved state of every tab; the active tab's entry is refreshed on switch
+    /// while the fields it mirrors hold its live values
+    pub tabs: Vec<TabState>,
+    pub active_tab: usize,
+    /// Index of the loaded file the active tab queries
+    pub source: usize,
+    /// Frozen result shown in a read-only pane next to the live one
+    pub pinned: Option<PinnedResult>,
+    /// Keep the pinned pane aligned with the live pane by line
+    pub sync_scroll: bool,
+    /// Index of the numeric literal being adjusted while the slider mode is on
+    pub slider: Option<usize>,
+    /// Selected entry of the completion popup
+    pub picker_index: usize,
+    /// Documentation popup for the builtin under the cursor is open
+    pub show_doc: bool,
+    /// The builtin manual replaces the result view while this is set
+    pub manual: Option<ManualState>,
+    /// The file browser replaces the result view while this is set
+    pub browser: Option<BrowserState>,
+    /// Selected entry of the cache inspector, which replaces the result view
+    /// while this is set
+    pub cache_inspector: Option<usize>,
+    /// Decoded base64 value shown in a popup over the result while this is set
+    pub value_inspector: Option<ValueInspectorState>,
+    /// Value counts of a field shown in a panel over the result while this is set
+    pub frequency: Option<FrequencyState>,
+    /// Dialog building a join of two arrays while this is set
+    pub join: Option<JoinState>,
+    /// SQL typed in the SQL mode while this is set, see [`crate::query::SqlQuery`]
+    pub sql: Option<String>,
+    /// Summary of the usage log, which replaces the result view while this
+    /// is set, and its first line shown
+    pub usage_stats: Option<(UsageStats, usize)>,
+    /// Arrays and objects that fit the view's width are shown on one line
+    pub compact_layout: bool,
+    /// Results that are lists of objects are shown as a table
+    pub table_mode: bool,
+    /// Selected entry of the column picker while it is open
+    pub column_picker: Option<usize>,
+    /// Sort and quick filter applied to the table view
+    pub table_query: TableQuery,
+    /// Selected column among the visible columns of the table view
+    pub table_column: usize,
+    /// Keys go to the table's quick filter instead of the query
+    pub table_filter_editing: bool,
+    /// Index of the named display filter applied to results for viewing
+    pub display_filter: Option<usize>,
+    /// Text narrowing the rows of the JSON view, see [`crate::render::RowFilter`]
+    pub row_filter: String,
+    /// Keys go to the row filter instead of the query
+    pub row_filter_editing: bool,
+    /// Numeric results are drawn as this chart
+    pub chart: Option<ChartKind>,
+    /// The result view is replaced by the sizes of the document's subtrees
+    pub size_view: bool,
+    /// Reload was tried on stdin and offered to save a snapshot; the next try does
+    pub stdin_snapshot_offered: bool,
+    /// The workspace's per-file summary is shown instead of one file's result
+    pub workspace_view: bool,
+    /// File selected in the workspace summary
+    pub workspace_file: usize,
+    /// Query whose result stays shown while edits wait for the execution
+    /// policy; `None` when the result follows the input
+    pub executed: Option<String>,
+    /// When the input was last typed into while `executed` is held
+    pub edited_at: Option<Instant>,
 }
 
 impl AppState {
@@ -17,14 +178,71 @@ impl AppState {
 
     pub fn clear_input(&mut self) {
         self.input.clear();
+        self.cursor = 0;
+    }
+
+    /// Replaces the input and moves the cursor to its end
+    pub fn set_input(&mut self, input: impl Into<String>) {
+        self.input = input.into();
+        self.cursor = self.input.len();
     }
 
+    /// Inserts `c` at the cursor
     pub fn push_char(&mut self, c: char) {
-        self.input.push(c);
+        self.input.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
     }
 
+    /// Deletes the character before the cursor
     pub fn pop_char(&mut self) {
-        self.input.pop();
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+            self.input.remove(self.cursor);
+        }
+    }
+
+    /// Types `c`, closing brackets and quotes outside string literals and
+    /// stepping over a closer that is already under the cursor
+    pub fn insert_with_pairs(&mut self, c: char) {
+        let in_string = open_string_start(&self.input[..self.cursor]).is_some();
+        let next = self.input[self.cursor..].chars().next();
+        let is_closer = PAIRS.iter().any(|&(_, close)| close == c);
+
+        if is_closer && next == Some(c) && (c != '"' || in_string) {
+            self.cursor += c.len_utf8();
+            return;
+        }
+        self.push_char(c);
+        if in_string {
+            return;
+        }
+        if let Some(&(_, close)) = PAIRS.iter().find(|&&(open, _)| open == c) {
+            self.input.insert(self.cursor, close);
+        }
+    }
+
+    /// Deletes the character before the cursor, and its closer if the pair is empty
+    pub fn delete_with_pairs(&mut self) {
+        let before = self.input[..self.cursor].chars().next_back();
+        let after = self.input[self.cursor..].chars().next();
+        if let (Some(open), Some(close)) = (before, after)
+            && PAIRS.contains(&(open, close))
+        {
+            self.input.remove(self.cursor);
+        }
+        self.pop_char();
+    }
+
+    pub fn move_cursor_left(&mut self) {
+        if let Some(c) = self.input[..self.cursor].chars().next_back() {
+            self.cursor -= c.len_utf8();
+        }
+    }
+
+    pub fn move_cursor_right(&mut self) {
+        if let Some(c) = self.input[self.cursor..].chars().next() {
+            self.cursor += c.len_utf8();
+        }
     }
 
     pub fn scroll_up(&mut self) {
@@ -42,6 +260,144 @@ impl AppState {
     pub fn reset_scroll(&mut self) {
         self.scroll_offset = 0;
     }
+
+    /// Adds a warning unless the same one is already pending; slow queries
+    /// are the same when their query is, however long each run took, and a
+    /// new count of stuck queries replaces the pending one
+    pub fn push_warning(&mut self, warning: AppWarning) {
+        if let AppWarning::StuckQueries(_) = warning {
+            self.warnings
+                .retain(|pending| !matches!(pending, AppWarning::StuckQueries(_)));
+        }
+        let pending = self
+            .warnings
+            .iter()
+            .any(|pending| match (pending, &warning) {
+                (AppWarning::SlowQuery { query, .. }, AppWarning::SlowQuery { query: new, .. }) => {
+                    query == new
+                }
+                (pending, warning) => pending == warning,
+            });
+        if !pending {
+            self.warnings.push(warning);
+        }
+    }
+
+    pub fn set_status_message(&mut self, message: impl Into<String>) {
+        self.status_message = Some(message.into());
+    }
+
+    /// Removes the most recent warning
+    pub fn dismiss_warning(&mut self) {
+        self.warnings.pop();
+    }
+
+    pub fn tab_count(&self) -> usize {
+        self.tabs.len().max(1)
+    }
+
+    /// Opens an empty tab on the same file after the existing ones and switches to it
+    pub fn new_tab(&mut self) {
+        self.save_active_tab();
+        self.tabs.push(TabState {
+            source: self.source,
+            ..TabState::default()
+        });
+        self.load_tab(self.tabs.len() - 1);
+    }
+
+    /// Opens an empty tab on another file after the existing ones, staying on the active tab
+    pub fn open_tab(&mut self, source: usize) {
+        self.save_active_tab();
+        self.tabs.push(TabState {
+            source,
+            ..TabState::default()
+        });
+    }
+
+    /// Switches to the next (or previous) tab, wrapping around
+    pub fn switch_tab(&mut self, forward: bool) {
+        let count = self.tab_count();
+        if count < 2 {
+            return;
+        }
+        self.save_active_tab();
+        let target = if forward {
+            (self.active_tab + 1) % count
+        } else {
+            (self.active_tab + count - 1) % count
+        };
+        self.load_tab(target);
+    }
+
+    /// Queries of all tabs, with the live input for the active one
+    pub fn tab_queries(&self) -> Vec<&str> {
+        if self.tabs.is_empty() {
+            return vec![&self.input];
+        }
+        self.tabs
+            .iter()
+            .enumerate()
+            .map(|(i, tab)| {
+                if i == self.active_tab {
+                    self.input.as_str()
+                } else {
+                    tab.input.as_str()
+                }
+            })
+            .collect()
+    }
+
+    /// Source of every tab, in tab order
+    pub fn tab_sources(&self) -> Vec<usize> {
+        if self.tabs.is_empty() {
+            return vec![self.source];
+        }
+        self.tabs.iter().map(|tab| tab.source).collect()
+    }
+
+    fn save_active_tab(&mut self) {
+        let tab = TabState {
+            input: self.input.clone(),
+            cursor: self.cursor,
+            scroll_offset: self.scroll_offset,
+            source: self.source,
+            table_mode: self.table_mode,
+            chart: self.chart,
+            size_view: self.size_view,
+            table_column: self.table_column,
+            display_filter: self.display_filter,
+            row_filter: self.row_filter.clone(),
+            table_query: self.table_query.clone(),
+        };
+        match self.tabs.get_mut(self.active_tab) {
+            Some(slot) => *slot = tab,
+            None => self.tabs.push(tab),
+        }
+    }
+
+    fn load_tab(&mut self, index: usize) {
+        let tab = self.tabs[index].clone();
+        self.input = tab.input;
+        self.cursor = tab.cursor;
+        self.scroll_offset = tab.scroll_offset;
+        self.source = tab.source;
+        self.table_mode = tab.table_mode;
+        self.chart = tab.chart;
+        self.size_view = tab.size_view;
+        self.table_column = tab.table_column;
+        self.display_filter = tab.display_filter;
+        self.row_filter = tab.row_filter;
+        self.table_query = tab.table_query;
+        self.active_tab = index;
+        // Keys go back to the query, and the column picker belonged to the other tab
+        self.row_filter_editing = false;
+        self.table_filter_editing = false;
+        self.column_picker = None;
+        // Another tab's query has not been held back
+        self.executed = None;
+        self.edited_at = None;
+    }
 }
 
 #[cfg(test)]
@@ -64,6 +420,57 @@ mod tests {
         assert_eq!(state.input, "");
     }
 
+    #[test]
+    fn test_cursor_editing() {
+        let mut state = AppState::default();
+        state.set_input(".ac");
+        state.move_cursor_left();
+        state.push_char('b');
+        assert_eq!(state.input, ".abc");
+        assert_eq!(state.cursor, 3);
+
+        state.move_cursor_right();
+        state.move_cursor_right();
+        state.pop_char();
+        assert_eq!(state.input, ".ab");
+    }
+
+    #[test]
+    fn test_auto_pairs() {
+        let mut state = AppState::default();
+        for c in "map(".chars() {
+            state.insert_with_pairs(c);
+        }
+        assert_eq!(state.input, "map()");
+        assert_eq!(state.cursor, 4);
+
+        // Typing the closer steps over the inserted one
+        for c in ".a)".chars() {
+            state.insert_with_pairs(c);
+        }
+        assert_eq!(state.input, "map(.a)");
+        assert_eq!(state.cursor, state.input.len());
+
+        // Brackets typed inside a string are left alone
+        state.set_input("");
+        for c in "\"[\"".chars() {
+            state.insert_with_pairs(c);
+        }
+        assert_eq!(state.input, "\"[\"");
+    }
+
+    #[test]
+    fn test_delete_empty_pair() {
+        let mut state = AppState::default();
+        state.insert_with_pairs('[');
+        state.delete_with_pairs();
+        assert_eq!(state.input, "");
+
+        state.set_input("[1]");
+        state.delete_with_pairs();
+        assert_eq!(state.input, "[1");
+    }
+
     #[test]
     fn test_state_exit_flag() {
         let mut state = AppState::default();
@@ -76,6 +483,123 @@ mod tests {
         assert!(!state.exit);
     }
 
+    #[test]
+    fn test_warning_operations() {
+        let mut state = AppState::default();
+        let warning = AppWarning::CacheDisabled("test".to_string());
+
+        state.push_warning(warning.clone());
+        state.push_warning(warning.clone());
+        assert_eq!(state.warnings, vec![warning.clone()]);
+
+        // Another run of a slow query does not add a second warning
+        let slow = |millis| AppWarning::SlowQuery {
+            query: "..".to_string(),
+            elapsed: std::time::Duration::from_millis(millis),
+        };
+        state.push_warning(slow(600));
+        state.push_warning(slow(900));
+        assert_eq!(state.warnings, vec![warning.clone(), slow(600)]);
+
+        state.push_warning(AppWarning::StuckQueries(1));
+        state.push_warning(AppWarning::StuckQueries(2));
+        assert_eq!(
+            state.warnings,
+            vec![warning, slow(600), AppWarning::StuckQueries(2)]
+        );
+        state.dismiss_warning();
+        state.dismiss_warning();
+
+        state.dismiss_warning();
+        assert!(state.warnings.is_empty());
+
+        // Dismissing with nothing pending is a no-op
+        state.dismiss_warning();
+        assert!(state.warnings.is_empty());
+    }
+
+    #[test]
+    fn test_tab_operations() {
+        let mut state = AppState::default();
+        assert_eq!(state.tab_count(), 1);
+        state.input = ".a".to_string();
+        state.scroll_offset = 3;
+
+        state.new_tab();
+        assert_eq!(state.tab_count(), 2);
+        assert_eq!(state.active_tab, 1);
+        assert_eq!(state.input, "");
+        assert_eq!(state.scroll_offset, 0);
+        state.input = ".b".to_string();
+        assert_eq!(state.tab_queries(), vec![".a", ".b"]);
+
+        // Switching restores each tab's query and scroll position
+        state.switch_tab(true);
+        assert_eq!(state.active_tab, 0);
+        assert_eq!(state.input, ".a");
+        assert_eq!(state.scroll_offset, 3);
+
+        state.switch_tab(false);
+        assert_eq!(state.input, ".b");
+    }
+
+    #[test]
+    fn test_tab_keeps_view_and_filters() {
+        let mut state = AppState {
+            table_mode: true,
+            display_filter: Some(1),
+            row_filter: "error".to_string(),
+            ..Default::default()
+        };
+        state.table_query.filter = "web".to_string();
+        state.row_filter_editing = true;
+
+        // A new tab starts in the JSON view without filters
+        state.new_tab();
+        assert!(!state.table_mode && !state.row_filter_editing);
+        assert_eq!(state.display_filter, None);
+        assert_eq!(state.row_filter, "");
+        state.size_view = true;
+
+        state.switch_tab(true);
+        assert!(state.table_mode && !state.size_view);
+        assert_eq!(state.display_filter, Some(1));
+        assert_eq!(state.row_filter, "error");
+        assert_eq!(state.table_query.filter, "web");
+
+        state.switch_tab(true);
+        assert!(!state.table_mode && state.size_view);
+        assert!(state.table_query.is_empty());
+    }
+
+    #[test]
+    fn test_open_tab_on_another_source() {
+        let mut state = AppState {
+            input: ".a".to_string(),
+            ..Default::default()
+        };
+        state.open_tab(1);
+        // The active tab stays, and new tabs follow the active tab's file
+        assert_eq!((state.active_tab, state.input.as_str()), (0, ".a"));
+        state.new_tab();
+        assert_eq!(state.tab_sources(), vec![0, 1, 0]);
+
+        state.switch_tab(false);
+        assert_eq!(state.source, 1);
+        assert_eq!(state.input, "");
+    }
+
+    #[test]
+    fn test_switch_tab_without_tabs_is_noop() {
+        let mut state = AppState {
+            input: ".a".to_string(),
+            ..Default::default()
+        };
+        state.switch_tab(true);
+        assert_eq!(state.input, ".a");
+        assert_eq!(state.tab_queries(), vec![".a"]);
+    }
+
     #[test]
     fn test_scroll_operations() {
         let mut state = AppState::default();
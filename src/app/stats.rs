@@ -0,0 +1,98 @@
+use super::{App, AppWarning};
+use crate::history::UsageStats;
+
+impl App {
+    /// Summary of the usage log and its first line shown, while it is open
+    pub fn usage_stats(&self) -> Option<(&UsageStats, usize)> {
+        let (stats, scroll) = self.state.usage_stats.as_ref()?;
+        Some((stats, *scroll))
+    }
+
+    /// Summarizes the usage log in place of the result view, or closes the
+    /// summary
+    pub fn toggle_usage_stats(&mut self) {
+        if self.state.usage_stats.take().is_some() {
+            return;
+        }
+        let Some(log) = &self.usage_log else {
+            self.state
+                .set_status_message("No usage log; it is kept in the user config directory");
+            return;
+        };
+        match log.read() {
+            Ok(records) => {
+                self.state.usage_stats = Some((UsageStats::from_records(&records), 0));
+            }
+            Err(e) => self.state.set_status_message(format!(
+                "Could not read {}: {}",
+                log.path().display(),
+                e
+            )),
+        }
+    }
+
+    pub fn scroll_usage_stats(&mut self, down: bool) {
+        if let Some((stats, scroll)) = &mut self.state.usage_stats {
+            *scroll = if down {
+                (*scroll + 1).min(stats.to_lines().len().saturating_sub(1))
+            } else {
+                scroll.saturating_sub(1)
+            };
+        }
+    }
+
+    /// Appends `query` to the usage log with how long it took, when its
+    /// result is the one shown
+    pub(super) fn log_usage(&mut self, query: &str) {
+        let Some(log) = &self.usage_log else {
+            return;
+        };
+        let elapsed = match &*self.result.borrow() {
+            Some(cached) if cached.query == query => Some(cached.elapsed),
+            _ => None,
+        };
+        if let Err(e) = log.record(query, self.source_path.as_deref(), elapsed) {
+            self.state.push_warning(AppWarning::LogWriteFailed(format!(
+                "{}: {}",
+                log.path().display(),
+                e
+            )));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::Effect;
+    use crate::history::UsageLog;
+    use serde_json::json;
+
+    #[test]
+    fn test_usage_stats_view() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_app_usage_stats_{}.jsonl", std::process::id()));
+        std::fs::remove_file(&path).ok();
+        let mut app = App::new(json!({"items": [3, 1, 2]}));
+        app.toggle_usage_stats();
+        assert!(app.usage_stats().is_none());
+
+        app.set_usage_log(UsageLog::new(path.clone()));
+        app.set_input(".items | sort");
+        app.current_values().unwrap();
+        app.run_effect(Effect::LogQuery(".items | sort".to_string()));
+        app.run_effect(Effect::LogQuery(".items | sort".to_string()));
+
+        app.toggle_usage_stats();
+        let (stats, scroll) = app.usage_stats().unwrap();
+        let lines = stats.to_lines();
+        assert_eq!(scroll, 0);
+        assert!(lines[0].starts_with("2 queries run, 1 different"));
+        assert!(lines.iter().any(|line| line.ends_with("2  sort")));
+        app.scroll_usage_stats(true);
+        assert_eq!(app.usage_stats().unwrap().1, 1);
+        app.toggle_usage_stats();
+        assert!(app.usage_stats().is_none());
+        std::fs::remove_file(&path).ok();
+    }
+}
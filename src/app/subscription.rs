@@ -0,0 +1,196 @@
+//! Notifications of state changes
+//!
+//! Hosts embedding rjq register callbacks with [`App::subscribe`] and hear
+//! about executed queries, changed data and errors as they happen, instead of
+//! comparing state after every update.
+
+use super::{App, AppError};
+use serde_json::Value;
+use std::fmt;
+
+/// Event delivered to subscribers
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum AppEvent {
+    /// The current query ran against the data at `generation`
+    QueryExecuted {
+        query: String,
+        generation: u64,
+        values: Vec<Value>,
+    },
+    /// The document was reloaded, replaced, merged into or appended to
+    DataReloaded {
+        /// File or URL of the active tab, `None` for stdin
+        source: Option<String>,
+        generation: u64,
+    },
+    /// A query failed or the input could not be read again
+    ErrorOccurred {
+        /// The failing query, `None` when the error is not about one
+        query: Option<String>,
+        /// [`AppError::code`] of the error
+        code: &'static str,
+        message: String,
+    },
+}
+
+impl AppEvent {
+    fn error(query: Option<&str>, error: &AppError) -> Self {
+        AppEvent::ErrorOccurred {
+            query: query.map(str::to_string),
+            code: error.code(),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// Handle returned by [`App::subscribe`], used to unsubscribe again
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SubscriptionId(u64);
+
+type Callback = Box<dyn FnMut(&AppEvent) + Send>;
+
+/// Registered callbacks
+#[derive(Default)]
+pub(super) struct Subscribers {
+    next_id: u64,
+    callbacks: Vec<(SubscriptionId, Callback)>,
+}
+
+impl fmt::Debug for Subscribers {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Subscribers")
+            .field("count", &self.callbacks.len())
+            .finish()
+    }
+}
+
+impl App {
+    /// Calls `callback` with every [`AppEvent`] from now on
+    pub fn subscribe(
+        &mut self,
+        callback: impl FnMut(&AppEvent) + Send + 'static,
+    ) -> SubscriptionId {
+        let subscribers = self.subscribers.get_mut();
+        let id = SubscriptionId(subscribers.next_id);
+        subscribers.next_id += 1;
+        subscribers.callbacks.push((id, Box::new(callback)));
+        id
+    }
+
+    /// Stops calling the callback registered as `id`; `false` if it was not
+    pub fn unsubscribe(&mut self, id: SubscriptionId) -> bool {
+        let callbacks = &mut self.subscribers.get_mut().callbacks;
+        let before = callbacks.len();
+        callbacks.retain(|(subscribed, _)| *subscribed != id);
+        callbacks.len() != before
+    }
+
+    /// Builds the event and hands it to every subscriber; nothing is built
+    /// while there are none
+    pub(super) fn emit(&self, event: impl FnOnce() -> AppEvent) {
+        let mut subscribers = self.subscribers.borrow_mut();
+        if subscribers.callbacks.is_empty() {
+            return;
+        }
+        let event = event();
+        for (_, callback) in &mut subscribers.callbacks {
+            callback(&event);
+        }
+    }
+
+    pub(super) fn emit_data_reloaded(&self) {
+        self.emit(|| AppEvent::DataReloaded {
+            source: self.source_path.clone(),
+            generation: self.data.generation(),
+        });
+    }
+
+    pub(super) fn emit_query_result(&self, result: &crate::Result<crate::QueryResult>) {
+        let query = self.query();
+        match result {
+            Ok(result) => self.emit(|| AppEvent::QueryExecuted {
+                query: query.to_string(),
+                generation: self.data.generation(),
+                values: result.values().to_vec(),
+            }),
+            // The whole document is shown for an empty query, so that is no error
+            Err(_) if query.is_empty() => {}
+            Err(e) => self.emit(|| AppEvent::error(Some(query), e)),
+        }
+    }
+
+    pub(super) fn emit_error(&self, error: &AppError) {
+        self.emit(|| AppEvent::error(None, error));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::{Arc, Mutex};
+
+    fn record(app: &mut App) -> (SubscriptionId, Arc<Mutex<Vec<AppEvent>>>) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&events);
+        let id = app.subscribe(move |event| sink.lock().unwrap().push(event.clone()));
+        (id, events)
+    }
+
+    #[test]
+    fn test_events_reach_subscribers() {
+        let mut app = App::new(json!({"a": 1}));
+        let (id, events) = record(&mut app);
+
+        app.set_input(".a");
+        app.document();
+        // The cached tree is reused, so drawing again emits nothing
+        app.document();
+        app.replace_data(json!({"a": 2}));
+        app.set_input(".a |");
+        app.document();
+
+        let events = events.lock().unwrap();
+        assert_eq!(events.len(), 3, "{:?}", events);
+        assert_eq!(
+            events[0],
+            AppEvent::QueryExecuted {
+                query: ".a".to_string(),
+                generation: 0,
+                values: vec![json!(1)],
+            }
+        );
+        assert_eq!(
+            events[1],
+            AppEvent::DataReloaded {
+                source: None,
+                generation: 1,
+            }
+        );
+        assert!(matches!(
+            &events[2],
+            AppEvent::ErrorOccurred { query: Some(query), code: "query_compile", .. } if query == ".a |"
+        ));
+        drop(events);
+
+        assert!(app.unsubscribe(id));
+        assert!(!app.unsubscribe(id));
+    }
+
+    #[test]
+    fn test_failed_reload_is_reported() {
+        let mut app = App::new(json!(null));
+        app.set_source_path(Some("/nonexistent/rjq/data.json".to_string()));
+        let (_, events) = record(&mut app);
+        app.reload();
+        assert!(matches!(
+            events.lock().unwrap().as_slice(),
+            [AppEvent::ErrorOccurred {
+                query: None,
+                code: "file_not_found",
+                ..
+            }]
+        ));
+    }
+}
@@ -0,0 +1,477 @@
+use super::{App, AppError, Effect};
+use crate::query::{JsonData, PrefixCache, QueryExecutor, QueryRun};
+use crate::render::{RowRule, RuleStyle, Table, TableLayout, TableQuery};
+use crate::session::SessionStore;
+use serde_json::Value;
+use std::time::Duration;
+
+/// Longest the row rules may run over a table before its rows are left unstyled
+const ROW_RULES_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Row styles of a table and the rows they were computed for
+#[derive(Debug)]
+pub(super) struct CachedRowStyles {
+    query: String,
+    generation: u64,
+    /// Sort and filter applied to the rows
+    table_jq: Option<String>,
+    styles: Vec<Option<RuleStyle>>,
+}
+
+impl App {
+    /// Where table layouts are remembered between sessions
+    pub fn set_session_store(&mut self, store: SessionStore) {
+        self.session_store = store;
+    }
+
+    pub fn table_mode(&self) -> bool {
+        self.state.table_mode
+    }
+
+    /// The current result as a table with its layout, when it is a list of objects
+    ///
+    /// A layout saved for data of the same shape is reused; otherwise every
+    /// column is shown. The rows are sorted and filtered by the
+    /// [`table_query`](Self::table_query).
+    pub fn current_table(&self) -> Option<(Table, TableLayout)> {
+        let values = self.current_values().ok()?;
+        let mut table = Table::from_values(&values)?;
+        let layout = match self.session_store.table_layout(&table.fingerprint()) {
+            Some(saved) => saved.clone().reconcile(&table),
+            None => table.default_layout(),
+        };
+        if let Some(jq) = self.table_jq(&layout) {
+            let rows = Value::Array(table.rows.iter().cloned().map(Value::Object).collect());
+            // A query that fails leaves the rows unsorted rather than hiding the table
+            if let Ok(output) = self.executor.execute(&rows, &jq)
+                && let [Value::Array(items)] = output.as_slice()
+            {
+                table.rows = items
+                    .iter()
+                    .filter_map(|v| v.as_object().cloned())
+                    .collect();
+            }
+        }
+        Some((table, layout))
+    }
+
+    /// Rules styling whole table rows for which a jq predicate holds
+    ///
+    /// Each predicate must be a jq program of its own. Together they become
+    /// one program run over the table's rows; a predicate that fails or
+    /// produces nothing counts as false for that row.
+    pub fn set_row_rules(&mut self, rules: &[RowRule]) -> crate::Result<()> {
+        *self.row_style_cache.get_mut() = None;
+        if rules.is_empty() {
+            self.row_rules = None;
+            return Ok(());
+        }
+        for rule in rules {
+            // Compiled over no inputs, so nothing runs
+            self.executor
+                .execute_inputs(&[], &rule.when)
+                .map_err(|e| match e {
+                    AppError::QueryCompile { message, .. } => AppError::Config {
+                        path: "[[row_rules]]".to_string(),
+                        message: format!("`{}`: {}", rule.when, message),
+                    },
+                    e => e,
+                })?;
+        }
+        let branches: Vec<String> = rules
+            .iter()
+            .enumerate()
+            .map(|(i, rule)| {
+                format!(
+                    "(first(try ({}) catch false) // false) then {}",
+                    rule.when, i
+                )
+            })
+            .collect();
+        let jq = format!("map(if {} else null end)", branches.join(" elif "));
+        self.row_rules = Some((jq, rules.iter().map(RowRule::style).collect()));
+        Ok(())
+    }
+
+    /// The style of each of the table's rows from the first row rule it satisfies
+    ///
+    /// The rules run once per table, on a worker given up after
+    /// [`ROW_RULES_TIMEOUT`], which leaves the rows unstyled.
+    pub fn row_styles(&self, table: &Table, layout: &TableLayout) -> Vec<Option<RuleStyle>> {
+        let Some((jq, styles)) = &self.row_rules else {
+            return vec![None; table.rows.len()];
+        };
+        let generation = self.data.generation();
+        let table_jq = self.table_jq(layout);
+        if let Some(cached) = &*self.row_style_cache.borrow()
+            && cached.query == self.query()
+            && cached.generation == generation
+            && cached.table_jq == table_jq
+            && cached.styles.len() == table.rows.len()
+        {
+            return cached.styles.clone();
+        }
+
+        let rows = JsonData::new(Value::Array(
+            table.rows.iter().cloned().map(Value::Object).collect(),
+        ));
+        let run = QueryRun::spawn(&rows, &self.executor, &PrefixCache::default(), jq);
+        let computed = match run.wait(ROW_RULES_TIMEOUT) {
+            Some(Ok(result)) => match result.values() {
+                [Value::Array(indices)] => indices
+                    .iter()
+                    .map(|i| i.as_u64().and_then(|i| styles.get(i as usize)).copied())
+                    .collect(),
+                _ => vec![None; table.rows.len()],
+            },
+            _ => vec![None; table.rows.len()],
+        };
+        *self.row_style_cache.borrow_mut() = Some(CachedRowStyles {
+            query: self.query().to_string(),
+            generation,
+            table_jq,
+            styles: computed.clone(),
+        });
+        computed
+    }
+
+    pub fn table_query(&self) -> &TableQuery {
+        &self.state.table_query
+    }
+
+    /// jq applied to the rows for the current sort and filter
+    fn table_jq(&self, layout: &TableLayout) -> Option<String> {
+        let columns: Vec<&str> = layout.visible().map(|c| c.key.as_str()).collect();
+        self.state.table_query.to_jq(&columns)
+    }
+
+    /// The query with the table's sort and filter appended, as one jq program
+    ///
+    /// `None` outside the table view or when the rows are not sorted or filtered.
+    pub fn table_export_query(&self) -> Option<String> {
+        if !self.state.table_mode {
+            return None;
+        }
+        let (_, layout) = self.current_table()?;
+        let jq = self.table_jq(&layout)?;
+        let query = self.query().trim();
+        let single_array = matches!(
+            self.current_values().ok().as_deref(),
+            Some([Value::Array(_)])
+        );
+        Some(match (query.is_empty(), single_array) {
+            (true, _) => jq,
+            (false, true) => format!("{} | {}", query, jq),
+            // Each output is a row; collect them first
+            (false, false) => format!("[{}] | {}", query, jq),
+        })
+    }
+
+    /// Index of the selected column among the visible ones
+    pub fn table_column(&self) -> usize {
+        self.state.table_column
+    }
+
+    /// Moves the column selection left or right in the table view
+    pub fn select_table_column(&mut self, right: bool) {
+        let Some((_, layout)) = self.current_table().filter(|_| self.state.table_mode) else {
+            return;
+        };
+        let last = layout.visible().count().saturating_sub(1);
+        let column = self.state.table_column.min(last);
+        self.state.table_column = if right {
+            (column + 1).min(last)
+        } else {
+            column.saturating_sub(1)
+        };
+    }
+
+    /// Sorts the table by the selected column: ascending, descending, then unsorted
+    pub fn cycle_table_sort(&mut self) {
+        let Some((_, layout)) = self.current_table().filter(|_| self.state.table_mode) else {
+            self.state
+                .set_status_message("Open the table view (F4) to sort");
+            return;
+        };
+        let last = layout.visible().count().saturating_sub(1);
+        if let Some(column) = layout.visible().nth(self.state.table_column.min(last)) {
+            self.state.table_query.cycle_sort(&column.key);
+            self.state.reset_scroll();
+        }
+    }
+
+    /// Whether typing edits the quick filter of the table
+    pub fn table_filter_editing(&self) -> bool {
+        self.state.table_filter_editing
+    }
+
+    /// Starts or stops editing the quick filter, keeping what was typed
+    pub fn toggle_table_filter(&mut self) {
+        if self.state.table_filter_editing {
+            self.state.table_filter_editing = false;
+        } else if self.state.table_mode {
+            self.state.table_filter_editing = true;
+        } else {
+            self.state
+                .set_status_message("Open the table view (F4) to filter rows");
+        }
+    }
+
+    pub fn table_filter_push(&mut self, c: char) {
+        self.state.table_query.filter.push(c);
+        self.state.reset_scroll();
+    }
+
+    pub fn table_filter_pop(&mut self) {
+        self.state.table_query.filter.pop();
+        self.state.reset_scroll();
+    }
+
+    /// Clears the quick filter and stops editing it
+    pub fn clear_table_filter(&mut self) {
+        self.state.table_query.filter.clear();
+        self.state.table_filter_editing = false;
+        self.state.reset_scroll();
+    }
+
+    /// Switches between the JSON view and the table view
+    pub fn toggle_table(&mut self) {
+        if self.state.table_mode {
+            self.state.table_mode = false;
+            self.state.column_picker = None;
+            self.state.table_query = TableQuery::default();
+            self.state.table_filter_editing = false;
+            self.state.table_column = 0;
+        } else if self.current_table().is_some() {
+            self.state.table_mode = true;
+        } else {
+            self.state
+                .set_status_message("Table view needs a list of objects");
+            return;
+        }
+        self.state.reset_scroll();
+    }
+
+    /// Selected entry of the column picker while it is open
+    pub fn column_picker(&self) -> Option<usize> {
+        self.state.column_picker
+    }
+
+    /// Opens the column picker, or closes it and saves the layout
+    pub fn toggle_column_picker(&mut self) -> Option<Effect> {
+        if self.state.column_picker.take().is_some() {
+            return self.save_session_store();
+        }
+        if !self.state.table_mode || self.current_table().is_none() {
+            self.state
+                .set_status_message("Open the table view (F4) to pick columns");
+            return None;
+        }
+        self.state.column_picker = Some(0);
+        None
+    }
+
+    pub fn select_column(&mut self, down: bool) {
+        self.edit_layout(|_, layout, selected| {
+            let last = layout.columns.len().saturating_sub(1);
+            if down {
+                (selected + 1).min(last)
+            } else {
+                selected.saturating_sub(1)
+            }
+        });
+    }
+
+    /// Shows or hides the selected column
+    pub fn toggle_column(&mut self) {
+        self.edit_layout(|_, layout, selected| {
+            layout.toggle(selected);
+            selected
+        });
+    }
+
+    /// Moves the selected column one place left (`up`) or right in the table
+    pub fn move_column(&mut self, up: bool) {
+        self.edit_layout(|_, layout, selected| layout.move_column(selected, up));
+    }
+
+    pub fn resize_column(&mut self, delta: i32) {
+        self.edit_layout(|table, layout, selected| {
+            if let Some(column) = layout.columns.get(selected) {
+                let current = table.auto_width(&column.key);
+                layout.resize(selected, delta, current);
+            }
+            selected
+        });
+    }
+
+    /// Applies `edit` to the layout of the current table and remembers it;
+    /// `edit` returns the new picker selection
+    fn edit_layout(&mut self, edit: impl FnOnce(&Table, &mut TableLayout, usize) -> usize) {
+        let Some(selected) = self.state.column_picker else {
+            return;
+        };
+        let Some((table, mut layout)) = self.current_table() else {
+            return;
+        };
+        let selected = edit(&table, &mut layout, selected);
+        self.session_store
+            .set_table_layout(table.fingerprint(), layout);
+        self.state.column_picker = Some(selected);
+    }
+
+    fn save_session_store(&self) -> Option<Effect> {
+        let path = self.session_store.path()?;
+        Some(Effect::WriteFile {
+            path: path.display().to_string(),
+            contents: self.session_store.contents(),
+            message: "Saved table layout".to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RuleColor;
+    use serde_json::json;
+
+    #[test]
+    fn test_table_mode_needs_objects() {
+        let mut app = App::new(json!([1, 2]));
+        app.toggle_table();
+        assert!(!app.table_mode());
+        assert_eq!(
+            app.status_message(),
+            Some("Table view needs a list of objects")
+        );
+
+        let mut app = App::new(json!([{"a": 1}]));
+        app.toggle_table();
+        assert!(app.table_mode());
+    }
+
+    #[test]
+    fn test_sort_and_filter_generate_exportable_jq() {
+        let mut app = App::new(json!({"users": [
+            {"name": "carol", "age": 40},
+            {"name": "alice", "age": 30},
+            {"name": "bob", "age": 25}
+        ]}));
+        app.set_input(".users[]");
+        app.toggle_table();
+        assert_eq!(app.table_export_query(), None);
+
+        app.select_table_column(true);
+        app.cycle_table_sort();
+        app.cycle_table_sort();
+        app.toggle_table_filter();
+        app.table_filter_push('O');
+        let (table, _) = app.current_table().unwrap();
+        let names: Vec<String> = (0..table.rows.len())
+            .map(|i| table.cell(i, "name"))
+            .collect();
+        assert_eq!(names, vec!["carol", "bob"]);
+
+        let query = app.table_export_query().unwrap();
+        assert_eq!(
+            query,
+            "[.users[]] | map(select(any(.age, .name; tostring | ascii_downcase \
+             | contains(\"o\")))) | sort_by(.name) | reverse"
+        );
+        // The exported program reproduces the view
+        let mut exported = App::new(app.data.get().clone());
+        exported.set_input(&query);
+        assert_eq!(
+            exported.current_values().unwrap(),
+            vec![json!([{"name": "carol", "age": 40}, {"name": "bob", "age": 25}])]
+        );
+
+        app.clear_table_filter();
+        app.toggle_table();
+        assert!(app.table_query().is_empty());
+    }
+
+    #[test]
+    fn test_picker_edits_are_remembered_per_shape() {
+        let mut app = App::new(json!([{"id": 1, "name": "a", "age": 3}]));
+        assert!(app.toggle_column_picker().is_none());
+        app.toggle_table();
+        assert!(app.toggle_column_picker().is_none());
+
+        app.select_column(true);
+        app.toggle_column();
+        app.move_column(false);
+        app.resize_column(2);
+        // An in-memory store has nowhere to save to
+        assert!(app.toggle_column_picker().is_none());
+
+        let (_, layout) = app.current_table().unwrap();
+        let keys: Vec<&str> = layout.columns.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["age", "name", "id"]);
+        assert!(!layout.columns[2].visible);
+        assert_eq!(layout.columns[2].width, Some(5));
+
+        // Other data with the same keys opens with the same layout
+        app.replace_data(json!([{"age": 9, "id": 2, "name": "b"}]));
+        let (_, reused) = app.current_table().unwrap();
+        assert_eq!(reused, layout);
+    }
+
+    #[test]
+    fn test_row_rules_style_matching_rows() {
+        let rule = |when: &str, color| RowRule {
+            when: when.to_string(),
+            color: Some(color),
+            bold: false,
+            dim: false,
+            underline: false,
+        };
+        let mut app = App::new(json!([
+            {"path": "/", "status": 200},
+            {"path": "/a", "status": 503},
+            {"path": "/b", "status": 404},
+            {"path": "/c"}
+        ]));
+        app.set_row_rules(&[
+            rule(".status >= 500", RuleColor::Red),
+            rule(".status >= 400", RuleColor::Yellow),
+            // Fails on the row without a status, which is then left unstyled
+            rule(".status | tostring | test(\"^2\")", RuleColor::Green),
+        ])
+        .unwrap();
+        let (table, layout) = app.current_table().unwrap();
+        let colors: Vec<Option<RuleColor>> = app
+            .row_styles(&table, &layout)
+            .into_iter()
+            .map(|style| style.and_then(|s| s.color))
+            .collect();
+        assert_eq!(
+            colors,
+            vec![
+                Some(RuleColor::Green),
+                Some(RuleColor::Red),
+                Some(RuleColor::Yellow),
+                None
+            ]
+        );
+
+        let err = app
+            .set_row_rules(&[rule(".status >=", RuleColor::Red)])
+            .unwrap_err();
+        assert!(matches!(err, AppError::Config { .. }));
+        // A predicate cannot close the parentheses it is put in
+        let err = app
+            .set_row_rules(&[rule(
+                "true) catch false) // true) then 0 elif (true",
+                RuleColor::Red,
+            )])
+            .unwrap_err();
+        assert!(matches!(err, AppError::Config { .. }));
+
+        // A predicate running too long leaves the rows unstyled
+        app.set_row_rules(&[rule("[range(1e12)] | true", RuleColor::Red)])
+            .unwrap();
+        assert_eq!(app.row_styles(&table, &layout), vec![None; 4]);
+    }
+}
@@ -0,0 +1,105 @@
+use std::fmt;
+use std::time::Duration;
+
+/// Non-fatal warning
+///
+/// Errors abort the current operation; warnings describe degraded but still
+/// usable results and are shown in the status area until dismissed.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AppWarning {
+    /// Output was cut short: `shown` of `total` lines were kept, or, with no
+    /// total, an error stopped the query after `shown` outputs
+    TruncatedOutput { shown: usize, total: Option<usize> },
+    /// Input contained invalid UTF-8 that was replaced with U+FFFD.
+    LossyDecode { source: String },
+    /// A query took longer than the slow-query threshold.
+    SlowQuery { query: String, elapsed: Duration },
+    /// Result caching is turned off for the given reason.
+    CacheDisabled(String),
+    /// Writing a session log or export file failed.
+    LogWriteFailed(String),
+    /// A line read with `--follow` was not JSON and was skipped.
+    InvalidRecord { line: usize, message: String },
+    /// There was no terminal for the TUI, so the result was printed instead.
+    NoTerminal,
+    /// Abandoned queries that could not be stopped keep evaluating.
+    StuckQueries(usize),
+}
+
+impl fmt::Display for AppWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AppWarning::TruncatedOutput {
+                shown,
+                total: Some(total),
+            } => write!(f, "output truncated: kept {} of {} lines", shown, total),
+            AppWarning::TruncatedOutput { shown, total: None } => {
+                write!(
+                    f,
+                    "output truncated: the query stopped after {} outputs",
+                    shown
+                )
+            }
+            AppWarning::LossyDecode { source } => {
+                write!(
+                    f,
+                    "{} contained invalid UTF-8; replaced with U+FFFD",
+                    source
+                )
+            }
+            AppWarning::SlowQuery { query, elapsed } => {
+                write!(f, "slow query ({} ms): {}", elapsed.as_millis(), query)
+            }
+            AppWarning::CacheDisabled(reason) => write!(f, "cache disabled: {}", reason),
+            AppWarning::LogWriteFailed(reason) => {
+                write!(f, "failed to write file: {}", reason)
+            }
+            AppWarning::InvalidRecord { line, message } => {
+                write!(f, "skipped stdin line {}: {}", line, message)
+            }
+            AppWarning::StuckQueries(count) => write!(
+                f,
+                "{} abandoned {} stuck producing no output, using CPU until rjq exits",
+                count,
+                if *count == 1 {
+                    "query is"
+                } else {
+                    "queries are"
+                }
+            ),
+            AppWarning::NoTerminal => write!(
+                f,
+                "no terminal to read keys from; printing the result instead (use -q to choose the filter)"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_warning_display() {
+        let warning = AppWarning::SlowQuery {
+            query: ".[]".to_string(),
+            elapsed: Duration::from_millis(750),
+        };
+        assert_eq!(warning.to_string(), "slow query (750 ms): .[]");
+
+        let warning = AppWarning::LossyDecode {
+            source: "stdin".to_string(),
+        };
+        assert!(
+            warning
+                .to_string()
+                .starts_with("stdin contained invalid UTF-8")
+        );
+
+        let warning = AppWarning::TruncatedOutput {
+            shown: 3,
+            total: Some(40),
+        };
+        assert_eq!(warning.to_string(), "output truncated: kept 3 of 40 lines");
+    }
+}
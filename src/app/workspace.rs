@@ -0,0 +1,144 @@
+use super::App;
+use crate::query::{FileSummary, Workspace};
+use std::cell::Ref;
+
+impl App {
+    /// Runs every query against all of `workspace`'s files, starting on the summary
+    ///
+    /// The first file is loaded as the document, so completions know its keys.
+    pub fn set_workspace(&mut self, workspace: Workspace) {
+        self.workspace = Some(workspace);
+        self.load_workspace_file(0);
+        self.state.workspace_view = true;
+    }
+
+    /// Whether the per-file summary is shown instead of one file's result
+    pub fn workspace_view(&self) -> bool {
+        self.workspace.is_some() && self.state.workspace_view
+    }
+
+    /// Number of files in the workspace, 0 outside workspace mode
+    pub fn workspace_len(&self) -> usize {
+        self.workspace.as_ref().map_or(0, |w| w.files().len())
+    }
+
+    /// Row selected in the summary, which is also the file drilled into
+    pub fn workspace_file(&self) -> usize {
+        self.state.workspace_file
+    }
+
+    /// Summary of the current query on every file, run again when the query changes
+    pub fn workspace_summary(&self) -> Option<Ref<'_, [FileSummary]>> {
+        let workspace = self.workspace.as_ref()?;
+        let current = matches!(
+            &*self.workspace_summary.borrow(),
+            Some((query, _)) if *query == self.query()
+        );
+        if !current {
+            let query = if self.query().is_empty() {
+                "."
+            } else {
+                self.query()
+            };
+            let summaries = workspace.run(&self.executor, query);
+            *self.workspace_summary.borrow_mut() = Some((self.query().to_string(), summaries));
+        }
+        Ref::filter_map(self.workspace_summary.borrow(), |cached| {
+            cached.as_ref().map(|(_, summaries)| summaries.as_slice())
+        })
+        .ok()
+    }
+
+    /// Moves the summary's selection down (or up) one file
+    pub fn select_workspace_file(&mut self, forward: bool) {
+        let count = self.workspace_len();
+        let selected = &mut self.state.workspace_file;
+        *selected = if forward {
+            (*selected + 1).min(count.saturating_sub(1))
+        } else {
+            selected.saturating_sub(1)
+        };
+    }
+
+    /// On the summary, opens the selected file; otherwise goes back to the summary
+    pub fn toggle_workspace_view(&mut self) {
+        if self.workspace.is_none() {
+            self.state
+                .set_status_message("Workspace mode needs --glob PATTERN");
+            return;
+        }
+        if self.state.workspace_view {
+            self.load_workspace_file(self.state.workspace_file);
+            self.state.workspace_view = false;
+        } else {
+            self.state.workspace_view = true;
+        }
+    }
+
+    /// Makes file `index` the document single-file views query
+    fn load_workspace_file(&mut self, index: usize) {
+        let Some(file) = self.workspace.as_ref().and_then(|w| w.files().get(index)) else {
+            return;
+        };
+        let (path, data) = (file.path.clone(), file.data.clone());
+        self.data.replace_with(data);
+        self.source_path = Some(path);
+        self.state.workspace_file = index;
+        self.data_changed();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{JsonData, WorkspaceFile};
+    use serde_json::json;
+
+    fn workspace_app() -> App {
+        let files = [("a.json", json!({"id": 1})), ("b.json", json!({"id": 2}))]
+            .into_iter()
+            .map(|(path, value)| WorkspaceFile {
+                path: path.to_string(),
+                data: JsonData::new(value),
+            })
+            .collect();
+        let mut app = App::new(json!(null));
+        app.set_workspace(Workspace::new(files));
+        app
+    }
+
+    #[test]
+    fn test_workspace_summary_follows_the_query() {
+        let mut app = workspace_app();
+        assert!(app.workspace_view());
+        assert_eq!(app.data().get(), &json!({"id": 1}));
+
+        app.set_input("select(.id == 2) | .id");
+        let summary = app.workspace_summary().unwrap();
+        let matches: Vec<usize> = summary.iter().map(|s| s.matches).collect();
+        assert_eq!(matches, [0, 1]);
+        assert_eq!(summary[1].first, Some(json!(2)));
+    }
+
+    #[test]
+    fn test_drill_down_and_back() {
+        let mut app = workspace_app();
+        app.select_workspace_file(true);
+        app.select_workspace_file(true);
+        assert_eq!(app.workspace_file(), 1);
+
+        app.toggle_workspace_view();
+        assert!(!app.workspace_view());
+        assert_eq!(app.data().get(), &json!({"id": 2}));
+        assert_eq!(app.command_export().jq_command(), "jq . b.json");
+
+        app.toggle_workspace_view();
+        assert!(app.workspace_view());
+        assert_eq!(app.workspace_file(), 1);
+
+        let mut plain = App::new(json!(null));
+        plain.toggle_workspace_view();
+        assert!(!plain.workspace_view());
+        assert!(plain.workspace_summary().is_none());
+    }
+}
@@ -0,0 +1,245 @@
+use super::exit_code;
+use crate::query::{JaqQueryExecutor, QueryExecutor};
+use serde_json::Value;
+use std::io::{BufRead, Write};
+
+/// An exercise
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Lesson {
+    pub title: &'static str,
+    /// What the filter should produce
+    pub prompt: &'static str,
+    /// Input document, as JSON
+    pub data: &'static str,
+    /// Expected outputs, as a JSON array
+    pub expected: &'static str,
+    pub hint: &'static str,
+    /// A reference answer, shown when the lesson is skipped
+    pub solution: &'static str,
+}
+
+pub const LESSONS: &[Lesson] = &[
+    Lesson {
+        title: "Fields",
+        prompt: "Get the name of the user",
+        data: r#"{"name": "alice", "age": 31}"#,
+        expected: r#"["alice"]"#,
+        hint: "`.key` reads a field of an object",
+        solution: ".name",
+    },
+    Lesson {
+        title: "Nested fields",
+        prompt: "Get the city the user lives in",
+        data: r#"{"name": "alice", "address": {"city": "Kyoto", "zip": "600-8001"}}"#,
+        expected: r#"["Kyoto"]"#,
+        hint: "Chain field accesses: `.a.b`",
+        solution: ".address.city",
+    },
+    Lesson {
+        title: "Iterating",
+        prompt: "Output every tag on its own",
+        data: r#"{"tags": ["rust", "json", "cli"]}"#,
+        expected: r#"["rust", "json", "cli"]"#,
+        hint: "`.[]` outputs each element of an array",
+        solution: ".tags[]",
+    },
+    Lesson {
+        title: "Pipes",
+        prompt: "Output the email of every user",
+        data: r#"{"users": [{"email": "a@example.com"}, {"email": "b@example.com"}]}"#,
+        expected: r#"["a@example.com", "b@example.com"]"#,
+        hint: "`|` feeds every output of the left side into the right side",
+        solution: ".users[] | .email",
+    },
+    Lesson {
+        title: "Filtering",
+        prompt: "Extract all emails of active users",
+        data: r#"{"users": [
+            {"email": "a@example.com", "active": true},
+            {"email": "b@example.com", "active": false},
+            {"email": "c@example.com", "active": true}
+        ]}"#,
+        expected: r#"["a@example.com", "c@example.com"]"#,
+        hint: "`select(cond)` keeps only the inputs for which `cond` is true",
+        solution: ".users[] | select(.active) | .email",
+    },
+    Lesson {
+        title: "Building arrays",
+        prompt: "Collect the prices into one array",
+        data: r#"[{"item": "pen", "price": 2}, {"item": "book", "price": 12}]"#,
+        expected: r#"[[2, 12]]"#,
+        hint: "Wrap a filter in `[...]` to collect its outputs, or use `map(f)`",
+        solution: "map(.price)",
+    },
+    Lesson {
+        title: "Building objects",
+        prompt: "Turn the user into {\"user\": <name>, \"admin\": <is_admin>}",
+        data: r#"{"name": "alice", "is_admin": true, "age": 31}"#,
+        expected: r#"[{"user": "alice", "admin": true}]"#,
+        hint: "Object construction: `{key: filter, ...}`",
+        solution: "{user: .name, admin: .is_admin}",
+    },
+    Lesson {
+        title: "Aggregating",
+        prompt: "Compute the total price of the order",
+        data: r#"{"lines": [{"price": 3}, {"price": 4.5}, {"price": 10}]}"#,
+        expected: r#"[17.5]"#,
+        hint: "`add` sums an array of numbers",
+        solution: "[.lines[].price] | add",
+    },
+    Lesson {
+        title: "Grouping",
+        prompt: "Count the orders per status as [[status, count], ...] sorted by status",
+        data: r#"[{"status": "open"}, {"status": "done"}, {"status": "open"}]"#,
+        expected: r#"[[["done", 1], ["open", 2]]]"#,
+        hint: "`group_by(.status)` makes arrays of equal statuses; `length` counts them",
+        solution: "group_by(.status) | map([.[0].status, length])",
+    },
+];
+
+/// Outcome of checking an answer
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Correct,
+    /// The filter ran but produced different outputs
+    Wrong {
+        actual: Vec<Value>,
+    },
+    /// The filter failed to compile or run
+    Error(String),
+}
+
+impl Lesson {
+    pub fn data(&self) -> Value {
+        serde_json::from_str(self.data).expect("lesson data is valid JSON")
+    }
+
+    pub fn expected(&self) -> Vec<Value> {
+        serde_json::from_str(self.expected).expect("lesson answers are a JSON array")
+    }
+
+    /// Runs `filter` on the lesson's data and compares its outputs
+    pub fn check(&self, filter: &str) -> Verdict {
+        match JaqQueryExecutor.execute(&self.data(), filter) {
+            Ok(actual) if actual == self.expected() => Verdict::Correct,
+            Ok(actual) => Verdict::Wrong { actual },
+            Err(error) => Verdict::Error(error.to_string()),
+        }
+    }
+}
+
+fn show_values(values: &[Value]) -> String {
+    if values.is_empty() {
+        return "(no output)".to_string();
+    }
+    values
+        .iter()
+        .map(|value| serde_json::to_string(value).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Interactive practice mode
+///
+/// Reads one filter per line from `input`. `:hint`, `:skip` and `:quit` are
+/// commands; end of input also quits. Starts at lesson `start` (0-based).
+pub fn run<R: BufRead, W: Write>(mut input: R, out: &mut W, start: usize) -> crate::Result<i32> {
+    let mut solved = 0;
+    for (index, lesson) in LESSONS.iter().enumerate().skip(start) {
+        let data = serde_json::to_string_pretty(&lesson.data())?;
+        writeln!(
+            out,
+            "\nLesson {}/{}: {}\n{}\n\nInput:\n{}\n",
+            index + 1,
+            LESSONS.len(),
+            lesson.title,
+            lesson.prompt,
+            data
+        )?;
+
+        loop {
+            write!(out, "filter> ")?;
+            out.flush()?;
+            let mut line = String::new();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(out)?;
+                return Ok(exit_code::SUCCESS);
+            }
+            match line.trim() {
+                "" => continue,
+                ":quit" | ":q" => return Ok(exit_code::SUCCESS),
+                ":hint" => writeln!(out, "hint: {}", lesson.hint)?,
+                ":skip" => {
+                    writeln!(out, "One answer: {}", lesson.solution)?;
+                    break;
+                }
+                filter => match lesson.check(filter) {
+                    Verdict::Correct => {
+                        writeln!(out, "Correct!")?;
+                        solved += 1;
+                        break;
+                    }
+                    Verdict::Wrong { actual } => writeln!(
+                        out,
+                        "Not quite. Your filter produced:\n{}\nExpected:\n{}\n(:hint for a hint, :skip to move on)",
+                        show_values(&actual),
+                        show_values(&lesson.expected())
+                    )?,
+                    Verdict::Error(message) => writeln!(out, "Error: {}", message)?,
+                },
+            }
+        }
+    }
+    writeln!(
+        out,
+        "\nAll lessons done: {} of {} solved.",
+        solved,
+        LESSONS.len().saturating_sub(start)
+    )?;
+    Ok(exit_code::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_solutions_are_correct() {
+        for lesson in LESSONS {
+            assert_eq!(
+                lesson.check(lesson.solution),
+                Verdict::Correct,
+                "{}",
+                lesson.title
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_reports_wrong_output() {
+        let lesson = &LESSONS[0];
+        assert_eq!(
+            lesson.check(".age"),
+            Verdict::Wrong {
+                actual: vec![Value::from(31)]
+            }
+        );
+        assert!(matches!(lesson.check(".name["), Verdict::Error(_)));
+    }
+
+    #[test]
+    fn test_run_advances_through_lessons() {
+        let input = ".age\n:hint\n.name\n:skip\n:quit\n";
+        let mut out = Vec::new();
+        let status = run(input.as_bytes(), &mut out, 0).unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        assert_eq!(status, exit_code::SUCCESS);
+        assert!(out.contains("Lesson 1/"));
+        assert!(out.contains("Not quite."));
+        assert!(out.contains("hint: `.key`"));
+        assert!(out.contains("Correct!"));
+        assert!(out.contains("One answer: .address.city"));
+        assert!(out.contains("Lesson 3/"));
+    }
+}
@@ -0,0 +1,229 @@
+pub mod learn;
+
+use crate::app::AppError;
+use crate::query::{JaqQueryExecutor, JsonData, PreludeExecutor};
+use serde_json::{Value, json};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+/// Format of error output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum ErrorFormat {
+    /// Human-readable message followed by a hint line
+    #[default]
+    Text,
+    /// One JSON object per error with code, message, span and hint
+    Json,
+}
+
+/// jq-compatible exit codes
+pub mod exit_code {
+    pub const SUCCESS: i32 = 0;
+    /// `-e` was given and the last output was `false` or `null`
+    pub const FALSY_OUTPUT: i32 = 1;
+    /// Usage, IO, or input parsing error
+    pub const USAGE: i32 = 2;
+    pub const COMPILE: i32 = 3;
+    /// Runtime error, or `-e` was given and no output was produced
+    pub const NO_OUTPUT: i32 = 5;
+}
+
+/// Options controlling the non-interactive execution path
+#[derive(Debug, Clone, Default)]
+pub struct BatchOptions {
+    /// Derive the exit status from the last output, like `jq -e`
+    pub exit_status: bool,
+    /// Also write the results as a highlighted HTML page to this path
+    pub export_html: Option<String>,
+    /// jq definitions prepended to the query
+    pub prelude: Option<String>,
+    /// Values bound to `$name` in the query
+    pub variables: Vec<(String, Value)>,
+    /// Highlight the results with ANSI colors
+    pub color: bool,
+}
+
+/// Maps an error to the exit status jq would use for it
+pub fn error_exit_code(error: &AppError) -> i32 {
+    match error {
+        AppError::QueryCompile { .. } => exit_code::COMPILE,
+        AppError::QueryExecution(_) | AppError::QueryRuntime { .. } => exit_code::NO_OUTPUT,
+        AppError::JsonParse(_)
+        | AppError::InputParse { .. }
+        | AppError::FileNotFound(_)
+        | AppError::Fetch { .. }
+        | AppError::Config { .. }
+        | AppError::Io(_) => exit_code::USAGE,
+    }
+}
+
+/// Formats an error for stderr according to `format`
+pub fn format_error(error: &AppError, format: ErrorFormat) -> String {
+    match format {
+        ErrorFormat::Text => match error.hint() {
+            Some(hint) => format!("Error: {}\nhint: {}", error, hint),
+            None => format!("Error: {}", error),
+        },
+        ErrorFormat::Json => {
+            let span = error
+                .span()
+                .map(|span| json!({"start": span.start, "end": span.end}));
+            json!({
+                "code": error.code(),
+                "message": error.to_string(),
+                "span": span,
+                "hint": error.hint(),
+            })
+            .to_string()
+        }
+    }
+}
+
+/// Runs `query` against `data` without the TUI, writing each result to `out`
+///
+/// Returns the exit status to use on success.
+pub fn run_query<W: Write>(
+    data: &JsonData,
+    query: &str,
+    options: &BatchOptions,
+    out: &mut W,
+) -> crate::Result<i32> {
+    let mut executor = PreludeExecutor::new(JaqQueryExecutor, options.prelude.clone());
+    executor.set_variables(options.variables.clone());
+    let result = data.execute_query_with(&executor, query)?;
+    // Outputs before a runtime error are written, then the error is reported
+    let error = result.error().map(str::to_string);
+    let results = result.into_values();
+    if let Some(path) = &options.export_html {
+        let file = BufWriter::new(File::create(path)?);
+        crate::export::write_html(file, query, &results)?;
+    }
+    for value in &results {
+        if options.color {
+            // Highlighting works on text, so only one result is held at a time
+            let pretty = serde_json::to_string_pretty(value)?;
+            crate::export::write_ansi(&mut *out, &pretty)?;
+        } else {
+            serde_json::to_writer_pretty(&mut *out, value)?;
+        }
+        writeln!(out)?;
+    }
+    if let Some(message) = error {
+        return Err(AppError::QueryRuntime {
+            message,
+            partial: Vec::new(),
+        });
+    }
+
+    if !options.exit_status {
+        return Ok(exit_code::SUCCESS);
+    }
+    Ok(match results.last() {
+        None => exit_code::NO_OUTPUT,
+        Some(Value::Null | Value::Bool(false)) => exit_code::FALSY_OUTPUT,
+        Some(_) => exit_code::SUCCESS,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::QueryExecutor;
+
+    #[test]
+    fn test_run_query_writes_each_result() {
+        let mut out = Vec::new();
+        let status = run_query(
+            &JsonData::new(json!({"items": [1, 2]})),
+            ".items[]",
+            &BatchOptions::default(),
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1\n2\n");
+        assert_eq!(status, exit_code::SUCCESS);
+    }
+
+    #[test]
+    fn test_run_query_over_inputs() {
+        let data = JsonData::from_inputs(vec![json!({"n": 1}), json!({"n": 2})]);
+        let mut out = Vec::new();
+        run_query(&data, ".n", &BatchOptions::default(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1\n2\n");
+    }
+
+    #[test]
+    fn test_runtime_error_after_outputs() {
+        let mut out = Vec::new();
+        let error = run_query(
+            &JsonData::new(json!([1, "a", 3])),
+            ".[] | . + 1",
+            &BatchOptions::default(),
+            &mut out,
+        )
+        .unwrap_err();
+        assert_eq!(String::from_utf8(out).unwrap(), "2\n");
+        assert!(matches!(error, AppError::QueryRuntime { .. }));
+        assert_eq!(error_exit_code(&error), exit_code::NO_OUTPUT);
+    }
+
+    #[test]
+    fn test_color_option() {
+        let options = BatchOptions {
+            color: true,
+            ..Default::default()
+        };
+        let mut out = Vec::new();
+        run_query(&JsonData::new(json!({"a": "x"})), ".a", &options, &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "\x1b[32m\"x\"\x1b[0m\n");
+    }
+
+    #[test]
+    fn test_exit_status_flag() {
+        let options = BatchOptions {
+            exit_status: true,
+            ..Default::default()
+        };
+        let data = JsonData::new(json!({"ok": true, "missing": null, "items": []}));
+        let status = |query: &str| run_query(&data, query, &options, &mut Vec::new()).unwrap();
+
+        assert_eq!(status(".ok"), exit_code::SUCCESS);
+        assert_eq!(status(".missing"), exit_code::FALSY_OUTPUT);
+        assert_eq!(status(".ok | not"), exit_code::FALSY_OUTPUT);
+        assert_eq!(status(".items[]"), exit_code::NO_OUTPUT);
+
+        // Without -e the outputs do not affect the status
+        let status = run_query(&data, ".missing", &BatchOptions::default(), &mut Vec::new());
+        assert_eq!(status.unwrap(), exit_code::SUCCESS);
+    }
+
+    #[test]
+    fn test_error_exit_codes() {
+        let compile = JaqQueryExecutor.execute(&json!({}), ".a]").unwrap_err();
+        assert_eq!(error_exit_code(&compile), exit_code::COMPILE);
+
+        let missing = AppError::FileNotFound("missing.json".to_string());
+        assert_eq!(error_exit_code(&missing), exit_code::USAGE);
+    }
+
+    #[test]
+    fn test_format_error_json() {
+        let error = JaqQueryExecutor.execute(&json!({}), ".a]").unwrap_err();
+        let formatted: Value = serde_json::from_str(&format_error(&error, ErrorFormat::Json))
+            .expect("error output should be valid JSON");
+
+        assert_eq!(formatted["code"], "query_compile");
+        assert_eq!(formatted["span"], json!({"start": 2, "end": 3}));
+        assert!(formatted["message"].is_string());
+        assert!(formatted["hint"].is_string());
+    }
+
+    #[test]
+    fn test_format_error_text() {
+        let error = AppError::QueryExecution("boom".to_string());
+        assert_eq!(
+            format_error(&error, ErrorFormat::Text),
+            "Error: Query execution error: boom"
+        );
+    }
+}
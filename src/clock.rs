@@ -0,0 +1,79 @@
+use std::fmt::Debug;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Source of the current time
+///
+/// History scoring and session logs read the time through this trait so
+/// tests can control it.
+pub trait Clock: Debug + Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// The real wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+/// A clock that only moves when told to
+///
+/// Clones share the same time, so a test can keep one handle and pass
+/// another to the code under test.
+#[derive(Debug, Clone)]
+pub struct MockClock {
+    now: Arc<Mutex<SystemTime>>,
+}
+
+impl MockClock {
+    pub fn new(start: SystemTime) -> Self {
+        Self {
+            now: Arc::new(Mutex::new(start)),
+        }
+    }
+
+    pub fn set(&self, time: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = time;
+    }
+
+    pub fn advance(&self, by: Duration) {
+        let mut now = self.now.lock().unwrap_or_else(|e| e.into_inner());
+        *now += by;
+    }
+}
+
+impl Default for MockClock {
+    /// Starts at the Unix epoch
+    fn default() -> Self {
+        Self::new(SystemTime::UNIX_EPOCH)
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_is_shared_between_clones() {
+        let clock = MockClock::default();
+        let handle = clock.clone();
+        handle.advance(Duration::from_secs(90));
+        assert_eq!(
+            clock.now(),
+            SystemTime::UNIX_EPOCH + Duration::from_secs(90)
+        );
+
+        clock.set(SystemTime::UNIX_EPOCH);
+        assert_eq!(handle.now(), SystemTime::UNIX_EPOCH);
+    }
+}
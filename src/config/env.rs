@@ -0,0 +1,102 @@
+/// Error raised when an interpolated variable is missing or malformed
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpolationError {
+    /// `${NAME}` referenced a variable that is not set
+    Missing(String),
+    /// `${` without a closing `}`
+    Unterminated,
+}
+
+impl std::fmt::Display for InterpolationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InterpolationError::Missing(name) => {
+                write!(f, "environment variable `{}` is not set", name)
+            }
+            InterpolationError::Unterminated => write!(f, "unterminated `${{` in value"),
+        }
+    }
+}
+
+/// Expands `${NAME}` using `lookup`; `$${` produces a literal `${`
+pub fn interpolate_with(
+    text: &str,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<String, InterpolationError> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(pos) = rest.find('$') {
+        out.push_str(&rest[..pos]);
+        let after = &rest[pos + 1..];
+        if let Some(escaped) = after.strip_prefix("${") {
+            out.push_str("${");
+            rest = escaped;
+        } else if let Some(body) = after.strip_prefix('{') {
+            let end = body.find('}').ok_or(InterpolationError::Unterminated)?;
+            let name = &body[..end];
+            let value =
+                lookup(name).ok_or_else(|| InterpolationError::Missing(name.to_string()))?;
+            out.push_str(&value);
+            rest = &body[end + 1..];
+        } else {
+            out.push('$');
+            rest = after;
+        }
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Expands `${NAME}` from the process environment
+pub fn interpolate(text: &str) -> Result<String, InterpolationError> {
+    interpolate_with(text, |name| std::env::var(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "HOST" => Some("example.com".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolation() {
+        assert_eq!(
+            interpolate_with("https://${HOST}/api", lookup).unwrap(),
+            "https://example.com/api"
+        );
+        assert_eq!(interpolate_with("a${EMPTY}b", lookup).unwrap(), "ab");
+        assert_eq!(interpolate_with("no vars", lookup).unwrap(), "no vars");
+    }
+
+    #[test]
+    fn test_jq_variables_are_untouched() {
+        assert_eq!(
+            interpolate_with(".[] | select(.x == $name)", lookup).unwrap(),
+            ".[] | select(.x == $name)"
+        );
+    }
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(interpolate_with("$${HOST}", lookup).unwrap(), "${HOST}");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            interpolate_with("${TOKEN}", lookup),
+            Err(InterpolationError::Missing("TOKEN".to_string()))
+        );
+        assert_eq!(
+            interpolate_with("${HOST", lookup),
+            Err(InterpolationError::Unterminated)
+        );
+    }
+}
@@ -0,0 +1,787 @@
+pub mod env;
+pub mod trust;
+
+use crate::app::AppError;
+use crate::app::execution::{
+    DEFAULT_DEBOUNCE, DEFAULT_QUERY_TIMEOUT, EXPENSIVE_LIMIT, ExecutionMode, ExecutionPolicy,
+};
+use crate::history::{Ranking, RankingStrategy, RedactMode, Redactor};
+use crate::render::{HighlightRule, HighlightRules, NumberFormat, NumberFormats, RowRule};
+use crate::source::http::Credentials;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub use env::{InterpolationError, interpolate};
+pub use trust::TrustStore;
+
+/// File name searched for in the input's directory and its ancestors
+pub const PROJECT_CONFIG_NAME: &str = ".rjq.toml";
+
+/// A named bundle of a starting query and variables, selected with `--preset`
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Preset {
+    pub query: Option<String>,
+    pub args: BTreeMap<String, String>,
+}
+
+/// Patterns for secrets that must not be persisted in history or the tee log
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RedactionConfig {
+    /// Regular expressions, e.g. `Bearer [A-Za-z0-9._-]+`
+    pub patterns: Vec<String>,
+    /// `mask` (default) stores the query with matches replaced, `skip` drops it
+    pub mode: Option<RedactMode>,
+}
+
+/// How query history suggestions are ranked
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HistoryConfig {
+    /// `frecency` (default), `frequency`, `decay` or `mru`
+    pub ranking: Option<Ranking>,
+    /// Half-life of the `decay` ranking, in hours
+    pub half_life_hours: Option<f64>,
+}
+
+/// When edits run the query
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExecutionConfig {
+    /// `auto` (default, chosen from the document's size), `immediate`,
+    /// `debounced` or `manual`
+    pub policy: Option<ExecutionMode>,
+    /// Pause after the last keystroke before a debounced query runs
+    pub debounce_ms: Option<u64>,
+    /// Ask before running `..`, `recurse` and the like on large documents
+    /// (default `true`)
+    pub confirm_expensive: Option<bool>,
+    /// Give up queries still running after this long (default 30000); `0`
+    /// never gives up
+    pub timeout_ms: Option<u64>,
+}
+
+/// Contents of a config file, user or project
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FileConfig {
+    /// jq definitions made available to every query, e.g. `def active: select(.active);`
+    pub prelude: Option<String>,
+    /// Named queries that can be cycled into the input line
+    pub bookmarks: BTreeMap<String, String>,
+    /// Named filters applied to results for viewing only, e.g. `no_meta = "del(.metadata)"`
+    pub display_filters: BTreeMap<String, String>,
+    /// Abbreviations expanded with Tab, e.g. `sel = "select($0)"` (`$0` marks the cursor)
+    pub aliases: BTreeMap<String, String>,
+    /// Named string variables, bound to `$name` in every query
+    pub args: BTreeMap<String, String>,
+    pub presets: BTreeMap<String, Preset>,
+    pub redaction: RedactionConfig,
+    pub history: HistoryConfig,
+    pub execution: ExecutionConfig,
+    /// Close brackets and quotes automatically while typing (default: on)
+    pub auto_pairs: Option<bool>,
+    /// Start with the compact layout, which keeps arrays and objects that fit
+    /// the view's width on one line (default: off)
+    pub compact_layout: Option<bool>,
+    /// How numbers under matching keys are shown, e.g. `"*_bytes" = "bytes"`,
+    /// `"*_ms" = "ms"` or `price = "decimals:2"`
+    pub number_formats: BTreeMap<String, NumberFormat>,
+    /// Headers sent to URL sources by host, e.g.
+    /// `[credentials."api.example.com"]` with `bearer = "${API_TOKEN}"`; only
+    /// taken from the user config
+    pub credentials: BTreeMap<String, Credentials>,
+    /// `[[highlight_rules]]` styling values by key and value, e.g.
+    /// `key = "level"`, `equals = "error"`, `color = "red"`
+    pub highlight_rules: Vec<HighlightRule>,
+    /// `[[row_rules]]` styling whole table rows for which a jq predicate
+    /// holds, e.g. `when = ".status >= 500"`, `color = "red"`
+    pub row_rules: Vec<RowRule>,
+}
+
+impl FileConfig {
+    /// Parses a config file and expands `${VAR}` references in all values
+    pub fn parse(content: &str, path: &Path) -> crate::Result<Self> {
+        let config: Self = toml::from_str(content).map_err(|e| AppError::Config {
+            path: path.display().to_string(),
+            message: e.message().to_string(),
+        })?;
+        let config = config.interpolated().map_err(|message| AppError::Config {
+            path: path.display().to_string(),
+            message,
+        })?;
+        // Reject invalid patterns here so the error names the file they came from
+        config.redactor().map_err(|e| match e {
+            AppError::Config { message, .. } => AppError::Config {
+                path: path.display().to_string(),
+                message,
+            },
+            other => other,
+        })?;
+        let invalid_pattern = NumberFormats::new(&config.number_formats)
+            .err()
+            .or_else(|| HighlightRules::new(&config.highlight_rules).err());
+        if let Some(message) = invalid_pattern {
+            return Err(AppError::Config {
+                path: path.display().to_string(),
+                message,
+            });
+        }
+        Ok(config)
+    }
+
+    fn interpolated(self) -> Result<Self, String> {
+        fn expand(field: &str, value: String) -> Result<String, String> {
+            interpolate(&value).map_err(|e| format!("{} (in `{}`)", e, field))
+        }
+        fn expand_map(
+            section: &str,
+            map: BTreeMap<String, String>,
+        ) -> Result<BTreeMap<String, String>, String> {
+            map.into_iter()
+                .map(|(k, v)| {
+                    let value = expand(&format!("{}.{}", section, k), v)?;
+                    Ok((k, value))
+                })
+                .collect()
+        }
+
+        let presets = self
+            .presets
+            .into_iter()
+            .map(|(name, preset)| {
+                let section = format!("presets.{}", name);
+                let preset = Preset {
+                    query: preset
+                        .query
+                        .map(|q| expand(&format!("{}.query", section), q))
+                        .transpose()?,
+                    args: expand_map(&format!("{}.args", section), preset.args)?,
+                };
+                Ok((name, preset))
+            })
+            .collect::<Result<_, String>>()?;
+
+        let credentials = self
+            .credentials
+            .into_iter()
+            .map(|(host, credentials)| {
+                let section = format!("credentials.{}", host);
+                let field = |name: &str, value: Option<String>| {
+                    value
+                        .map(|v| expand(&format!("{}.{}", section, name), v))
+                        .transpose()
+                };
+                let credentials = Credentials {
+                    bearer: field("bearer", credentials.bearer)?,
+                    username: field("username", credentials.username)?,
+                    password: field("password", credentials.password)?,
+                    headers: expand_map(&format!("{}.headers", section), credentials.headers)?,
+                };
+                Ok((host, credentials))
+            })
+            .collect::<Result<_, String>>()?;
+
+        Ok(Self {
+            prelude: self.prelude.map(|p| expand("prelude", p)).transpose()?,
+            bookmarks: expand_map("bookmarks", self.bookmarks)?,
+            display_filters: expand_map("display_filters", self.display_filters)?,
+            aliases: expand_map("aliases", self.aliases)?,
+            args: expand_map("args", self.args)?,
+            presets,
+            redaction: self.redaction,
+            history: self.history,
+            execution: self.execution,
+            auto_pairs: self.auto_pairs,
+            compact_layout: self.compact_layout,
+            number_formats: self.number_formats,
+            credentials,
+            highlight_rules: self.highlight_rules,
+            row_rules: self.row_rules,
+        })
+    }
+
+    /// Compiles the redaction patterns
+    pub fn redactor(&self) -> crate::Result<Redactor> {
+        Redactor::new(
+            &self.redaction.patterns,
+            self.redaction.mode.unwrap_or_default(),
+        )
+        .map_err(|message| AppError::Config {
+            path: "[redaction]".to_string(),
+            message,
+        })
+    }
+
+    /// The `[number_formats]` rules
+    pub fn number_formats(&self) -> crate::Result<NumberFormats> {
+        NumberFormats::new(&self.number_formats).map_err(|message| AppError::Config {
+            path: "[number_formats]".to_string(),
+            message,
+        })
+    }
+
+    /// The `[[highlight_rules]]`, in the order they are tried
+    pub fn highlight_rules(&self) -> crate::Result<HighlightRules> {
+        HighlightRules::new(&self.highlight_rules).map_err(|message| AppError::Config {
+            path: "[[highlight_rules]]".to_string(),
+            message,
+        })
+    }
+
+    /// The configured history ranking strategy
+    pub fn ranking(&self) -> Box<dyn RankingStrategy> {
+        let half_life = self
+            .history
+            .half_life_hours
+            .filter(|hours| hours.is_finite() && *hours > 0.0)
+            .map(|hours| Duration::from_secs_f64(hours * 3600.0));
+        self.history.ranking.unwrap_or_default().strategy(half_life)
+    }
+
+    /// The configured execution policy for a document of `document_bytes`
+    pub fn execution_policy(&self, document_bytes: u64) -> ExecutionPolicy {
+        let debounce = self
+            .execution
+            .debounce_ms
+            .map_or(DEFAULT_DEBOUNCE, Duration::from_millis);
+        self.execution
+            .policy
+            .unwrap_or_default()
+            .policy(document_bytes, debounce)
+    }
+
+    /// How long a query may run before it is given up, `None` for no limit
+    pub fn query_timeout(&self) -> Option<Duration> {
+        match self.execution.timeout_ms {
+            Some(0) => None,
+            Some(millis) => Some(Duration::from_millis(millis)),
+            None => Some(DEFAULT_QUERY_TIMEOUT),
+        }
+    }
+
+    /// Whether expensive queries wait for confirmation on a document of `document_bytes`
+    pub fn confirm_expensive(&self, document_bytes: u64) -> bool {
+        document_bytes >= EXPENSIVE_LIMIT && self.execution.confirm_expensive.unwrap_or(true)
+    }
+
+    /// The `args` as query variables, followed by those of `preset`, which win
+    pub fn variables(&self, preset: Option<&Preset>) -> Vec<(String, serde_json::Value)> {
+        let preset_args = preset.into_iter().flat_map(|preset| &preset.args);
+        self.args
+            .iter()
+            .chain(preset_args)
+            .map(|(name, value)| (name.clone(), serde_json::Value::String(value.clone())))
+            .collect()
+    }
+
+    pub fn load(path: &Path) -> crate::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        Self::parse(&content, path)
+    }
+
+    /// Overlays `other` on top of `self`: maps are merged key by key with
+    /// `other` winning, and preludes and redaction patterns are concatenated
+    /// so both sets stay in effect.
+    pub fn merge(mut self, other: FileConfig) -> Self {
+        self.prelude = match (self.prelude, other.prelude) {
+            (Some(base), Some(overlay)) => Some(format!("{}\n{}", base, overlay)),
+            (base, overlay) => overlay.or(base),
+        };
+        self.bookmarks.extend(other.bookmarks);
+        self.display_filters.extend(other.display_filters);
+        self.number_formats.extend(other.number_formats);
+        self.credentials.extend(other.credentials);
+        // The first matching rule wins, so `other`'s rules go first
+        self.highlight_rules = other
+            .highlight_rules
+            .into_iter()
+            .chain(self.highlight_rules)
+            .collect();
+        self.row_rules = other.row_rules.into_iter().chain(self.row_rules).collect();
+        self.aliases.extend(other.aliases);
+        self.args.extend(other.args);
+        self.presets.extend(other.presets);
+        self.redaction.patterns.extend(other.redaction.patterns);
+        self.redaction.mode = other.redaction.mode.or(self.redaction.mode);
+        self.history.ranking = other.history.ranking.or(self.history.ranking);
+        self.history.half_life_hours = other
+            .history
+            .half_life_hours
+            .or(self.history.half_life_hours);
+        self.execution.policy = other.execution.policy.or(self.execution.policy);
+        self.execution.debounce_ms = other.execution.debounce_ms.or(self.execution.debounce_ms);
+        self.execution.confirm_expensive = other
+            .execution
+            .confirm_expensive
+            .or(self.execution.confirm_expensive);
+        self.execution.timeout_ms = other.execution.timeout_ms.or(self.execution.timeout_ms);
+        self.auto_pairs = other.auto_pairs.or(self.auto_pairs);
+        self.compact_layout = other.compact_layout.or(self.compact_layout);
+        self
+    }
+}
+
+/// Directory holding the user configuration and the trust store
+pub fn user_config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()) {
+        return Some(PathBuf::from(dir).join("rjq"));
+    }
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("rjq"))
+}
+
+/// Searches `start` and its ancestors for a project configuration file
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join(PROJECT_CONFIG_NAME))
+        .find(|candidate| candidate.is_file())
+}
+
+/// Directory where project config discovery starts for the given input
+pub fn discovery_root(input_path: Option<&str>) -> PathBuf {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    let Some(input) = input_path else {
+        return cwd;
+    };
+    let input_dir = cwd.join(input);
+    match input_dir.parent() {
+        Some(parent) => parent.to_path_buf(),
+        None => cwd,
+    }
+}
+
+/// Result of loading the user and project configuration
+#[derive(Debug, Default)]
+pub struct LoadedConfig {
+    /// Merged configuration, excluding any untrusted project code
+    pub config: FileConfig,
+    /// Project code that needs to be trusted before it is used
+    pub untrusted: Option<UntrustedCode>,
+    /// Project file whose `[credentials]` were ignored
+    pub ignored_credentials: Option<PathBuf>,
+}
+
+/// jq programs of a project file, held back until the file is trusted
+#[derive(Debug)]
+pub struct UntrustedCode {
+    pub path: PathBuf,
+    pub prelude: Option<String>,
+    /// Rules whose `when` predicates run on the rows of every table shown
+    pub row_rules: Vec<RowRule>,
+}
+
+impl UntrustedCode {
+    /// The programs as shown when asking whether to trust the file
+    pub fn describe(&self) -> String {
+        let prelude = self
+            .prelude
+            .iter()
+            .map(|prelude| format!("prelude, run with every query:\n{}\n", prelude));
+        let rules = self
+            .row_rules
+            .iter()
+            .map(|rule| format!("row rule, run on every table row:\n{}\n", rule.when));
+        prelude.chain(rules).collect::<Vec<_>>().join("\n")
+    }
+
+    /// The held-back settings, to merge once the file is trusted
+    pub fn into_config(self) -> FileConfig {
+        FileConfig {
+            prelude: self.prelude,
+            row_rules: self.row_rules,
+            ..Default::default()
+        }
+    }
+}
+
+/// Loads `~/.config/rjq/config.toml` and the nearest `.rjq.toml` above `start`
+///
+/// A project prelude and row rules are only merged when the trust store already knows the
+/// file with its current content; otherwise it is returned separately so the
+/// caller can ask the user. Project credentials are never merged: a checked-out
+/// repository must not pick where the user's secrets are sent.
+pub fn load(start: &Path, trust: &TrustStore) -> crate::Result<LoadedConfig> {
+    let user_config = match user_config_dir().map(|dir| dir.join("config.toml")) {
+        Some(path) if path.is_file() => FileConfig::load(&path)?,
+        _ => FileConfig::default(),
+    };
+
+    let Some(project_path) = find_project_config(start) else {
+        return Ok(LoadedConfig {
+            config: user_config,
+            ..Default::default()
+        });
+    };
+
+    let content = std::fs::read_to_string(&project_path)?;
+    let mut project = FileConfig::parse(&content, &project_path)?;
+    let mut untrusted = None;
+    if (project.prelude.is_some() || !project.row_rules.is_empty())
+        && !trust.is_trusted(&project_path, &content)
+    {
+        untrusted = Some(UntrustedCode {
+            path: project_path.clone(),
+            prelude: project.prelude.take(),
+            row_rules: std::mem::take(&mut project.row_rules),
+        });
+    }
+    let ignored_credentials =
+        (!std::mem::take(&mut project.credentials).is_empty()).then_some(project_path);
+
+    Ok(LoadedConfig {
+        config: user_config.merge(project),
+        untrusted,
+        ignored_credentials,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RuleColor;
+
+    #[test]
+    fn test_parse_config() {
+        let config = FileConfig::parse(
+            r#"
+prelude = "def active: select(.active);"
+
+[bookmarks]
+names = ".users[].name"
+
+[display_filters]
+no_meta = "del(.metadata)"
+
+[aliases]
+sel = "select($0)"
+
+[args]
+env = "prod"
+
+[presets.emails]
+query = ".users[].email"
+args = { domain = "example.com" }
+"#,
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+
+        assert_eq!(
+            config.prelude.as_deref(),
+            Some("def active: select(.active);")
+        );
+        assert_eq!(config.bookmarks["names"], ".users[].name");
+        assert_eq!(config.display_filters["no_meta"], "del(.metadata)");
+        assert_eq!(config.aliases["sel"], "select($0)");
+        assert_eq!(config.args["env"], "prod");
+        assert_eq!(
+            config.presets["emails"].query.as_deref(),
+            Some(".users[].email")
+        );
+        assert_eq!(config.presets["emails"].args["domain"], "example.com");
+        assert_eq!(
+            config.variables(config.presets.get("emails")),
+            vec![
+                ("env".to_string(), serde_json::json!("prod")),
+                ("domain".to_string(), serde_json::json!("example.com")),
+            ]
+        );
+        assert_eq!(config.auto_pairs, None);
+
+        let config = FileConfig::parse(
+            "auto_pairs = false\ncompact_layout = true",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        assert_eq!(config.auto_pairs, Some(false));
+        assert_eq!(config.compact_layout, Some(true));
+    }
+
+    #[test]
+    fn test_parse_number_formats() {
+        let config = FileConfig::parse(
+            "[number_formats]\n\"*_bytes\" = \"bytes\"\nprice = \"decimals:2\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        assert_eq!(config.number_formats["*_bytes"], NumberFormat::Bytes);
+        assert_eq!(config.number_formats["price"], NumberFormat::Decimals(2));
+
+        let err = FileConfig::parse(
+            "[number_formats]\nsize = \"hex\"\n",
+            Path::new("/p/.rjq.toml"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::Config { ref path, .. } if path == "/p/.rjq.toml"));
+        assert!(err.to_string().contains("hex"));
+    }
+
+    #[test]
+    fn test_parse_credentials() {
+        let config = FileConfig::parse(
+            r#"
+[credentials."api.example.com"]
+bearer = "${PATH}"
+
+[credentials.localhost]
+username = "me"
+password = "secret"
+headers = { "X-Team" = "core" }
+"#,
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        let path_value = std::env::var("PATH").unwrap();
+        assert_eq!(
+            config.credentials["api.example.com"].bearer.as_deref(),
+            Some(path_value.as_str())
+        );
+        assert_eq!(config.credentials["localhost"].headers["X-Team"], "core");
+
+        let err = FileConfig::parse("[credentials.h]\ntoken = \"x\"\n", Path::new(".rjq.toml"))
+            .unwrap_err();
+        assert!(matches!(err, AppError::Config { .. }));
+    }
+
+    #[test]
+    fn test_parse_highlight_rules() {
+        let config = FileConfig::parse(
+            r#"
+[[highlight_rules]]
+key = "level"
+equals = "error"
+color = "red"
+bold = true
+
+[[highlight_rules]]
+key = "*_id"
+dim = true
+"#,
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        assert_eq!(config.highlight_rules.len(), 2);
+        assert_eq!(
+            config.highlight_rules[0].equals,
+            Some(serde_json::json!("error"))
+        );
+        let rules = config.highlight_rules().unwrap();
+        let style = rules.style("order_id", &serde_json::json!(1)).unwrap();
+        assert!(style.dim && style.color.is_none());
+
+        let config = FileConfig::parse(
+            "[[row_rules]]\nwhen = \".status >= 500\"\ncolor = \"red\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        assert_eq!(config.row_rules[0].when, ".status >= 500");
+        assert_eq!(config.row_rules[0].style().color, Some(RuleColor::Red));
+
+        let err = FileConfig::parse(
+            "[[highlight_rules]]\nkey = \"level\"\ncolor = \"chartreuse\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap_err();
+        assert!(matches!(err, AppError::Config { .. }));
+    }
+
+    #[test]
+    fn test_parse_error_names_file() {
+        let err = FileConfig::parse("unknown_key = 1", Path::new("/p/.rjq.toml")).unwrap_err();
+        assert!(matches!(err, AppError::Config { ref path, .. } if path == "/p/.rjq.toml"));
+    }
+
+    #[test]
+    fn test_parse_interpolates_environment() {
+        // PATH is set in any environment the tests run in
+        let path_value = std::env::var("PATH").unwrap();
+        let config =
+            FileConfig::parse("[args]\npath = \"${PATH}\"\n", Path::new(".rjq.toml")).unwrap();
+        assert_eq!(config.args["path"], path_value);
+
+        let err = FileConfig::parse(
+            "[presets.p.args]\ntoken = \"${RJQ_SURELY_UNSET_VARIABLE}\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap_err();
+        let message = err.to_string();
+        assert!(message.contains("RJQ_SURELY_UNSET_VARIABLE"));
+        assert!(message.contains("presets.p.args.token"));
+    }
+
+    #[test]
+    fn test_merge_project_over_user() {
+        let user = FileConfig {
+            prelude: Some("def a: 1;".to_string()),
+            bookmarks: BTreeMap::from([
+                ("x".to_string(), ".user_x".to_string()),
+                ("y".to_string(), ".user_y".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let project = FileConfig {
+            prelude: Some("def b: 2;".to_string()),
+            bookmarks: BTreeMap::from([("x".to_string(), ".project_x".to_string())]),
+            ..Default::default()
+        };
+
+        let merged = user.merge(project);
+        assert_eq!(merged.prelude.as_deref(), Some("def a: 1;\ndef b: 2;"));
+        assert_eq!(merged.bookmarks["x"], ".project_x");
+        assert_eq!(merged.bookmarks["y"], ".user_y");
+    }
+
+    #[test]
+    fn test_redaction_config() {
+        let user = FileConfig::parse(
+            "[redaction]\npatterns = [\"Bearer [A-Za-z0-9._-]+\"]\n",
+            Path::new("config.toml"),
+        )
+        .unwrap();
+        let project = FileConfig::parse(
+            "[redaction]\npatterns = [\"sk_[a-z0-9]+\"]\nmode = \"skip\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+
+        let merged = user.merge(project);
+        assert_eq!(merged.redaction.patterns.len(), 2);
+        assert_eq!(merged.redaction.mode, Some(RedactMode::Skip));
+        let redactor = merged.redactor().unwrap();
+        assert_eq!(redactor.apply(r#""Bearer abc""#), None);
+        assert_eq!(redactor.apply(".a"), Some(".a".to_string()));
+
+        let invalid = FileConfig {
+            redaction: RedactionConfig {
+                patterns: vec!["[".to_string()],
+                mode: None,
+            },
+            ..Default::default()
+        };
+        assert!(matches!(invalid.redactor(), Err(AppError::Config { .. })));
+    }
+
+    #[test]
+    fn test_history_config() {
+        let user = FileConfig::parse(
+            "[history]\nranking = \"frequency\"\nhalf_life_hours = 12\n",
+            Path::new("config.toml"),
+        )
+        .unwrap();
+        let project =
+            FileConfig::parse("[history]\nranking = \"decay\"\n", Path::new(".rjq.toml")).unwrap();
+
+        let merged = user.merge(project);
+        assert_eq!(merged.history.ranking, Some(Ranking::Decay));
+        assert_eq!(merged.history.half_life_hours, Some(12.0));
+        assert!(format!("{:?}", merged.ranking()).contains("43200"));
+
+        assert!(FileConfig::parse("[history]\nranking = \"best\"\n", Path::new("c.toml")).is_err());
+    }
+
+    #[test]
+    fn test_execution_config() {
+        let defaults = FileConfig::default();
+        assert_eq!(defaults.execution_policy(100), ExecutionPolicy::Immediate);
+        assert_eq!(defaults.execution_policy(u64::MAX), ExecutionPolicy::Manual);
+
+        let user =
+            FileConfig::parse("[execution]\ndebounce_ms = 50\n", Path::new("config.toml")).unwrap();
+        let project = FileConfig::parse(
+            "[execution]\npolicy = \"debounced\"\n",
+            Path::new(".rjq.toml"),
+        )
+        .unwrap();
+        assert_eq!(
+            user.merge(project).execution_policy(100),
+            ExecutionPolicy::Debounced(Duration::from_millis(50))
+        );
+        assert!(
+            FileConfig::parse("[execution]\npolicy = \"lazy\"\n", Path::new("c.toml")).is_err()
+        );
+
+        assert_eq!(defaults.query_timeout(), Some(DEFAULT_QUERY_TIMEOUT));
+        let endless =
+            FileConfig::parse("[execution]\ntimeout_ms = 0\n", Path::new("c.toml")).unwrap();
+        assert_eq!(endless.query_timeout(), None);
+        assert_eq!(
+            endless
+                .merge(
+                    FileConfig::parse("[execution]\ntimeout_ms = 5\n", Path::new("c.toml"))
+                        .unwrap()
+                )
+                .query_timeout(),
+            Some(Duration::from_millis(5))
+        );
+
+        assert!(!defaults.confirm_expensive(100));
+        assert!(defaults.confirm_expensive(EXPENSIVE_LIMIT));
+        let off = FileConfig::parse(
+            "[execution]\nconfirm_expensive = false\n",
+            Path::new("c.toml"),
+        )
+        .unwrap();
+        assert!(!defaults.merge(off).confirm_expensive(EXPENSIVE_LIMIT));
+    }
+
+    #[test]
+    fn test_find_project_config_searches_ancestors() {
+        let root =
+            std::env::temp_dir().join(format!("rjq_config_discovery_{}", std::process::id()));
+        let nested = root.join("a").join("b");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join(PROJECT_CONFIG_NAME), "").unwrap();
+
+        let found = find_project_config(&nested);
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(found, Some(root.join(PROJECT_CONFIG_NAME)));
+    }
+
+    #[test]
+    fn test_untrusted_prelude_is_held_back() {
+        let root =
+            std::env::temp_dir().join(format!("rjq_config_untrusted_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join(PROJECT_CONFIG_NAME),
+            "prelude = \"def f: 1;\"\n[bookmarks]\nb = \".b\"\n[[row_rules]]\nwhen = \".a\"\ncolor = \"red\"\n",
+        )
+        .unwrap();
+
+        let trust = TrustStore::default();
+        let loaded = load(&root, &trust).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+
+        assert_eq!(loaded.config.bookmarks["b"], ".b");
+        assert!(loaded.config.row_rules.is_empty());
+        let untrusted = loaded.untrusted.unwrap();
+        assert_eq!(untrusted.prelude.as_deref(), Some("def f: 1;"));
+        assert_eq!(untrusted.row_rules[0].when, ".a");
+    }
+
+    #[test]
+    fn test_project_credentials_are_ignored() {
+        let root =
+            std::env::temp_dir().join(format!("rjq_config_credentials_{}", std::process::id()));
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(
+            root.join(PROJECT_CONFIG_NAME),
+            "[credentials.\"api.example.com\"]\nheaders = { \"X-Api-Key\" = \"k\" }\n",
+        )
+        .unwrap();
+
+        let loaded = load(&root, &TrustStore::default()).unwrap();
+        std::fs::remove_dir_all(&root).ok();
+
+        assert!(!loaded.config.credentials.contains_key("api.example.com"));
+        assert_eq!(
+            loaded.ignored_credentials,
+            Some(root.join(PROJECT_CONFIG_NAME))
+        );
+    }
+}
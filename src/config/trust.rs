@@ -0,0 +1,106 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Trusted project configs that contain executable definitions
+///
+/// Each entry pins a config file to a hash of its content, so editing a
+/// trusted file requires trusting it again.
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    file: Option<PathBuf>,
+    entries: BTreeMap<PathBuf, String>,
+}
+
+impl TrustStore {
+    /// Loads the store from `~/.config/rjq/trusted`
+    pub fn load_default() -> Self {
+        match super::user_config_dir() {
+            Some(dir) => Self::load(dir.join("trusted")),
+            None => Self::default(),
+        }
+    }
+
+    /// Loads a store backed by `file`; a missing file yields an empty store
+    pub fn load(file: PathBuf) -> Self {
+        let entries = std::fs::read_to_string(&file)
+            .unwrap_or_default()
+            .lines()
+            .filter_map(|line| line.split_once(' '))
+            .map(|(hash, path)| (PathBuf::from(path), hash.to_string()))
+            .collect();
+        Self {
+            file: Some(file),
+            entries,
+        }
+    }
+
+    pub fn is_trusted(&self, config_path: &Path, content: &str) -> bool {
+        self.entries.get(&canonical(config_path)) == Some(&content_hash(content))
+    }
+
+    /// Marks `config_path` with its current `content` as trusted and saves the store
+    pub fn trust(&mut self, config_path: &Path, content: &str) -> std::io::Result<()> {
+        self.entries
+            .insert(canonical(config_path), content_hash(content));
+        self.save()
+    }
+
+    fn save(&self) -> std::io::Result<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut out = std::fs::File::create(file)?;
+        for (path, hash) in &self.entries {
+            writeln!(out, "{} {}", hash, path.display())?;
+        }
+        Ok(())
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// FNV-1a hash; stable across Rust versions unlike `DefaultHasher`
+pub(crate) fn content_hash(content: &str) -> String {
+    let hash = content.bytes().fold(0xcbf29ce484222325u64, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    });
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trust_round_trip() {
+        let dir = std::env::temp_dir().join(format!("rjq_trust_test_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store_file = dir.join("trusted");
+        let config = dir.join(".rjq.toml");
+        std::fs::write(&config, "prelude = \"def f: 1;\"").unwrap();
+
+        let mut store = TrustStore::load(store_file.clone());
+        assert!(!store.is_trusted(&config, "prelude = \"def f: 1;\""));
+        store.trust(&config, "prelude = \"def f: 1;\"").unwrap();
+
+        let reloaded = TrustStore::load(store_file);
+        let trusted = reloaded.is_trusted(&config, "prelude = \"def f: 1;\"");
+        let trusted_after_edit = reloaded.is_trusted(&config, "prelude = \"def f: 2;\"");
+        std::fs::remove_dir_all(&dir).ok();
+
+        assert!(trusted);
+        assert!(!trusted_after_edit);
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash(""), "cbf29ce484222325");
+        assert_ne!(content_hash("a"), content_hash("b"));
+    }
+}
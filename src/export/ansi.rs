@@ -0,0 +1,98 @@
+use crate::ui::SyntaxHighlighter;
+use ratatui::style::{Color, Modifier, Style};
+use std::io::{self, Write};
+
+const RESET: &str = "\x1b[0m";
+
+/// Highlights pretty-printed JSON with ANSI escape codes, using the same
+/// colors as the TUI
+pub fn render_ansi(json: &str) -> String {
+    let mut out = Vec::with_capacity(json.len() * 2);
+    // Writing to memory cannot fail
+    let _ = write_ansi(&mut out, json);
+    String::from_utf8(out).expect("escape codes keep the text valid UTF-8")
+}
+
+/// Streams the highlighted text of [`render_ansi`] to `out`
+pub fn write_ansi<W: Write>(mut out: W, json: &str) -> io::Result<()> {
+    let highlighter = SyntaxHighlighter::new();
+    let mut last_end = 0;
+    for token in highlighter.tokenize(json) {
+        out.write_all(&json.as_bytes()[last_end..token.start])?;
+        match sgr(highlighter.get_style(&token.token_type)) {
+            Some(codes) => write!(out, "\x1b[{}m{}{}", codes, token.text, RESET)?,
+            None => out.write_all(token.text.as_bytes())?,
+        }
+        last_end = token.end;
+    }
+    out.write_all(&json.as_bytes()[last_end..])
+}
+
+/// SGR parameters for `style`, or `None` when it changes nothing
+fn sgr(style: Style) -> Option<String> {
+    let mut codes = Vec::new();
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(color) = style.fg.and_then(foreground) {
+        codes.push(color);
+    }
+    (!codes.is_empty()).then(|| codes.join(";"))
+}
+
+fn foreground(color: Color) -> Option<String> {
+    let code = match color {
+        Color::Reset => return None,
+        Color::Black => "30",
+        Color::Red => "31",
+        Color::Green => "32",
+        Color::Yellow => "33",
+        Color::Blue => "34",
+        Color::Magenta => "35",
+        Color::Cyan => "36",
+        Color::Gray => "37",
+        Color::DarkGray => "90",
+        Color::LightRed => "91",
+        Color::LightGreen => "92",
+        Color::LightYellow => "93",
+        Color::LightBlue => "94",
+        Color::LightMagenta => "95",
+        Color::LightCyan => "96",
+        Color::White => "97",
+        Color::Indexed(index) => return Some(format!("38;5;{}", index)),
+        Color::Rgb(r, g, b) => return Some(format!("38;2;{};{};{}", r, g, b)),
+    };
+    Some(code.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_ansi() {
+        let out = render_ansi("{\n  \"a\": [1, null]\n}");
+        assert_eq!(
+            out,
+            "\x1b[97m{\x1b[0m\n  \x1b[34m\"a\"\x1b[0m\x1b[37m:\x1b[0m \x1b[97m[\x1b[0m\x1b[36m1\x1b[0m\x1b[37m,\x1b[0m \x1b[37mnull\x1b[0m\x1b[97m]\x1b[0m\n\x1b[97m}\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_stripping_escapes_gives_original() {
+        let json = "[\"x\", true, {\"k\": -1.5e3}]";
+        let out = render_ansi(json);
+        let stripped = regex::Regex::new("\x1b\\[[0-9;]*m")
+            .unwrap()
+            .replace_all(&out, "");
+        assert_eq!(stripped, json);
+    }
+}
@@ -0,0 +1,366 @@
+use crate::input::InputFormat;
+use crate::source::compression::{self, Compression};
+use crate::source::{LoadOptions, http};
+use clap::ValueEnum;
+use serde_json::Value;
+use std::path::Path;
+
+/// Quotes `text` for POSIX shells using single quotes
+pub fn shell_quote(text: &str) -> String {
+    if !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./=:,+@%".contains(c))
+    {
+        return text.to_string();
+    }
+    format!("'{}'", text.replace('\'', r"'\''"))
+}
+
+/// Command lines reproducing the current exploration
+#[derive(Debug, Clone, Default)]
+pub struct CommandExport {
+    pub query: String,
+    /// Input file or URL; `None` means the data came from stdin
+    pub input_path: Option<String>,
+    /// Definitions the query may use, from the config's `prelude`
+    pub prelude: Option<String>,
+    /// Values bound to `$name`, e.g. from `--arg` or `--slurpfile`
+    pub variables: Vec<(String, Value)>,
+    /// How the input was read
+    pub input: InputFlags,
+}
+
+/// Flags reproducing how the input was read
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct InputFlags {
+    /// Format chosen on the command line; guessed from the file name otherwise
+    pub format: Option<InputFormat>,
+    pub lenient: bool,
+    pub slurp: bool,
+    pub delimiter: Option<u8>,
+    /// CSV/TSV input has no header row
+    pub no_header: bool,
+    /// Read the subdirectories of a directory input too
+    pub recursive: bool,
+    /// Names of the files read from a directory input
+    pub include: Option<String>,
+    /// Pattern of the files read with `--glob`, in place of an input path
+    pub glob: Option<String>,
+    /// jq cannot read the input whatever the flags: it is compressed, a
+    /// directory, or its name implies a format other than JSON
+    pub needs_rjq: bool,
+}
+
+impl InputFlags {
+    /// The flags of `options` for the input at `path` (a file, directory or URL)
+    pub fn from_options(options: &LoadOptions, path: Option<&str>) -> Self {
+        let needs_rjq = path.is_some_and(|path| {
+            let path = match http::is_url(path) {
+                true => Path::new(http::url_path(path)),
+                false => Path::new(path),
+            };
+            let format = InputFormat::resolve(options.format, Some(&compression::inner_path(path)));
+            Compression::from_path(path).is_some()
+                || path.is_dir()
+                || !matches!(format, InputFormat::Json | InputFormat::Ndjson)
+        });
+        Self {
+            format: options.format,
+            lenient: options.lenient,
+            slurp: options.slurp,
+            delimiter: options.delimiter,
+            no_header: !options.headers,
+            recursive: options.recursive,
+            include: options.include.clone(),
+            glob: options.glob.clone(),
+            needs_rjq,
+        }
+    }
+
+    /// Whether jq cannot read the input itself, so rjq turns it into JSON first
+    fn needs_conversion(&self) -> bool {
+        self.needs_rjq
+            || self.lenient
+            || self.glob.is_some()
+            || matches!(
+                self.format,
+                Some(InputFormat::Json5 | InputFormat::Yaml | InputFormat::Csv | InputFormat::Tsv)
+            )
+    }
+
+    /// The rjq flags reading the input the same way
+    fn rjq_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(format) = self.format.and_then(|f| f.to_possible_value()) {
+            args.extend(["--format".to_string(), format.get_name().to_string()]);
+        }
+        if self.lenient {
+            args.push("--lenient".to_string());
+        }
+        if let Some(delimiter) = self.delimiter {
+            let delimiter = match delimiter {
+                b'\t' => "\\t".to_string(),
+                byte => char::from(byte).to_string(),
+            };
+            args.extend(["--delimiter".to_string(), shell_quote(&delimiter)]);
+        }
+        if self.no_header {
+            args.push("--no-header".to_string());
+        }
+        if self.recursive {
+            args.push("--recursive".to_string());
+        }
+        if let Some(include) = &self.include {
+            args.extend(["--include".to_string(), shell_quote(include)]);
+        }
+        if let Some(glob) = &self.glob {
+            args.extend(["--glob".to_string(), shell_quote(glob)]);
+        }
+        if self.slurp {
+            args.push("-s".to_string());
+        }
+        args
+    }
+}
+
+impl CommandExport {
+    pub fn new(query: impl Into<String>, input_path: Option<String>) -> Self {
+        Self {
+            query: query.into(),
+            input_path,
+            ..Default::default()
+        }
+    }
+
+    /// Equivalent `jq` invocation; jq cannot fetch URLs, so curl pipes them
+    /// in, and input jq cannot parse is converted to JSON by rjq
+    pub fn jq_command(&self) -> String {
+        if self.input.needs_conversion() {
+            let convert = Self {
+                query: ".".to_string(),
+                input_path: self.input_path.clone(),
+                input: self.input.clone(),
+                ..Default::default()
+            };
+            return format!("{} | {}", convert.rjq_command(), self.jq(false, None));
+        }
+        match self.input_path.as_deref() {
+            Some(url) if crate::source::http::is_url(url) => format!(
+                "curl -fsSL {} | {}",
+                shell_quote(url),
+                self.jq(self.input.slurp, None)
+            ),
+            path => self.jq(self.input.slurp, path),
+        }
+    }
+
+    fn jq(&self, slurp: bool, path: Option<&str>) -> String {
+        let mut parts = vec!["jq".to_string()];
+        if slurp {
+            parts.push("-s".to_string());
+        }
+        parts.extend(self.variable_args(false));
+        parts.push(shell_quote(&self.program()));
+        parts.extend(path.map(shell_quote));
+        parts.join(" ")
+    }
+
+    /// Equivalent rjq batch invocation
+    pub fn rjq_command(&self) -> String {
+        let mut parts = vec!["rjq".to_string()];
+        parts.extend(self.input.rjq_args());
+        parts.extend(self.variable_args(true));
+        parts.extend(["-q".to_string(), shell_quote(&self.program())]);
+        parts.extend(self.input_path.as_deref().map(shell_quote));
+        parts.join(" ")
+    }
+
+    /// `--arg` for strings and `--argjson` for other values, understood by
+    /// both jq and rjq; rjq expands `${VAR}` in `--arg`, so it is escaped
+    fn variable_args(&self, rjq: bool) -> Vec<String> {
+        let mut args = Vec::new();
+        for (name, value) in &self.variables {
+            let (flag, text) = match value {
+                Value::String(text) if rjq => ("--arg", text.replace("${", "$${")),
+                Value::String(text) => ("--arg", text.clone()),
+                other => ("--argjson", other.to_string()),
+            };
+            args.extend([flag.to_string(), shell_quote(name), shell_quote(&text)]);
+        }
+        args
+    }
+
+    /// The query with the prelude in front, as the executor runs it
+    fn program(&self) -> String {
+        match self.prelude.as_deref().filter(|p| !p.trim().is_empty()) {
+            Some(prelude) => format!("{}\n{}", prelude, self.query),
+            None => self.query.clone(),
+        }
+    }
+
+    /// Standalone shell script running the query with jq
+    pub fn shell_script(&self) -> String {
+        format!(
+            "#!/bin/sh\n# Generated by rjq\nset -e\n\n{}\n\n# rjq equivalent:\n# {}\n",
+            self.jq_command(),
+            // A prelude spreads the query over several lines
+            self.rjq_command().replace('\n', "\n# ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_shell_quote() {
+        assert_eq!(shell_quote("data.json"), "data.json");
+        assert_eq!(shell_quote(".users[0]"), "'.users[0]'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+
+    #[test]
+    fn test_jq_and_rjq_commands() {
+        let export = CommandExport::new(".users[] | .name", Some("data.json".to_string()));
+        assert_eq!(export.jq_command(), "jq '.users[] | .name' data.json");
+        assert_eq!(export.rjq_command(), "rjq -q '.users[] | .name' data.json");
+
+        let from_stdin = CommandExport::new(".", None);
+        assert_eq!(from_stdin.jq_command(), "jq .");
+
+        let from_url = CommandExport::new(".[0]", Some("https://x.test/a?b=1".to_string()));
+        assert_eq!(
+            from_url.jq_command(),
+            "curl -fsSL 'https://x.test/a?b=1' | jq '.[0]'"
+        );
+        assert_eq!(
+            from_url.rjq_command(),
+            "rjq -q '.[0]' 'https://x.test/a?b=1'"
+        );
+    }
+
+    #[test]
+    fn test_variables_and_slurp() {
+        let export = CommandExport {
+            variables: vec![
+                ("name".to_string(), json!("Ann O'Neil")),
+                ("ids".to_string(), json!([1, 2])),
+            ],
+            input: InputFlags {
+                slurp: true,
+                ..Default::default()
+            },
+            ..CommandExport::new("map(select(.id | IN($ids[])))", Some("a.json".to_string()))
+        };
+        assert_eq!(
+            export.jq_command(),
+            r#"jq -s --arg name 'Ann O'\''Neil' --argjson ids '[1,2]' 'map(select(.id | IN($ids[])))' a.json"#
+        );
+        assert_eq!(
+            export.rjq_command(),
+            r#"rjq -s --arg name 'Ann O'\''Neil' --argjson ids '[1,2]' -q 'map(select(.id | IN($ids[])))' a.json"#
+        );
+    }
+
+    #[test]
+    fn test_rjq_arg_keeps_literal_interpolation() {
+        let export = CommandExport {
+            variables: vec![("t".to_string(), json!("${HOME}"))],
+            ..CommandExport::new("$t", None)
+        };
+        assert_eq!(export.jq_command(), "jq --arg t '${HOME}' '$t'");
+        assert_eq!(export.rjq_command(), "rjq --arg t '$${HOME}' -q '$t'");
+    }
+
+    #[test]
+    fn test_input_jq_cannot_read_is_converted() {
+        let export = CommandExport {
+            input: InputFlags {
+                format: Some(InputFormat::Csv),
+                delimiter: Some(b';'),
+                slurp: true,
+                ..Default::default()
+            },
+            ..CommandExport::new(".[0]", Some("orders.csv".to_string()))
+        };
+        assert_eq!(
+            export.jq_command(),
+            "rjq --format csv --delimiter ';' -s -q . orders.csv | jq '.[0]'"
+        );
+        assert_eq!(
+            export.rjq_command(),
+            "rjq --format csv --delimiter ';' -s -q '.[0]' orders.csv"
+        );
+
+        // The format and compression implied by the file name count too
+        for path in [
+            "data.yaml",
+            "conf.jsonc",
+            "x.json.gz",
+            "https://x.test/a.csv",
+        ] {
+            let input = InputFlags::from_options(&LoadOptions::default(), Some(path));
+            assert!(input.needs_conversion(), "{}", path);
+        }
+        for path in ["a.json", "events.ndjson", "https://x.test/items"] {
+            let input = InputFlags::from_options(&LoadOptions::default(), Some(path));
+            assert!(!input.needs_conversion(), "{}", path);
+        }
+        let export = CommandExport {
+            input: InputFlags::from_options(&LoadOptions::default(), Some("logs.ndjson.zst")),
+            ..CommandExport::new(".level", Some("logs.ndjson.zst".to_string()))
+        };
+        assert_eq!(export.jq_command(), "rjq -q . logs.ndjson.zst | jq .level");
+
+        let export = CommandExport {
+            input: InputFlags::from_options(
+                &LoadOptions {
+                    glob: Some("logs/*.json".to_string()),
+                    ..Default::default()
+                },
+                None,
+            ),
+            ..CommandExport::new(".id", None)
+        };
+        assert_eq!(
+            export.jq_command(),
+            "rjq --glob 'logs/*.json' -q . | jq .id"
+        );
+
+        let ndjson = CommandExport {
+            input: InputFlags {
+                format: Some(InputFormat::Ndjson),
+                ..Default::default()
+            },
+            ..CommandExport::new(".id", None)
+        };
+        assert_eq!(ndjson.jq_command(), "jq .id");
+        assert_eq!(ndjson.rjq_command(), "rjq --format ndjson -q .id");
+    }
+
+    #[test]
+    fn test_prelude_is_part_of_the_program() {
+        let export = CommandExport {
+            prelude: Some("def double: . * 2;".to_string()),
+            ..CommandExport::new(".n | double", None)
+        };
+        assert_eq!(export.jq_command(), "jq 'def double: . * 2;\n.n | double'");
+        assert!(
+            export
+                .shell_script()
+                .contains("# rjq -q 'def double: . * 2;\n# .n | double'")
+        );
+    }
+
+    #[test]
+    fn test_shell_script() {
+        let script = CommandExport::new(".a | keys", None).shell_script();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("\njq '.a | keys'\n"));
+        assert!(script.contains("# rjq -q '.a | keys'"));
+    }
+}
@@ -0,0 +1,150 @@
+use crate::ui::SyntaxHighlighter;
+use serde_json::Value;
+use std::io::{self, Write};
+
+const STYLE: &str = "body { background: #1e1e1e; color: #d4d4d4; font-family: monospace; }
+details > div { padding-left: 2ch; }
+summary { cursor: pointer; list-style-position: outside; }
+.line { white-space: pre; }
+.tok-key { color: #569cd6; }
+.tok-string { color: #6a9955; }
+.tok-number { color: #4ec9b0; }
+.tok-boolean { color: #dcdcaa; }
+.tok-null { color: #808080; }
+.tok-bracket { color: #ffffff; }
+.tok-punct { color: #808080; }
+.result + .result { border-top: 1px solid #444; margin-top: 1em; padding-top: 1em; }";
+
+/// Renders query results as a standalone HTML page with collapsible containers
+pub fn render_html(title: &str, values: &[Value]) -> String {
+    let mut out = Vec::new();
+    // Writing to memory cannot fail
+    let _ = write_html(&mut out, title, values);
+    String::from_utf8(out).expect("the page is built from strings")
+}
+
+/// Streams the page of [`render_html`] to `out`, one line at a time, so a
+/// large result is never held as a whole page in memory
+pub fn write_html<W: Write>(mut out: W, title: &str, values: &[Value]) -> io::Result<()> {
+    let highlighter = SyntaxHighlighter::new();
+    write!(
+        out,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<style>\n{}\n</style>\n</head>\n<body>\n<h1 class=\"line\">{}</h1>\n",
+        escape_html(title),
+        STYLE,
+        escape_html(title),
+    )?;
+    for value in values {
+        out.write_all(b"<div class=\"result\">\n")?;
+        render_node(&highlighter, &mut out, None, value, false)?;
+        out.write_all(b"</div>\n")?;
+    }
+    out.write_all(b"</body>\n</html>\n")?;
+    out.flush()
+}
+
+fn render_node<W: Write>(
+    highlighter: &SyntaxHighlighter,
+    out: &mut W,
+    key: Option<&str>,
+    value: &Value,
+    trailing_comma: bool,
+) -> io::Result<()> {
+    let prefix = key
+        .map(|k| format!("{}: ", Value::String(k.to_string())))
+        .unwrap_or_default();
+    let comma = if trailing_comma { "," } else { "" };
+
+    let children: Vec<(Option<&str>, &Value)> = match value {
+        Value::Object(map) if !map.is_empty() => {
+            map.iter().map(|(k, v)| (Some(k.as_str()), v)).collect()
+        }
+        Value::Array(items) if !items.is_empty() => items.iter().map(|v| (None, v)).collect(),
+        _ => {
+            let line = format!("{}{}{}", prefix, value, comma);
+            return write_line(highlighter, out, &line);
+        }
+    };
+
+    let (open, close) = if value.is_object() {
+        ("{", "}")
+    } else {
+        ("[", "]")
+    };
+    out.write_all(b"<details open><summary>")?;
+    write_line(highlighter, out, &format!("{}{}", prefix, open))?;
+    out.write_all(b"</summary>\n<div>\n")?;
+    let last = children.len() - 1;
+    for (i, (child_key, child)) in children.into_iter().enumerate() {
+        render_node(highlighter, out, child_key, child, i != last)?;
+    }
+    out.write_all(b"</div>\n")?;
+    write_line(highlighter, out, &format!("{}{}", close, comma))?;
+    out.write_all(b"</details>\n")
+}
+
+/// Wraps each token of `line` in a span carrying its CSS class
+fn write_line<W: Write>(
+    highlighter: &SyntaxHighlighter,
+    out: &mut W,
+    line: &str,
+) -> io::Result<()> {
+    out.write_all(b"<span class=\"line\">")?;
+    let mut last_end = 0;
+    for token in highlighter.tokenize(line) {
+        out.write_all(escape_html(&line[last_end..token.start]).as_bytes())?;
+        write!(
+            out,
+            "<span class=\"{}\">{}</span>",
+            token.token_type.css_class(),
+            escape_html(&token.text)
+        )?;
+        last_end = token.end;
+    }
+    out.write_all(escape_html(&line[last_end..]).as_bytes())?;
+    out.write_all(b"</span>\n")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_html_classes() {
+        let html = render_html(".", &[json!({"name": "Alice", "age": 30, "ok": true})]);
+
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert!(html.contains("<span class=\"tok-key\">&quot;name&quot;</span>"));
+        assert!(html.contains("<span class=\"tok-string\">&quot;Alice&quot;</span>"));
+        assert!(html.contains("<span class=\"tok-number\">30</span>"));
+        assert!(html.contains("<span class=\"tok-boolean\">true</span>"));
+    }
+
+    #[test]
+    fn test_containers_are_collapsible() {
+        let html = render_html(".", &[json!({"items": [1, 2], "empty": []})]);
+
+        // Root object and the non-empty array are collapsible, the empty one is not
+        assert_eq!(html.matches("<details open>").count(), 2);
+        assert!(
+            html.contains(
+                "<span class=\"tok-bracket\">[</span><span class=\"tok-bracket\">]</span>"
+            )
+        );
+    }
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(escape_html("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;");
+        let html = render_html("<title>", &[json!("<script>")]);
+        assert!(!html.contains("<script>"));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod ansi;
+pub mod command;
+pub mod html;
+pub mod progress;
+
+pub use ansi::{render_ansi, write_ansi};
+pub use command::{CommandExport, InputFlags, shell_quote};
+pub use html::{render_html, write_html};
+pub use progress::{ExportRun, ProgressWriter};
@@ -0,0 +1,125 @@
+//! Background exports and their progress
+//!
+//! Exporting a result of hundreds of megabytes takes a while. The export
+//! streams to its file on a worker thread and counts the bytes written, so
+//! the status line can show how far it got while the TUI stays responsive.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// `Write` counting the bytes written
+#[derive(Debug)]
+pub struct ProgressWriter<W> {
+    inner: W,
+    written: Arc<AtomicUsize>,
+}
+
+impl<W: Write> ProgressWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            written: Arc::default(),
+        }
+    }
+
+    /// Bytes written so far, readable from other threads
+    pub fn counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.written)
+    }
+}
+
+impl<W: Write> Write for ProgressWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.written.fetch_add(n, Ordering::Relaxed);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Export running on a worker thread
+#[derive(Debug)]
+pub struct ExportRun {
+    path: String,
+    written: Arc<AtomicUsize>,
+    result: Receiver<io::Result<()>>,
+}
+
+impl ExportRun {
+    /// Creates `path` and streams `write`'s output to it on a thread of its own
+    pub fn spawn<F>(path: &str, write: F) -> io::Result<Self>
+    where
+        F: FnOnce(&mut dyn Write) -> io::Result<()> + Send + 'static,
+    {
+        let mut out = ProgressWriter::new(BufWriter::new(File::create(path)?));
+        let written = out.counter();
+        let (sender, result) = mpsc::channel();
+        thread::spawn(move || {
+            let result = write(&mut out).and_then(|()| out.flush());
+            let _ = sender.send(result);
+        });
+        Ok(Self {
+            path: path.to_string(),
+            written,
+            result,
+        })
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Bytes handed to the file so far
+    pub fn written(&self) -> usize {
+        self.written.load(Ordering::Relaxed)
+    }
+
+    /// The outcome once the worker is done, without waiting
+    pub fn try_finish(&self) -> Option<io::Result<()>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(io::Error::other("the export panicked"))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_counts_bytes_written() {
+        let mut out = ProgressWriter::new(Vec::new());
+        let counter = out.counter();
+        write!(out, "{}", "x".repeat(10)).unwrap();
+        assert_eq!(counter.load(Ordering::Relaxed), 10);
+    }
+
+    #[test]
+    fn test_export_streams_to_file() {
+        let path = std::env::temp_dir().join(format!("rjq_export_run_{}.txt", std::process::id()));
+        let path = path.display().to_string();
+        let run = ExportRun::spawn(&path, |out| out.write_all(b"hello")).unwrap();
+        let result = loop {
+            if let Some(result) = run.try_finish() {
+                break result;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        result.unwrap();
+        assert_eq!(run.written(), 5);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        std::fs::remove_file(&path).ok();
+
+        assert!(ExportRun::spawn("/nonexistent-dir/out.txt", |_| Ok(())).is_err());
+    }
+}
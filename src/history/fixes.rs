@@ -0,0 +1,288 @@
+use crate::app::error::edit_distance;
+use crate::query::lexer::{QueryTokenKind, tokenize_query};
+use std::time::{Duration, SystemTime};
+
+/// Fixes remembered; the oldest are forgotten first
+const MAX_FIXES: usize = 50;
+
+/// Failures kept while waiting for the query that fixes them
+const MAX_FAILURES: usize = 32;
+
+/// How alike two errors or two query shapes must be, from 0 to 1, for a
+/// fix of one to be suggested for the other
+const MIN_SIMILARITY: f64 = 0.5;
+
+/// A failed query and the query that fixed it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fix {
+    pub failed: String,
+    pub error: String,
+    pub fixed: String,
+    /// Times the same fix was made
+    pub count: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Failure {
+    query: String,
+    error: String,
+    /// How long the failure was on screen, up to the last result seen
+    shown: Duration,
+}
+
+/// Record of fixes from failures to successes
+///
+/// Results are seen as the query is typed, so most failures are half-typed
+/// queries. When a query is recorded and did not fail, the failure shown
+/// longest since the previous recorded query is taken as the one it fixed,
+/// provided the two queries have a similar shape.
+#[derive(Debug, Clone, Default)]
+pub struct FixStore {
+    /// Oldest first
+    fixes: Vec<Fix>,
+    failures: Vec<Failure>,
+    /// The query of the last result seen, whether it failed, and when
+    last: Option<(String, bool, SystemTime)>,
+}
+
+impl FixStore {
+    /// Notes the result of `query` being shown at `now`, with the error
+    /// message when it failed
+    pub fn observe(&mut self, query: &str, error: Option<&str>, now: SystemTime) {
+        if let Some((last, true, since)) = &self.last
+            && let Some(failure) = self.failures.iter_mut().find(|f| &f.query == last)
+        {
+            failure.shown += now.duration_since(*since).unwrap_or_default();
+        }
+        if let Some(error) = error
+            && !self.failures.iter().any(|f| f.query == query)
+        {
+            if self.failures.len() == MAX_FAILURES {
+                self.failures.remove(0);
+            }
+            self.failures.push(Failure {
+                query: query.to_string(),
+                error: error.to_string(),
+                shown: Duration::ZERO,
+            });
+        }
+        self.last = Some((query.to_string(), error.is_some(), now));
+    }
+
+    /// Pairs `query`, recorded at `now`, with the failure it fixed, unless it
+    /// failed itself
+    pub fn recorded(&mut self, query: &str, now: SystemTime) {
+        if matches!(&self.last, Some((last, true, _)) if last == query) {
+            return;
+        }
+        // Counts the time up to now for the failure still on screen
+        self.observe(query, None, now);
+        let failures = std::mem::take(&mut self.failures);
+        let Some(failure) = failures
+            .into_iter()
+            .filter(|f| f.query != query && shape_similarity(&f.query, query) >= MIN_SIMILARITY)
+            .max_by_key(|f| f.shown)
+        else {
+            return;
+        };
+        match self
+            .fixes
+            .iter()
+            .position(|fix| fix.failed == failure.query && fix.fixed == query)
+        {
+            Some(i) => {
+                let mut fix = self.fixes.remove(i);
+                fix.count += 1;
+                fix.error = failure.error;
+                self.fixes.push(fix);
+            }
+            None => {
+                if self.fixes.len() == MAX_FIXES {
+                    self.fixes.remove(0);
+                }
+                self.fixes.push(Fix {
+                    failed: failure.query,
+                    error: failure.error,
+                    fixed: query.to_string(),
+                    count: 1,
+                });
+            }
+        }
+    }
+
+    /// The remembered fix of the failure most like `query` failing with
+    /// `error`; the same query failing again matches best
+    pub fn suggest(&self, query: &str, error: &str) -> Option<&Fix> {
+        self.fixes
+            .iter()
+            .filter(|fix| fix.fixed != query)
+            .filter_map(|fix| {
+                if fix.failed == query {
+                    return Some((fix, f64::MAX));
+                }
+                let errors = error_similarity(&fix.error, error);
+                let shapes = shape_similarity(&fix.failed, query);
+                (errors >= MIN_SIMILARITY && shapes >= MIN_SIMILARITY)
+                    .then_some((fix, errors + shapes + fix.count as f64 * 0.01))
+            })
+            // Later fixes win ties
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(fix, _)| fix)
+    }
+
+    pub fn fixes(&self) -> &[Fix] {
+        &self.fixes
+    }
+}
+
+/// Words of an error message, with quoted text and numbers left out so
+/// errors about different keys or values compare equal
+fn error_words(error: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut chars = error.chars();
+    let mut previous = ' ';
+    while let Some(c) = chars.next() {
+        if c.is_alphabetic() {
+            word.extend(c.to_lowercase());
+        } else {
+            if !word.is_empty() {
+                words.push(std::mem::take(&mut word));
+            }
+            if matches!(c, '"' | '`') {
+                chars.by_ref().take_while(|&end| end != c).for_each(drop);
+                words.push("_".to_string());
+            } else if c.is_ascii_digit() && !previous.is_ascii_digit() {
+                words.push("_".to_string());
+            }
+        }
+        previous = c;
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    words
+}
+
+/// Share of the words two errors have in common
+fn error_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (error_words(a), error_words(b));
+    let common = a.iter().filter(|word| b.contains(word)).count();
+    let total = a.len().max(b.len());
+    if total == 0 {
+        return 1.0;
+    }
+    common as f64 / total as f64
+}
+
+/// A query as one character per token: fields, strings, numbers,
+/// variables and function names by their kind, everything else by its text
+fn shape(query: &str) -> String {
+    tokenize_query(query)
+        .iter()
+        .filter(|token| token.kind != QueryTokenKind::Comment)
+        .map(|token| match token.kind {
+            QueryTokenKind::Field => 'f',
+            QueryTokenKind::String => 's',
+            QueryTokenKind::Number => 'n',
+            QueryTokenKind::Variable => 'v',
+            // A misspelt function is the same shape as the one meant
+            QueryTokenKind::Ident => 'i',
+            _ => {
+                // Distinct texts get distinct characters from a private-use range
+                let text = token.text(query);
+                let hash = text
+                    .bytes()
+                    .fold(0u32, |hash, b| hash.wrapping_mul(31).wrapping_add(b as u32));
+                char::from_u32(0xE000 + hash % 0x1900).unwrap_or('?')
+            }
+        })
+        .collect()
+}
+
+/// How alike two queries are token by token, from 0 to 1
+fn shape_similarity(a: &str, b: &str) -> f64 {
+    let (a, b) = (shape(a), shape(b));
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(&a, &b) as f64 / longest as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(secs: u64) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_longest_shown_failure_is_fixed() {
+        let mut store = FixStore::default();
+        store.observe(".users", None, at(0));
+        store.observe(".users | .n", Some("cannot index array with \"n\""), at(1));
+        store.observe(
+            ".users | .name",
+            Some("cannot index array with \"name\""),
+            at(2),
+        );
+        store.observe(".users[ | .name", Some("unclosed bracket"), at(30));
+        store.observe(".users[] | .name", None, at(31));
+        store.recorded(".users[] | .name", at(35));
+
+        assert_eq!(
+            store.fixes(),
+            &[Fix {
+                failed: ".users | .name".to_string(),
+                error: "cannot index array with \"name\"".to_string(),
+                fixed: ".users[] | .name".to_string(),
+                count: 1,
+            }]
+        );
+
+        // Another key fails the same way
+        let fix = store
+            .suggest(".items | .id", "cannot index array with \"id\"")
+            .unwrap();
+        assert_eq!(fix.fixed, ".users[] | .name");
+        assert!(store.suggest(".items | .id", "unclosed bracket").is_none());
+        assert!(
+            store
+                .suggest("[.a, .b] | add", "cannot index array with \"a\"")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_failing_or_unrelated_queries_are_not_fixes() {
+        let mut store = FixStore::default();
+        store.observe("lenght", Some("undefined filter lenght/0"), at(0));
+        store.recorded("lenght", at(5));
+        assert!(store.fixes().is_empty());
+
+        store.observe("[.a, .b, .c] | map(tostring) | join(\",\")", None, at(6));
+        store.recorded("[.a, .b, .c] | map(tostring) | join(\",\")", at(7));
+        assert!(store.fixes().is_empty());
+
+        store.observe("lenght", Some("undefined filter lenght/0"), at(8));
+        store.observe("length", None, at(10));
+        store.recorded("length", at(10));
+        assert_eq!(
+            store
+                .suggest("lenght", "undefined filter lenght/0")
+                .unwrap()
+                .fixed,
+            "length"
+        );
+    }
+
+    #[test]
+    fn test_error_words_ignore_quoted_text() {
+        assert_eq!(
+            error_words("cannot index \"first name\" of 3"),
+            vec!["cannot", "index", "_", "of", "_"]
+        );
+    }
+}
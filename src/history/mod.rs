@@ -1,12 +1,28 @@
+pub mod fixes;
+pub mod ranking;
+pub mod redact;
+pub mod stages;
+pub mod usage;
+
+use crate::clock::{Clock, SystemClock};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::SystemTime;
 
+pub use fixes::{Fix, FixStore};
+pub use ranking::{ExponentialDecay, Frecency, Frequency, MostRecent, Ranking, RankingStrategy};
+pub use redact::{RedactMode, Redactor};
+pub use stages::StageModel;
+pub use usage::{UsageLog, UsageRecord, UsageStats};
+
 #[derive(Debug, Clone)]
 pub struct QueryEntry {
     pub query: String,
     pub count: usize,
     pub last_used: SystemTime,
     pub first_used: SystemTime,
+    /// The query was cancelled for taking too long
+    pub slow: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -19,27 +35,74 @@ pub struct SuggestionItem {
 pub struct QueryHistory {
     entries: HashMap<String, QueryEntry>,
     max_entries: usize,
+    redactor: Redactor,
+    clock: Arc<dyn Clock>,
+    ranking: Box<dyn RankingStrategy>,
+    stages: StageModel,
+    fixes: FixStore,
 }
 
 impl QueryHistory {
     pub fn new(max_entries: usize) -> Self {
+        Self::with_clock(max_entries, Arc::new(SystemClock))
+    }
+
+    /// Uses `clock` instead of the system time for `first_used`/`last_used`
+    pub fn with_clock(max_entries: usize, clock: Arc<dyn Clock>) -> Self {
         Self {
             entries: HashMap::new(),
             max_entries,
+            redactor: Redactor::default(),
+            clock,
+            ranking: Box::new(Frecency),
+            stages: StageModel::default(),
+            fixes: FixStore::default(),
         }
     }
 
+    /// Changes how suggestions are ordered and which entries are evicted first
+    pub fn set_ranking(&mut self, ranking: Box<dyn RankingStrategy>) {
+        self.ranking = ranking;
+    }
+
+    pub fn entry(&self, query: &str) -> Option<&QueryEntry> {
+        self.entries.get(query)
+    }
+
+    /// Queries matching the redactor's patterns are masked or not recorded
+    pub fn set_redactor(&mut self, redactor: Redactor) {
+        self.redactor = redactor;
+    }
+
     pub fn record_query(&mut self, query: String) {
+        self.record(query, false);
+    }
+
+    /// Records a query that was cancelled before it finished, so
+    /// suggesting it again can warn that it is slow
+    pub fn record_slow_query(&mut self, query: String) {
+        self.record(query, true);
+    }
+
+    fn record(&mut self, query: String, slow: bool) {
         if query.trim().is_empty() {
             return;
         }
+        let Some(query) = self.redactor.apply(&query) else {
+            return;
+        };
 
-        let now = SystemTime::now();
+        let now = self.clock.now();
+        self.stages.learn(&query);
+        if !slow {
+            self.fixes.recorded(&query, now);
+        }
 
         match self.entries.get_mut(&query) {
             Some(entry) => {
                 entry.count += 1;
                 entry.last_used = now;
+                entry.slow |= slow;
             }
             None => {
                 let entry = QueryEntry {
@@ -47,6 +110,7 @@ impl QueryHistory {
                     count: 1,
                     last_used: now,
                     first_used: now,
+                    slow,
                 };
                 self.entries.insert(query, entry);
             }
@@ -56,6 +120,17 @@ impl QueryHistory {
     }
 
     pub fn get_suggestions(&self, prefix: &str, limit: usize) -> Vec<SuggestionItem> {
+        self.get_suggestions_with(prefix, limit, |_| 1.0)
+    }
+
+    /// Like [`get_suggestions`](Self::get_suggestions), with each score
+    /// multiplied by `boost(query)`
+    pub fn get_suggestions_with(
+        &self,
+        prefix: &str,
+        limit: usize,
+        boost: impl Fn(&str) -> f64,
+    ) -> Vec<SuggestionItem> {
         if prefix.len() < 2 {
             return vec![];
         }
@@ -65,7 +140,7 @@ impl QueryHistory {
             .values()
             .filter(|entry| entry.query.starts_with(prefix) && entry.query != prefix)
             .map(|entry| {
-                let score = self.calculate_score(entry);
+                let score = self.calculate_score(entry) * boost(&entry.query);
                 SuggestionItem {
                     text: entry.query.clone(),
                     score,
@@ -82,8 +157,29 @@ impl QueryHistory {
         candidates
     }
 
+    /// Notes that the result of `query` is being shown, with the error
+    /// message when it failed, so the query that fixes it can be remembered
+    pub fn note_result(&mut self, query: &str, error: Option<&str>) {
+        if query.trim().is_empty() {
+            return;
+        }
+        if let Some(query) = self.redactor.apply(query) {
+            self.fixes.observe(&query, error, self.clock.now());
+        }
+    }
+
+    /// The query that fixed an earlier failure like `query` failing with `error`
+    pub fn suggest_fix(&self, query: &str, error: &str) -> Option<&Fix> {
+        self.fixes.suggest(query, error)
+    }
+
+    /// Stages that followed `previous` in recorded queries, most frequent first
+    pub fn next_stages(&self, previous: &str) -> Vec<String> {
+        self.stages.next_stages(previous)
+    }
+
     fn calculate_score(&self, entry: &QueryEntry) -> f64 {
-        entry.count as f64
+        self.ranking.score(entry, self.clock.now())
     }
 
     fn cleanup_old_entries(&mut self) {
@@ -127,6 +223,19 @@ mod tests {
         assert!(name_entry.score > 0.0);
     }
 
+    #[test]
+    fn test_slow_queries_stay_flagged() {
+        let mut history = QueryHistory::new(100);
+        history.record_query(".name".to_string());
+        assert!(!history.entry(".name").unwrap().slow);
+
+        history.record_slow_query(".name".to_string());
+        history.record_query(".name".to_string());
+        let entry = history.entry(".name").unwrap();
+        assert!(entry.slow);
+        assert_eq!(entry.count, 3);
+    }
+
     #[test]
     fn test_prefix_matching() {
         let mut history = QueryHistory::new(100);
@@ -179,6 +288,95 @@ mod tests {
         assert_eq!(suggestions.len(), 0);
     }
 
+    #[test]
+    fn test_redacted_queries() {
+        let mut history = QueryHistory::new(100);
+        history
+            .set_redactor(Redactor::new(&["secret-[a-z]+".to_string()], RedactMode::Mask).unwrap());
+        history.record_query(r#".token == "secret-abc""#.to_string());
+
+        let suggestions = history.get_suggestions(".t", 5);
+        assert_eq!(suggestions.len(), 1);
+        assert_eq!(suggestions[0].text, r#".token == "***""#);
+
+        history
+            .set_redactor(Redactor::new(&["secret-[a-z]+".to_string()], RedactMode::Skip).unwrap());
+        history.record_query(r#".key == "secret-xyz""#.to_string());
+        assert!(history.get_suggestions(".k", 5).is_empty());
+    }
+
+    #[test]
+    fn test_timestamps_come_from_clock() {
+        use crate::clock::MockClock;
+        use std::time::Duration;
+
+        let clock = MockClock::default();
+        let mut history = QueryHistory::with_clock(100, Arc::new(clock.clone()));
+        history.record_query(".name".to_string());
+        clock.advance(Duration::from_secs(60));
+        history.record_query(".name".to_string());
+
+        let entry = history.entry(".name").unwrap();
+        assert_eq!(entry.first_used, SystemTime::UNIX_EPOCH);
+        assert_eq!(
+            entry.last_used,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(60)
+        );
+        assert_eq!(entry.count, 2);
+    }
+
+    #[test]
+    fn test_ranking_strategy_orders_suggestions() {
+        use crate::clock::MockClock;
+        use std::time::Duration;
+
+        let clock = MockClock::default();
+        let mut history = QueryHistory::with_clock(100, Arc::new(clock.clone()));
+        for _ in 0..3 {
+            history.record_query(".users".to_string());
+        }
+        clock.advance(Duration::from_secs(60));
+        history.record_query(".user_ids".to_string());
+
+        let top = |history: &QueryHistory| history.get_suggestions(".us", 1)[0].text.clone();
+        assert_eq!(top(&history), ".users");
+        history.set_ranking(Box::new(MostRecent));
+        assert_eq!(top(&history), ".user_ids");
+    }
+
+    #[test]
+    fn test_boosted_suggestions() {
+        let mut history = QueryHistory::new(100);
+        for _ in 0..3 {
+            history.record_query(".items[0]".to_string());
+        }
+        history.record_query(".id".to_string());
+
+        assert_eq!(history.get_suggestions(".i", 1)[0].text, ".items[0]");
+        let boosted =
+            history.get_suggestions_with(".i", 1, |q| if q == ".id" { 10.0 } else { 1.0 });
+        assert_eq!(boosted[0].text, ".id");
+    }
+
+    #[test]
+    fn test_fix_is_suggested_for_similar_failure() {
+        use crate::clock::MockClock;
+        use std::time::Duration;
+
+        let clock = MockClock::default();
+        let mut history = QueryHistory::with_clock(100, Arc::new(clock.clone()));
+        history.note_result(".users | .name", Some("cannot index array with \"name\""));
+        clock.advance(Duration::from_secs(10));
+        history.note_result(".users[] | .name", None);
+        history.record_query(".users[] | .name".to_string());
+
+        let fix = history
+            .suggest_fix(".orders | .total", "cannot index array with \"total\"")
+            .unwrap();
+        assert_eq!(fix.failed, ".users | .name");
+        assert_eq!(fix.fixed, ".users[] | .name");
+    }
+
     #[test]
     fn test_min_prefix_length() {
         let mut history = QueryHistory::new(100);
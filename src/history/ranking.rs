@@ -0,0 +1,185 @@
+use super::QueryEntry;
+use serde::Deserialize;
+use std::fmt::Debug;
+use std::time::{Duration, SystemTime};
+
+const HOUR: u64 = 60 * 60;
+const DAY: u64 = 24 * HOUR;
+
+/// How history candidates are ordered
+///
+/// Higher scores are suggested first and lower scores are evicted first
+/// when the history is full.
+pub trait RankingStrategy: Debug + Send + Sync {
+    fn score(&self, entry: &QueryEntry, now: SystemTime) -> f64;
+}
+
+/// Time since `entry` was last used; entries from the future count as fresh
+fn age(entry: &QueryEntry, now: SystemTime) -> Duration {
+    now.duration_since(entry.last_used).unwrap_or_default()
+}
+
+/// Use count only
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Frequency;
+
+impl RankingStrategy for Frequency {
+    fn score(&self, entry: &QueryEntry, _now: SystemTime) -> f64 {
+        entry.count as f64
+    }
+}
+
+/// Most recently used first, regardless of count
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MostRecent;
+
+impl RankingStrategy for MostRecent {
+    fn score(&self, entry: &QueryEntry, now: SystemTime) -> f64 {
+        -age(entry, now).as_secs_f64()
+    }
+}
+
+/// Use count weighted by recency buckets, like Firefox's frecency
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Frecency;
+
+impl Frecency {
+    /// (maximum age, weight) pairs; anything older gets [`Self::OLDEST_WEIGHT`]
+    const BUCKETS: [(u64, f64); 4] = [
+        (4 * DAY, 100.0),
+        (14 * DAY, 70.0),
+        (31 * DAY, 50.0),
+        (90 * DAY, 30.0),
+    ];
+    const OLDEST_WEIGHT: f64 = 10.0;
+}
+
+impl RankingStrategy for Frecency {
+    fn score(&self, entry: &QueryEntry, now: SystemTime) -> f64 {
+        let age = age(entry, now).as_secs();
+        let weight = Self::BUCKETS
+            .iter()
+            .find(|(max_age, _)| age < *max_age)
+            .map_or(Self::OLDEST_WEIGHT, |(_, weight)| *weight);
+        entry.count as f64 * weight
+    }
+}
+
+/// Use count halved for every `half_life` since the last use
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialDecay {
+    pub half_life: Duration,
+}
+
+impl Default for ExponentialDecay {
+    fn default() -> Self {
+        Self {
+            half_life: Duration::from_secs(7 * DAY),
+        }
+    }
+}
+
+impl RankingStrategy for ExponentialDecay {
+    fn score(&self, entry: &QueryEntry, now: SystemTime) -> f64 {
+        let half_lives = age(entry, now).as_secs_f64() / self.half_life.as_secs_f64().max(1.0);
+        entry.count as f64 * 0.5f64.powf(half_lives)
+    }
+}
+
+/// Strategy names accepted in the `[history]` config section
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Ranking {
+    Frequency,
+    #[default]
+    Frecency,
+    Decay,
+    Mru,
+}
+
+impl Ranking {
+    /// `half_life` only applies to [`Ranking::Decay`]
+    pub fn strategy(self, half_life: Option<Duration>) -> Box<dyn RankingStrategy> {
+        match self {
+            Ranking::Frequency => Box::new(Frequency),
+            Ranking::Frecency => Box::new(Frecency),
+            Ranking::Decay => Box::new(
+                half_life.map_or_else(ExponentialDecay::default, |half_life| ExponentialDecay {
+                    half_life,
+                }),
+            ),
+            Ranking::Mru => Box::new(MostRecent),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_000 * DAY;
+
+    /// A query used `count` times, last `days_ago` days before [`NOW`]
+    fn used(query: &str, count: usize, days_ago: u64) -> QueryEntry {
+        let last_used = SystemTime::UNIX_EPOCH + Duration::from_secs(NOW - days_ago * DAY);
+        QueryEntry {
+            query: query.to_string(),
+            count,
+            last_used,
+            first_used: last_used,
+            slow: false,
+        }
+    }
+
+    fn ranked(strategy: &dyn RankingStrategy, entries: &[QueryEntry]) -> Vec<String> {
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(NOW);
+        let mut entries = entries.to_vec();
+        entries.sort_by(|a, b| strategy.score(b, now).total_cmp(&strategy.score(a, now)));
+        entries.into_iter().map(|e| e.query).collect()
+    }
+
+    #[test]
+    fn test_strategies_on_habit_change() {
+        // Heavily used a season ago vs. a few uses this week
+        let entries = [
+            used(".old", 20, 120),
+            used(".new", 3, 1),
+            used(".today", 1, 0),
+        ];
+
+        assert_eq!(ranked(&Frequency, &entries), [".old", ".new", ".today"]);
+        assert_eq!(ranked(&MostRecent, &entries), [".today", ".new", ".old"]);
+        assert_eq!(ranked(&Frecency, &entries), [".new", ".old", ".today"]);
+        assert_eq!(
+            ranked(&ExponentialDecay::default(), &entries),
+            [".new", ".today", ".old"]
+        );
+    }
+
+    #[test]
+    fn test_frecency_prefers_count_within_bucket() {
+        let entries = [used(".a", 1, 0), used(".b", 5, 2)];
+        assert_eq!(ranked(&Frecency, &entries), [".b", ".a"]);
+    }
+
+    #[test]
+    fn test_decay_half_life() {
+        let strategy = ExponentialDecay {
+            half_life: Duration::from_secs(DAY),
+        };
+        let now = SystemTime::UNIX_EPOCH + Duration::from_secs(NOW);
+        assert_eq!(strategy.score(&used(".a", 8, 0), now), 8.0);
+        assert_eq!(strategy.score(&used(".a", 8, 2), now), 2.0);
+    }
+
+    #[test]
+    fn test_ranking_names() {
+        #[derive(Deserialize)]
+        struct Wrapper {
+            ranking: Ranking,
+        }
+        let parsed: Wrapper = toml::from_str("ranking = \"mru\"").unwrap();
+        assert_eq!(parsed.ranking, Ranking::Mru);
+        assert_eq!(Ranking::default(), Ranking::Frecency);
+    }
+}
@@ -0,0 +1,91 @@
+use regex::Regex;
+use serde::Deserialize;
+
+const MASK: &str = "***";
+
+/// What to do with queries containing secrets
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactMode {
+    /// Record the query with every match replaced by `***`
+    #[default]
+    Mask,
+    /// Do not record the query at all
+    Skip,
+}
+
+/// Masks or drops text matching configured secret patterns before it is persisted
+#[derive(Debug, Clone, Default)]
+pub struct Redactor {
+    patterns: Vec<Regex>,
+    mode: RedactMode,
+}
+
+impl Redactor {
+    /// Compiles `patterns`, returning the first invalid pattern's error message
+    pub fn new(patterns: &[String], mode: RedactMode) -> Result<Self, String> {
+        let patterns = patterns
+            .iter()
+            .map(|p| Regex::new(p).map_err(|e| format!("invalid pattern `{}`: {}", p, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(Self { patterns, mode })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.patterns.is_empty()
+    }
+
+    pub fn contains_secret(&self, text: &str) -> bool {
+        self.patterns.iter().any(|p| p.is_match(text))
+    }
+
+    /// Replaces every match with `***`
+    pub fn mask(&self, text: &str) -> String {
+        self.patterns.iter().fold(text.to_string(), |acc, p| {
+            p.replace_all(&acc, MASK).into_owned()
+        })
+    }
+
+    /// Returns the text safe to record, or `None` when it must not be recorded
+    pub fn apply(&self, text: &str) -> Option<String> {
+        if !self.contains_secret(text) {
+            return Some(text.to_string());
+        }
+        match self.mode {
+            RedactMode::Mask => Some(self.mask(text)),
+            RedactMode::Skip => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bearer(mode: RedactMode) -> Redactor {
+        Redactor::new(&["Bearer [A-Za-z0-9._-]+".to_string()], mode).unwrap()
+    }
+
+    #[test]
+    fn test_mask_mode() {
+        let redactor = bearer(RedactMode::Mask);
+        assert_eq!(
+            redactor.apply(r#"select(.auth == "Bearer abc.def-1")"#),
+            Some(r#"select(.auth == "***")"#.to_string())
+        );
+        assert_eq!(redactor.apply(".name"), Some(".name".to_string()));
+    }
+
+    #[test]
+    fn test_skip_mode() {
+        let redactor = bearer(RedactMode::Skip);
+        assert_eq!(redactor.apply(r#""Bearer abc""#), None);
+        assert_eq!(redactor.apply(".name"), Some(".name".to_string()));
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        let err = Redactor::new(&["(".to_string()], RedactMode::Mask).unwrap_err();
+        assert!(err.contains("invalid pattern `(`"));
+    }
+}
@@ -0,0 +1,72 @@
+use crate::query::format::format_query;
+use crate::query::pipeline::stage_ranges;
+use std::collections::HashMap;
+
+/// Canonical form of a stage so spacing differences count as the same stage
+fn normalize(stage: &str) -> String {
+    format_query(stage.trim())
+}
+
+/// Bigram model predicting the next pipe stage from history
+///
+/// Counts which stage followed which in recorded queries; stages seen after
+/// any pipe are the fallback for an unseen previous stage.
+#[derive(Debug, Clone, Default)]
+pub struct StageModel {
+    following: HashMap<String, HashMap<String, usize>>,
+    overall: HashMap<String, usize>,
+}
+
+impl StageModel {
+    pub fn learn(&mut self, query: &str) {
+        let stages: Vec<String> = stage_ranges(query)
+            .into_iter()
+            .map(|range| normalize(&query[range]))
+            .collect();
+        if stages.iter().any(String::is_empty) {
+            return;
+        }
+        for pair in stages.windows(2) {
+            *self
+                .following
+                .entry(pair[0].clone())
+                .or_default()
+                .entry(pair[1].clone())
+                .or_default() += 1;
+            *self.overall.entry(pair[1].clone()).or_default() += 1;
+        }
+    }
+
+    /// Most likely stages after `previous`, most frequent first
+    pub fn next_stages(&self, previous: &str) -> Vec<String> {
+        let counts = match self.following.get(&normalize(previous)) {
+            Some(counts) => counts,
+            None => &self.overall,
+        };
+        let mut stages: Vec<(&String, &usize)> = counts.iter().collect();
+        stages.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        stages.into_iter().map(|(stage, _)| stage.clone()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_stages() {
+        let mut model = StageModel::default();
+        model.learn(".users[] | select(.active) | .name");
+        model.learn(".users[]|select(.active)");
+        model.learn(".users[] | .email");
+        model.learn(".items | length");
+
+        assert_eq!(
+            model.next_stages(".users[] "),
+            vec!["select(.active)", ".email"]
+        );
+        assert_eq!(model.next_stages("select(.active)"), vec![".name"]);
+        // Unseen context falls back to all continuations
+        assert_eq!(model.next_stages(".other")[0], "select(.active)");
+    }
+}
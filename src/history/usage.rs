@@ -0,0 +1,357 @@
+use crate::clock::{Clock, SystemClock};
+use crate::history::Redactor;
+use crate::query::lexer::{QueryTokenKind, tokenize_query};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Entries shown in each list of [`UsageStats`]
+const TOP: usize = 10;
+
+/// One entry of the usage log
+///
+/// One JSON object per line of the usage log.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UsageRecord {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub query: String,
+    /// File or URL queried; `None` for stdin
+    pub file: Option<String>,
+    /// How long the query took to run, when it was timed
+    pub millis: Option<u64>,
+}
+
+impl UsageRecord {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "timestamp": self.timestamp,
+            "query": self.query,
+            "file": self.file,
+            "millis": self.millis,
+        })
+    }
+
+    /// Parses one log line; returns `None` for blank or malformed lines
+    pub fn from_json_line(line: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        Some(Self {
+            timestamp: value.get("timestamp")?.as_u64()?,
+            query: value.get("query")?.as_str()?.to_string(),
+            file: value
+                .get("file")
+                .and_then(Value::as_str)
+                .map(str::to_string),
+            millis: value.get("millis").and_then(Value::as_u64),
+        })
+    }
+}
+
+/// Local usage log
+///
+/// Every query run with Enter is appended to `~/.config/rjq/usage.jsonl`
+/// with the file it ran on and how long it took. Nothing leaves the
+/// machine; `rjq stats` and Alt+U summarize the log as [`UsageStats`].
+#[derive(Debug)]
+pub struct UsageLog {
+    path: PathBuf,
+    redactor: Redactor,
+    clock: Arc<dyn Clock>,
+}
+
+impl UsageLog {
+    /// The log in the user configuration directory, if there is one
+    pub fn default_path() -> Option<PathBuf> {
+        crate::config::user_config_dir().map(|dir| dir.join("usage.jsonl"))
+    }
+
+    /// A log appending to `path`; the file is created on the first record
+    pub fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            redactor: Redactor::default(),
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Applies the same secret masking as the query history
+    pub fn set_redactor(&mut self, redactor: Redactor) {
+        self.redactor = redactor;
+    }
+
+    /// Timestamps records with `clock` instead of the system time
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Appends a run of `query` on `file`; queries the redactor drops are
+    /// not recorded
+    pub fn record(
+        &self,
+        query: &str,
+        file: Option<&str>,
+        elapsed: Option<Duration>,
+    ) -> std::io::Result<()> {
+        let Some(query) = self.redactor.apply(query) else {
+            return Ok(());
+        };
+        let record = UsageRecord {
+            timestamp: self
+                .clock
+                .now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            query,
+            file: file.map(absolute),
+            millis: elapsed.map(|elapsed| elapsed.as_millis() as u64),
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", record.to_json())
+    }
+
+    /// Every record in the log; a missing log has none
+    pub fn read(&self) -> std::io::Result<Vec<UsageRecord>> {
+        match std::fs::read_to_string(&self.path) {
+            Ok(content) => Ok(content
+                .lines()
+                .filter_map(UsageRecord::from_json_line)
+                .collect()),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Files as absolute paths so runs from different directories count
+/// together; URLs are kept as they are
+fn absolute(file: &str) -> String {
+    if crate::source::http::is_url(file) {
+        return file.to_string();
+    }
+    std::path::absolute(file)
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| file.to_string())
+}
+
+/// Summary of the usage log
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UsageStats {
+    /// Queries run
+    pub runs: usize,
+    /// Different queries run
+    pub distinct: usize,
+    /// Characters per query run
+    pub average_length: f64,
+    /// Queries run most often, with their counts
+    pub queries: Vec<(String, usize)>,
+    /// Functions used most often, e.g. `select` or `map`, with their counts
+    pub filters: Vec<(String, usize)>,
+    /// Files queried most often, with their counts
+    pub files: Vec<(String, usize)>,
+    /// Queries with the longest single run
+    pub slowest: Vec<(String, Duration)>,
+}
+
+impl UsageStats {
+    pub fn from_records(records: &[UsageRecord]) -> Self {
+        let mut queries: HashMap<&str, usize> = HashMap::new();
+        let mut filters: HashMap<&str, usize> = HashMap::new();
+        let mut files: HashMap<&str, usize> = HashMap::new();
+        let mut slowest: HashMap<&str, u64> = HashMap::new();
+        for record in records {
+            *queries.entry(&record.query).or_default() += 1;
+            for token in tokenize_query(&record.query) {
+                let name = token.text(&record.query);
+                if token.kind == QueryTokenKind::Ident && !matches!(name, "true" | "false" | "null")
+                {
+                    *filters.entry(name).or_default() += 1;
+                }
+            }
+            if let Some(file) = &record.file {
+                *files.entry(file).or_default() += 1;
+            }
+            if let Some(millis) = record.millis {
+                let longest = slowest.entry(&record.query).or_default();
+                *longest = (*longest).max(millis);
+            }
+        }
+        let length: usize = records.iter().map(|r| r.query.chars().count()).sum();
+        Self {
+            runs: records.len(),
+            distinct: queries.len(),
+            average_length: if records.is_empty() {
+                0.0
+            } else {
+                length as f64 / records.len() as f64
+            },
+            queries: top(queries),
+            filters: top(filters),
+            files: top(files),
+            slowest: top(slowest)
+                .into_iter()
+                .map(|(query, millis)| (query, Duration::from_millis(millis)))
+                .collect(),
+        }
+    }
+
+    /// The totals in one sentence
+    pub fn summary(&self) -> String {
+        if self.runs == 0 {
+            return "No queries recorded yet; queries run with Enter are counted".to_string();
+        }
+        format!(
+            "{} queries run, {} different, {:.1} characters on average",
+            self.runs, self.distinct, self.average_length
+        )
+    }
+
+    /// Titled lists of a figure and what it is about, leaving out empty ones
+    pub fn sections(&self) -> Vec<(&'static str, Vec<(String, &str)>)> {
+        let slowest = self
+            .slowest
+            .iter()
+            .map(|(query, elapsed)| (format!("{} ms", elapsed.as_millis()), query.as_str()))
+            .collect();
+        [
+            ("Most used queries", counted(&self.queries)),
+            ("Most used filters", counted(&self.filters)),
+            ("Busiest files", counted(&self.files)),
+            ("Slowest queries", slowest),
+        ]
+        .into_iter()
+        .filter(|(_, rows)| !rows.is_empty())
+        .collect()
+    }
+
+    /// The summary as text, as printed by `rjq stats`
+    pub fn to_lines(&self) -> Vec<String> {
+        let mut lines = vec![self.summary()];
+        for (title, rows) in self.sections() {
+            lines.push(String::new());
+            lines.push(title.to_string());
+            lines.extend(
+                rows.into_iter()
+                    .map(|(n, text)| format!("{:>8}  {}", n, text)),
+            );
+        }
+        lines
+    }
+}
+
+fn counted(rows: &[(String, usize)]) -> Vec<(String, &str)> {
+    rows.iter()
+        .map(|(text, count)| (count.to_string(), text.as_str()))
+        .collect()
+}
+
+/// The largest counts first, ties in text order, at most [`TOP`] of them
+fn top<N: Ord>(counts: HashMap<&str, N>) -> Vec<(String, N)> {
+    let mut counts: Vec<(&str, N)> = counts.into_iter().collect();
+    counts.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+    counts
+        .into_iter()
+        .take(TOP)
+        .map(|(text, n)| (text.to_string(), n))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn record(query: &str, file: Option<&str>, millis: Option<u64>) -> UsageRecord {
+        UsageRecord {
+            timestamp: 0,
+            query: query.to_string(),
+            file: file.map(str::to_string),
+            millis,
+        }
+    }
+
+    #[test]
+    fn test_stats_summarize_records() {
+        let stats = UsageStats::from_records(&[
+            record(".users[] | select(.active)", Some("/a.json"), Some(5)),
+            record(".users | map(.name) | sort", Some("/a.json"), Some(40)),
+            record(".users[] | select(.active)", Some("/b.json"), Some(12)),
+            record("length", None, None),
+        ]);
+        assert_eq!((stats.runs, stats.distinct), (4, 3));
+        assert_eq!(
+            stats.queries[0],
+            (".users[] | select(.active)".to_string(), 2)
+        );
+        assert_eq!(
+            stats.filters,
+            vec![
+                ("select".to_string(), 2),
+                ("length".to_string(), 1),
+                ("map".to_string(), 1),
+                ("sort".to_string(), 1),
+            ]
+        );
+        assert_eq!(stats.files[0], ("/a.json".to_string(), 2));
+        assert_eq!(
+            stats.slowest[0],
+            (
+                ".users | map(.name) | sort".to_string(),
+                Duration::from_millis(40)
+            )
+        );
+        assert!(stats.to_lines()[0].starts_with("4 queries run, 3 different"));
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let path = std::env::temp_dir().join(format!(
+            "rjq_usage_log_test_{}/usage.jsonl",
+            std::process::id()
+        ));
+        std::fs::remove_file(&path).ok();
+        let mut log = UsageLog::new(path.clone());
+        log.set_clock(Arc::new(MockClock::new(
+            UNIX_EPOCH + Duration::from_secs(60),
+        )));
+        assert!(log.read().unwrap().is_empty());
+
+        log.record(
+            ".a",
+            Some("https://example.com/x.json"),
+            Some(Duration::from_millis(3)),
+        )
+        .unwrap();
+        log.record(".b", None, None).unwrap();
+        assert_eq!(
+            log.read().unwrap(),
+            vec![
+                UsageRecord {
+                    timestamp: 60,
+                    query: ".a".to_string(),
+                    file: Some("https://example.com/x.json".to_string()),
+                    millis: Some(3),
+                },
+                UsageRecord {
+                    timestamp: 60,
+                    ..record(".b", None, None)
+                },
+            ]
+        );
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -0,0 +1,119 @@
+use crate::app::AppError;
+use serde_json::{Map, Value};
+
+/// How CSV and TSV are read
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    /// The first row names the columns; without it every record becomes an array
+    pub headers: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            headers: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    pub fn tsv() -> Self {
+        Self {
+            delimiter: b'\t',
+            ..Self::default()
+        }
+    }
+}
+
+/// Parses delimited text into an array with one element per record
+///
+/// With headers, each record is an object keyed by column name; a repeated
+/// column name keeps the last value. Fields stay strings, since CSV has no
+/// types; convert them with `tonumber` where needed.
+pub fn parse(text: &str, options: &CsvOptions) -> Result<Value, AppError> {
+    let format = if options.delimiter == b'\t' {
+        "TSV"
+    } else {
+        "CSV"
+    };
+    let error = |e: csv::Error| AppError::InputParse {
+        format,
+        message: e.to_string(),
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter)
+        .has_headers(options.headers)
+        .from_reader(text.as_bytes());
+
+    let headers: Vec<String> = if options.headers {
+        reader
+            .headers()
+            .map_err(error)?
+            .iter()
+            .map(str::to_string)
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let mut rows = Vec::new();
+    for record in reader.records() {
+        let record = record.map_err(error)?;
+        let row = if options.headers {
+            Value::Object(
+                headers
+                    .iter()
+                    .zip(record.iter())
+                    .map(|(name, field)| (name.clone(), Value::from(field)))
+                    .collect::<Map<_, _>>(),
+            )
+        } else {
+            Value::Array(record.iter().map(Value::from).collect())
+        };
+        rows.push(row);
+    }
+    Ok(Value::Array(rows))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_records_become_objects() {
+        let text = "id,name,note\n1,alice,\"likes, commas\"\n2,bob,\n";
+        assert_eq!(
+            parse(text, &CsvOptions::default()).unwrap(),
+            json!([
+                {"id": "1", "name": "alice", "note": "likes, commas"},
+                {"id": "2", "name": "bob", "note": ""}
+            ])
+        );
+        assert_eq!(
+            parse("id,name\n", &CsvOptions::default()).unwrap(),
+            json!([])
+        );
+    }
+
+    #[test]
+    fn test_tsv_without_headers() {
+        let options = CsvOptions {
+            headers: false,
+            ..CsvOptions::tsv()
+        };
+        assert_eq!(
+            parse("a\tb\nc\td\n", &options).unwrap(),
+            json!([["a", "b"], ["c", "d"]])
+        );
+    }
+
+    #[test]
+    fn test_ragged_rows_are_an_error() {
+        let error = parse("a,b\n1,2,3\n", &CsvOptions::default()).unwrap_err();
+        assert_eq!(error.code(), "input_parse");
+        assert!(error.to_string().starts_with("CSV parsing error"));
+    }
+}
@@ -0,0 +1,42 @@
+use crate::app::AppError;
+use serde_json::Value;
+
+/// Parses JSON with the JSON5 extensions config files tend to use
+///
+/// Accepts `//` and `/* */` comments, trailing commas, unquoted keys and
+/// single-quoted strings, so `tsconfig.json` or `.vscode/settings.json` load
+/// as they are.
+pub fn parse(text: &str) -> Result<Value, AppError> {
+    json5::from_str(text).map_err(|e| AppError::InputParse {
+        format: "JSON5",
+        message: e.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_comments_and_trailing_commas() {
+        let text = r#"{
+            // compiler options
+            "compilerOptions": {
+                "strict": true, /* required */
+                paths: ['src',],
+            },
+        }"#;
+        assert_eq!(
+            parse(text).unwrap(),
+            json!({"compilerOptions": {"strict": true, "paths": ["src"]}})
+        );
+        assert!(matches!(
+            parse("{\"a\": }"),
+            Err(AppError::InputParse {
+                format: "JSON5",
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,278 @@
+pub mod csv;
+pub mod lenient;
+pub mod ndjson;
+#[cfg(feature = "simd")]
+pub mod simd;
+pub mod yaml;
+
+pub use csv::CsvOptions;
+
+use crate::app::AppError;
+use crate::query::JsonData;
+use serde_json::Value;
+use std::path::Path;
+
+/// Format of the input data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum InputFormat {
+    #[default]
+    Json,
+    /// JSON with comments, trailing commas and other JSON5 extensions
+    Json5,
+    Yaml,
+    /// Comma-separated values, one object per record
+    Csv,
+    /// Tab-separated values, one object per record
+    Tsv,
+    /// Newline-delimited JSON, one jq input per line
+    Ndjson,
+}
+
+impl InputFormat {
+    /// Guesses the format from the file extension, falling back to JSON
+    pub fn from_path(path: &Path) -> Self {
+        Self::from_extension(path).unwrap_or_default()
+    }
+
+    /// The format a known extension stands for, `None` for any other file
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        let format = match path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()?
+        {
+            "json" => InputFormat::Json,
+            "yaml" | "yml" => InputFormat::Yaml,
+            "json5" | "jsonc" => InputFormat::Json5,
+            "csv" => InputFormat::Csv,
+            "tsv" | "tab" => InputFormat::Tsv,
+            "ndjson" | "jsonl" => InputFormat::Ndjson,
+            _ => return None,
+        };
+        Some(format)
+    }
+
+    /// Delimited-text options for this format: commas for CSV, tabs for TSV
+    pub fn csv_options(self) -> CsvOptions {
+        match self {
+            InputFormat::Tsv => CsvOptions::tsv(),
+            _ => CsvOptions::default(),
+        }
+    }
+
+    /// The format to use when parsing leniently: JSON becomes JSON5
+    pub fn lenient(self) -> Self {
+        match self {
+            InputFormat::Json => InputFormat::Json5,
+            other => other,
+        }
+    }
+
+    /// Whether the input is a sequence of separate jq inputs
+    pub fn is_stream(self) -> bool {
+        self == InputFormat::Ndjson
+    }
+
+    /// The explicitly selected format, or the one implied by `path`
+    pub fn resolve(selected: Option<Self>, path: Option<&Path>) -> Self {
+        selected
+            .or_else(|| path.map(Self::from_path))
+            .unwrap_or_default()
+    }
+}
+
+/// Parses `text` in the given format into a JSON value
+pub fn parse(text: &str, format: InputFormat) -> Result<Value, AppError> {
+    parse_with(text, format, &format.csv_options())
+}
+
+/// Like [`parse`], reading CSV and TSV with `csv_options`
+pub fn parse_with(
+    text: &str,
+    format: InputFormat,
+    csv_options: &CsvOptions,
+) -> Result<Value, AppError> {
+    match format {
+        #[cfg(feature = "simd")]
+        InputFormat::Json => simd::parse(text),
+        #[cfg(not(feature = "simd"))]
+        InputFormat::Json => Ok(serde_json::from_str(text)?),
+        InputFormat::Json5 => lenient::parse(text),
+        InputFormat::Yaml => yaml::parse(text),
+        InputFormat::Csv | InputFormat::Tsv => csv::parse(text, csv_options),
+        InputFormat::Ndjson => ndjson::parse(text).map(Value::Array),
+    }
+}
+
+/// Parses every value in `text` into one array, like `jq --slurp`
+///
+/// JSON may hold several documents one after another; NDJSON contributes one
+/// element per line, and other formats their single document.
+pub fn slurp(text: &str, format: InputFormat, csv_options: &CsvOptions) -> Result<Value, AppError> {
+    let values = match format {
+        InputFormat::Json => documents(text)?,
+        InputFormat::Ndjson => ndjson::parse(text)?,
+        _ if text.trim().is_empty() => Vec::new(),
+        _ => vec![parse_with(text, format, csv_options)?],
+    };
+    Ok(Value::Array(values))
+}
+
+/// Parses JSON documents that follow one another, separated by whitespace
+/// or nothing at all, as APIs and `jq -c` print them
+pub fn documents(text: &str) -> Result<Vec<Value>, AppError> {
+    Ok(serde_json::Deserializer::from_str(text)
+        .into_iter()
+        .collect::<Result<Vec<Value>, _>>()?)
+}
+
+/// Parses `text` into the data queries run on
+///
+/// Streaming formats keep their values as separate inputs; empty text is
+/// `null`, or no inputs at all for a stream. JSON holding several documents
+/// one after another becomes a stream of them, like jq reads it.
+pub fn load(
+    text: &str,
+    format: InputFormat,
+    csv_options: &CsvOptions,
+) -> Result<JsonData, AppError> {
+    if format.is_stream() {
+        return Ok(JsonData::from_inputs(ndjson::parse(text)?));
+    }
+    if text.trim().is_empty() {
+        return Ok(JsonData::new(Value::Null));
+    }
+    match parse_with(text, format, csv_options) {
+        Ok(value) => Ok(JsonData::new(value)),
+        // Only a second document is worth parsing the text again for
+        Err(AppError::JsonParse(e))
+            if format == InputFormat::Json && e.to_string().starts_with("trailing characters") =>
+        {
+            documents(text).map(JsonData::from_inputs)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_slurp_collects_documents() {
+        let csv = CsvOptions::default();
+        assert_eq!(
+            slurp("{\"a\": 1} {\"a\": 2}\n3", InputFormat::Json, &csv).unwrap(),
+            json!([{"a": 1}, {"a": 2}, 3])
+        );
+        assert_eq!(
+            slurp("1\n\n2\n", InputFormat::Ndjson, &csv).unwrap(),
+            json!([1, 2])
+        );
+        assert_eq!(
+            slurp("a: 1\n", InputFormat::Yaml, &csv).unwrap(),
+            json!([{"a": 1}])
+        );
+        assert_eq!(slurp("  ", InputFormat::Json, &csv).unwrap(), json!([]));
+        assert!(slurp("1 {", InputFormat::Json, &csv).is_err());
+    }
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            InputFormat::from_path(Path::new("deploy.YML")),
+            InputFormat::Yaml
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("ci/config.yaml")),
+            InputFormat::Yaml
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("data.json")),
+            InputFormat::Json
+        );
+        assert_eq!(
+            InputFormat::resolve(Some(InputFormat::Json), Some(Path::new("a.yaml"))),
+            InputFormat::Json
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("export.tsv")),
+            InputFormat::Tsv
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new("events.jsonl")),
+            InputFormat::Ndjson
+        );
+        assert_eq!(
+            InputFormat::from_path(Path::new(".vscode/settings.jsonc")),
+            InputFormat::Json5
+        );
+        assert_eq!(InputFormat::Json.lenient(), InputFormat::Json5);
+        assert_eq!(InputFormat::Yaml.lenient(), InputFormat::Yaml);
+        assert_eq!(InputFormat::resolve(None, None), InputFormat::Json);
+    }
+
+    #[test]
+    fn test_parse_dispatches_on_format() {
+        assert_eq!(parse("a: 1", InputFormat::Yaml).unwrap(), json!({"a": 1}));
+        assert_eq!(
+            parse("a\tb\n1\t2\n", InputFormat::Tsv).unwrap(),
+            json!([{"a": "1", "b": "2"}])
+        );
+        assert!(matches!(
+            parse("a: 1", InputFormat::Json),
+            Err(AppError::JsonParse(_))
+        ));
+    }
+
+    #[test]
+    fn test_load_keeps_stream_inputs_apart() {
+        let options = CsvOptions::default();
+        let data = load(
+            "1
+2
+",
+            InputFormat::Ndjson,
+            &options,
+        )
+        .unwrap();
+        assert!(data.is_stream());
+        assert_eq!(data.inputs(), &[json!(1), json!(2)]);
+        assert!(
+            load("", InputFormat::Ndjson, &options)
+                .unwrap()
+                .inputs()
+                .is_empty()
+        );
+        assert_eq!(
+            load(" \n", InputFormat::Json, &options).unwrap().get(),
+            &Value::Null
+        );
+    }
+
+    #[test]
+    fn test_load_concatenated_documents() {
+        let options = CsvOptions::default();
+        let data = load("{\"a\": 1}\n{\"a\": 2} [3]{}", InputFormat::Json, &options).unwrap();
+        assert!(data.is_stream());
+        assert_eq!(
+            data.inputs(),
+            &[json!({"a": 1}), json!({"a": 2}), json!([3]), json!({})]
+        );
+        assert_eq!(
+            data.execute_query("[., input]").unwrap().values(),
+            [json!([{"a": 1}, {"a": 2}]), json!([[3], {}])]
+        );
+
+        // A single document stays one, and a broken later one is still an error
+        assert!(
+            !load("[1, 2]", InputFormat::Json, &options)
+                .unwrap()
+                .is_stream()
+        );
+        let error = load("1 {", InputFormat::Json, &options).unwrap_err();
+        assert!(error.to_string().contains("EOF"), "{}", error);
+    }
+}
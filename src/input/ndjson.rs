@@ -0,0 +1,40 @@
+use crate::app::AppError;
+use serde_json::Value;
+
+/// Parses newline-delimited JSON into its separate values
+///
+/// Each non-blank line holds one value; errors name the line they occur on.
+pub fn parse(text: &str) -> Result<Vec<Value>, AppError> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            serde_json::from_str(line).map_err(|e| AppError::InputParse {
+                format: "NDJSON",
+                message: format!("line {}: {}", index + 1, e),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_lines() {
+        let text = "{\"a\": 1}\n\n[2]\r\n\"three\"\n";
+        assert_eq!(
+            parse(text).unwrap(),
+            vec![json!({"a": 1}), json!([2]), json!("three")]
+        );
+        assert!(parse("").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_error_names_the_line() {
+        let error = parse("{\"a\": 1}\n{\"a\": \n").unwrap_err();
+        assert!(error.to_string().contains("line 2"), "{}", error);
+    }
+}
@@ -0,0 +1,56 @@
+//! SIMD JSON parsing (the `simd` feature)
+//!
+//! simd-json parses large documents several times faster than serde_json,
+//! at the cost of a mutable copy of the text. Smaller inputs are not worth
+//! the copy and keep using serde_json.
+
+use crate::app::AppError;
+use serde_json::Value;
+
+/// JSON text at least this long is parsed with simd-json
+pub const SIMD_THRESHOLD: usize = 1024 * 1024;
+
+/// Parses `text` with simd-json once it is at least [`SIMD_THRESHOLD`] bytes
+///
+/// simd-json only reports the byte offset of an error, so invalid input is
+/// parsed again by serde_json for the usual line, column and hint.
+pub fn parse(text: &str) -> Result<Value, AppError> {
+    if text.len() >= SIMD_THRESHOLD {
+        let mut bytes = text.as_bytes().to_vec();
+        if let Ok(value) = simd_json::serde::from_slice(&mut bytes) {
+            return Ok(value);
+        }
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_large_input_matches_serde_json() {
+        let record =
+            r#"{"id": 1, "name": "café", "tags": ["a", "b"], "score": -1.5e3, "ok": null}"#;
+        let text = format!(
+            "[{}]",
+            vec![record; SIMD_THRESHOLD / record.len() + 1].join(",")
+        );
+        assert!(text.len() >= SIMD_THRESHOLD);
+        assert_eq!(
+            parse(&text).unwrap(),
+            serde_json::from_str::<Value>(&text).unwrap()
+        );
+        assert_eq!(parse("[1, 2]").unwrap(), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_errors_come_from_serde_json() {
+        let text = format!("[{}", "1,".repeat(SIMD_THRESHOLD));
+        let Err(AppError::JsonParse(error)) = parse(&text) else {
+            panic!("expected a JSON parse error");
+        };
+        assert_eq!(error.line(), 1);
+    }
+}
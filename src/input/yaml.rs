@@ -0,0 +1,124 @@
+use crate::app::AppError;
+use serde::Deserialize;
+use serde_json::{Map, Number, Value};
+use serde_yaml::Value as Yaml;
+
+/// Parses YAML into JSON
+///
+/// A stream of several `---` separated documents becomes an array of them,
+/// so multi-document Kubernetes manifests can be queried with `.[]`.
+pub fn parse(text: &str) -> Result<Value, AppError> {
+    let mut documents = serde_yaml::Deserializer::from_str(text)
+        .map(|document| Yaml::deserialize(document).map(to_json))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::InputParse {
+            format: "YAML",
+            message: e.to_string(),
+        })?;
+    Ok(match documents.len() {
+        0 => Value::Null,
+        1 => documents.remove(0),
+        _ => Value::Array(documents),
+    })
+}
+
+fn to_json(value: Yaml) -> Value {
+    match value {
+        Yaml::Null => Value::Null,
+        Yaml::Bool(b) => Value::Bool(b),
+        Yaml::Number(n) => {
+            if let Some(i) = n.as_i64() {
+                Value::from(i)
+            } else if let Some(u) = n.as_u64() {
+                Value::from(u)
+            } else {
+                // NaN and infinities have no JSON representation
+                n.as_f64()
+                    .and_then(Number::from_f64)
+                    .map_or(Value::Null, Value::Number)
+            }
+        }
+        Yaml::String(s) => Value::String(s),
+        Yaml::Sequence(items) => Value::Array(items.into_iter().map(to_json).collect()),
+        Yaml::Mapping(mapping) => Value::Object(
+            mapping
+                .into_iter()
+                .map(|(key, value)| (key_string(key), to_json(value)))
+                .collect::<Map<_, _>>(),
+        ),
+        // Custom tags such as `!Ref` keep only their value
+        Yaml::Tagged(tagged) => to_json(tagged.value),
+    }
+}
+
+/// JSON object keys must be strings; other scalar keys are written out as YAML
+fn key_string(key: Yaml) -> String {
+    match key {
+        Yaml::String(s) => s,
+        other => serde_yaml::to_string(&other)
+            .map(|s| s.trim_end().to_string())
+            .unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_yaml() {
+        let yaml = "\
+apiVersion: v1
+kind: Service
+metadata:
+  name: web
+  labels: {app: web}
+spec:
+  ports:
+    - port: 80
+      protocol: TCP
+  weight: 0.5
+  enabled: yes
+  note: ~
+";
+        assert_eq!(
+            parse(yaml).unwrap(),
+            json!({
+                "apiVersion": "v1",
+                "kind": "Service",
+                "metadata": {"name": "web", "labels": {"app": "web"}},
+                "spec": {
+                    "ports": [{"port": 80, "protocol": "TCP"}],
+                    "weight": 0.5,
+                    "enabled": "yes",
+                    "note": null
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn test_multiple_documents_become_an_array() {
+        assert_eq!(
+            parse("kind: A\n---\nkind: B\n").unwrap(),
+            json!([{"kind": "A"}, {"kind": "B"}])
+        );
+        assert_eq!(parse("").unwrap(), Value::Null);
+    }
+
+    #[test]
+    fn test_non_string_keys_and_tags() {
+        assert_eq!(
+            parse("1: one\ntrue: yes\nref: !Ref bucket\n").unwrap(),
+            json!({"1": "one", "true": "yes", "ref": "bucket"})
+        );
+    }
+
+    #[test]
+    fn test_invalid_yaml() {
+        let error = parse("a: [1, 2").unwrap_err();
+        assert_eq!(error.code(), "input_parse");
+        assert!(error.to_string().starts_with("YAML parsing error"));
+    }
+}
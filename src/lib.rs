@@ -1,14 +1,31 @@
 pub mod app;
+pub mod batch;
+pub mod clock;
+pub mod config;
+pub mod export;
 pub mod history;
+pub mod input;
+pub mod prelude;
 pub mod query;
+pub mod render;
+pub mod session;
+pub mod source;
 pub mod ui;
 
 // 公開API
-pub use app::{App, AppBuilder, AppConfig, AppError, AppState, EnhancedApp};
+pub use app::{
+    App, AppBuilder, AppConfig, AppError, AppEvent, AppWarning, Effect, EnhancedApp, SubscriptionId,
+};
+pub use batch::ErrorFormat;
+pub use clock::{Clock, MockClock, SystemClock};
 pub use query::{
-    CachedQueryExecutor, InMemoryQueryCache, JaqQueryExecutor, JsonData, QueryCache, QueryExecutor,
-    QueryResult,
+    CacheStats, CachedQueryExecutor, InMemoryQueryCache, JaqQueryExecutor, JsonData,
+    PreludeExecutor, QueryCache, QueryExecutor, QueryResult,
+};
+pub use render::RenderTree;
+pub use ui::{
+    Action, ActionContext, DefaultEventHandler, EditAction, EventHandler, SystemAction, ViewAction,
+    get_action, get_event_action, restore_terminal, update,
 };
-pub use ui::{Action, DefaultEventHandler, EventHandler, get_action, restore_terminal, update};
 
 pub type Result<T> = std::result::Result<T, AppError>;
@@ -1,10 +1,29 @@
-use clap::Parser;
-use crossterm::{event::EnableMouseCapture, execute, terminal::enable_raw_mode};
+use clap::error::ErrorKind;
+use clap::{CommandFactory, Parser, Subcommand};
+use crossterm::{
+    event::{EnableBracketedPaste, EnableFocusChange, EnableMouseCapture},
+    execute,
+    terminal::enable_raw_mode,
+};
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::fs;
-use std::io::{self, Read};
+use std::io::{self, BufRead, Read, Write};
+use std::time::Duration;
 
-use rjq::{App, Result, restore_terminal};
+use rjq::app::ExecutionPolicy;
+use rjq::batch::{self, BatchOptions};
+use rjq::config::{self, FileConfig, TrustStore, UntrustedCode};
+use rjq::history::{UsageLog, UsageStats};
+use rjq::input::{self, CsvOptions, InputFormat};
+use rjq::query::{Workspace, WorkspaceFile};
+use rjq::render::BarMapping;
+use rjq::session::{Replay, SessionStore, TeeLog};
+use rjq::source::follow::Follower;
+use rjq::source::progress::LoadProgress;
+use rjq::source::watch::FileWatcher;
+use rjq::source::{self, DataSource, LoadOptions};
+use rjq::ui::{self, open_tui_output};
+use rjq::{App, AppError, AppWarning, ErrorFormat, JsonData, Result, restore_terminal};
 
 /// A command-line jq processor with interactive TUI
 #[derive(Parser, Debug, Clone, PartialEq)]
@@ -15,55 +34,699 @@ struct CliArgs {
     /// JSON file to process (reads from stdin if not provided)
     #[arg(short, long, value_name = "FILE")]
     file: Option<String>,
+
+    /// Files, directories or http(s) URLs to process, as an alternative to --file; each opens
+    /// in its own tab, and a directory is read as an array of `{filename, content}` objects
+    #[arg(value_name = "JSON_FILE", conflicts_with = "file")]
+    inputs: Vec<String>,
+
+    /// Run every query against all files matching PATTERN, with a per-file summary
+    #[arg(long, value_name = "PATTERN", value_parser = parse_glob, conflicts_with_all = ["file", "inputs"])]
+    glob: Option<String>,
+
+    /// Also read the subdirectories of a directory input
+    #[arg(long)]
+    recursive: bool,
+
+    /// Names of the files read from a directory input (default `*.json`)
+    #[arg(long, value_name = "PATTERN", value_parser = parse_glob)]
+    include: Option<String>,
+
+    /// Format of the input (guessed from the file extension when omitted)
+    #[arg(long, visible_alias = "format", value_enum, value_name = "FORMAT")]
+    input_format: Option<InputFormat>,
+
+    /// Read newline-delimited JSON: each line is a separate jq input (same as --format ndjson)
+    #[arg(long, conflicts_with = "input_format")]
+    ndjson: bool,
+
+    /// Accept comments, trailing commas and other JSON5 extensions in JSON input
+    #[arg(long)]
+    lenient: bool,
+
+    /// Field separator of CSV/TSV input (`\t` for a tab)
+    #[arg(long, value_name = "CHAR", value_parser = parse_delimiter)]
+    delimiter: Option<u8>,
+
+    /// CSV/TSV input has no header row; each record becomes an array
+    #[arg(long)]
+    no_header: bool,
+
+    /// Seconds to wait for a URL given as the input to answer
+    #[arg(long, value_name = "SECS", default_value_t = source::DEFAULT_FETCH_TIMEOUT.as_secs())]
+    fetch_timeout: u64,
+
+    /// Send HEADER (`Name: value`) with every URL request, e.g. `Authorization: Bearer TOKEN`
+    #[arg(short = 'H', long = "header", value_name = "HEADER", value_parser = source::http::parse_header)]
+    headers: Vec<(String, String)>,
+
+    /// Reload the input file whenever it changes, re-running the current query
+    #[arg(long, conflicts_with_all = ["glob", "query", "export_html"])]
+    watch: bool,
+
+    /// Keep reading NDJSON records from stdin and append them to the document as they arrive
+    #[arg(long, conflicts_with_all = ["file", "inputs", "glob", "query", "export_html", "watch"])]
+    follow: bool,
+
+    /// Read every document of all inputs into one array and query that, like jq -s
+    #[arg(short, long, conflicts_with_all = ["glob", "follow"])]
+    slurp: bool,
+
+    /// Run FILTER once, print the results and exit without starting the TUI
+    #[arg(short, long, value_name = "FILTER")]
+    query: Option<String>,
+
+    /// Set the exit status from the last output in batch mode (1 if false/null, 5 if none)
+    #[arg(short, long)]
+    exit_status: bool,
+
+    /// Colorize the output of --query (default when stdout is a terminal)
+    #[arg(short = 'C', long, conflicts_with = "monochrome_output")]
+    color_output: bool,
+
+    /// Never colorize the output of --query
+    #[arg(short = 'M', long)]
+    monochrome_output: bool,
+
+    /// Format of error messages written to stderr
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Text)]
+    error_format: ErrorFormat,
+
+    /// Write the result of --query (or the whole document) as highlighted HTML to FILE and exit
+    #[arg(long, value_name = "FILE")]
+    export_html: Option<String>,
+
+    /// Append every executed query and its result to LOG
+    #[arg(long, value_name = "LOG")]
+    tee: Option<String>,
+
+    /// Truncate results written to the tee log to BYTES
+    #[arg(long, value_name = "BYTES", requires = "tee")]
+    tee_limit: Option<usize>,
+
+    /// Replay the queries recorded in a --tee LOG against the current data
+    #[arg(long, value_name = "LOG")]
+    replay: Option<String>,
+
+    /// Advance the replay automatically every MS milliseconds instead of on Ctrl+N
+    #[arg(long, value_name = "MS", requires = "replay")]
+    replay_interval: Option<u64>,
+
+    /// Bind VALUE to $NAME as a string in every query
+    #[arg(long, num_args = 2, value_names = ["NAME", "VALUE"])]
+    arg: Vec<String>,
+
+    /// Bind the JSON text to $NAME in every query
+    #[arg(long, num_args = 2, value_names = ["NAME", "JSON"])]
+    argjson: Vec<String>,
+
+    /// Bind the contents of FILE to $NAME as a string in every query
+    #[arg(long, num_args = 2, value_names = ["NAME", "FILE"])]
+    rawfile: Vec<String>,
+
+    /// Bind an array of the JSON documents in FILE to $NAME in every query
+    #[arg(long, num_args = 2, value_names = ["NAME", "FILE"])]
+    slurpfile: Vec<String>,
+
+    /// Start with the query and variables of a preset from the config file
+    #[arg(long, value_name = "NAME")]
+    preset: Option<String>,
+
+    /// Show results through FILTER, or the configured display filter of that name (F3 cycles)
+    #[arg(long, value_name = "FILTER")]
+    display_filter: Option<String>,
+
+    /// Keys the bar chart (F8) reads labels and values from, instead of guessing
+    #[arg(long, value_name = "LABEL,VALUE", value_parser = parse_bar_keys)]
+    bar_keys: Option<BarMapping>,
+
+    /// Run the query only on Enter or F5, never while typing (F11 switches at runtime)
+    #[arg(long)]
+    manual: bool,
+
+    /// Run the query once typing pauses for MS milliseconds; 0 runs it on every keystroke
+    /// (overrides `[execution] policy`)
+    #[arg(long, value_name = "MS", conflicts_with = "manual")]
+    debounce: Option<u64>,
+
+    /// Give up queries still running after SECS seconds; 0 never gives up
+    /// (overrides `[execution] timeout_ms`)
+    #[arg(long, value_name = "SECS")]
+    timeout: Option<f64>,
+
+    /// Draw in the normal screen buffer, leaving the last screen and query in the scrollback on exit
+    #[arg(long)]
+    no_alt_screen: bool,
+
+    /// Ignore the user and project configuration files
+    #[arg(long)]
+    no_config: bool,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug, Clone, PartialEq)]
+enum Command {
+    /// Practice jq filters on small example datasets
+    Learn {
+        /// Lesson to start from
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+        lesson: u64,
+    },
+    /// Summarize your own usage from the local usage log; nothing is sent anywhere
+    Stats,
+}
+
+impl CliArgs {
+    fn input_path(&self) -> Option<&String> {
+        self.input_paths().first().copied()
+    }
+
+    /// Every file named on the command line, in order
+    fn input_paths(&self) -> Vec<&String> {
+        self.file.iter().chain(&self.inputs).collect()
+    }
+
+    /// The file `--watch` monitors: the first input, unless it is a URL
+    fn watch_path(&self) -> Option<&String> {
+        self.input_path().filter(|path| !source::http::is_url(path))
+    }
+
+    /// How input is read, from the format and CSV flags
+    fn load_options(&self) -> LoadOptions {
+        LoadOptions {
+            format: self
+                .input_format
+                .or(self.ndjson.then_some(InputFormat::Ndjson)),
+            lenient: self.lenient,
+            delimiter: self.delimiter,
+            headers: !self.no_header,
+            fetch_timeout: Duration::from_secs(self.fetch_timeout),
+            slurp: self.slurp,
+            recursive: self.recursive,
+            include: self.include.clone(),
+            glob: self.glob.clone(),
+            http_headers: self.headers.clone(),
+            ..Default::default()
+        }
+    }
+
+    /// Values bound with `--arg`, `--argjson`, `--rawfile` and `--slurpfile`
+    fn variables(&self) -> Result<Vec<(String, serde_json::Value)>> {
+        // `${VAR}` is expanded from the environment, as in the config file
+        let interpolate = |flag: &str, pair: &[String]| {
+            config::interpolate(&pair[1]).map_err(|e| AppError::Config {
+                path: format!("{} {}", flag, pair[0]),
+                message: e.to_string(),
+            })
+        };
+        let read = |path: &String| {
+            fs::read_to_string(path).map_err(|e| match e.kind() {
+                io::ErrorKind::NotFound => AppError::FileNotFound(path.clone()),
+                _ => AppError::Io(e),
+            })
+        };
+        let mut variables = Vec::new();
+        for pair in self.arg.chunks(2) {
+            let value = interpolate("--arg", pair)?;
+            variables.push((pair[0].clone(), serde_json::Value::String(value)));
+        }
+        for pair in self.argjson.chunks(2) {
+            let value = serde_json::from_str(&pair[1]).map_err(|e| AppError::InputParse {
+                format: "JSON",
+                message: format!("{} in --argjson {}", e, pair[0]),
+            })?;
+            variables.push((pair[0].clone(), value));
+        }
+        for pair in self.rawfile.chunks(2) {
+            let path = interpolate("--rawfile", pair)?;
+            variables.push((pair[0].clone(), serde_json::Value::String(read(&path)?)));
+        }
+        for pair in self.slurpfile.chunks(2) {
+            let text = read(&interpolate("--slurpfile", pair)?)?;
+            let values = input::slurp(&text, InputFormat::Json, &CsvOptions::default()).map_err(
+                |e| match e {
+                    AppError::JsonParse(e) => AppError::InputParse {
+                        format: "JSON",
+                        message: format!("{} in {}", e, pair[1]),
+                    },
+                    other => other,
+                },
+            )?;
+            variables.push((pair[0].clone(), values));
+        }
+        Ok(variables)
+    }
+
+    /// Whether batch output is colorized; `NO_COLOR` disables the default
+    fn use_color(&self, stdout_is_tty: bool, no_color: bool) -> bool {
+        if self.color_output {
+            true
+        } else if self.monochrome_output {
+            false
+        } else {
+            stdout_is_tty && !no_color
+        }
+    }
 }
 
-fn load_json_data(args: &CliArgs, stdin_input: &str) -> Result<serde_json::Value> {
-    if let Some(file_path) = &args.file {
-        let file_content = fs::read_to_string(file_path)?;
-        Ok(serde_json::from_str(&file_content)?)
-    } else if stdin_input.trim().is_empty() {
-        Ok(serde_json::Value::Null)
+fn load_json_data(
+    args: &CliArgs,
+    options: &LoadOptions,
+    stdin_input: &str,
+    warnings: &mut Vec<AppWarning>,
+    progress: &LoadProgress,
+) -> Result<JsonData> {
+    match args.input_path() {
+        Some(path) => DataSource::from_arg(path).load_reporting(options, warnings, progress),
+        None => {
+            progress.start("Parsing stdin", None);
+            options.parse(stdin_input, options.format_for(None))
+        }
+    }
+}
+
+/// Everything read before the TUI or the batch query starts
+struct Inputs {
+    data: JsonData,
+    /// Files after the first, each opened in a tab of its own
+    more_files: Vec<(String, JsonData)>,
+    workspace: Option<Workspace>,
+    /// Size of the input, from which the execution policy is chosen
+    document_bytes: u64,
+    warnings: Vec<AppWarning>,
+}
+
+/// Reads stdin and every input named on the command line
+fn load_inputs(
+    cli_args: &CliArgs,
+    options: &LoadOptions,
+    progress: &LoadProgress,
+) -> Result<Inputs> {
+    let mut warnings = Vec::new();
+    let mut document_bytes = 0;
+    // With --follow, stdin is read in the background once the TUI runs
+    let data = if cli_args.follow {
+        JsonData::from_inputs(Vec::new())
     } else {
-        Ok(serde_json::from_str(stdin_input)?)
+        let stdin_bytes = source::compression::decompress(read_stdin(progress)?)?;
+        document_bytes = stdin_bytes.len() as u64;
+        let input_string = source::decode(stdin_bytes, "stdin", &mut warnings);
+        load_json_data(cli_args, options, &input_string, &mut warnings, progress)?
+    };
+    let more_files = cli_args
+        .input_paths()
+        .into_iter()
+        .skip(1)
+        .map(|path| {
+            let data =
+                DataSource::from_arg(path).load_reporting(options, &mut warnings, progress)?;
+            Ok((path.clone(), data))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let workspace = match &cli_args.glob {
+        Some(pattern) => {
+            progress.start(format!("Loading {}", pattern), None);
+            Some(load_workspace(pattern, options, &mut warnings)?)
+        }
+        None => None,
+    };
+    Ok(Inputs {
+        data,
+        more_files,
+        workspace,
+        document_bytes,
+        warnings,
+    })
+}
+
+/// Loads every file matching `pattern`, in path order
+fn load_workspace(
+    pattern: &str,
+    options: &LoadOptions,
+    warnings: &mut Vec<AppWarning>,
+) -> Result<Workspace> {
+    let paths = glob::glob(pattern).map_err(|e| AppError::Config {
+        path: "--glob".to_string(),
+        message: e.to_string(),
+    })?;
+    let files = paths
+        .filter_map(|entry| entry.ok())
+        .filter(|path| path.is_file())
+        .map(|path| {
+            let path = path.display().to_string();
+            let data = DataSource::File(path.clone()).load(options, warnings)?;
+            Ok(WorkspaceFile { path, data })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if files.is_empty() {
+        return Err(AppError::FileNotFound(pattern.to_string()));
+    }
+    Ok(Workspace::new(files))
+}
+
+/// A glob pattern such as `logs/*.json`
+fn parse_glob(value: &str) -> std::result::Result<String, String> {
+    glob::Pattern::new(value)
+        .map(|_| value.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Two keys separated by a comma: the label and the value of each bar
+fn parse_bar_keys(value: &str) -> std::result::Result<BarMapping, String> {
+    match value.split_once(',') {
+        Some((label, value)) if !label.trim().is_empty() && !value.trim().is_empty() => {
+            Ok(BarMapping {
+                label: label.trim().to_string(),
+                value: value.trim().to_string(),
+            })
+        }
+        _ => Err("expected two keys such as `name,count`".to_string()),
+    }
+}
+
+/// A single ASCII character, or `\t` for a tab
+fn parse_delimiter(value: &str) -> std::result::Result<u8, String> {
+    match value {
+        "\\t" | "tab" => Ok(b'\t'),
+        _ => match value.as_bytes() {
+            [byte] if byte.is_ascii() => Ok(*byte),
+            _ => Err("the delimiter must be a single ASCII character".to_string()),
+        },
     }
 }
 
-fn read_stdin() -> std::result::Result<String, std::io::Error> {
+fn read_stdin(progress: &LoadProgress) -> std::result::Result<Vec<u8>, std::io::Error> {
     if atty::is(atty::Stream::Stdin) {
-        Ok(String::new())
+        Ok(Vec::new())
     } else {
-        let mut buffer = String::new();
-        io::stdin().read_to_string(&mut buffer)?;
+        progress.start("Reading stdin", None);
+        let mut buffer = Vec::new();
+        progress.reader(io::stdin()).read_to_end(&mut buffer)?;
         Ok(buffer)
     }
 }
 
-fn main() -> Result<()> {
+/// Asks on the controlling terminal whether a project's jq code may be loaded
+fn confirm_trust(untrusted: &UntrustedCode) -> bool {
+    let Ok(tty) = fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("/dev/tty")
+    else {
+        return false;
+    };
+    let mut writer = &tty;
+    let prompt = format!(
+        "{} defines jq code:\n\n{}\nTrust this file? [y/N] ",
+        untrusted.path.display(),
+        untrusted.describe()
+    );
+    if writer.write_all(prompt.as_bytes()).is_err() {
+        return false;
+    }
+    let mut answer = String::new();
+    io::BufReader::new(&tty).read_line(&mut answer).ok();
+    matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+/// Loads the user config and the nearest `.rjq.toml`, asking before trusting its jq code
+fn load_config(args: &CliArgs, interactive: bool) -> Result<FileConfig> {
+    if args.no_config {
+        return Ok(FileConfig::default());
+    }
+
+    let mut trust = TrustStore::load_default();
+    // A URL has no directory of its own; its project config is the current one
+    let local_path = args
+        .input_path()
+        .map(String::as_str)
+        .filter(|path| !source::http::is_url(path));
+    let root = config::discovery_root(local_path);
+    let loaded = config::load(&root, &trust)?;
+    let mut file_config = loaded.config;
+    if let Some(path) = loaded.ignored_credentials {
+        eprintln!(
+            "Warning: ignoring [credentials] from {}; set them in the user config",
+            path.display()
+        );
+    }
+
+    if let Some(untrusted) = loaded.untrusted {
+        if interactive && confirm_trust(&untrusted) {
+            trust.trust(&untrusted.path, &fs::read_to_string(&untrusted.path)?)?;
+            file_config = file_config.merge(untrusted.into_config());
+        } else {
+            eprintln!(
+                "Warning: ignoring prelude and row rules from untrusted {}",
+                untrusted.path.display()
+            );
+        }
+    }
+    Ok(file_config)
+}
+
+fn main() {
     let cli_args = CliArgs::parse();
+    match run(&cli_args) {
+        Ok(status) => std::process::exit(status),
+        Err(e) => {
+            eprintln!("{}", batch::format_error(&e, cli_args.error_format));
+            std::process::exit(batch::error_exit_code(&e));
+        }
+    }
+}
+
+fn run(cli_args: &CliArgs) -> Result<i32> {
+    if let Some(Command::Learn { lesson }) = cli_args.command {
+        let start = (lesson - 1) as usize;
+        return batch::learn::run(io::stdin().lock(), &mut io::stdout().lock(), start);
+    }
+    if cli_args.command == Some(Command::Stats) {
+        let records = match UsageLog::default_path() {
+            Some(path) => UsageLog::new(path).read()?,
+            None => Vec::new(),
+        };
+        let mut out = io::stdout().lock();
+        for line in UsageStats::from_records(&records).to_lines() {
+            writeln!(out, "{}", line)?;
+        }
+        return Ok(batch::exit_code::SUCCESS);
+    }
+    if cli_args.watch && cli_args.watch_path().is_none() {
+        CliArgs::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--watch needs an input file; stdin and URLs cannot be watched",
+            )
+            .exit();
+    }
+
+    if cli_args.follow && atty::is(atty::Stream::Stdin) {
+        CliArgs::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "--follow reads records piped to stdin, e.g. `tail -f app.log | rjq --follow`",
+            )
+            .exit();
+    }
 
-    let input_string = read_stdin()?;
-    let json_value = load_json_data(&cli_args, &input_string)?;
+    let mut batch_mode = cli_args.query.is_some() || cli_args.export_html.is_some();
+    // Without any terminal, e.g. at the end of a pipeline in CI, the result is printed
+    let mut tui_output = if batch_mode { None } else { open_tui_output() };
+    let no_terminal = !batch_mode && tui_output.is_none();
+    batch_mode |= no_terminal;
+
+    // The config is read first: it may ask to trust a prelude, and it holds
+    // the credentials for URL inputs
+    let file_config = load_config(cli_args, !batch_mode)?;
+    let load_options = LoadOptions {
+        credentials: file_config.credentials.clone(),
+        ..cli_args.load_options()
+    };
+
+    // Reading happens before the TUI starts, so its progress goes to the bare terminal
+    let progress = LoadProgress::default();
+    let load = || load_inputs(cli_args, &load_options, &progress);
+    let Inputs {
+        data,
+        mut more_files,
+        workspace,
+        mut document_bytes,
+        mut warnings,
+    } = match tui_output.as_mut() {
+        Some(output) => ui::loading::load_with_progress(output, &progress, load),
+        None => load(),
+    }?;
+    if no_terminal {
+        warnings.push(AppWarning::NoTerminal);
+    }
+    // Slurped files make up one array, queried in a single tab
+    let data = if cli_args.slurp {
+        JsonData::slurp(std::iter::once(data).chain(more_files.drain(..).map(|(_, d)| d)))
+    } else {
+        data
+    };
+    let preset = match &cli_args.preset {
+        Some(name) => {
+            Some(
+                file_config
+                    .presets
+                    .get(name)
+                    .cloned()
+                    .ok_or_else(|| AppError::Config {
+                        path: config::PROJECT_CONFIG_NAME.to_string(),
+                        message: format!("unknown preset `{}`", name),
+                    })?,
+            )
+        }
+        None => None,
+    };
+    // Command-line variables win over those from the config
+    let mut variables = file_config.variables(preset.as_ref());
+    variables.extend(cli_args.variables()?);
+
+    if batch_mode {
+        for warning in &warnings {
+            eprintln!("Warning: {}", warning);
+        }
+        let options = BatchOptions {
+            exit_status: cli_args.exit_status,
+            export_html: cli_args.export_html.clone(),
+            prelude: file_config.prelude.clone(),
+            variables: variables.clone(),
+            color: cli_args.use_color(
+                atty::is(atty::Stream::Stdout),
+                std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            ),
+        };
+        let preset_query = preset.and_then(|p| p.query);
+        let query = cli_args
+            .query
+            .as_deref()
+            .or(preset_query.as_deref())
+            .unwrap_or(".");
+        // Like jq, several files are one sequence of inputs
+        let data = if let Some(workspace) = workspace {
+            JsonData::concat(workspace.into_files().into_iter().map(|file| file.data))
+        } else if more_files.is_empty() {
+            data
+        } else {
+            JsonData::concat(std::iter::once(data).chain(more_files.into_iter().map(|(_, d)| d)))
+        };
+        return batch::run_query(&data, query, &options, &mut io::stdout().lock());
+    }
 
+    if let Some(metadata) = cli_args
+        .input_path()
+        .and_then(|path| fs::metadata(path).ok())
+    {
+        document_bytes = metadata.len();
+    }
+    let mut app = App::from_data(data);
+    app.enable_background_indexing();
+    app.enable_background_execution();
+    app.set_execution_policy(match cli_args.debounce {
+        _ if cli_args.manual => ExecutionPolicy::Manual,
+        Some(0) => ExecutionPolicy::Immediate,
+        Some(millis) => ExecutionPolicy::Debounced(Duration::from_millis(millis)),
+        None => file_config.execution_policy(document_bytes),
+    });
+    app.set_confirm_expensive(file_config.confirm_expensive(document_bytes));
+    app.set_query_timeout(match cli_args.timeout {
+        Some(secs) if secs > 0.0 => Some(Duration::from_secs_f64(secs)),
+        Some(_) => None,
+        None => file_config.query_timeout(),
+    });
+    app.set_source_path(cli_args.input_path().cloned());
+    app.set_load_options(load_options);
+    for (path, data) in more_files {
+        app.open_document(Some(path), data);
+    }
+    if let Some(workspace) = workspace {
+        app.set_workspace(workspace);
+    }
+    if let Some(path) = cli_args.watch_path().filter(|_| cli_args.watch) {
+        app.set_watcher(FileWatcher::new(path)?);
+    }
+    if cli_args.follow {
+        app.set_follower(Follower::spawn(io::BufReader::new(io::stdin())));
+    }
+    app.set_session_store(SessionStore::load_default());
+    app.set_prelude(file_config.prelude.clone());
+    app.set_variables(variables);
+    app.set_bookmarks(file_config.bookmarks.clone().into_iter().collect());
+    app.set_aliases(file_config.aliases.clone());
+    app.set_display_filters(file_config.display_filters.clone().into_iter().collect());
+    app.set_number_formats(file_config.number_formats()?);
+    app.set_highlight_rules(file_config.highlight_rules()?);
+    app.set_row_rules(&file_config.row_rules)?;
+    app.set_bar_mapping(cli_args.bar_keys.clone());
+    if let Some(filter) = &cli_args.display_filter {
+        app.activate_display_filter(filter);
+    }
+    if let Some(enabled) = file_config.auto_pairs {
+        app.set_auto_pairs(enabled);
+    }
+    if let Some(enabled) = file_config.compact_layout {
+        app.set_compact_layout(enabled);
+    }
+    if let Some(query) = preset.and_then(|p| p.query) {
+        app.set_input(query);
+    }
+    for warning in warnings {
+        app.push_warning(warning);
+    }
+    if let Some(path) = &cli_args.tee {
+        app.set_tee_log(TeeLog::open(path, cli_args.tee_limit)?);
+    }
+    if let Some(path) = UsageLog::default_path() {
+        app.set_usage_log(UsageLog::new(path));
+    }
+    app.set_redactor(file_config.redactor()?);
+    app.set_ranking(file_config.ranking());
+    if let Some(path) = &cli_args.replay {
+        let interval = cli_args.replay_interval.map(Duration::from_millis);
+        app.set_replay(Replay::load(path, interval)?);
+    }
+
+    // Set when the TUI runs; batch mode has returned above
+    let Some(mut output) = tui_output else {
+        unreachable!("the TUI runs only with a terminal");
+    };
     enable_raw_mode()?;
-    let mut stderr = std::io::stderr();
+    if !cli_args.no_alt_screen {
+        execute!(output, crossterm::terminal::EnterAlternateScreen)?;
+    }
     execute!(
-        stderr,
-        crossterm::terminal::EnterAlternateScreen,
-        EnableMouseCapture
+        output,
+        EnableMouseCapture,
+        EnableBracketedPaste,
+        EnableFocusChange
     )?;
-    let backend = CrosstermBackend::new(stderr);
+    let backend = CrosstermBackend::new(output);
     let mut terminal = Terminal::new(backend)?;
 
-    let mut app = App::new(json_value);
+    if cli_args.no_alt_screen {
+        ui::enter_in_place(&mut terminal)?;
+    }
     let res = app.run(&mut terminal);
 
-    restore_terminal(&mut terminal).ok();
-
-    if let Err(e) = res {
-        eprintln!("Error: {}", e);
+    if cli_args.no_alt_screen {
+        ui::restore_in_place(&mut terminal).ok();
+        // The query that produced the screen left above, ready to run again
+        writeln!(
+            terminal.backend_mut(),
+            "{}",
+            app.command_export().rjq_command()
+        )
+        .ok();
+    } else {
+        restore_terminal(&mut terminal).ok();
     }
 
-    Ok(())
+    res.map(|_| batch::exit_code::SUCCESS)
 }
 
 #[cfg(test)]
@@ -71,6 +734,7 @@ mod tests {
     use super::*;
     use serde_json::json;
     use std::fs;
+    use std::path::Path;
 
     #[test]
     fn test_app_creation() {
@@ -84,6 +748,63 @@ mod tests {
         use clap::Parser;
         let args = CliArgs::parse_from(["rjq"]);
         assert_eq!(args.file, None);
+        assert_eq!(args.query, None);
+        assert_eq!(args.error_format, ErrorFormat::Text);
+    }
+
+    #[test]
+    fn test_color_output_flags() {
+        use clap::Parser;
+        let auto = CliArgs::parse_from(["rjq", "-q", "."]);
+        assert!(auto.use_color(true, false));
+        assert!(!auto.use_color(true, true));
+        assert!(!auto.use_color(false, false));
+
+        let forced = CliArgs::parse_from(["rjq", "-C", "-q", "."]);
+        assert!(forced.use_color(false, true));
+        let mono = CliArgs::parse_from(["rjq", "--monochrome-output", "-q", "."]);
+        assert!(!mono.use_color(true, false));
+        assert!(CliArgs::try_parse_from(["rjq", "-C", "-M"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_args_batch_mode() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "-q", ".name", "-e", "--error-format", "json"]);
+        assert_eq!(args.query, Some(".name".to_string()));
+        assert!(args.exit_status);
+        assert_eq!(args.error_format, ErrorFormat::Json);
+    }
+
+    #[test]
+    fn test_cli_args_learn_subcommand() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "learn", "--lesson", "3"]);
+        assert_eq!(args.command, Some(Command::Learn { lesson: 3 }));
+        assert!(CliArgs::try_parse_from(["rjq", "learn", "--lesson", "0"]).is_err());
+        assert_eq!(CliArgs::parse_from(["rjq", "data.json"]).command, None);
+        assert_eq!(
+            CliArgs::parse_from(["rjq", "stats"]).command,
+            Some(Command::Stats)
+        );
+    }
+
+    #[test]
+    fn test_cli_args_debounce() {
+        use clap::Parser;
+        assert_eq!(CliArgs::parse_from(["rjq"]).debounce, None);
+        assert_eq!(
+            CliArgs::parse_from(["rjq", "--debounce", "100"]).debounce,
+            Some(100)
+        );
+        assert!(CliArgs::try_parse_from(["rjq", "--debounce", "100", "--manual"]).is_err());
+    }
+
+    #[test]
+    fn test_cli_args_no_alt_screen() {
+        use clap::Parser;
+        assert!(!CliArgs::parse_from(["rjq"]).no_alt_screen);
+        assert!(CliArgs::parse_from(["rjq", "--no-alt-screen", "data.json"]).no_alt_screen);
     }
 
     #[test]
@@ -100,6 +821,134 @@ mod tests {
         assert_eq!(args.file, Some("test.json".to_string()));
     }
 
+    #[test]
+    fn test_cli_args_positional_file() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "--replay", "session.log", "data.json"]);
+        assert_eq!(args.input_path(), Some(&"data.json".to_string()));
+        assert_eq!(args.replay, Some("session.log".to_string()));
+        assert_eq!(args.replay_interval, None);
+    }
+
+    #[test]
+    fn test_cli_args_several_files() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "a.json", "b.yaml"]);
+        assert_eq!(args.input_paths(), vec!["a.json", "b.yaml"]);
+        // Each file's format comes from its own extension
+        assert_eq!(
+            args.load_options().format_for(Some(Path::new("b.yaml"))),
+            InputFormat::Yaml
+        );
+        assert!(CliArgs::try_parse_from(["rjq", "-f", "a.json", "b.json"]).is_err());
+    }
+
+    #[test]
+    fn test_glob_workspace() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("rjq_glob_workspace_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("b.json"), "{\"id\": 2}").unwrap();
+        fs::write(dir.join("a.json"), "{\"id\": 1}").unwrap();
+        fs::write(dir.join("notes.txt"), "not json").unwrap();
+
+        let pattern = format!("{}/*.json", dir.display());
+        let workspace = load_workspace(&pattern, &LoadOptions::default(), &mut Vec::new()).unwrap();
+        let data: Vec<&serde_json::Value> = workspace
+            .files()
+            .iter()
+            .map(|file| file.data.get())
+            .collect();
+        assert_eq!(data, [&json!({"id": 1}), &json!({"id": 2})]);
+
+        let missing = format!("{}/*.yaml", dir.display());
+        let result = load_workspace(&missing, &LoadOptions::default(), &mut Vec::new());
+        assert!(matches!(result, Err(AppError::FileNotFound(_))));
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(CliArgs::try_parse_from(["rjq", "--glob", "logs/*.json", "a.json"]).is_err());
+        assert!(CliArgs::try_parse_from(["rjq", "--glob", "logs/[.json"]).is_err());
+    }
+
+    #[test]
+    fn test_directory_input() {
+        use clap::Parser;
+        let dir = std::env::temp_dir().join(format!("rjq_directory_input_{}", std::process::id()));
+        fs::remove_dir_all(&dir).ok();
+        fs::create_dir_all(dir.join("2024")).unwrap();
+        fs::write(dir.join("a.json"), r#"{"id": 1}"#).unwrap();
+        fs::write(dir.join("2024").join("b.json"), r#"{"id": 2}"#).unwrap();
+        let path = dir.display().to_string();
+
+        let args = CliArgs::parse_from(["rjq", "--recursive", path.as_str()]);
+        let data = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            data.get(),
+            &json!([
+                {"filename": "2024/b.json", "content": {"id": 2}},
+                {"filename": "a.json", "content": {"id": 1}},
+            ])
+        );
+
+        let args = CliArgs::parse_from(["rjq", "--include", "a.*", path.as_str()]);
+        let data = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(
+            data.get(),
+            &json!([{"filename": "a.json", "content": {"id": 1}}])
+        );
+        fs::remove_dir_all(&dir).ok();
+
+        assert!(CliArgs::try_parse_from(["rjq", "--include", "[", "logs"]).is_err());
+    }
+
+    #[test]
+    fn test_header_flags() {
+        use clap::Parser;
+        let args = CliArgs::parse_from([
+            "rjq",
+            "-H",
+            "Authorization: Bearer t0k",
+            "--header",
+            "X-Api-Key:abc",
+            "https://api.example.com/items",
+        ]);
+        assert_eq!(
+            args.load_options().http_headers,
+            [
+                ("Authorization".to_string(), "Bearer t0k".to_string()),
+                ("X-Api-Key".to_string(), "abc".to_string()),
+            ]
+        );
+        assert!(CliArgs::try_parse_from(["rjq", "-H", "no colon", "a.json"]).is_err());
+    }
+
+    #[test]
+    fn test_watch_path() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "--watch", "a.json", "b.json"]);
+        assert_eq!(args.watch_path().map(String::as_str), Some("a.json"));
+        let url = CliArgs::parse_from(["rjq", "--watch", "https://example.com/a.json"]);
+        assert_eq!(url.watch_path(), None);
+        assert_eq!(CliArgs::parse_from(["rjq", "--watch"]).watch_path(), None);
+        assert!(CliArgs::try_parse_from(["rjq", "--watch", "-q", ".", "a.json"]).is_err());
+        assert!(CliArgs::try_parse_from(["rjq", "--follow", "a.json"]).is_err());
+        assert!(CliArgs::try_parse_from(["rjq", "--follow"]).is_ok());
+    }
+
     #[test]
     fn test_cli_args_help() {
         use clap::Parser;
@@ -113,8 +962,15 @@ mod tests {
     fn test_load_json_from_stdin_empty() {
         use clap::Parser;
         let args = CliArgs::parse_from(["rjq"]);
-        let result = load_json_data(&args, "").unwrap();
-        assert_eq!(result, serde_json::Value::Null);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &serde_json::Value::Null);
     }
 
     #[test]
@@ -122,8 +978,15 @@ mod tests {
         use clap::Parser;
         let args = CliArgs::parse_from(["rjq"]);
         let input = r#"{"key": "value"}"#;
-        let result = load_json_data(&args, input).unwrap();
-        assert_eq!(result, json!({"key": "value"}));
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            input,
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &json!({"key": "value"}));
     }
 
     #[test]
@@ -136,11 +999,266 @@ mod tests {
         fs::write(temp_file, test_data.to_string()).expect("Failed to write test file");
 
         let args = CliArgs::parse_from(["rjq", "-f", temp_file]);
-        let result = load_json_data(&args, "").unwrap();
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
 
         // Clean up
         fs::remove_file(temp_file).ok();
 
-        assert_eq!(result, test_data);
+        assert_eq!(result.get(), &test_data);
+    }
+
+    #[test]
+    fn test_load_yaml_by_extension_or_flag() {
+        use clap::Parser;
+        let temp_file =
+            std::env::temp_dir().join(format!("rjq_test_input_{}.yaml", std::process::id()));
+        fs::write(
+            &temp_file,
+            "name: web
+ports: [80, 443]
+",
+        )
+        .unwrap();
+        let path = temp_file.to_str().unwrap();
+
+        let args = CliArgs::parse_from(["rjq", path]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        fs::remove_file(&temp_file).ok();
+        assert_eq!(result.get(), &json!({"name": "web", "ports": [80, 443]}));
+
+        let args = CliArgs::parse_from(["rjq", "--format", "yaml"]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "- a\n- b\n",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &json!(["a", "b"]));
+    }
+
+    #[test]
+    fn test_load_csv_with_options() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "--format", "csv", "--delimiter", ";"]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "id;name
+1;alice
+",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &json!([{"id": "1", "name": "alice"}]));
+
+        let args = CliArgs::parse_from([
+            "rjq",
+            "--format",
+            "csv",
+            "--delimiter",
+            "\\t",
+            "--no-header",
+        ]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "1\talice\n",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &json!([["1", "alice"]]));
+
+        assert!(CliArgs::try_parse_from(["rjq", "--delimiter", "ab"]).is_err());
+    }
+
+    #[test]
+    fn test_bar_keys() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "--bar-keys", "path, hits"]);
+        assert_eq!(
+            args.bar_keys,
+            Some(BarMapping {
+                label: "path".to_string(),
+                value: "hits".to_string(),
+            })
+        );
+        assert!(CliArgs::try_parse_from(["rjq", "--bar-keys", "path"]).is_err());
+    }
+
+    #[test]
+    fn test_load_lenient_json() {
+        use clap::Parser;
+        let text = "{\"a\": 1, // note\n}";
+        let args = CliArgs::parse_from(["rjq"]);
+        assert!(matches!(
+            load_json_data(
+                &args,
+                &args.load_options(),
+                text,
+                &mut Vec::new(),
+                &LoadProgress::default()
+            ),
+            Err(AppError::JsonParse(_))
+        ));
+        let args = CliArgs::parse_from(["rjq", "--lenient"]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            text,
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.get(), &json!({"a": 1}));
+    }
+
+    #[test]
+    fn test_load_ndjson() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "--ndjson"]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "{\"a\": 1}\n{\"a\": 2}\n",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(result.inputs(), &[json!({"a": 1}), json!({"a": 2})]);
+        assert!(CliArgs::try_parse_from(["rjq", "--ndjson", "--format", "yaml"]).is_err());
+
+        let args = CliArgs::parse_from(["rjq", "--ndjson", "events.log"]);
+        assert_eq!(
+            args.load_options()
+                .format_for(Some(Path::new("events.log"))),
+            InputFormat::Ndjson
+        );
+    }
+
+    #[test]
+    fn test_arg_and_argjson_variables() {
+        use clap::Parser;
+        let args = CliArgs::parse_from([
+            "rjq",
+            "--arg",
+            "env",
+            "prod",
+            "--argjson",
+            "ids",
+            "[1, 2]",
+            "--arg",
+            "n",
+            "3",
+        ]);
+        assert_eq!(
+            args.variables().unwrap(),
+            vec![
+                ("env".to_string(), json!("prod")),
+                ("n".to_string(), json!("3")),
+                ("ids".to_string(), json!([1, 2])),
+            ]
+        );
+        let args = CliArgs::parse_from(["rjq", "--argjson", "ids", "[1,"]);
+        assert!(matches!(
+            args.variables(),
+            Err(AppError::InputParse { format: "JSON", .. })
+        ));
+        assert!(CliArgs::try_parse_from(["rjq", "--arg", "env"]).is_err());
+    }
+
+    #[test]
+    fn test_rawfile_and_slurpfile_variables() {
+        use clap::Parser;
+        let raw = "test_temp_rawfile.txt";
+        let docs = "test_temp_slurpfile.json";
+        fs::write(raw, "line\n").expect("Failed to write test file");
+        fs::write(docs, "{\"a\": 1} {\"a\": 2}").expect("Failed to write test file");
+
+        let args =
+            CliArgs::parse_from(["rjq", "--rawfile", "text", raw, "--slurpfile", "docs", docs]);
+        let variables = args.variables();
+        fs::remove_file(raw).ok();
+        fs::remove_file(docs).ok();
+
+        assert_eq!(
+            variables.unwrap(),
+            vec![
+                ("text".to_string(), json!("line\n")),
+                ("docs".to_string(), json!([{"a": 1}, {"a": 2}])),
+            ]
+        );
+        let args = CliArgs::parse_from(["rjq", "--rawfile", "text", "no_such_file.txt"]);
+        assert!(matches!(args.variables(), Err(AppError::FileNotFound(_))));
+    }
+
+    #[test]
+    fn test_variables_interpolate_environment() {
+        use clap::Parser;
+        let raw = "test_temp_interpolated_prod.txt";
+        fs::write(raw, "contents").expect("Failed to write test file");
+        // SAFETY: no other test reads or writes this variable
+        unsafe { std::env::set_var("RJQ_TEST_ARG_STAGE", "prod") };
+        let args = CliArgs::parse_from([
+            "rjq",
+            "--arg",
+            "url",
+            "https://${RJQ_TEST_ARG_STAGE}.test/$${literal}",
+            "--rawfile",
+            "text",
+            "test_temp_interpolated_${RJQ_TEST_ARG_STAGE}.txt",
+        ]);
+        let variables = args.variables();
+        fs::remove_file(raw).ok();
+        assert_eq!(
+            variables.unwrap(),
+            vec![
+                ("url".to_string(), json!("https://prod.test/${literal}")),
+                ("text".to_string(), json!("contents")),
+            ]
+        );
+
+        let args = CliArgs::parse_from(["rjq", "--arg", "token", "${RJQ_TEST_ARG_UNSET}"]);
+        match args.variables() {
+            Err(AppError::Config { path, message }) => {
+                assert_eq!(path, "--arg token");
+                assert_eq!(
+                    message,
+                    "environment variable `RJQ_TEST_ARG_UNSET` is not set"
+                );
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_load_json_from_missing_file() {
+        use clap::Parser;
+        let args = CliArgs::parse_from(["rjq", "-f", "no_such_file.json"]);
+        let result = load_json_data(
+            &args,
+            &args.load_options(),
+            "",
+            &mut Vec::new(),
+            &LoadProgress::default(),
+        );
+        assert!(matches!(result, Err(AppError::FileNotFound(_))));
     }
 }
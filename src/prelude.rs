@@ -0,0 +1,15 @@
+//! Commonly used types in one import
+//!
+//! `use rjq::prelude::*;` brings in everything needed to embed rjq: building
+//! an app, running queries and mapping key events. Items outside the prelude
+//! and the crate root re-exports may change between minor versions.
+
+pub use crate::Result;
+pub use crate::app::{
+    App, AppBuilder, AppConfig, AppError, AppEvent, AppWarning, Effect, EnhancedApp, SubscriptionId,
+};
+pub use crate::query::{JaqQueryExecutor, JsonData, QueryExecutor, QueryResult};
+pub use crate::render::RenderTree;
+pub use crate::ui::{
+    Action, ActionContext, DefaultEventHandler, EditAction, EventHandler, SystemAction, ViewAction,
+};
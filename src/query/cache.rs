@@ -1,20 +1,124 @@
 use serde_json::Value;
 use std::collections::HashMap;
+use std::mem;
+
+/// Results kept by an [`InMemoryQueryCache`] created with `new`
+pub const DEFAULT_CACHE_ENTRIES: usize = 256;
+
+/// Estimated memory kept by an [`InMemoryQueryCache`] created with `new`
+pub const DEFAULT_CACHE_BYTES: usize = 64 * 1024 * 1024;
 
 pub trait QueryCache {
-    fn get(&self, key: &str) -> Option<Vec<Value>>;
+    /// The stored value for `key`, counting a hit or a miss
+    fn get(&mut self, key: &str) -> Option<Vec<Value>>;
     fn set(&mut self, key: String, value: Vec<Value>);
+
+    /// Forgets every stored value and the statistics
     fn clear(&mut self);
+
+    /// Drops the stored values because the data they were computed from
+    /// changed, keeping the hit and miss counts
+    ///
+    /// Called through [`QueryExecutor::invalidate`](super::QueryExecutor::invalidate)
+    /// whenever the document is reloaded, replaced or merged into.
+    fn invalidate(&mut self) {
+        self.clear();
+    }
+
+    /// Hit and miss counts and how much is stored
+    fn stats(&self) -> CacheStats {
+        CacheStats::default()
+    }
+}
+
+/// Usage statistics of a cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// Entries dropped to stay within the limits
+    pub evictions: usize,
+    /// Times the stored values were dropped for changed data
+    pub invalidations: usize,
+    pub entries: usize,
+    /// Estimated memory held by the stored values
+    pub bytes: usize,
+    pub max_entries: usize,
+    pub max_bytes: usize,
+}
+
+impl CacheStats {
+    /// Share of lookups answered from the cache, `None` before any lookup
+    pub fn hit_rate(&self) -> Option<f64> {
+        let lookups = self.hits + self.misses;
+        (lookups > 0).then(|| self.hits as f64 / lookups as f64)
+    }
 }
 
+/// In-memory LRU cache
+///
+/// Keeps at most `max_entries` results and about `max_bytes` of them, as
+/// estimated by [`estimated_size`]; the least recently used results make
+/// room for new ones. A single result larger than `max_bytes` is not stored.
+#[derive(Debug)]
 pub struct InMemoryQueryCache {
-    cache: HashMap<String, Vec<Value>>,
+    cache: HashMap<String, Entry>,
+    /// Bumped on every use, so the smallest `used` is the least recent
+    clock: u64,
+    stats: CacheStats,
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: Vec<Value>,
+    bytes: usize,
+    used: u64,
 }
 
 impl InMemoryQueryCache {
     pub fn new() -> Self {
+        Self::with_limits(DEFAULT_CACHE_ENTRIES, DEFAULT_CACHE_BYTES)
+    }
+
+    pub fn with_limits(max_entries: usize, max_bytes: usize) -> Self {
         Self {
             cache: HashMap::new(),
+            clock: 0,
+            stats: CacheStats {
+                max_entries,
+                max_bytes,
+                ..Default::default()
+            },
+        }
+    }
+
+    fn tick(&mut self) -> u64 {
+        self.clock += 1;
+        self.clock
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.cache.remove(key) {
+            self.stats.bytes -= entry.bytes;
+        }
+    }
+
+    /// Drops the least recently used entries until `bytes` more fit
+    fn make_room(&mut self, bytes: usize) {
+        while !self.cache.is_empty()
+            && (self.cache.len() >= self.stats.max_entries
+                || self.stats.bytes + bytes > self.stats.max_bytes)
+        {
+            let Some(oldest) = self
+                .cache
+                .iter()
+                .min_by_key(|(_, entry)| entry.used)
+                .map(|(key, _)| key.clone())
+            else {
+                break;
+            };
+            self.remove(&oldest);
+            self.stats.evictions += 1;
         }
     }
 }
@@ -26,19 +130,74 @@ impl Default for InMemoryQueryCache {
 }
 
 impl QueryCache for InMemoryQueryCache {
-    fn get(&self, key: &str) -> Option<Vec<Value>> {
-        self.cache.get(key).cloned()
+    fn get(&mut self, key: &str) -> Option<Vec<Value>> {
+        let used = self.tick();
+        match self.cache.get_mut(key) {
+            Some(entry) => {
+                entry.used = used;
+                self.stats.hits += 1;
+                Some(entry.value.clone())
+            }
+            None => {
+                self.stats.misses += 1;
+                None
+            }
+        }
     }
 
     fn set(&mut self, key: String, value: Vec<Value>) {
-        self.cache.insert(key, value);
+        self.remove(&key);
+        let bytes = key.len() + value.iter().map(estimated_size).sum::<usize>();
+        if self.stats.max_entries == 0 || bytes > self.stats.max_bytes {
+            return;
+        }
+        self.make_room(bytes);
+        let used = self.tick();
+        self.stats.bytes += bytes;
+        self.cache.insert(key, Entry { value, bytes, used });
     }
 
     fn clear(&mut self) {
         self.cache.clear();
+        self.stats = CacheStats {
+            max_entries: self.stats.max_entries,
+            max_bytes: self.stats.max_bytes,
+            ..Default::default()
+        };
+    }
+
+    fn invalidate(&mut self) {
+        self.cache.clear();
+        self.stats.bytes = 0;
+        self.stats.invalidations += 1;
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            entries: self.cache.len(),
+            ..self.stats
+        }
     }
 }
 
+/// Rough memory held by `value`: its node plus the text of its strings,
+/// keys and children
+///
+/// Allocator overhead and spare capacity are not counted, so the real use is
+/// somewhat higher; the estimate only has to grow with the value.
+pub fn estimated_size(value: &Value) -> usize {
+    mem::size_of::<Value>()
+        + match value {
+            Value::String(s) => s.len(),
+            Value::Array(items) => items.iter().map(estimated_size).sum(),
+            Value::Object(map) => map
+                .iter()
+                .map(|(key, value)| mem::size_of::<String>() + key.len() + estimated_size(value))
+                .sum(),
+            _ => 0,
+        }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -55,7 +214,49 @@ mod tests {
         cache.set(key.to_string(), value.clone());
         assert_eq!(cache.get(key), Some(value));
 
-        cache.clear();
+        cache.invalidate();
         assert!(cache.get(key).is_none());
+
+        let stats = cache.stats();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (1, 2, 0));
+        assert_eq!(stats.invalidations, 1);
+        assert_eq!(stats.hit_rate(), Some(1.0 / 3.0));
+
+        cache.clear();
+        assert_eq!(cache.stats().hit_rate(), None);
+        assert_eq!(cache.stats().max_entries, DEFAULT_CACHE_ENTRIES);
+    }
+
+    #[test]
+    fn test_least_recently_used_entry_is_evicted() {
+        let mut cache = InMemoryQueryCache::with_limits(2, usize::MAX);
+        cache.set("a".to_string(), vec![json!(1)]);
+        cache.set("b".to_string(), vec![json!(2)]);
+        cache.get("a");
+        cache.set("c".to_string(), vec![json!(3)]);
+
+        assert!(cache.get("b").is_none());
+        assert!(cache.get("a").is_some() && cache.get("c").is_some());
+        assert_eq!(cache.stats().evictions, 1);
+        assert_eq!(cache.stats().entries, 2);
+    }
+
+    #[test]
+    fn test_memory_limit() {
+        let big = vec![json!("x".repeat(1000))];
+        let size = 1 + estimated_size(&big[0]);
+        let mut cache = InMemoryQueryCache::with_limits(10, size * 2);
+        cache.set("a".to_string(), big.clone());
+        cache.set("b".to_string(), big.clone());
+        assert_eq!(cache.stats().bytes, size * 2);
+
+        cache.set("c".to_string(), big);
+        assert_eq!(cache.stats().entries, 2);
+        assert!(cache.get("a").is_none());
+
+        // Too large to keep at all
+        cache.set("d".to_string(), vec![json!("x".repeat(size * 2))]);
+        assert!(cache.get("d").is_none());
+        assert_eq!(cache.stats().bytes, size * 2);
     }
 }
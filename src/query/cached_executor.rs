@@ -1,10 +1,15 @@
-use super::{QueryCache, QueryExecutor};
+use super::{CacheStats, QueryCache, QueryExecutor};
 use crate::app::error::AppError;
 use serde_json::Value;
 use std::cell::RefCell;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::{Hash, Hasher};
 
+/// Query executor caching its results
+///
+/// [`execute`](QueryExecutor::execute) keys results by a hash of the data, so
+/// every lookup reads all of it; data given with a fingerprint, see
+/// [`QueryExecutor::execute_fingerprinted`], is keyed by that number instead.
 pub struct CachedQueryExecutor<E: QueryExecutor, C: QueryCache> {
     executor: E,
     cache: RefCell<C>,
@@ -24,23 +29,63 @@ impl<E: QueryExecutor, C: QueryCache> CachedQueryExecutor<E, C> {
         query.hash(&mut hasher);
         format!("{:x}", hasher.finish())
     }
-}
 
-impl<E: QueryExecutor, C: QueryCache> QueryExecutor for CachedQueryExecutor<E, C> {
-    fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
-        let key = Self::cache_key(data, query);
+    /// Key for data identified by a fingerprint, never equal to a [`cache_key`](Self::cache_key)
+    fn fingerprint_key(fingerprint: u64, query: &str) -> String {
+        format!("{:x}:{}", fingerprint, query)
+    }
 
-        if let Some(cached_result) = self.cache.borrow().get(&key) {
+    /// The result stored under `key`, or that of `run`, which is then stored
+    fn cached(
+        &self,
+        key: String,
+        run: impl FnOnce() -> Result<Vec<Value>, AppError>,
+    ) -> Result<Vec<Value>, AppError> {
+        if let Some(cached_result) = self.cache.borrow_mut().get(&key) {
             return Ok(cached_result);
         }
 
-        let result = self.executor.execute(data, query)?;
+        let result = run()?;
         self.cache.borrow_mut().set(key, result.clone());
 
         Ok(result)
     }
 }
 
+impl<E: QueryExecutor, C: QueryCache> QueryExecutor for CachedQueryExecutor<E, C> {
+    fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
+        self.cached(Self::cache_key(data, query), || {
+            self.executor.execute(data, query)
+        })
+    }
+
+    fn execute_fingerprinted(
+        &self,
+        data: &Value,
+        fingerprint: u64,
+        query: &str,
+    ) -> Result<Vec<Value>, AppError> {
+        self.cached(Self::fingerprint_key(fingerprint, query), || {
+            self.executor
+                .execute_fingerprinted(data, fingerprint, query)
+        })
+    }
+
+    /// Streams are not cached; keying on every input would cost as much as running the query
+    fn execute_inputs(&self, inputs: &[Value], query: &str) -> Result<Vec<Value>, AppError> {
+        self.executor.execute_inputs(inputs, query)
+    }
+
+    fn invalidate(&self) {
+        self.cache.borrow_mut().invalidate();
+        self.executor.invalidate();
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        Some(self.cache.borrow().stats())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -63,6 +108,38 @@ mod tests {
         assert_eq!(result1[0], json!("test"));
     }
 
+    #[test]
+    fn test_invalidate_clears_cache() {
+        #[derive(Default)]
+        struct Counting(std::cell::Cell<usize>);
+        impl QueryExecutor for Counting {
+            fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
+                self.0.set(self.0.get() + 1);
+                JaqQueryExecutor.execute(data, query)
+            }
+        }
+
+        let cached = CachedQueryExecutor::new(Counting::default(), InMemoryQueryCache::new());
+        let data = json!([1]);
+        cached.execute(&data, ".[0]").unwrap();
+        cached.execute(&data, ".[0]").unwrap();
+        assert_eq!(cached.executor.0.get(), 1);
+
+        cached.invalidate();
+        cached.execute(&data, ".[0]").unwrap();
+        assert_eq!(cached.executor.0.get(), 2);
+
+        // Fingerprinted data is cached apart from the hashed one, by its fingerprint
+        cached.execute_fingerprinted(&data, 7, ".[0]").unwrap();
+        cached.execute_fingerprinted(&data, 7, ".[0]").unwrap();
+        cached.execute_fingerprinted(&data, 8, ".[0]").unwrap();
+        assert_eq!(cached.executor.0.get(), 4);
+
+        let stats = cached.cache_stats().unwrap();
+        assert_eq!((stats.hits, stats.misses, stats.entries), (2, 4, 3));
+        assert_eq!(stats.invalidations, 1);
+    }
+
     #[test]
     fn test_cache_key_generation() {
         let data = json!({"test": "data"});
@@ -75,5 +152,10 @@ mod tests {
 
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
+
+        let key = CachedQueryExecutor::<JaqQueryExecutor, InMemoryQueryCache>::fingerprint_key;
+        assert_eq!(key(1, ".test"), key(1, ".test"));
+        assert_ne!(key(1, ".test"), key(1, ".other"));
+        assert_ne!(key(1, ".test"), key(2, ".test"));
     }
 }
@@ -0,0 +1,162 @@
+//! Query cancellation
+//!
+//! jaq has no way to stop a filter from the outside, so evaluation checks
+//! the token of its thread between outputs, and [`natives`] replace the
+//! loops of `range` and `limit` with ones checking it on every step, so
+//! `[range(1e9)]` stops although it outputs nothing until the end. Other
+//! loops producing nothing, like `last(repeat(1))`, still run on.
+
+use jaq_core::box_iter::then;
+use jaq_core::compile::Lut;
+use jaq_core::{Bind, Cv, Error, Exn, FilterT, Native, ValX, ValXs};
+use jaq_json::Val;
+use jaq_std::ValT;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+thread_local! {
+    static CURRENT: RefCell<Option<CancelToken>> = const { RefCell::new(None) };
+}
+
+/// Flag cancelling a query's evaluation
+///
+/// Clones share the flag, so the thread that started a query can cancel it
+/// while a worker evaluates it.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Runs `f` with this token as the one queries on this thread check
+    pub fn install<T>(&self, f: impl FnOnce() -> T) -> T {
+        let previous = CURRENT.with(|current| current.replace(Some(self.clone())));
+        let result = f();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+        result
+    }
+
+    /// The token installed on this thread, to hand on to helper threads
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+}
+
+/// Whether the query running on this thread was cancelled
+pub fn cancelled() -> bool {
+    CURRENT.with(|current| {
+        current
+            .borrow()
+            .as_ref()
+            .is_some_and(CancelToken::is_cancelled)
+    })
+}
+
+/// Natives standing in for jaq's own ones of the same name and arity
+pub fn natives() -> [jaq_std::Filter<Native<Val>>; 2] {
+    [
+        ("range", jaq_std::v(3), Native::new(range)),
+        (
+            "limit",
+            Box::new([Bind::Var(()), Bind::Fun(())]),
+            Native::new(limit),
+        ),
+    ]
+}
+
+/// `range($from; $to; $by)` as in jaq-std, stopping when cancelled
+fn range<'a>(_: &'a Lut<Native<Val>>, mut cv: Cv<'a, Val>) -> ValXs<'a, Val> {
+    use std::cmp::Ordering::{Equal, Greater, Less};
+    let by = cv.0.pop_var();
+    let to = cv.0.pop_var();
+    let mut from: ValX<'a, Val> = Ok(cv.0.pop_var());
+    let cmp = by.partial_cmp(&Val::from(0)).unwrap_or(Equal);
+    Box::new(checked(std::iter::from_fn(move || match from.clone() {
+        Ok(x) => match cmp {
+            Greater => x < to,
+            Less => x > to,
+            Equal => x != to,
+        }
+        .then(|| std::mem::replace(&mut from, (x + by.clone()).map_err(Exn::from))),
+        e @ Err(_) => {
+            from = Ok(to.clone());
+            Some(e)
+        }
+    })))
+}
+
+/// `limit($n; f)` as in jaq-std, stopping when cancelled
+fn limit<'a>(lut: &'a Lut<Native<Val>>, mut cv: Cv<'a, Val>) -> ValXs<'a, Val> {
+    let (f, fc) = cv.0.pop_fun();
+    let n = cv.0.pop_var();
+    let n = n.as_isize().ok_or_else(|| Error::typ(n.clone(), "integer"));
+    then(n.map_err(Exn::from), move |n| {
+        match usize::try_from(n).unwrap_or(0) {
+            0 => Box::new(std::iter::empty()),
+            n => Box::new(checked(f.run(lut, (fc, cv.1))).take(n)),
+        }
+    })
+}
+
+/// `iter` until the query on this thread is cancelled, then an error
+fn checked<'a>(
+    mut iter: impl Iterator<Item = ValX<'a, Val>> + 'a,
+) -> impl Iterator<Item = ValX<'a, Val>> + 'a {
+    let mut stopped = false;
+    std::iter::from_fn(move || {
+        if stopped {
+            return None;
+        }
+        if cancelled() {
+            stopped = true;
+            return Some(Err(Exn::from(Error::str("cancelled"))));
+        }
+        iter.next()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_is_scoped_to_install() {
+        let token = CancelToken::new();
+        assert!(!cancelled());
+        token.install(|| {
+            assert!(!cancelled());
+            token.clone().cancel();
+            assert!(cancelled());
+            assert!(CancelToken::current().is_some());
+        });
+        assert!(!cancelled());
+        assert!(CancelToken::current().is_none());
+    }
+
+    #[test]
+    fn test_natives_behave_like_jq() {
+        use crate::query::{JaqQueryExecutor, QueryExecutor};
+        use serde_json::json;
+        let run = |query| JaqQueryExecutor.execute(&json!(null), query).unwrap();
+        assert_eq!(run("[range(4)]"), vec![json!([0, 1, 2, 3])]);
+        assert_eq!(run("[range(5; 0; -2)]"), vec![json!([5, 3, 1])]);
+        assert_eq!(run("[limit(2; 1, 2, 3)]"), vec![json!([1, 2])]);
+        assert_eq!(run("[limit(0; 1, 2)]"), vec![json!([])]);
+        assert!(
+            JaqQueryExecutor
+                .execute(&json!(null), "limit(\"a\"; 1)")
+                .is_err()
+        );
+    }
+}
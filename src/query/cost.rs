@@ -0,0 +1,93 @@
+//! Detection of slow constructs
+//!
+//! Some filters visit every value of the document, or every pair of values.
+//! On a large input they can keep the terminal busy for minutes, so the TUI
+//! asks before running them.
+
+use super::lexer::{QueryTokenKind, tokenize_query};
+use std::fmt;
+
+/// Construct that is slow on large inputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpensiveConstruct {
+    /// `..`, visiting every value
+    RecursiveDescent,
+    /// `recurse`, visiting every value
+    Recurse,
+    /// `combinations`, the cartesian product of arrays
+    Combinations,
+    /// `.[] as $a | .[] as $b`, every pair of values
+    NestedIteration,
+}
+
+impl fmt::Display for ExpensiveConstruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpensiveConstruct::RecursiveDescent => write!(f, "`..`"),
+            ExpensiveConstruct::Recurse => write!(f, "`recurse`"),
+            ExpensiveConstruct::Combinations => write!(f, "`combinations`"),
+            ExpensiveConstruct::NestedIteration => write!(f, "nested iteration"),
+        }
+    }
+}
+
+/// The first construct in `query` that is slow on large inputs
+///
+/// Strings and comments are skipped; an iteration only counts as nested
+/// once two of them are bound to variables.
+pub fn find_expensive(query: &str) -> Option<ExpensiveConstruct> {
+    let tokens = tokenize_query(query);
+    let mut bound_iterations = 0;
+    for (i, token) in tokens.iter().enumerate() {
+        let found = match (token.kind, token.text(query)) {
+            (QueryTokenKind::Dot, "..") => Some(ExpensiveConstruct::RecursiveDescent),
+            (QueryTokenKind::Ident, "recurse") => Some(ExpensiveConstruct::Recurse),
+            (QueryTokenKind::Ident, "combinations") => Some(ExpensiveConstruct::Combinations),
+            (QueryTokenKind::Open, "[") => {
+                let iteration = tokens.get(i + 1).map(|t| t.text(query)) == Some("]")
+                    && tokens.get(i + 2).map(|t| t.text(query)) == Some("as");
+                bound_iterations += iteration as usize;
+                (bound_iterations >= 2).then_some(ExpensiveConstruct::NestedIteration)
+            }
+            _ => None,
+        };
+        if found.is_some() {
+            return found;
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_finds_expensive_constructs() {
+        assert_eq!(
+            find_expensive(".. | numbers"),
+            Some(ExpensiveConstruct::RecursiveDescent)
+        );
+        assert_eq!(
+            find_expensive("[recurse(.children[])]"),
+            Some(ExpensiveConstruct::Recurse)
+        );
+        assert_eq!(
+            find_expensive("[.a, .b] | combinations"),
+            Some(ExpensiveConstruct::Combinations)
+        );
+        assert_eq!(
+            find_expensive(".[] as $a | .[] as $b | [$a, $b]"),
+            Some(ExpensiveConstruct::NestedIteration)
+        );
+    }
+
+    #[test]
+    fn test_cheap_queries_pass() {
+        assert_eq!(find_expensive(".users[] | .name"), None);
+        assert_eq!(find_expensive(".[] as $a | $a.id"), None);
+        assert_eq!(find_expensive(r#"select(.path == "..")"#), None);
+        assert_eq!(find_expensive(".recurse"), None);
+        assert_eq!(find_expensive(". # recurse"), None);
+    }
+}
@@ -0,0 +1,92 @@
+use super::literals::open_string_start;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::LazyLock;
+
+/// Trailing `.a.b` path on the left-hand side of a comparison
+static COMPARED_PATH: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"((?:\.[A-Za-z_][A-Za-z0-9_]*)+)\s*[!=]=\s*$").expect("valid regex")
+});
+
+/// String literal being typed on the right of a comparison
+#[derive(Debug, Clone, PartialEq)]
+pub struct StringComparison {
+    /// Field path compared against, e.g. `["status"]` for `.status == "`
+    pub path: Vec<String>,
+    /// Text typed so far inside the literal
+    pub prefix: String,
+    /// Byte offset just after the opening quote
+    pub start: usize,
+}
+
+/// Detects a query ending inside a string literal compared with a field,
+/// such as `select(.status == "ac`
+pub fn open_string_comparison(query: &str) -> Option<StringComparison> {
+    let start = open_string_start(query)?;
+    let before = &query[..start - 1];
+    let captures = COMPARED_PATH.captures(before)?;
+    let path = captures[1].split('.').skip(1).map(str::to_string).collect();
+    Some(StringComparison {
+        path,
+        prefix: query[start..].to_string(),
+        start,
+    })
+}
+
+/// Distinct string values found at `path` below any node of `data`, sorted
+pub fn distinct_strings(data: &Value, path: &[String]) -> Vec<String> {
+    let mut found = BTreeSet::new();
+    collect(data, path, &mut found);
+    found.into_iter().collect()
+}
+
+fn collect(node: &Value, path: &[String], found: &mut BTreeSet<String>) {
+    let target = path
+        .iter()
+        .try_fold(node, |value, key| value.as_object()?.get(key));
+    if let Some(Value::String(s)) = target {
+        found.insert(s.clone());
+    }
+    match node {
+        Value::Object(map) => map.values().for_each(|v| collect(v, path, found)),
+        Value::Array(items) => items.iter().for_each(|v| collect(v, path, found)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_open_string_comparison() {
+        let context = open_string_comparison(r#".[] | select(.meta.status == "ac"#).unwrap();
+        assert_eq!(context.path, vec!["meta", "status"]);
+        assert_eq!(context.prefix, "ac");
+        assert_eq!(context.start, 30);
+
+        assert!(open_string_comparison(r#"select(.status == "active")"#).is_none());
+        assert!(open_string_comparison(r#"select(.status | test("a"#).is_none());
+        assert!(open_string_comparison(r#".a != ""#).is_some());
+    }
+
+    #[test]
+    fn test_distinct_strings() {
+        let data = json!({
+            "users": [
+                {"status": "active"},
+                {"status": "banned"},
+                {"status": "active"},
+                {"status": 3},
+            ],
+            "status": "root"
+        });
+        let path = vec!["status".to_string()];
+        assert_eq!(
+            distinct_strings(&data, &path),
+            vec!["active", "banned", "root"]
+        );
+    }
+}
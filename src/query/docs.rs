@@ -0,0 +1,205 @@
+/// Documentation of a builtin
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuiltinDoc {
+    pub name: &'static str,
+    /// Call syntax, e.g. `group_by(f)`
+    pub signature: &'static str,
+    pub category: &'static str,
+    pub summary: &'static str,
+    /// `input | filter` and the output it produces
+    pub example: &'static str,
+}
+
+const fn doc(
+    name: &'static str,
+    signature: &'static str,
+    category: &'static str,
+    summary: &'static str,
+    example: &'static str,
+) -> BuiltinDoc {
+    BuiltinDoc {
+        name,
+        signature,
+        category,
+        summary,
+        example,
+    }
+}
+
+/// Documented builtins, grouped by category
+#[rustfmt::skip]
+pub const BUILTINS: &[BuiltinDoc] = &[
+    // Types and values
+    doc("length", "length", "Types", "Number of elements, keys or characters; absolute value of a number", "[1,2,3] | length  =>  3"),
+    doc("type", "type", "Types", "Name of the input's type", "{} | type  =>  \"object\""),
+    doc("not", "not", "Types", "Logical negation", "true | not  =>  false"),
+    doc("empty", "empty", "Types", "Produces no output", "[1, empty, 2]  =>  [1,2]"),
+    doc("error", "error, error(msg)", "Types", "Raises an error with the input or `msg` as message", "try error(\"x\") catch .  =>  \"x\""),
+    doc("tostring", "tostring", "Types", "Converts to a string; strings are left as they are", "[1] | tostring  =>  \"[1]\""),
+    doc("tonumber", "tonumber", "Types", "Parses a string as a number", "\"42\" | tonumber  =>  42"),
+    doc("tojson", "tojson", "Types", "Encodes the input as a JSON string", "{\"a\":1} | tojson  =>  \"{\\\"a\\\":1}\""),
+    doc("fromjson", "fromjson", "Types", "Parses a JSON string", "\"[1]\" | fromjson  =>  [1]"),
+    doc("arrays", "arrays, objects, strings, numbers, booleans, nulls, iterables, scalars", "Types", "Selects inputs of the given type", "[1,\"a\"] | .[] | numbers  =>  1"),
+    doc("infinite", "infinite, nan, isinfinite, isnan, isnormal", "Types", "Special numbers and tests for them", "nan | isnan  =>  true"),
+    // Arrays
+    doc("map", "map(f)", "Arrays", "Applies `f` to every element", "[1,2] | map(. * 10)  =>  [10,20]"),
+    doc("select", "select(cond)", "Arrays", "Passes the input through only if `cond` is true", ".[] | select(.age > 25)"),
+    doc("add", "add", "Arrays", "Sums numbers, concatenates strings and arrays, merges objects", "[1,2,3] | add  =>  6"),
+    doc("any", "any, any(cond), any(gen; cond)", "Arrays", "True if any element is true (or satisfies `cond`)", "[1,5] | any(. > 3)  =>  true"),
+    doc("all", "all, all(cond), all(gen; cond)", "Arrays", "True if every element is true (or satisfies `cond`)", "[1,5] | all(. > 3)  =>  false"),
+    doc("flatten", "flatten, flatten(depth)", "Arrays", "Flattens nested arrays", "[1,[2,[3]]] | flatten  =>  [1,2,3]"),
+    doc("range", "range(upto), range(from; upto), range(from; upto; by)", "Arrays", "Produces a sequence of numbers", "[range(3)]  =>  [0,1,2]"),
+    doc("reverse", "reverse", "Arrays", "Reverses an array or string", "[1,2,3] | reverse  =>  [3,2,1]"),
+    doc("sort", "sort", "Arrays", "Sorts an array in jq's value order", "[3,1,2] | sort  =>  [1,2,3]"),
+    doc("sort_by", "sort_by(f)", "Arrays", "Sorts by the value of `f` for each element", "sort_by(.age)"),
+    doc("group_by", "group_by(f)", "Arrays", "Groups elements with equal `f` into arrays, sorted by `f`", "[{\"k\":1},{\"k\":2},{\"k\":1}] | group_by(.k)  =>  [[{\"k\":1},{\"k\":1}],[{\"k\":2}]]"),
+    doc("unique", "unique", "Arrays", "Sorted array without duplicates", "[2,1,2] | unique  =>  [1,2]"),
+    doc("unique_by", "unique_by(f)", "Arrays", "Keeps one element per distinct value of `f`", "unique_by(.id)"),
+    doc("min", "min, max", "Arrays", "Smallest or largest element", "[3,1,2] | min  =>  1"),
+    doc("min_by", "min_by(f), max_by(f)", "Arrays", "Element with the smallest or largest `f`", "max_by(.score)"),
+    doc("first", "first, last, first(f), last(f)", "Arrays", "First or last element, or first or last output of `f`", "[1,2,3] | last  =>  3"),
+    doc("nth", "nth(n), nth(n; f)", "Arrays", "The `n`th element, or `n`th output of `f`", "[5,6,7] | nth(1)  =>  6"),
+    doc("index", "index(s), rindex(s), indices(s)", "Arrays", "Positions of `s` in a string or array", "\"a,b\" | index(\",\")  =>  1"),
+    doc("inside", "contains(b), inside(b)", "Arrays", "Whether the input contains `b`, or is contained in it", "[1,2] | contains([1])  =>  true"),
+    doc("transpose", "transpose", "Arrays", "Transposes an array of arrays", "[[1,2],[3,4]] | transpose  =>  [[1,3],[2,4]]"),
+    doc("limit", "limit(n; f)", "Streams", "At most `n` outputs of `f`", "[limit(2; .[])]"),
+    // Objects
+    doc("keys", "keys, keys_unsorted", "Objects", "Keys of an object (sorted) or indices of an array", "{\"b\":1,\"a\":2} | keys  =>  [\"a\",\"b\"]"),
+    doc("has", "has(key)", "Objects", "Whether the input has the key or index", "{\"a\":1} | has(\"a\")  =>  true"),
+    doc("in", "in(obj)", "Objects", "Whether the input key exists in `obj`", "\"a\" | in({\"a\":1})  =>  true"),
+    doc("to_entries", "to_entries", "Objects", "Converts an object to `{key, value}` pairs", "{\"a\":1} | to_entries  =>  [{\"key\":\"a\",\"value\":1}]"),
+    doc("from_entries", "from_entries", "Objects", "Builds an object from `{key, value}` pairs", "[{\"key\":\"a\",\"value\":1}] | from_entries  =>  {\"a\":1}"),
+    doc("with_entries", "with_entries(f)", "Objects", "Shorthand for `to_entries | map(f) | from_entries`", "with_entries(.value += 1)"),
+    doc("del", "del(path)", "Objects", "Removes the given paths", "{\"a\":1,\"b\":2} | del(.a)  =>  {\"b\":2}"),
+    doc("paths", "paths, paths(f)", "Paths", "All paths in the input, optionally only those whose value satisfies `f`", "{\"a\":{\"b\":1}} | [paths]  =>  [[\"a\"],[\"a\",\"b\"]]"),
+    doc("getpath", "getpath(p)", "Paths", "Value at a path array", "{\"a\":{\"b\":1}} | getpath([\"a\",\"b\"])  =>  1"),
+    doc("recurse", "recurse, recurse(f), ..", "Paths", "The input and all values reachable through `f` (default: all children)", "[.. | numbers]"),
+    doc("walk", "walk(f)", "Paths", "Applies `f` bottom-up to every value", "walk(if type == \"string\" then ascii_upcase else . end)"),
+    // Strings
+    doc("split", "split(s)", "Strings", "Splits a string at every `s`", "\"a,b\" | split(\",\")  =>  [\"a\",\"b\"]"),
+    doc("join", "join(s)", "Strings", "Concatenates array elements with `s` between them", "[\"a\",\"b\"] | join(\"-\")  =>  \"a-b\""),
+    doc("ascii_downcase", "ascii_downcase, ascii_upcase", "Strings", "Changes the case of ASCII letters", "\"Ab\" | ascii_downcase  =>  \"ab\""),
+    doc("ltrimstr", "ltrimstr(s), rtrimstr(s)", "Strings", "Removes a prefix or suffix if present", "\"v1.2\" | ltrimstr(\"v\")  =>  \"1.2\""),
+    doc("trim", "trim, ltrim, rtrim", "Strings", "Removes surrounding whitespace", "\" a \" | trim  =>  \"a\""),
+    doc("startswith", "startswith(s), endswith(s)", "Strings", "Whether the string starts or ends with `s`", "\"abc\" | startswith(\"ab\")  =>  true"),
+    doc("explode", "explode, implode", "Strings", "Converts between a string and its code points", "\"A\" | explode  =>  [65]"),
+    doc("test", "test(re), test(re; flags)", "Regex", "Whether the string matches the regular expression", "\"abc\" | test(\"b+\")  =>  true"),
+    doc("match", "match(re), capture(re), scan(re)", "Regex", "Match objects, named captures, or all matches", "\"a1\" | capture(\"(?<n>[0-9])\")  =>  {\"n\":\"1\"}"),
+    doc("sub", "sub(re; s), gsub(re; s)", "Regex", "Replaces the first or every match", "\"aa\" | gsub(\"a\"; \"b\")  =>  \"bb\""),
+    doc("splits", "splits(re)", "Regex", "Splits a string at every match", "\"a1b\" | [splits(\"[0-9]\")]  =>  [\"a\",\"b\"]"),
+    // Math
+    doc("floor", "floor, ceil, round, fabs, sqrt", "Math", "Rounding and basic math functions", "3.7 | floor  =>  3"),
+    doc("pow", "pow(a; b), log, exp, log10", "Math", "Powers and logarithms", "pow(2; 10)  =>  1024.0"),
+    doc("abs", "abs", "Math", "Absolute value", "-5 | abs  =>  5"),
+    // Control
+    doc("if", "if c then a elif d then b else e end", "Control", "Conditional", "if . > 0 then \"pos\" else \"neg\" end"),
+    doc("try", "try f catch g, f?", "Control", "Suppresses or handles errors raised by `f`", "try error(\"x\") catch \"caught\""),
+    doc("reduce", "reduce gen as $x (init; update)", "Control", "Folds the outputs of `gen` into one value", "reduce .[] as $x (0; . + $x)"),
+    doc("foreach", "foreach gen as $x (init; update; extract)", "Control", "Like `reduce`, but outputs every intermediate state", "[foreach .[] as $x (0; . + $x)]"),
+    doc("label", "label $name | ... break $name", "Control", "Stops a generator early", "label $out | .[] | if . > 2 then ., break $out else . end"),
+    doc("until", "until(cond; next), while(cond; next), repeat(f)", "Control", "Loops driven by a condition", "0 | until(. > 3; . + 1)  =>  4"),
+    doc("input", "input, inputs", "Streams", "Reads the next input value(s)", "[inputs]"),
+    doc("debug", "debug, debug(msg), stderr", "Streams", "Prints the input to stderr and passes it through", ".a | debug"),
+    // Dates and formats
+    doc("now", "now", "Dates", "Current Unix time in seconds", "now | floor"),
+    doc("todate", "todate, fromdate, strftime(fmt), strptime(fmt)", "Dates", "Converts between Unix time and date strings", "0 | todate  =>  \"1970-01-01T00:00:00Z\""),
+    doc("@base64", "@base64, @base64d", "Formats", "Base64 encoding and decoding", "\"hi\" | @base64  =>  \"aGk=\""),
+    doc("@csv", "@csv, @tsv", "Formats", "Formats an array as a CSV or TSV row", "[1,\"a\"] | @csv  =>  \"1,\\\"a\\\"\""),
+    doc("@json", "@json, @text, @html, @uri, @sh", "Formats", "Encodes the input for the given context", "\"a b\" | @uri  =>  \"a%20b\""),
+];
+
+/// Documentation for a builtin, looked up by the name as written in a query
+pub fn lookup(name: &str) -> Option<&'static BuiltinDoc> {
+    BUILTINS.iter().find(|doc| doc.name == name).or_else(|| {
+        // Functions documented together with a sibling, e.g. `max` with `min`
+        BUILTINS.iter().find(|doc| {
+            doc.signature
+                .split([',', ' ', '('])
+                .any(|part| part == name)
+        })
+    })
+}
+
+/// Category names in the order they are shown in the manual
+pub fn categories() -> Vec<&'static str> {
+    let mut categories: Vec<&str> = Vec::new();
+    for doc in BUILTINS {
+        if !categories.contains(&doc.category) {
+            categories.push(doc.category);
+        }
+    }
+    categories
+}
+
+/// Builtins whose name, signature or summary contain `search` (ignoring
+/// case), grouped by category
+pub fn search(search: &str) -> Vec<&'static BuiltinDoc> {
+    let search = search.to_lowercase();
+    let matches = |doc: &&BuiltinDoc| {
+        [doc.name, doc.signature, doc.summary]
+            .iter()
+            .any(|text| text.to_lowercase().contains(&search))
+    };
+    categories()
+        .into_iter()
+        .flat_map(|category| {
+            BUILTINS
+                .iter()
+                .filter(move |doc| doc.category == category)
+                .filter(matches)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lookup() {
+        assert_eq!(lookup("group_by").unwrap().signature, "group_by(f)");
+        assert_eq!(lookup("max").unwrap().name, "min");
+        assert_eq!(lookup("gsub").unwrap().name, "sub");
+        assert_eq!(lookup("@csv").unwrap().category, "Formats");
+        assert!(lookup("no_such_builtin").is_none());
+    }
+
+    #[test]
+    fn test_examples_produce_documented_output() {
+        use crate::query::{JaqQueryExecutor, QueryExecutor};
+
+        for doc in BUILTINS {
+            let Some((query, expected)) = doc.example.split_once("  =>  ") else {
+                continue;
+            };
+            let expected: serde_json::Value = serde_json::from_str(expected).unwrap();
+            let output = JaqQueryExecutor.execute(&serde_json::Value::Null, query);
+            assert_eq!(output.ok(), Some(vec![expected]), "example of {}", doc.name);
+        }
+    }
+
+    #[test]
+    fn test_search() {
+        let all = search("");
+        assert_eq!(all.len(), BUILTINS.len());
+        // Grouped: each category appears in one contiguous run
+        let mut seen = Vec::new();
+        for doc in &all {
+            if seen.last() != Some(&doc.category) {
+                assert!(!seen.contains(&doc.category));
+                seen.push(doc.category);
+            }
+        }
+
+        let names: Vec<_> = search("ENTRIES").iter().map(|doc| doc.name).collect();
+        assert_eq!(names, vec!["to_entries", "from_entries", "with_entries"]);
+    }
+
+    #[test]
+    fn test_names_are_unique() {
+        let mut names: Vec<_> = BUILTINS.iter().map(|doc| doc.name).collect();
+        names.sort_unstable();
+        names.dedup();
+        assert_eq!(names.len(), BUILTINS.len());
+    }
+}
@@ -1,23 +1,195 @@
+use super::CacheStats;
 use crate::app::error::AppError;
 use jaq_core::{
-    Ctx, RcIter,
-    load::{Arena, File, Loader},
+    Ctx, Filter, Native, RcIter,
+    compile::Undefined,
+    load::{self, Arena, File, Loader, lex, parse},
 };
 use jaq_json::Val;
 use serde_json::Value;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, MutexGuard};
 
 pub trait QueryExecutor {
     fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError>;
+
+    /// Runs `query` once per input, like jq on a stream of documents
+    ///
+    /// Executors backed by jaq let `input` and `inputs` read the documents that
+    /// follow the current one; this default runs each input on its own.
+    fn execute_inputs(&self, inputs: &[Value], query: &str) -> Result<Vec<Value>, AppError> {
+        let mut outputs = Vec::new();
+        for input in inputs {
+            outputs.extend(self.execute(input, query)?);
+        }
+        Ok(outputs)
+    }
+
+    /// Like [`execute_inputs`](Self::execute_inputs), with each of `variables`
+    /// bound to `$name` in the query
+    ///
+    /// Executors that cannot bind variables reject any.
+    fn execute_with_vars(
+        &self,
+        inputs: &[Value],
+        query: &str,
+        variables: &[(String, Value)],
+    ) -> Result<Vec<Value>, AppError> {
+        if let Some((name, _)) = variables.first() {
+            return Err(AppError::QueryExecution(format!(
+                "cannot bind ${}: the executor does not support variables",
+                name
+            )));
+        }
+        self.execute_inputs(inputs, query)
+    }
+
+    /// Like [`execute`](Self::execute) on data whose content is identified by
+    /// `fingerprint`, e.g. [`JsonData::fingerprint`](crate::query::JsonData::fingerprint)
+    ///
+    /// Executors that cache results key them by the fingerprint rather than
+    /// by the data, so a lookup costs no more than the query's length.
+    fn execute_fingerprinted(
+        &self,
+        data: &Value,
+        fingerprint: u64,
+        query: &str,
+    ) -> Result<Vec<Value>, AppError> {
+        let _ = fingerprint;
+        self.execute(data, query)
+    }
+
+    /// Called when the data changed; executors that keep derived state drop it
+    fn invalidate(&self) {}
+
+    /// Statistics of the results cache, for executors that keep one
+    fn cache_stats(&self) -> Option<CacheStats> {
+        None
+    }
 }
 
+/// Longest program accepted; together with [`EXECUTION_STACK_SIZE`] this keeps
+/// jaq's recursive parser and interpreter from overflowing the stack
+pub const MAX_QUERY_LEN: usize = 16 * 1024;
+
+/// Stack reserved for the thread compiling and running a query
+const EXECUTION_STACK_SIZE: usize = 256 * 1024 * 1024;
+
+/// Compiled programs kept by a [`JaqQueryExecutor`]; typing a query runs it
+/// again on every redraw and most edits return to one typed before
+const COMPILED_QUERIES: usize = 32;
+
+/// Query executor using jaq
+///
+/// Compiled filters are kept by query in a cache shared by every executor
+/// and thread, so running the same query again, as scrolling and redrawing
+/// do, skips parsing and compiling.
+#[derive(Debug, Clone, Copy, Default)]
 pub struct JaqQueryExecutor;
 
+static COMPILED: Mutex<CompiledQueries> = Mutex::new(CompiledQueries {
+    entries: Vec::new(),
+});
+
+impl JaqQueryExecutor {
+    /// `query` compiled with `$name` bound for each of `variables`, from the
+    /// cache when it was compiled before
+    pub fn compile(
+        &self,
+        query: &str,
+        variables: &[String],
+    ) -> Result<Arc<CompiledQuery>, AppError> {
+        if let Some(compiled) = compiled_queries().get(query, variables) {
+            return Ok(compiled);
+        }
+        let compiled = Arc::new(CompiledQuery::new(query, variables)?);
+        compiled_queries().insert(Arc::clone(&compiled));
+        Ok(compiled)
+    }
+}
+
+fn compiled_queries() -> MutexGuard<'static, CompiledQueries> {
+    // The cache stays consistent even if a thread panicked holding it
+    COMPILED
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
 impl QueryExecutor for JaqQueryExecutor {
     fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
+        self.execute_inputs(std::slice::from_ref(data), query)
+    }
+
+    fn execute_inputs(&self, inputs: &[Value], query: &str) -> Result<Vec<Value>, AppError> {
+        self.execute_with_vars(inputs, query, &[])
+    }
+
+    fn execute_with_vars(
+        &self,
+        inputs: &[Value],
+        query: &str,
+        variables: &[(String, Value)],
+    ) -> Result<Vec<Value>, AppError> {
         if query.is_empty() {
-            return Err(AppError::QueryCompile("Empty query".to_string()));
+            return Err(AppError::query_compile("Empty query"));
+        }
+        if query.len() > MAX_QUERY_LEN {
+            return Err(AppError::query_compile(format!(
+                "Query is too long ({} bytes, limit {})",
+                query.len(),
+                MAX_QUERY_LEN
+            )));
         }
 
+        let token = super::CancelToken::current();
+        let run = || {
+            let names: Vec<String> = variables.iter().map(|(name, _)| name.clone()).collect();
+            let compiled = self.compile(query, &names)?;
+            let values: Vec<Value> = variables.iter().map(|(_, value)| value.clone()).collect();
+            compiled.run(inputs, &values)
+        };
+        // 深いネストでもスタックが溢れないよう専用スレッドで実行する
+        std::thread::scope(|scope| {
+            let worker = std::thread::Builder::new()
+                .stack_size(EXECUTION_STACK_SIZE)
+                .spawn_scoped(scope, || match &token {
+                    Some(token) => token.install(run),
+                    None => run(),
+                });
+            match worker {
+                Ok(handle) => handle.join().unwrap_or_else(|_| {
+                    Err(AppError::QueryExecution(
+                        "query evaluation panicked".to_string(),
+                    ))
+                }),
+                Err(_) => run(),
+            }
+        })
+    }
+}
+
+/// Compiled query
+///
+/// Holds the jaq filter of one program with the names of the variables it
+/// was compiled for; [`run`](Self::run) takes their values in that order.
+pub struct CompiledQuery {
+    query: String,
+    variables: Vec<String>,
+    filter: Filter<Native<Val>>,
+}
+
+impl std::fmt::Debug for CompiledQuery {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CompiledQuery")
+            .field("query", &self.query)
+            .field("variables", &self.variables)
+            .finish_non_exhaustive()
+    }
+}
+
+impl CompiledQuery {
+    /// Parses and compiles `query` with `$name` bound for each of `variables`
+    pub fn new(query: &str, variables: &[String]) -> Result<Self, AppError> {
         let program = File {
             code: query,
             path: (),
@@ -26,21 +198,204 @@ impl QueryExecutor for JaqQueryExecutor {
         let arena = Arena::default();
         let modules = loader
             .load(&arena, program)
-            .map_err(|e| AppError::QueryCompile(format!("Loader: {:?}", e)))?;
+            .map_err(|e| load_error(query, &e))?;
+        // `$ENV` comes first so a variable of the same name given by the user wins
+        let names: Vec<String> = std::iter::once("$ENV".to_string())
+            .chain(variables.iter().map(|name| format!("${}", name)))
+            .collect();
+        let replaced = super::cancel::natives();
+        let funs = jaq_std::funs()
+            .chain(jaq_json::funs())
+            .filter(|(name, args, _)| {
+                !replaced
+                    .iter()
+                    .any(|(other, other_args, _)| name == other && args.len() == other_args.len())
+            })
+            .chain(replaced.clone());
         let filter = jaq_core::Compiler::default()
-            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .with_funs(funs)
+            .with_global_vars(names.iter().map(String::as_str))
             .compile(modules)
-            .map_err(|e| AppError::QueryCompile(format!("Compiler: {:?}", e)))?;
-
-        let inputs = RcIter::new(core::iter::empty());
-        let results = filter.run((Ctx::new([], &inputs), Val::from(data.clone())));
-        let values: Vec<Value> = results
-            .into_iter()
-            .filter_map(|r| r.ok())
-            .map(|val| val.into())
-            .collect();
+            .map_err(|e| compile_error(query, &e))?;
+        Ok(Self {
+            query: query.to_string(),
+            variables: variables.to_vec(),
+            filter,
+        })
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
 
-        Ok(values)
+    /// Runs the filter on each input in turn, sharing the remaining inputs
+    /// with `input`/`inputs`; `values` are those of the variables, in order
+    ///
+    /// The first runtime error stops the run and is returned with the
+    /// outputs produced before it, see [`AppError::QueryRuntime`]. A
+    /// cancelled [`CancelToken`](super::CancelToken) stops it at the next
+    /// output, or sooner in `range` and `limit`.
+    pub fn run(&self, inputs: &[Value], values: &[Value]) -> Result<Vec<Value>, AppError> {
+        let inputs = RcIter::new(
+            inputs
+                .iter()
+                .map(|value| Ok::<_, String>(Val::from(value.clone()))),
+        );
+        let env = Val::from(environment());
+        let mut outputs = Vec::new();
+        while let Some(Ok(input)) = (&inputs).next() {
+            if super::cancel::cancelled() {
+                return Err(cancelled());
+            }
+            let vars = std::iter::once(env.clone()).chain(values.iter().cloned().map(Val::from));
+            for result in self.filter.run((Ctx::new(vars, &inputs), input)) {
+                match result {
+                    Ok(_) if super::cancel::cancelled() => return Err(cancelled()),
+                    Ok(output) => outputs.push(Value::from(output)),
+                    Err(_) if super::cancel::cancelled() => return Err(cancelled()),
+                    Err(error) => {
+                        return Err(AppError::QueryRuntime {
+                            message: runtime_message(error),
+                            partial: outputs,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(outputs)
+    }
+}
+
+fn cancelled() -> AppError {
+    AppError::QueryExecution("cancelled".to_string())
+}
+
+/// The text of a runtime error; `error("boom")` reads `boom`, not `"boom"`
+fn runtime_message(error: jaq_core::Error<Val>) -> String {
+    match Value::from(error.into_val()) {
+        Value::String(message) => message,
+        value => value.to_string(),
+    }
+}
+
+/// Recently compiled queries
+#[derive(Debug, Default)]
+struct CompiledQueries {
+    /// Least recently used first
+    entries: Vec<Arc<CompiledQuery>>,
+}
+
+impl CompiledQueries {
+    fn get(&mut self, query: &str, variables: &[String]) -> Option<Arc<CompiledQuery>> {
+        let position = self
+            .entries
+            .iter()
+            .position(|entry| entry.query == query && entry.variables == variables)?;
+        let entry = self.entries.remove(position);
+        self.entries.push(Arc::clone(&entry));
+        Some(entry)
+    }
+
+    fn insert(&mut self, compiled: Arc<CompiledQuery>) {
+        self.entries
+            .retain(|entry| entry.query != compiled.query || entry.variables != compiled.variables);
+        if self.entries.len() == COMPILED_QUERIES {
+            self.entries.remove(0);
+        }
+        self.entries.push(compiled);
+    }
+}
+
+/// The environment variables as an object, bound to `$ENV` like in jq
+fn environment() -> Value {
+    Value::Object(
+        std::env::vars_os()
+            .map(|(name, value)| {
+                let value = value.to_string_lossy().into_owned();
+                (name.to_string_lossy().into_owned(), Value::String(value))
+            })
+            .collect(),
+    )
+}
+
+/// Converts a slice borrowed from `query` into its byte range within `query`
+fn offset_in(query: &str, part: &str) -> Option<Range<usize>> {
+    let start = (part.as_ptr() as usize).checked_sub(query.as_ptr() as usize)?;
+    let end = start + part.len();
+    (end <= query.len()).then_some(start..end)
+}
+
+/// A loader error as a readable message, with the span of its first problem
+fn load_error(query: &str, errors: &load::Errors<&str, ()>) -> AppError {
+    let problems = errors.iter().flat_map(|(_, error)| match error {
+        load::Error::Io(errs) => errs
+            .iter()
+            .map(|(path, e)| (format!("cannot load `{}`: {}", path, e), *path))
+            .collect(),
+        load::Error::Lex(errs) => errs
+            .iter()
+            .map(|(expect, rest)| lex_problem(expect, rest))
+            .collect(),
+        load::Error::Parse(errs) => errs
+            .iter()
+            .map(|(expect, found)| (parse_problem(expect, found), *found))
+            .collect::<Vec<_>>(),
+    });
+    query_error(query, problems)
+}
+
+/// A compiler error as a readable message, with the span of its first problem
+fn compile_error(query: &str, errors: &jaq_core::compile::Errors<&str, ()>) -> AppError {
+    let problems = errors.iter().flat_map(|(_, errs)| {
+        errs.iter().map(|(name, undefined)| {
+            let message = match undefined {
+                Undefined::Filter(arity) => format!("undefined filter `{}/{}`", name, arity),
+                Undefined::Var => format!("undefined variable `{}`", name),
+                other => format!("undefined {} `{}`", other.as_str(), name),
+            };
+            (message, *name)
+        })
+    });
+    query_error(query, problems)
+}
+
+/// What the lexer stopped at: the opening delimiter of an unclosed one, the
+/// first character of an unknown token, or the text from where it stopped
+fn lex_problem<'a>(expect: &lex::Expect<&'a str>, rest: &'a str) -> (String, &'a str) {
+    match expect {
+        lex::Expect::Delim(open) if *open == "\"" => ("unterminated string".to_string(), open),
+        lex::Expect::Delim(open) => (format!("unclosed `{}`", open), open),
+        lex::Expect::Token => {
+            let len = rest.chars().next().map_or(0, char::len_utf8);
+            (format!("unexpected `{}`", &rest[..len]), &rest[..len])
+        }
+        expect => (format!("expected {}", expect.as_str()), rest),
+    }
+}
+
+fn parse_problem(expect: &parse::Expect<&str>, found: &str) -> String {
+    let found = if found.is_empty() {
+        "the end of the query".to_string()
+    } else {
+        format!("`{}`", found)
+    };
+    format!("expected {}, found {}", expect.as_str(), found)
+}
+
+/// Joins the messages of `problems` and points at the part of `query` the
+/// first is about
+fn query_error<'a>(query: &str, problems: impl IntoIterator<Item = (String, &'a str)>) -> AppError {
+    let mut span = None;
+    let mut messages = Vec::new();
+    for (message, part) in problems {
+        if messages.is_empty() {
+            span = offset_in(query, part);
+        }
+        messages.push(message);
+    }
+    AppError::QueryCompile {
+        message: messages.join("; "),
+        span,
     }
 }
 
@@ -59,6 +414,41 @@ mod tests {
         assert_eq!(result[0], json!("test"));
     }
 
+    #[test]
+    fn test_compiled_queries_are_reused() {
+        let first = JaqQueryExecutor
+            .compile(".compiled_once | length", &[])
+            .unwrap();
+        let again = JaqQueryExecutor
+            .compile(".compiled_once | length", &[])
+            .unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+        // Variables are part of what was compiled
+        let with_var = JaqQueryExecutor
+            .compile(".compiled_once | length", &["n".to_string()])
+            .unwrap();
+        assert!(!Arc::ptr_eq(&first, &with_var));
+        assert_eq!(
+            again.run(&[json!({"compiled_once": [1, 2]})], &[]).unwrap(),
+            vec![json!(2)]
+        );
+    }
+
+    #[test]
+    fn test_least_recently_used_query_is_dropped() {
+        let mut cache = CompiledQueries::default();
+        for i in 0..COMPILED_QUERIES {
+            cache.insert(Arc::new(
+                CompiledQuery::new(&format!(".[{}]", i), &[]).unwrap(),
+            ));
+        }
+        assert!(cache.get(".[0]", &[]).is_some());
+        cache.insert(Arc::new(CompiledQuery::new(".extra", &[]).unwrap()));
+        assert_eq!(cache.entries.len(), COMPILED_QUERIES);
+        assert!(cache.get(".[0]", &[]).is_some());
+        assert!(cache.get(".[1]", &[]).is_none());
+    }
+
     #[test]
     fn test_invalid_query() {
         let executor = JaqQueryExecutor;
@@ -67,4 +457,73 @@ mod tests {
         let result = executor.execute(&data, "invalid query syntax");
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_runtime_error_is_returned() {
+        let error = JaqQueryExecutor
+            .execute(&json!([1, 2]), ".[], error(\"boom\")")
+            .unwrap_err();
+        match error {
+            AppError::QueryRuntime { message, partial } => {
+                assert_eq!(message, "boom");
+                assert_eq!(partial, vec![json!(1), json!(2)]);
+            }
+            other => panic!("expected a runtime error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_variables_are_bound() {
+        let variables = [
+            ("name".to_string(), json!("ann")),
+            ("ids".to_string(), json!([1, 2])),
+        ];
+        assert_eq!(
+            JaqQueryExecutor
+                .execute_with_vars(&[json!(null)], "[$name, $ids[1]]", &variables)
+                .unwrap(),
+            vec![json!(["ann", 2])]
+        );
+        assert!(JaqQueryExecutor.execute(&json!(null), "$name").is_err());
+    }
+
+    #[test]
+    fn test_environment_is_available() {
+        let path = std::env::var("PATH").unwrap_or_default();
+        assert_eq!(
+            JaqQueryExecutor
+                .execute(&json!(null), "[$ENV.PATH, env.PATH]")
+                .unwrap(),
+            vec![json!([path, path])]
+        );
+        let shadowed = [("ENV".to_string(), json!(1))];
+        assert_eq!(
+            JaqQueryExecutor
+                .execute_with_vars(&[json!(null)], "$ENV", &shadowed)
+                .unwrap(),
+            vec![json!(1)]
+        );
+    }
+
+    #[test]
+    fn test_execute_inputs_shares_remaining_inputs() {
+        let inputs = [json!(1), json!(2), json!(3)];
+        assert_eq!(
+            JaqQueryExecutor.execute_inputs(&inputs, ". * 10").unwrap(),
+            vec![json!(10), json!(20), json!(30)]
+        );
+        // Like `jq -n '[inputs]'` after the first document was read as `.`
+        assert_eq!(
+            JaqQueryExecutor
+                .execute_inputs(&inputs, "[., inputs]")
+                .unwrap(),
+            vec![json!([1, 2, 3])]
+        );
+        assert!(
+            JaqQueryExecutor
+                .execute_inputs(&[], ".")
+                .unwrap()
+                .is_empty()
+        );
+    }
 }
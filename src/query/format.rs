@@ -0,0 +1,148 @@
+use super::lexer::{QueryToken, QueryTokenKind, next_token, tokenize_query};
+
+/// Formats a query with normalized whitespace
+///
+/// Pipes, binary operators and keywords are surrounded by single spaces,
+/// commas and semicolons are followed by one, and brackets, postfix access
+/// and function calls are kept tight. String literals are left untouched.
+pub fn format_query(query: &str) -> String {
+    let tokens = tokenize_query(query);
+    let mut out = String::new();
+    // Innermost open bracket, to tell slices (`[1:2]`) from objects (`{a: 1}`)
+    let mut brackets: Vec<char> = Vec::new();
+    let mut prev: Option<(QueryToken, bool)> = None;
+
+    for token in tokens {
+        let text = token.text(query);
+        if let Some((prev_token, prev_unary)) = prev {
+            if prev_token.kind == QueryTokenKind::Comment {
+                out.push('\n');
+            } else if needs_space(query, prev_token, prev_unary, token, brackets.last())
+                || fuses(prev_token.text(query), text)
+            {
+                out.push(' ');
+            }
+        }
+        out.push_str(text);
+
+        match token.kind {
+            QueryTokenKind::Open => brackets.push(text.chars().next().unwrap_or('(')),
+            QueryTokenKind::Close => {
+                brackets.pop();
+            }
+            _ => {}
+        }
+        let unary = token.kind == QueryTokenKind::Operator
+            && text == "-"
+            && prev.is_none_or(|(p, _)| starts_operand(query, p));
+        prev = Some((token, unary));
+    }
+    out
+}
+
+/// Whether writing `prev` and `next` without a space would lex as different
+/// tokens, as with `.` followed by `.if`
+fn fuses(prev: &str, next: &str) -> bool {
+    let joined = format!("{prev}{next}");
+    next_token(&joined, 0).is_none_or(|token| token.end != prev.len())
+}
+
+/// Whether an operand is expected after `token` (so a following `-` is unary)
+fn starts_operand(query: &str, token: QueryToken) -> bool {
+    use QueryTokenKind::*;
+    match token.kind {
+        Operator | Pipe | Comma | Semicolon | Colon | Open => true,
+        Keyword => token.text(query) != "end",
+        _ => false,
+    }
+}
+
+fn is_term(kind: QueryTokenKind) -> bool {
+    use QueryTokenKind::*;
+    matches!(
+        kind,
+        Field | Dot | Variable | Ident | String | Number | Close | Question | Format
+    )
+}
+
+fn needs_space(
+    query: &str,
+    prev: QueryToken,
+    prev_unary: bool,
+    next: QueryToken,
+    bracket: Option<&char>,
+) -> bool {
+    use QueryTokenKind::*;
+    let in_slice = bracket == Some(&'[');
+    match (prev.kind, next.kind) {
+        (_, Comma | Semicolon | Close | Question) => false,
+        (Open, _) => false,
+        (Comma | Semicolon, _) => true,
+        (Pipe, _) | (_, Pipe) => true,
+        (Colon, _) => !in_slice,
+        (_, Colon) => false,
+        (Operator, _) if prev_unary => false,
+        (Operator, _) | (_, Operator) => true,
+        (Keyword, _) | (_, Keyword) => true,
+        // A variable cannot be called, so `as $x (...)` keeps its space
+        (Variable, Open) => next.text(query) == "(",
+        (_, Open) => !is_term(prev.kind),
+        (Field | Dot | Variable | Close | Question | String, Field) => false,
+        (Dot, String) => false,
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_spacing() {
+        assert_eq!(
+            format_query(".users[]|select(.age>25)|{name,age}"),
+            ".users[] | select(.age > 25) | {name, age}"
+        );
+        assert_eq!(
+            format_query("  .a  ,  .b[1:3] ,.c[-1]"),
+            ".a, .b[1:3], .c[-1]"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_tokens_apart() {
+        assert_eq!(format_query(". .if"), ". .if");
+        assert_eq!(format_query(". .a"), ". .a");
+    }
+
+    #[test]
+    fn test_format_keywords() {
+        assert_eq!(
+            format_query("reduce .[] as $x (0;.+$x)"),
+            "reduce .[] as $x (0; . + $x)"
+        );
+        assert_eq!(
+            format_query("if .a==1 then \"x\" else -.b end"),
+            "if .a == 1 then \"x\" else -.b end"
+        );
+        assert_eq!(
+            format_query("def f(x):x*2;{(.k):f(.v)}"),
+            "def f(x): x * 2; {(.k): f(.v)}"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_postfix_tight() {
+        assert_eq!(
+            format_query(".a.b?.c | $x.y | .\"k\""),
+            ".a.b?.c | $x.y | .\"k\""
+        );
+        assert_eq!(format_query("\"a  b\"|@base64"), "\"a  b\" | @base64");
+    }
+
+    #[test]
+    fn test_format_is_idempotent() {
+        let once = format_query("[.[]|{id,tags:(.tags//[])}]|map(select(.id!=null))");
+        assert_eq!(format_query(&once), once);
+    }
+}
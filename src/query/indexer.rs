@@ -0,0 +1,148 @@
+//! Rebuilding indexes in the background
+//!
+//! Reloads and followed records change the document while the TUI runs.
+//! The key index is rebuilt on a worker thread and handed back whole, so
+//! the event loop swaps it in at once instead of walking the document itself.
+
+use super::KeyIndex;
+use serde_json::Value;
+use std::sync::Arc;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+
+/// Request to update the indexes
+#[derive(Debug)]
+enum Job {
+    /// Index the whole document from scratch
+    Rebuild { generation: u64, data: Arc<Value> },
+    /// Add the keys of values appended to the previous document
+    Extend { generation: u64, values: Vec<Value> },
+}
+
+/// Worker thread rebuilding the indexes
+#[derive(Debug)]
+pub struct Indexer {
+    jobs: Sender<Job>,
+    /// Finished indexes with the number of jobs done so far
+    built: Receiver<(usize, KeyIndex)>,
+    submitted: usize,
+    completed: usize,
+}
+
+impl Indexer {
+    /// Starts a worker that indexes on top of `initial`
+    pub fn spawn(initial: KeyIndex) -> Self {
+        let (jobs, queue) = mpsc::channel();
+        let (sender, built) = mpsc::channel();
+        thread::spawn(move || work(initial, queue, sender));
+        Self {
+            jobs,
+            built,
+            submitted: 0,
+            completed: 0,
+        }
+    }
+
+    /// Re-indexes `data`, which is at `generation`
+    pub fn rebuild(&mut self, data: Arc<Value>, generation: u64) {
+        self.submit(Job::Rebuild { generation, data });
+    }
+
+    /// Adds `values`, appended to the document to make `generation`
+    pub fn extend(&mut self, values: Vec<Value>, generation: u64) {
+        self.submit(Job::Extend { generation, values });
+    }
+
+    fn submit(&mut self, job: Job) {
+        if self.jobs.send(job).is_ok() {
+            self.submitted += 1;
+        }
+    }
+
+    /// The newest index finished since the last call, without waiting
+    pub fn receive(&mut self) -> Option<KeyIndex> {
+        let mut newest = None;
+        while let Ok((completed, index)) = self.built.try_recv() {
+            self.completed = completed;
+            newest = Some(index);
+        }
+        newest
+    }
+
+    /// Whether a submitted change has not been indexed yet
+    pub fn is_pending(&self) -> bool {
+        self.completed != self.submitted
+    }
+}
+
+/// Runs jobs until the [`Indexer`] is dropped, skipping work a later
+/// rebuild makes pointless
+fn work(mut index: KeyIndex, queue: Receiver<Job>, built: Sender<(usize, KeyIndex)>) {
+    let mut completed = 0;
+    while let Ok(job) = queue.recv() {
+        let mut jobs = vec![job];
+        jobs.extend(queue.try_iter());
+        completed += jobs.len();
+        let start = jobs
+            .iter()
+            .rposition(|job| matches!(job, Job::Rebuild { .. }))
+            .unwrap_or(0);
+        for job in jobs.drain(..).skip(start) {
+            let generation = match job {
+                Job::Rebuild { generation, data } => {
+                    index = KeyIndex::build(&data);
+                    generation
+                }
+                Job::Extend { generation, values } => {
+                    index.extend(&values);
+                    generation
+                }
+            };
+            index.set_generation(generation);
+        }
+        if built.send((completed, index.clone())).is_err() {
+            break;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::time::{Duration, Instant};
+
+    fn wait(indexer: &mut Indexer) -> KeyIndex {
+        let started = Instant::now();
+        let mut last = None;
+        while indexer.is_pending() || last.is_none() {
+            assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+            if let Some(index) = indexer.receive() {
+                last = Some(index);
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        last.unwrap()
+    }
+
+    #[test]
+    fn test_extend_then_rebuild() {
+        let mut indexer = Indexer::spawn(KeyIndex::build(&json!({"a": 1})));
+        assert!(!indexer.is_pending());
+
+        indexer.extend(vec![json!({"b": {"c": 2}})], 1);
+        assert!(indexer.is_pending());
+        let index = wait(&mut indexer);
+        assert_eq!(index.generation(), 1);
+        assert!(index.contains("a") && index.contains("c"));
+
+        // A rebuild forgets keys of the old document
+        indexer.extend(vec![json!({"d": 3})], 2);
+        indexer.rebuild(Arc::new(json!({"x": 1})), 3);
+        indexer.extend(vec![json!({"y": 2})], 4);
+        let index = wait(&mut indexer);
+        assert_eq!(index.generation(), 4);
+        assert!(index.contains("x") && index.contains("y"));
+        assert!(!index.contains("a") && !index.contains("d"));
+    }
+}
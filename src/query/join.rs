@@ -0,0 +1,111 @@
+//! Joins of two arrays
+//!
+//! Joining two arrays on a key takes an index of one built with `reduce`
+//! and a lookup per element of the other, which is hard to get right by
+//! hand. [`JoinSpec`] writes that program from the two arrays and keys.
+
+use crate::render::tree::field;
+
+/// Two arrays to join and their keys
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct JoinSpec {
+    /// jq path of the array whose elements are kept, e.g. `.orders`
+    pub left: String,
+    /// Key of the left elements, e.g. `user_id`
+    pub left_key: String,
+    /// jq path of the array looked up, e.g. `.users`
+    pub right: String,
+    /// Key of the right elements, e.g. `id`
+    pub right_key: String,
+}
+
+impl JoinSpec {
+    /// Whether every part has been given
+    pub fn is_complete(&self) -> bool {
+        [&self.left, &self.left_key, &self.right, &self.right_key]
+            .iter()
+            .all(|part| !part.trim().is_empty())
+    }
+
+    /// A left join: every left element merged with the right element whose
+    /// key equals its own, compared as text so `1` matches `"1"`
+    pub fn to_jq(&self) -> String {
+        format!(
+            "(reduce {}[] as $row ({{}}; .[$row{} | tostring] = $row)) as $index \
+             | [{}[] | . + ($index[{} | tostring] // {{}})]",
+            array_path(&self.right),
+            key_path(&self.right_key),
+            array_path(&self.left),
+            key_path(&self.left_key),
+        )
+    }
+}
+
+/// `path` without a trailing `.`, so `[]` can follow it; `.` for the root
+fn array_path(path: &str) -> &str {
+    match path.trim() {
+        "" | "." => ".",
+        path => path,
+    }
+}
+
+/// A key as a jq path; a key written as a path, e.g. `.user.id`, is kept
+fn key_path(key: &str) -> String {
+    let key = key.trim();
+    if key.starts_with('.') {
+        key.to_string()
+    } else {
+        field(key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{JaqQueryExecutor, QueryExecutor};
+    use serde_json::json;
+
+    #[test]
+    fn test_generated_join_runs() {
+        let spec = JoinSpec {
+            left: ".orders".to_string(),
+            left_key: "user_id".to_string(),
+            right: ".users".to_string(),
+            right_key: "id".to_string(),
+        };
+        assert!(spec.is_complete());
+        assert_eq!(
+            spec.to_jq(),
+            "(reduce .users[] as $row ({}; .[$row.id | tostring] = $row)) as $index \
+             | [.orders[] | . + ($index[.user_id | tostring] // {})]"
+        );
+        let data = json!({
+            "orders": [{"user_id": 1, "total": 5}, {"user_id": "2", "total": 7}, {"user_id": 3}],
+            "users": [{"id": 1, "name": "ann"}, {"id": 2, "name": "bob"}]
+        });
+        assert_eq!(
+            JaqQueryExecutor.execute(&data, &spec.to_jq()).unwrap(),
+            vec![json!([
+                {"user_id": 1, "total": 5, "id": 1, "name": "ann"},
+                {"user_id": "2", "total": 7, "id": 2, "name": "bob"},
+                {"user_id": 3}
+            ])]
+        );
+    }
+
+    #[test]
+    fn test_root_array_and_quoted_keys() {
+        let spec = JoinSpec {
+            left: ".".to_string(),
+            left_key: "user id".to_string(),
+            right: ".[]".to_string(),
+            right_key: ".id".to_string(),
+        };
+        assert_eq!(
+            spec.to_jq(),
+            "(reduce .[][] as $row ({}; .[$row.id | tostring] = $row)) as $index \
+             | [.[] | . + ($index[.\"user id\" | tostring] // {})]"
+        );
+        assert!(!JoinSpec::default().is_complete());
+    }
+}
@@ -0,0 +1,328 @@
+//! Documents parsed only where needed
+//!
+//! Building a `serde_json::Value` for a document of several hundred
+//! megabytes takes seconds. A [`LazyDocument`] only records where each
+//! top-level member or element starts and ends, and parses one when a query
+//! reaches into it; the whole document is parsed the first time something
+//! needs all of it.
+
+use super::lexer::{QueryTokenKind, tokenize_query};
+use crate::app::AppError;
+use crate::render::chart::format_bytes;
+use serde_json::Value;
+use serde_json::value::RawValue;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Members shorter than this are shown as they are in the outline
+const OUTLINE_INLINE_BYTES: usize = 256;
+
+/// Top-level structure
+#[derive(Debug)]
+enum TopLevel {
+    /// Keys in sorted order, like `serde_json::Map`, with the byte range of each value
+    Object(Vec<(String, Range<usize>)>),
+    Array(Vec<Range<usize>>),
+    /// A number, string, boolean or null, parsed as a whole when needed
+    Scalar,
+}
+
+/// First step below the top level
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RootStep {
+    /// `.key` or `.["key"]`
+    Key(String),
+    /// `.[3]`
+    Index(usize),
+}
+
+/// JSON document parsed lazily
+pub struct LazyDocument {
+    text: Box<dyn AsRef<str> + Send + Sync>,
+    top: TopLevel,
+    /// Members parsed so far, by position in `top`
+    parsed: Mutex<HashMap<usize, Arc<Value>>>,
+    whole: OnceLock<Arc<Value>>,
+}
+
+impl fmt::Debug for LazyDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LazyDocument")
+            .field("bytes", &self.text().len())
+            .field("top", &self.top)
+            .field("parsed", &self.is_parsed())
+            .finish()
+    }
+}
+
+impl LazyDocument {
+    /// Checks that `text` is JSON and indexes its top level, without
+    /// building any values
+    pub fn index(text: impl AsRef<str> + Send + Sync + 'static) -> Result<Self, AppError> {
+        let base = text.as_ref().as_ptr() as usize;
+        let range = |raw: &RawValue| {
+            let start = raw.get().as_ptr() as usize - base;
+            start..start + raw.get().len()
+        };
+        let top = match text.as_ref().trim_start().as_bytes().first() {
+            Some(b'{') => {
+                let members: BTreeMap<String, &RawValue> = serde_json::from_str(text.as_ref())?;
+                TopLevel::Object(
+                    members
+                        .into_iter()
+                        .map(|(key, raw)| (key, range(raw)))
+                        .collect(),
+                )
+            }
+            Some(b'[') => {
+                let elements: Vec<&RawValue> = serde_json::from_str(text.as_ref())?;
+                TopLevel::Array(elements.into_iter().map(range).collect())
+            }
+            _ => {
+                serde_json::from_str::<&RawValue>(text.as_ref())?;
+                TopLevel::Scalar
+            }
+        };
+        Ok(Self {
+            text: Box::new(text),
+            top,
+            parsed: Mutex::new(HashMap::new()),
+            whole: OnceLock::new(),
+        })
+    }
+
+    fn text(&self) -> &str {
+        (*self.text).as_ref()
+    }
+
+    /// Whether the whole document has been parsed
+    pub fn is_parsed(&self) -> bool {
+        self.whole.get().is_some()
+    }
+
+    /// The whole document, parsed on first use
+    pub fn value(&self) -> &Arc<Value> {
+        self.whole.get_or_init(|| {
+            Arc::new(serde_json::from_str(self.text()).expect("validated when indexed"))
+        })
+    }
+
+    /// Keys of a top-level object
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        let members = match &self.top {
+            TopLevel::Object(members) => members.as_slice(),
+            TopLevel::Array(_) | TopLevel::Scalar => &[],
+        };
+        members.iter().map(|(key, _)| key.as_str())
+    }
+
+    /// What `step` selects from the document, parsing only that part; `None`
+    /// when the step does not apply to it, like a key of an array
+    pub fn select(&self, step: &RootStep) -> Option<Arc<Value>> {
+        let position = match (&self.top, step) {
+            (TopLevel::Object(members), RootStep::Key(key)) => {
+                match members.binary_search_by(|(k, _)| k.as_str().cmp(key)) {
+                    Ok(position) => position,
+                    Err(_) => return Some(Arc::new(Value::Null)),
+                }
+            }
+            (TopLevel::Array(elements), RootStep::Index(index)) if *index < elements.len() => {
+                *index
+            }
+            (TopLevel::Array(_), RootStep::Index(_)) => return Some(Arc::new(Value::Null)),
+            _ => return None,
+        };
+        if let Some(whole) = self.whole.get() {
+            return Some(Arc::new(match step {
+                RootStep::Key(key) => whole[key.as_str()].clone(),
+                RootStep::Index(index) => whole[*index].clone(),
+            }));
+        }
+        let mut parsed = self.parsed.lock().unwrap_or_else(|e| e.into_inner());
+        let value = parsed.entry(position).or_insert_with(|| {
+            let text = &self.text()[self.range(position)];
+            Arc::new(serde_json::from_str(text).expect("validated when indexed"))
+        });
+        Some(Arc::clone(value))
+    }
+
+    fn range(&self, position: usize) -> Range<usize> {
+        match &self.top {
+            TopLevel::Object(members) => members[position].1.clone(),
+            TopLevel::Array(elements) => elements[position].clone(),
+            TopLevel::Scalar => 0..self.text().len(),
+        }
+    }
+
+    /// The document with large members replaced by a note of their size,
+    /// shown until a query needs the whole document
+    pub fn outline(&self) -> Value {
+        let summarize = |range: Range<usize>| {
+            let text = &self.text()[range];
+            if text.len() < OUTLINE_INLINE_BYTES {
+                return serde_json::from_str(text).expect("validated when indexed");
+            }
+            let kind = match text.as_bytes()[0] {
+                b'{' => "object",
+                b'[' => "array",
+                _ => "value",
+            };
+            Value::String(format!(
+                "… {} of {}, not parsed yet",
+                kind,
+                format_bytes(text.len())
+            ))
+        };
+        match &self.top {
+            TopLevel::Object(members) => Value::Object(
+                members
+                    .iter()
+                    .map(|(key, range)| (key.clone(), summarize(range.clone())))
+                    .collect(),
+            ),
+            TopLevel::Array(elements) => {
+                Value::Array(elements.iter().cloned().map(summarize).collect())
+            }
+            TopLevel::Scalar => summarize(self.range(0)),
+        }
+    }
+}
+
+/// Splits `query` into a leading `.key` or `.[n]` and the filter that runs on
+/// what it selects, when the query only descends from there
+///
+/// `.items[0].name | length` becomes `Key("items")` and `.[0].name | length`.
+/// Anything that reads the document elsewhere, like `.a + .b`, is `None`.
+pub fn split_root_step(query: &str) -> Option<(RootStep, String)> {
+    let tokens = tokenize_query(query);
+    let (step, consumed) = match tokens.as_slice() {
+        [first, ..] if first.kind == QueryTokenKind::Field => {
+            (RootStep::Key(first.text(query)[1..].to_string()), 1)
+        }
+        [dot, open, inner, close, ..]
+            if dot.kind == QueryTokenKind::Dot
+                && dot.text(query) == "."
+                && open.text(query) == "["
+                && close.text(query) == "]" =>
+        {
+            let step = match inner.kind {
+                QueryTokenKind::Number => RootStep::Index(inner.text(query).parse().ok()?),
+                QueryTokenKind::String => {
+                    RootStep::Key(serde_json::from_str(inner.text(query)).ok()?)
+                }
+                _ => return None,
+            };
+            (step, 4)
+        }
+        _ => return None,
+    };
+    // Only further path steps may follow before the first pipe
+    for token in &tokens[consumed..] {
+        match token.kind {
+            QueryTokenKind::Pipe => break,
+            QueryTokenKind::Field
+            | QueryTokenKind::Dot
+            | QueryTokenKind::Number
+            | QueryTokenKind::String
+            | QueryTokenKind::Question => {}
+            QueryTokenKind::Open | QueryTokenKind::Close
+                if matches!(token.text(query), "[" | "]") => {}
+            _ => return None,
+        }
+        if token.kind == QueryTokenKind::Dot && token.text(query) != "." {
+            return None;
+        }
+    }
+    // A `?` after the step has nothing left to catch once the step applies
+    let rest = query[tokens[consumed - 1].end..]
+        .trim_start()
+        .trim_start_matches('?');
+    let rest = match rest.chars().next() {
+        None => ".".to_string(),
+        Some('.') => rest.to_string(),
+        Some('[') => format!(".{}", rest),
+        Some(_) => format!(". {}", rest),
+    };
+    Some((step, rest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_members_are_parsed_on_demand() {
+        let text = r#"{"b": [1, 2, {"c": 3}], "a": "x"}"#;
+        let document = LazyDocument::index(text.to_string()).unwrap();
+        assert_eq!(document.keys().collect::<Vec<_>>(), vec!["a", "b"]);
+
+        let b = document.select(&RootStep::Key("b".to_string())).unwrap();
+        assert_eq!(*b, json!([1, 2, {"c": 3}]));
+        assert!(!document.is_parsed());
+        assert_eq!(
+            *document.select(&RootStep::Key("zz".to_string())).unwrap(),
+            Value::Null
+        );
+        assert!(document.select(&RootStep::Index(0)).is_none());
+
+        assert_eq!(
+            **document.value(),
+            serde_json::from_str::<Value>(text).unwrap()
+        );
+        assert!(document.is_parsed());
+    }
+
+    #[test]
+    fn test_index_rejects_invalid_json() {
+        assert!(LazyDocument::index("[1, {]".to_string()).is_err());
+        assert!(LazyDocument::index("4 2".to_string()).is_err());
+        let scalar = LazyDocument::index(" 42 ".to_string()).unwrap();
+        assert!(scalar.select(&RootStep::Index(0)).is_none());
+        assert_eq!(scalar.outline(), json!(42));
+    }
+
+    #[test]
+    fn test_outline_summarizes_large_members() {
+        let big = format!("[{}]", vec!["1"; 200].join(","));
+        let text = format!(r#"{{"big": {}, "small": {{"a": 1}}}}"#, big);
+        let document = LazyDocument::index(text).unwrap();
+        let outline = document.outline();
+        assert_eq!(outline["small"], json!({"a": 1}));
+        assert_eq!(outline["big"], json!("… array of 401 B, not parsed yet"));
+    }
+
+    #[test]
+    fn test_split_root_step() {
+        assert_eq!(
+            split_root_step(".items[0].name | length"),
+            Some((
+                RootStep::Key("items".to_string()),
+                ".[0].name | length".to_string()
+            ))
+        );
+        assert_eq!(
+            split_root_step(".a"),
+            Some((RootStep::Key("a".to_string()), ".".to_string()))
+        );
+        assert_eq!(
+            split_root_step(".a.b"),
+            Some((RootStep::Key("a".to_string()), ".b".to_string()))
+        );
+        assert_eq!(
+            split_root_step(r#".["a b"] | keys"#),
+            Some((RootStep::Key("a b".to_string()), ". | keys".to_string()))
+        );
+        assert_eq!(
+            split_root_step(".[2]"),
+            Some((RootStep::Index(2), ".".to_string()))
+        );
+        for query in [
+            ".a + .b", ".a, .b", ".a[1:2]", ".[-1]", ". | .a", ".a..b", "keys", ".a // 1",
+        ] {
+            assert_eq!(split_root_step(query), None, "{}", query);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+/// Kind of a jq query token
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryTokenKind {
+    /// `.foo`
+    Field,
+    /// `.` or `..`
+    Dot,
+    /// `$name`
+    Variable,
+    /// `if`, `then`, `reduce`, `and`, ...
+    Keyword,
+    /// Function names and `true`/`false`/`null`
+    Ident,
+    /// `@base64`, `@csv`, ...
+    Format,
+    String,
+    Number,
+    /// Comparison, arithmetic, alternative and assignment operators
+    Operator,
+    Pipe,
+    Comma,
+    Semicolon,
+    Colon,
+    Question,
+    Open,
+    Close,
+    Comment,
+    Unknown,
+}
+
+/// Token of a query, as a byte range
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QueryToken {
+    pub kind: QueryTokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl QueryToken {
+    pub fn text<'a>(&self, query: &'a str) -> &'a str {
+        &query[self.start..self.end]
+    }
+}
+
+const KEYWORDS: &[&str] = &[
+    "if", "then", "elif", "else", "end", "as", "def", "reduce", "foreach", "try", "catch", "label",
+    "import", "include", "and", "or", "__loc__",
+];
+
+/// Longest operators first so `//=` wins over `//` and `/`
+const OPERATORS: &[&str] = &[
+    "?//", "//=", "|=", "+=", "-=", "*=", "/=", "%=", "==", "!=", "<=", ">=", "//", "<", ">", "+",
+    "-", "*", "/", "%", "=",
+];
+
+/// Splits a query into tokens, leaving out whitespace
+pub fn tokenize_query(query: &str) -> Vec<QueryToken> {
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+    while let Some(token) = next_token(query, pos) {
+        pos = token.end;
+        tokens.push(token);
+    }
+    tokens
+}
+
+/// How far past a token's end the lexer may look to decide where it ends
+/// (`?//`, `a::b`)
+const MAX_LOOKAHEAD: usize = 3;
+
+/// Tokenizer reusing the tokens before the edit
+///
+/// The query line is tokenized on every frame; keeping the previous result
+/// lets long, mostly unchanged inputs be re-lexed only from the edit point.
+#[derive(Debug, Clone, Default)]
+pub struct IncrementalTokenizer {
+    text: String,
+    tokens: Vec<QueryToken>,
+}
+
+impl IncrementalTokenizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tokens of the last text passed to [`update`](Self::update), if it equals `text`
+    pub fn tokens_for(&self, text: &str) -> Option<&[QueryToken]> {
+        (self.text == text).then_some(self.tokens.as_slice())
+    }
+
+    /// Re-tokenizes `text`, reusing tokens that end well before the first changed byte
+    pub fn update(&mut self, text: &str) -> &[QueryToken] {
+        if self.text != text {
+            let edit_start = self
+                .text
+                .bytes()
+                .zip(text.bytes())
+                .position(|(old, new)| old != new)
+                .unwrap_or(self.text.len().min(text.len()));
+            let keep = self
+                .tokens
+                .iter()
+                .take_while(|token| token.end + MAX_LOOKAHEAD <= edit_start)
+                .count();
+            self.tokens.truncate(keep);
+
+            let mut pos = self.tokens.last().map_or(0, |token| token.end);
+            while let Some(token) = next_token(text, pos) {
+                pos = token.end;
+                self.tokens.push(token);
+            }
+            self.text = text.to_string();
+        }
+        &self.tokens
+    }
+}
+
+/// Scans the token starting at or after `pos`, skipping whitespace
+pub(crate) fn next_token(query: &str, pos: usize) -> Option<QueryToken> {
+    let rest = &query[pos..];
+    let skipped = rest.len() - rest.trim_start().len();
+    let start = pos + skipped;
+    let rest = &query[start..];
+    let first = rest.chars().next()?;
+
+    let (kind, len) = match first {
+        '"' => (QueryTokenKind::String, string_len(rest)),
+        '#' => (
+            QueryTokenKind::Comment,
+            rest.find('\n').unwrap_or(rest.len()),
+        ),
+        '.' if rest.starts_with("..") => (QueryTokenKind::Dot, 2),
+        '.' => match ident_len(&rest[1..]) {
+            0 => (QueryTokenKind::Dot, 1),
+            n => (QueryTokenKind::Field, 1 + n),
+        },
+        '$' => (QueryTokenKind::Variable, 1 + ident_len(&rest[1..])),
+        '@' => (QueryTokenKind::Format, 1 + ident_len(&rest[1..])),
+        '0'..='9' => (QueryTokenKind::Number, number_len(rest)),
+        c if c.is_ascii_alphabetic() || c == '_' => {
+            let len = ident_len(rest);
+            let kind = if KEYWORDS.contains(&&rest[..len]) {
+                QueryTokenKind::Keyword
+            } else {
+                QueryTokenKind::Ident
+            };
+            (kind, len)
+        }
+        '(' | '[' | '{' => (QueryTokenKind::Open, 1),
+        ')' | ']' | '}' => (QueryTokenKind::Close, 1),
+        ',' => (QueryTokenKind::Comma, 1),
+        ';' => (QueryTokenKind::Semicolon, 1),
+        ':' => (QueryTokenKind::Colon, 1),
+        _ => match OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            Some(op) => (QueryTokenKind::Operator, op.len()),
+            None if first == '|' => (QueryTokenKind::Pipe, 1),
+            None if first == '?' => (QueryTokenKind::Question, 1),
+            None => (QueryTokenKind::Unknown, first.len_utf8()),
+        },
+    };
+    Some(QueryToken {
+        kind,
+        start,
+        end: start + len,
+    })
+}
+
+/// Identifier length, including `::` module separators
+fn ident_len(text: &str) -> usize {
+    let word = |text: &str| {
+        if text.starts_with(|c: char| c.is_ascii_digit()) {
+            return 0;
+        }
+        text.find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(text.len())
+    };
+    let mut len = word(text);
+    while len > 0 && text[len..].starts_with("::") && word(&text[len + 2..]) > 0 {
+        len += 2 + word(&text[len + 2..]);
+    }
+    len
+}
+
+fn number_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = 0;
+    while len < bytes.len() {
+        let b = bytes[len];
+        let exponent_sign =
+            (b == b'+' || b == b'-') && len > 0 && matches!(bytes[len - 1], b'e' | b'E');
+        if b.is_ascii_digit() || b == b'.' || b == b'e' || b == b'E' || exponent_sign {
+            len += 1;
+        } else {
+            break;
+        }
+    }
+    len
+}
+
+/// Length of a string literal including `\(...)` interpolations; an
+/// unterminated string extends to the end of the input
+fn string_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut pos = 1;
+    while pos < bytes.len() {
+        match bytes[pos] {
+            b'"' => return pos + 1,
+            b'\\' if bytes.get(pos + 1) == Some(&b'(') => {
+                pos = interpolation_end(text, pos + 2);
+            }
+            b'\\' => pos += 2,
+            _ => pos += 1,
+        }
+    }
+    text.len()
+}
+
+/// Position just after the `)` closing an interpolation opened before `pos`
+fn interpolation_end(text: &str, mut pos: usize) -> usize {
+    let mut depth = 1;
+    while let Some(token) = next_token(text, pos) {
+        pos = token.end;
+        match &text[token.start..token.end] {
+            "(" => depth += 1,
+            ")" => {
+                depth -= 1;
+                if depth == 0 {
+                    return pos;
+                }
+            }
+            _ => {}
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use QueryTokenKind::*;
+
+    fn kinds(query: &str) -> Vec<(QueryTokenKind, &str)> {
+        tokenize_query(query)
+            .into_iter()
+            .map(|t| (t.kind, t.text(query)))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_query() {
+        assert_eq!(
+            kinds(".users[] | select(.age >= 25)"),
+            vec![
+                (Field, ".users"),
+                (Open, "["),
+                (Close, "]"),
+                (Pipe, "|"),
+                (Ident, "select"),
+                (Open, "("),
+                (Field, ".age"),
+                (Operator, ">="),
+                (Number, "25"),
+                (Close, ")"),
+            ]
+        );
+        assert_eq!(
+            kinds("reduce .[] as $x (0; . + $x)")
+                .into_iter()
+                .map(|(kind, _)| kind)
+                .collect::<Vec<_>>(),
+            vec![
+                Keyword, Dot, Open, Close, Keyword, Variable, Open, Number, Semicolon, Dot,
+                Operator, Variable, Close
+            ]
+        );
+    }
+
+    #[test]
+    fn test_string_interpolation_is_one_token() {
+        assert_eq!(
+            kinds(r#""a \("b" + (.c)) d" | @base64"#),
+            vec![
+                (String, r#""a \("b" + (.c)) d""#),
+                (Pipe, "|"),
+                (Format, "@base64"),
+            ]
+        );
+        // Unterminated strings run to the end of the input
+        assert_eq!(
+            kinds(r#".a == "x"#),
+            vec![(Field, ".a"), (Operator, "=="), (String, "\"x")]
+        );
+    }
+
+    #[test]
+    fn test_incremental_tokenizer_reuses_prefix() {
+        let mut tokenizer = IncrementalTokenizer::new();
+        tokenizer.update(".a | .b");
+        assert!(tokenizer.tokens_for(".a | .b").is_some());
+        assert!(tokenizer.tokens_for(".a | .c").is_none());
+
+        // Extending an operator past the lookahead window still relexes it
+        for text in ["?/", "?//", ".aa", ".a", ".a::", ".a::b", ""] {
+            assert_eq!(tokenizer.update(text), tokenize_query(text).as_slice());
+        }
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn prop_incremental_matches_full_scan(
+            base in r#"[.a-c$@0-9 |,:;?/=+*()\[\]{}"\\-]{0,24}"#,
+            edit in r#"[.a-c0-9 |/=()"\\]{0,4}"#,
+            at in 0usize..25,
+            remove in 0usize..4,
+        ) {
+            let at = at.min(base.len());
+            let end = (at + remove).min(base.len());
+            let edited = format!("{}{}{}", &base[..at], edit, &base[end..]);
+
+            let mut tokenizer = IncrementalTokenizer::new();
+            tokenizer.update(&base);
+            let expected = tokenize_query(&edited);
+            proptest::prop_assert_eq!(tokenizer.update(&edited), expected.as_slice());
+        }
+    }
+
+    #[test]
+    fn test_operators_longest_match() {
+        assert_eq!(
+            kinds(".a //= 1 // 2"),
+            vec![
+                (Field, ".a"),
+                (Operator, "//="),
+                (Number, "1"),
+                (Operator, "//"),
+                (Number, "2"),
+            ]
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use crate::ui::SyntaxHighlighter;
+use crate::ui::syntax::TokenType;
+use std::ops::Range;
+
+/// Characters after which a `-` is a sign rather than binary minus
+const SIGN_CONTEXT: &[char] = &[
+    '(', '[', '{', ',', ':', '|', '=', '<', '>', '!', '+', '*', '/', '%', ';',
+];
+
+/// Positions of the numeric literals in the query
+///
+/// Digits that are part of an identifier (`.a2`, `$x1`) are skipped, and a
+/// leading `-` is only included when it cannot be binary minus.
+pub fn numeric_literals(query: &str) -> Vec<Range<usize>> {
+    SyntaxHighlighter::new()
+        .tokenize(query)
+        .into_iter()
+        .filter(|token| token.token_type == TokenType::Number)
+        .filter_map(|token| {
+            let before = &query[..token.start];
+            if before.ends_with(|c: char| c.is_alphanumeric() || c == '_' || c == '$') {
+                return None;
+            }
+            let mut start = token.start;
+            if token.text.starts_with('-') {
+                let context = before.trim_end();
+                if !context.is_empty() && !context.ends_with(SIGN_CONTEXT) {
+                    start += 1;
+                }
+            }
+            query[start..token.end]
+                .parse::<f64>()
+                .ok()
+                .map(|_| start..token.end)
+        })
+        .collect()
+}
+
+/// Offset after the opening quote if the query ends inside a string
+pub fn open_string_start(query: &str) -> Option<usize> {
+    let mut open = None;
+    let mut escaped = false;
+    for (pos, ch) in query.char_indices() {
+        match (open, ch) {
+            (Some(_), _) if escaped => escaped = false,
+            (Some(_), '\\') => escaped = true,
+            (Some(_), '"') => open = None,
+            (None, '"') => open = Some(pos + 1),
+            _ => {}
+        }
+    }
+    open
+}
+
+/// Adds `steps` units of the literal's last decimal place, keeping its precision
+pub fn adjust_number(literal: &str, steps: i64) -> Option<String> {
+    if let Ok(value) = literal.parse::<i64>() {
+        return Some(value.saturating_add(steps).to_string());
+    }
+    if literal.contains(['e', 'E']) {
+        return None;
+    }
+    let value = literal.parse::<f64>().ok()?;
+    let decimals = literal.split_once('.').map_or(0, |(_, frac)| frac.len());
+    let step = 10f64.powi(-(decimals as i32));
+    Some(format!("{:.*}", decimals, value + step * steps as f64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn literals(query: &str) -> Vec<&str> {
+        numeric_literals(query)
+            .into_iter()
+            .map(|range| &query[range])
+            .collect()
+    }
+
+    #[test]
+    fn test_numeric_literals() {
+        assert_eq!(literals("select(.age > 25)"), vec!["25"]);
+        assert_eq!(
+            literals(".items[0:10] | map(. * 1.5)"),
+            vec!["0", "10", "1.5"]
+        );
+        assert_eq!(literals(r#".a2 | $x1 | "42""#), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn test_negative_literals() {
+        assert_eq!(literals("select(.t > -5)"), vec!["-5"]);
+        // Binary minus is not part of the literal
+        assert_eq!(literals(".a -1"), vec!["1"]);
+    }
+
+    #[test]
+    fn test_open_string_start() {
+        assert_eq!(open_string_start(r#".a == "x"#), Some(7));
+        assert_eq!(open_string_start(r#".a == "x\"y"#), Some(7));
+        assert_eq!(open_string_start(r#".a == "x""#), None);
+    }
+
+    #[test]
+    fn test_adjust_number() {
+        assert_eq!(adjust_number("25", 1).as_deref(), Some("26"));
+        assert_eq!(adjust_number("0", -1).as_deref(), Some("-1"));
+        assert_eq!(adjust_number("1.50", 2).as_deref(), Some("1.52"));
+        assert_eq!(adjust_number("1e3", 1), None);
+    }
+}
@@ -1,83 +1,357 @@
 pub mod cache;
 pub mod cached_executor;
+pub mod cancel;
+pub mod cost;
+pub mod distinct;
+pub mod docs;
 pub mod executor;
+pub mod format;
+pub mod indexer;
+pub mod join;
+pub mod lazy;
+pub mod lexer;
+pub mod literals;
+pub mod pipeline;
+pub mod prelude;
+pub mod runner;
+pub mod schema;
+pub mod sql;
+pub mod workspace;
 
-use crate::app::error::AppError;
-use jaq_core::{
-    Ctx, RcIter,
-    load::{Arena, File, Loader},
-};
-use jaq_json::Val;
-
-pub use cache::{InMemoryQueryCache, QueryCache};
+pub use cache::{CacheStats, InMemoryQueryCache, QueryCache};
 pub use cached_executor::CachedQueryExecutor;
-pub use executor::{JaqQueryExecutor, QueryExecutor};
+pub use cancel::CancelToken;
+pub use executor::{CompiledQuery, JaqQueryExecutor, QueryExecutor};
+pub use indexer::Indexer;
+pub use join::JoinSpec;
+pub use lazy::LazyDocument;
+pub use pipeline::{PrefixCache, PrefixCacheStats, PrefixEntry};
+pub use prelude::PreludeExecutor;
+pub use runner::QueryRun;
+pub use schema::{KeyIndex, ObjectArray, SizeIndex, SizeNode, object_arrays};
+pub use sql::{SqlQuery, sql_to_jq};
+pub use workspace::{FileSummary, Workspace, WorkspaceFile};
+
+use crate::app::AppError;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum QueryResult {
     Single(serde_json::Value),
     Multiple(Vec<serde_json::Value>),
     Empty,
+    /// Outputs produced before a runtime error stopped the query, with the
+    /// error's message
+    Partial {
+        values: Vec<serde_json::Value>,
+        error: String,
+    },
 }
 
 impl QueryResult {
     pub fn format_pretty(&self) -> String {
+        let mut out = Vec::new();
+        match self.write_pretty(&mut out) {
+            Ok(()) => String::from_utf8(out).expect("serde_json writes UTF-8"),
+            Err(_) => "Error formatting result".to_string(),
+        }
+    }
+
+    /// Streams the text of [`format_pretty`](Self::format_pretty) to `out`
+    /// without building it in memory
+    pub fn write_pretty<W: std::io::Write>(&self, mut out: W) -> std::io::Result<()> {
         match self {
-            QueryResult::Single(val) => serde_json::to_string_pretty(val)
-                .unwrap_or_else(|_| "Error formatting result".to_string()),
-            QueryResult::Multiple(vals) => serde_json::to_string_pretty(vals)
-                .unwrap_or_else(|_| "Error formatting result".to_string()),
-            QueryResult::Empty => "null".to_string(),
+            QueryResult::Single(val) => serde_json::to_writer_pretty(out, val)?,
+            QueryResult::Multiple(vals) => serde_json::to_writer_pretty(out, vals)?,
+            QueryResult::Empty => out.write_all(b"null")?,
+            QueryResult::Partial { values, .. } => match values.as_slice() {
+                [value] => serde_json::to_writer_pretty(out, value)?,
+                values => serde_json::to_writer_pretty(out, values)?,
+            },
+        }
+        Ok(())
+    }
+
+    /// Wraps the outputs of a filter
+    pub fn from_values(mut values: Vec<serde_json::Value>) -> Self {
+        match values.len() {
+            0 => QueryResult::Empty,
+            1 => QueryResult::Single(values.remove(0)),
+            _ => QueryResult::Multiple(values),
+        }
+    }
+
+    /// Wraps the outputs of a run, keeping those produced before a runtime
+    /// error as a [`Partial`](QueryResult::Partial) result
+    pub fn from_run(run: crate::Result<Vec<serde_json::Value>>) -> crate::Result<Self> {
+        match run {
+            Ok(values) => Ok(Self::from_values(values)),
+            Err(AppError::QueryRuntime { message, partial }) if !partial.is_empty() => {
+                Ok(QueryResult::Partial {
+                    values: partial,
+                    error: message,
+                })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The produced values, borrowed
+    pub fn values(&self) -> &[serde_json::Value] {
+        match self {
+            QueryResult::Single(val) => std::slice::from_ref(val),
+            QueryResult::Multiple(vals) | QueryResult::Partial { values: vals, .. } => vals,
+            QueryResult::Empty => &[],
+        }
+    }
+
+    /// Flattens the result into the list of produced values
+    pub fn into_values(self) -> Vec<serde_json::Value> {
+        match self {
+            QueryResult::Single(val) => vec![val],
+            QueryResult::Multiple(vals) | QueryResult::Partial { values: vals, .. } => vals,
+            QueryResult::Empty => vec![],
+        }
+    }
+
+    /// Message of the runtime error that stopped a partial result
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            QueryResult::Partial { error, .. } => Some(error),
+            _ => None,
         }
     }
 }
 
-#[derive(Debug)]
+/// How the document is held
+#[derive(Debug, Clone)]
+enum Document {
+    /// Shared so background work such as re-indexing can read it without a copy
+    Parsed(Arc<serde_json::Value>),
+    /// Parsed piece by piece as queries reach into it
+    Lazy(Arc<LazyDocument>),
+}
+
+#[derive(Debug, Clone)]
 pub struct JsonData {
-    inner: serde_json::Value,
+    inner: Document,
+    /// Bumped on every change so derived data can tell it is stale
+    generation: u64,
+    /// Identifies the content across all `JsonData`, see [`JsonData::fingerprint`]
+    fingerprint: u64,
+    /// `inner` is an array of separate inputs (e.g. NDJSON) rather than one document
+    stream: bool,
 }
 
 impl JsonData {
     pub fn new(value: serde_json::Value) -> Self {
-        Self { inner: value }
+        Self {
+            inner: Document::Parsed(Arc::new(value)),
+            generation: 0,
+            fingerprint: next_fingerprint(),
+            stream: false,
+        }
     }
 
-    pub fn get(&self) -> &serde_json::Value {
-        &self.inner
+    /// A large document whose members are parsed when a query needs them
+    pub fn lazy(document: LazyDocument) -> Self {
+        Self {
+            inner: Document::Lazy(Arc::new(document)),
+            generation: 0,
+            fingerprint: next_fingerprint(),
+            stream: false,
+        }
     }
 
-    pub fn execute_query(&self, query: &str) -> crate::Result<QueryResult> {
-        if query.is_empty() {
-            return Err(AppError::QueryCompile("Empty query".to_string()));
+    /// Data made of several inputs, e.g. NDJSON
+    ///
+    /// The inputs are shown as one array, but queries run once per input and
+    /// can read the rest with `input`/`inputs`, like jq.
+    pub fn from_inputs(inputs: Vec<serde_json::Value>) -> Self {
+        Self {
+            inner: Document::Parsed(Arc::new(serde_json::Value::Array(inputs))),
+            generation: 0,
+            fingerprint: next_fingerprint(),
+            stream: true,
         }
+    }
 
-        let program = File {
-            code: query,
-            path: (),
-        };
-        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
-        let arena = Arena::default();
-        let modules = loader
-            .load(&arena, program)
-            .map_err(|e| AppError::QueryCompile(format!("Loader: {:?}", e)))?;
-        let filter = jaq_core::Compiler::default()
-            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
-            .compile(modules)
-            .map_err(|e| AppError::QueryCompile(format!("Compiler: {:?}", e)))?;
-
-        let inputs = RcIter::new(core::iter::empty());
-        let results = filter.run((Ctx::new([], &inputs), Val::from(self.inner.clone())));
-        let values: Vec<serde_json::Value> = results
+    /// Inputs of several documents one after another, as jq reads several files
+    pub fn concat(documents: impl IntoIterator<Item = JsonData>) -> Self {
+        let inputs = documents
             .into_iter()
-            .filter_map(|r| r.ok())
-            .map(|val| val.into())
+            .flat_map(|data| match Arc::unwrap_or_clone(data.shared()) {
+                serde_json::Value::Array(items) if data.stream => items,
+                other => vec![other],
+            })
             .collect();
+        Self::from_inputs(inputs)
+    }
 
-        Ok(match values.len() {
-            0 => QueryResult::Empty,
-            1 => QueryResult::Single(values.into_iter().next().unwrap()),
-            _ => QueryResult::Multiple(values),
-        })
+    /// Joins documents read with [`LoadOptions::slurp`](crate::source::LoadOptions::slurp)
+    /// into one array, as `jq --slurp` does across files
+    pub fn slurp(documents: impl IntoIterator<Item = JsonData>) -> Self {
+        let items = documents
+            .into_iter()
+            .flat_map(|data| match Arc::unwrap_or_clone(data.shared()) {
+                serde_json::Value::Array(items) => items,
+                other => vec![other],
+            })
+            .collect();
+        Self::new(serde_json::Value::Array(items))
+    }
+
+    pub fn is_stream(&self) -> bool {
+        self.stream
+    }
+
+    /// Values queries run on: the single document, or each input of a stream
+    pub fn inputs(&self) -> &[serde_json::Value] {
+        match self.get() {
+            serde_json::Value::Array(items) if self.stream => items,
+            other => std::slice::from_ref(other),
+        }
+    }
+
+    /// The whole document; a lazy one is parsed completely first
+    pub fn get(&self) -> &serde_json::Value {
+        match &self.inner {
+            Document::Parsed(value) => value,
+            Document::Lazy(document) => document.value(),
+        }
+    }
+
+    /// A handle on the document for another thread; changing the data while
+    /// it is held copies the document first
+    pub fn shared(&self) -> Arc<serde_json::Value> {
+        match &self.inner {
+            Document::Parsed(value) => Arc::clone(value),
+            Document::Lazy(document) => Arc::clone(document.value()),
+        }
+    }
+
+    /// The lazily parsed document, while it has not been parsed as a whole
+    pub fn unparsed(&self) -> Option<&LazyDocument> {
+        match &self.inner {
+            Document::Lazy(document) if !document.is_parsed() => Some(document),
+            _ => None,
+        }
+    }
+
+    /// The document for changing in place, parsing a lazy one first
+    fn value_mut(&mut self) -> &mut serde_json::Value {
+        if let Document::Lazy(document) = &self.inner {
+            self.inner = Document::Parsed(Arc::clone(document.value()));
+        }
+        match &mut self.inner {
+            Document::Parsed(value) => Arc::make_mut(value),
+            Document::Lazy(_) => unreachable!("replaced above"),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// A number identifying the content, unique among all `JsonData` of the
+    /// process and renewed on every change; clones share it until one changes
+    ///
+    /// Caches key results by it instead of by the whole document.
+    pub fn fingerprint(&self) -> u64 {
+        self.fingerprint
+    }
+
+    fn changed(&mut self) {
+        self.generation += 1;
+        self.fingerprint = next_fingerprint();
+    }
+
+    /// Swaps in a new document, e.g. after the input file was reloaded
+    ///
+    /// A stream stays a stream: an array replaces its inputs.
+    pub fn replace(&mut self, value: serde_json::Value) {
+        self.inner = Document::Parsed(Arc::new(value));
+        self.changed();
+    }
+
+    /// Swaps in freshly loaded data, which may or may not be a stream
+    pub fn replace_with(&mut self, data: JsonData) {
+        self.inner = data.inner;
+        self.stream = data.stream;
+        self.changed();
+    }
+
+    /// Adds inputs to the end of the stream, e.g. records still arriving on stdin
+    ///
+    /// A single document becomes the first input of a new stream.
+    pub fn append(&mut self, values: impl IntoIterator<Item = serde_json::Value>) {
+        let stream = self.stream;
+        let inner = self.value_mut();
+        if !stream {
+            let first = std::mem::take(inner);
+            *inner = serde_json::Value::Array(vec![first]);
+        }
+        if let serde_json::Value::Array(items) = inner {
+            items.extend(values);
+        }
+        self.stream = true;
+        self.changed();
+    }
+
+    /// Deep-merges `patch` into the document like jq's `*`: objects are merged
+    /// key by key, any other value replaces what was there
+    pub fn merge(&mut self, patch: serde_json::Value) {
+        deep_merge(self.value_mut(), patch);
+        self.changed();
+    }
+
+    pub fn execute_query(&self, query: &str) -> crate::Result<QueryResult> {
+        self.execute_query_with(&JaqQueryExecutor, query)
+    }
+
+    pub fn execute_query_with<E: QueryExecutor>(
+        &self,
+        executor: &E,
+        query: &str,
+    ) -> crate::Result<QueryResult> {
+        let values = if self.stream {
+            executor.execute_inputs(self.inputs(), query)
+        } else if query.is_empty() && self.unparsed().is_some() {
+            // Rejected like any empty query, without parsing the document first
+            return Err(AppError::query_compile("Empty query"));
+        } else if let Some((subtree, rest)) = self.unparsed().and_then(|document| {
+            let (step, rest) = lazy::split_root_step(query)?;
+            Some((document.select(&step)?, rest))
+        }) {
+            // Only the member the query starts from is parsed
+            executor.execute(&subtree, &rest)
+        } else {
+            executor.execute_fingerprinted(self.get(), self.fingerprint, query)
+        };
+        QueryResult::from_run(values)
+    }
+}
+
+fn next_fingerprint() -> u64 {
+    static NEXT: AtomicU64 = AtomicU64::new(0);
+    NEXT.fetch_add(1, Ordering::Relaxed)
+}
+
+fn deep_merge(target: &mut serde_json::Value, patch: serde_json::Value) {
+    match (target, patch) {
+        (serde_json::Value::Object(target), serde_json::Value::Object(patch)) => {
+            for (key, value) in patch {
+                match target.get_mut(&key) {
+                    Some(existing) => deep_merge(existing, value),
+                    None => {
+                        target.insert(key, value);
+                    }
+                }
+            }
+        }
+        (target, patch) => *target = patch,
     }
 }
 
@@ -92,6 +366,107 @@ mod tests {
         assert_eq!(data.get(), &json!({"test": "value"}));
     }
 
+    #[test]
+    fn test_concat_reads_documents_in_order() {
+        let data = JsonData::concat([
+            JsonData::new(json!({"a": 1})),
+            JsonData::from_inputs(vec![json!(2), json!(3)]),
+        ]);
+        assert!(data.is_stream());
+        assert_eq!(data.inputs(), &[json!({"a": 1}), json!(2), json!(3)]);
+    }
+
+    #[test]
+    fn test_append_grows_the_stream() {
+        let mut data = JsonData::from_inputs(Vec::new());
+        data.append([json!(1), json!(2)]);
+        assert_eq!(data.inputs(), &[json!(1), json!(2)]);
+        assert_eq!(data.generation(), 1);
+
+        let mut single = JsonData::new(json!({"a": 1}));
+        single.append([json!(2)]);
+        assert!(single.is_stream());
+        assert_eq!(single.inputs(), &[json!({"a": 1}), json!(2)]);
+    }
+
+    #[test]
+    fn test_fingerprint_follows_content() {
+        let mut data = JsonData::new(json!({"a": 1}));
+        let copy = data.clone();
+        assert_eq!(copy.fingerprint(), data.fingerprint());
+        assert_ne!(
+            JsonData::new(json!({"a": 1})).fingerprint(),
+            data.fingerprint()
+        );
+        data.merge(json!({"b": 2}));
+        assert_ne!(copy.fingerprint(), data.fingerprint());
+    }
+
+    #[test]
+    fn test_replace_and_merge_bump_generation() {
+        let mut data = JsonData::new(json!({"a": {"x": 1, "y": 2}, "list": [1]}));
+        assert_eq!(data.generation(), 0);
+
+        data.merge(json!({"a": {"y": 3, "z": 4}, "list": [2], "b": true}));
+        assert_eq!(
+            data.get(),
+            &json!({"a": {"x": 1, "y": 3, "z": 4}, "list": [2], "b": true})
+        );
+        assert_eq!(data.generation(), 1);
+
+        data.replace(json!([1, 2]));
+        assert_eq!(data.get(), &json!([1, 2]));
+        assert_eq!(data.generation(), 2);
+        assert_eq!(data.execute_query("length").unwrap().format_pretty(), "2");
+    }
+
+    #[test]
+    fn test_lazy_document_parses_what_queries_reach() {
+        let text = r#"{"items": [{"name": "a"}, {"name": "b"}], "meta": {"n": 2}}"#;
+        let data = JsonData::lazy(LazyDocument::index(text.to_string()).unwrap());
+        assert_eq!(
+            data.execute_query(".items[].name").unwrap().into_values(),
+            vec![json!("a"), json!("b")]
+        );
+        assert!(data.unparsed().is_some());
+
+        // A query over the whole document parses all of it
+        assert_eq!(
+            data.execute_query(".meta.n + (.items | length)")
+                .unwrap()
+                .into_values(),
+            vec![json!(4)]
+        );
+        assert!(data.unparsed().is_none());
+
+        let mut data = JsonData::lazy(LazyDocument::index(text.to_string()).unwrap());
+        data.merge(json!({"meta": {"n": 3}}));
+        assert_eq!(data.get()["meta"], json!({"n": 3}));
+    }
+
+    #[test]
+    fn test_stream_runs_once_per_input() {
+        let data = JsonData::from_inputs(vec![json!({"a": 1}), json!({"a": 2})]);
+        assert_eq!(data.get(), &json!([{"a": 1}, {"a": 2}]));
+        assert_eq!(data.inputs().len(), 2);
+        assert_eq!(
+            data.execute_query(".a").unwrap().into_values(),
+            vec![json!(1), json!(2)]
+        );
+        assert_eq!(
+            data.execute_query("[., inputs] | map(.a)")
+                .unwrap()
+                .into_values(),
+            vec![json!([1, 2])]
+        );
+        assert!(
+            data.execute_query("empty")
+                .unwrap()
+                .into_values()
+                .is_empty()
+        );
+    }
+
     #[test]
     fn test_identity_query() {
         let data = JsonData::new(json!({"name": "test"}));
@@ -103,6 +478,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_runtime_error_keeps_earlier_outputs() {
+        let data = JsonData::new(json!([1, "a", 3]));
+        let result = data.execute_query(".[] | . + 1").unwrap();
+        assert_eq!(result.values(), &[json!(2)]);
+        assert!(result.error().unwrap().contains("cannot"));
+
+        // Without outputs to show, the error is the result
+        assert!(matches!(
+            data.execute_query(".[] | error"),
+            Err(AppError::QueryRuntime { .. })
+        ));
+    }
+
     #[test]
     fn test_query_formatting() {
         let result = QueryResult::Single(json!({"key": "value"}));
@@ -0,0 +1,384 @@
+//! Splitting pipelines and reusing the output of earlier stages
+//!
+//! Queries usually grow one stage at a time while typing:
+//! `.users[] | select(.active)` becomes `.users[] | select(.active) | .email`.
+//! The outputs of recent queries are kept, so the grown query only runs its
+//! new stages on them instead of the whole pipeline on the document.
+
+use super::lexer::{QueryTokenKind, tokenize_query};
+use super::{JsonData, QueryExecutor, QueryResult};
+use serde_json::Value;
+use std::io;
+use std::ops::Range;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Byte ranges of the pipeline stages of `query`, split at pipes that are not
+/// nested inside brackets or `if ... end`. Ranges include surrounding whitespace.
+pub fn stage_ranges(query: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut depth = 0usize;
+    let mut start = 0;
+    for token in tokenize_query(query) {
+        match (token.kind, token.text(query)) {
+            (QueryTokenKind::Open, _) | (QueryTokenKind::Keyword, "if") => depth += 1,
+            (QueryTokenKind::Close, _) | (QueryTokenKind::Keyword, "end") => {
+                depth = depth.saturating_sub(1)
+            }
+            (QueryTokenKind::Pipe, _) if depth == 0 => {
+                ranges.push(start..token.start);
+                start = token.end;
+            }
+            _ => {}
+        }
+    }
+    ranges.push(start..query.len());
+    ranges
+}
+
+/// The stages `query` adds after `prefix`, when running them on the outputs
+/// of `prefix` gives the same result as running all of `query`
+///
+/// Names bound in `prefix` (`as $x`, `def`, `label`) would be out of scope
+/// for the new stages on their own, and `input`/`inputs` in them would read
+/// the stored outputs instead of the document, so those queries run whole.
+pub fn appended_stages<'a>(prefix: &str, query: &'a str) -> Option<&'a str> {
+    let prefix = prefix.trim();
+    if prefix.is_empty() {
+        return None;
+    }
+    let ranges = stage_ranges(query);
+    let split = ranges
+        .iter()
+        .position(|range| query[..range.end].trim() == prefix)?;
+    let tail_start = ranges.get(split + 1)?.start;
+    let tail = &query[tail_start..];
+    if tail.trim().is_empty() {
+        return None;
+    }
+
+    let binds = |text: &str| {
+        tokenize_query(text).iter().any(|token| {
+            matches!(
+                (token.kind, token.text(text)),
+                (QueryTokenKind::Keyword, "as" | "def" | "label")
+            )
+        })
+    };
+    let reads_inputs = tokenize_query(tail).iter().any(|token| {
+        matches!(
+            (token.kind, token.text(tail)),
+            (QueryTokenKind::Ident, "input" | "inputs")
+        )
+    });
+    (!binds(prefix) && !reads_inputs).then_some(tail)
+}
+
+/// Number of earlier queries whose outputs are kept
+const PREFIX_CACHE_ENTRIES: usize = 8;
+
+/// An executed query and its output
+#[derive(Debug)]
+struct Stored {
+    query: String,
+    generation: u64,
+    outputs: Vec<Value>,
+    created: Instant,
+    /// Queries that ran from these outputs, counted from worker threads too
+    hits: AtomicUsize,
+    /// Newer queries do not evict a pinned entry
+    pinned: AtomicBool,
+}
+
+/// State of one cache entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct PrefixEntry {
+    pub query: String,
+    pub outputs: usize,
+    /// Length of the outputs as compact JSON
+    pub bytes: usize,
+    pub hits: usize,
+    pub age: Duration,
+    pub pinned: bool,
+}
+
+/// Statistics of the whole cache
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PrefixCacheStats {
+    /// Queries that ran from stored outputs
+    pub hits: usize,
+    /// Queries that ran on the whole document
+    pub misses: usize,
+}
+
+/// Cache of earlier stages' output
+///
+/// Holds the outputs of the last few queries that ran: while a stage is
+/// typed character by character, the query it extends ran several edits
+/// before. Cloning shares the outputs and the counters, so a worker thread
+/// can start from them.
+#[derive(Debug, Clone, Default)]
+pub struct PrefixCache {
+    /// Oldest first
+    stored: Vec<Arc<Stored>>,
+    misses: Arc<AtomicUsize>,
+}
+
+impl PrefixCache {
+    /// Remembers the outputs of `query` on the data at `generation`,
+    /// forgetting those of other generations
+    ///
+    /// The identity's output is the document itself, not worth a copy. When
+    /// the cache is full the oldest unpinned entry makes room; with every
+    /// entry pinned nothing is stored and `false` is returned.
+    pub fn store(&mut self, query: &str, generation: u64, outputs: Vec<Value>) -> bool {
+        if query.trim() == "." {
+            return true;
+        }
+        let mut pinned = false;
+        let mut hits = 0;
+        self.stored.retain(|stored| {
+            if stored.query == query {
+                pinned = stored.pinned.load(Ordering::Relaxed);
+                hits = stored.hits.load(Ordering::Relaxed);
+            }
+            stored.generation == generation && stored.query != query
+        });
+        if self.stored.len() == PREFIX_CACHE_ENTRIES {
+            let Some(oldest) = self
+                .stored
+                .iter()
+                .position(|stored| !stored.pinned.load(Ordering::Relaxed))
+            else {
+                return false;
+            };
+            self.stored.remove(oldest);
+        }
+        self.stored.push(Arc::new(Stored {
+            query: query.to_string(),
+            generation,
+            outputs,
+            created: Instant::now(),
+            hits: AtomicUsize::new(hits),
+            pinned: AtomicBool::new(pinned),
+        }));
+        true
+    }
+
+    pub fn clear(&mut self) {
+        self.stored.clear();
+    }
+
+    /// Forgets every entry that is not pinned
+    pub fn clear_unpinned(&mut self) {
+        self.stored
+            .retain(|stored| stored.pinned.load(Ordering::Relaxed));
+    }
+
+    /// Forgets the entry at `index` of [`entries`](Self::entries)
+    pub fn remove(&mut self, index: usize) {
+        if index < self.stored.len() {
+            self.stored.remove(index);
+        }
+    }
+
+    /// Pins the entry at `index` of [`entries`](Self::entries), or unpins it
+    pub fn toggle_pin(&mut self, index: usize) {
+        if let Some(stored) = self.stored.get(index) {
+            stored.pinned.fetch_xor(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.stored.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.stored.is_empty()
+    }
+
+    /// The stored entries, oldest first
+    ///
+    /// Sizes are measured here rather than when storing, so only the
+    /// inspector pays for them.
+    pub fn entries(&self) -> Vec<PrefixEntry> {
+        self.stored
+            .iter()
+            .map(|stored| PrefixEntry {
+                query: stored.query.clone(),
+                outputs: stored.outputs.len(),
+                bytes: stored.outputs.iter().map(json_len).sum(),
+                hits: stored.hits.load(Ordering::Relaxed),
+                age: stored.created.elapsed(),
+                pinned: stored.pinned.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+
+    pub fn stats(&self) -> PrefixCacheStats {
+        PrefixCacheStats {
+            hits: self
+                .stored
+                .iter()
+                .map(|stored| stored.hits.load(Ordering::Relaxed))
+                .sum(),
+            misses: self.misses.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Runs `query` on `data`; when it extends a stored query by more
+    /// stages, only those run, on the stored outputs of the longest one
+    pub fn execute<E: QueryExecutor>(
+        &self,
+        data: &JsonData,
+        executor: &E,
+        query: &str,
+    ) -> crate::Result<QueryResult> {
+        let reusable = self
+            .stored
+            .iter()
+            .filter(|stored| stored.generation == data.generation())
+            .filter_map(|stored| Some((stored, appended_stages(&stored.query, query)?)))
+            .min_by_key(|(_, tail)| tail.len());
+        match reusable {
+            Some((stored, tail)) => {
+                stored.hits.fetch_add(1, Ordering::Relaxed);
+                QueryResult::from_run(executor.execute_inputs(&stored.outputs, tail))
+            }
+            None => {
+                self.misses.fetch_add(1, Ordering::Relaxed);
+                data.execute_query_with(executor, query)
+            }
+        }
+    }
+}
+
+/// Length of `value` as compact JSON, without building the string
+fn json_len(value: &Value) -> usize {
+    struct Counter(usize);
+
+    impl io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // Writing to the counter cannot fail
+    let _ = serde_json::to_writer(&mut counter, value);
+    counter.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::AppError;
+    use serde_json::json;
+    use std::cell::RefCell;
+
+    fn stages(query: &str) -> Vec<&str> {
+        stage_ranges(query)
+            .into_iter()
+            .map(|range| query[range].trim())
+            .collect()
+    }
+
+    #[test]
+    fn test_stage_ranges() {
+        assert_eq!(
+            stages(".users[] | select(.a | not) | .name"),
+            vec![".users[]", "select(.a | not)", ".name"]
+        );
+        assert_eq!(stages(r#""a|b" | .c |= 1"#), vec![r#""a|b""#, ".c |= 1"]);
+        assert_eq!(stages(".a | "), vec![".a", ""]);
+        assert_eq!(
+            stages("if .a | not then 1 else 2 end | . + 1"),
+            vec!["if .a | not then 1 else 2 end", ". + 1"]
+        );
+    }
+
+    #[test]
+    fn test_appended_stages() {
+        assert_eq!(
+            appended_stages(".users[] | select(.a)", ".users[] | select(.a) | .email"),
+            Some(" .email")
+        );
+        assert_eq!(appended_stages(".a ", ".a|.b|.c"), Some(".b|.c"));
+        assert_eq!(appended_stages(".a", ".a | "), None);
+        assert_eq!(appended_stages(".a", ".ab | .c"), None);
+        assert_eq!(appended_stages(".a", "[.a | .b]"), None);
+        assert_eq!(appended_stages(".[] as $x", ".[] as $x | $x"), None);
+        assert_eq!(appended_stages(".a", ".a | [., input]"), None);
+    }
+
+    /// Records every query it runs
+    #[derive(Default)]
+    struct Recording(RefCell<Vec<String>>);
+
+    impl QueryExecutor for Recording {
+        fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
+            self.0.borrow_mut().push(query.to_string());
+            crate::query::JaqQueryExecutor.execute(data, query)
+        }
+    }
+
+    #[test]
+    fn test_runs_only_new_stages() {
+        let data = JsonData::new(json!({"users": [{"n": 1}, {"n": 2}]}));
+        let executor = Recording::default();
+        let mut cache = PrefixCache::default();
+        let first = cache.execute(&data, &executor, ".users").unwrap();
+        cache.store(".users", data.generation(), first.values().to_vec());
+        let second = cache.execute(&data, &executor, ".users | .[]").unwrap();
+        cache.store(".users | .[]", data.generation(), second.values().to_vec());
+        cache.store(".users | .", data.generation(), vec![]);
+
+        // The longest stored prefix is used
+        let grown = cache
+            .execute(&data, &executor, ".users | .[] | .n")
+            .unwrap();
+        assert_eq!(grown.values(), [json!(1), json!(2)]);
+        assert_eq!(*executor.0.borrow(), [".users", " .[]", " .n", " .n"]);
+
+        // Other queries and other data run whole
+        cache.execute(&data, &executor, ".users | length").unwrap();
+        let mut changed = data.clone();
+        changed.replace(json!({"users": []}));
+        let result = cache.execute(&changed, &executor, ".users[] | .n").unwrap();
+        assert!(result.values().is_empty());
+        assert_eq!(executor.0.borrow().last().unwrap(), ".users[] | .n");
+        assert_eq!(cache.stats(), PrefixCacheStats { hits: 3, misses: 2 });
+    }
+
+    #[test]
+    fn test_entries_pin_and_remove() {
+        let mut cache = PrefixCache::default();
+        for i in 0..PREFIX_CACHE_ENTRIES {
+            cache.store(&format!(".a{i}"), 1, vec![json!({"k": i})]);
+        }
+        cache.toggle_pin(0);
+        cache.store(".b", 1, vec![]);
+
+        // The pinned oldest entry outlives the next oldest
+        let entries = cache.entries();
+        assert_eq!(entries.len(), PREFIX_CACHE_ENTRIES);
+        assert_eq!(entries[0].query, ".a0");
+        assert!(entries[0].pinned);
+        assert_eq!(entries[0].bytes, r#"{"k":0}"#.len());
+        assert_eq!(entries[1].query, ".a2");
+
+        cache.remove(1);
+        assert_eq!(cache.len(), PREFIX_CACHE_ENTRIES - 1);
+        cache.clear_unpinned();
+        let queries: Vec<_> = cache.entries().into_iter().map(|e| e.query).collect();
+        assert_eq!(queries, [".a0"]);
+        cache.toggle_pin(0);
+        assert!(!cache.entries()[0].pinned);
+    }
+}
@@ -0,0 +1,145 @@
+use super::{CacheStats, QueryExecutor};
+use crate::app::error::AppError;
+use serde_json::Value;
+
+/// Executor decorator that makes user-defined jq definitions and variables
+/// available to every query
+#[derive(Debug, Clone, Default)]
+pub struct PreludeExecutor<E: QueryExecutor> {
+    executor: E,
+    prelude: Option<String>,
+    /// Bound to `$name` in every query, e.g. from `--rawfile`
+    variables: Vec<(String, Value)>,
+}
+
+impl<E: QueryExecutor> PreludeExecutor<E> {
+    pub fn new(executor: E, prelude: Option<String>) -> Self {
+        Self {
+            executor,
+            prelude,
+            variables: Vec::new(),
+        }
+    }
+
+    pub fn prelude(&self) -> Option<&str> {
+        self.prelude.as_deref()
+    }
+
+    pub fn set_prelude(&mut self, prelude: Option<String>) {
+        self.prelude = prelude;
+    }
+
+    pub fn variables(&self) -> &[(String, Value)] {
+        &self.variables
+    }
+
+    /// Binds each value to `$name`; a later binding of the same name wins
+    pub fn set_variables(&mut self, variables: Vec<(String, Value)>) {
+        self.variables = variables;
+    }
+}
+
+impl<E: QueryExecutor> PreludeExecutor<E> {
+    /// Runs `run` with the prelude prepended to `query`
+    fn with_prelude(
+        &self,
+        query: &str,
+        run: impl FnOnce(&str) -> Result<Vec<Value>, AppError>,
+    ) -> Result<Vec<Value>, AppError> {
+        let Some(prelude) = self.prelude.as_deref().filter(|p| !p.trim().is_empty()) else {
+            return run(query);
+        };
+        if query.is_empty() {
+            return Err(AppError::query_compile("Empty query"));
+        }
+
+        let offset = prelude.len() + 1;
+        let program = format!("{}\n{}", prelude, query);
+        run(&program).map_err(|error| match error {
+            // Report positions relative to the user's query, not the combined program
+            AppError::QueryCompile { message, span } => AppError::QueryCompile {
+                message,
+                span: span
+                    .filter(|s| s.start >= offset)
+                    .map(|s| s.start - offset..s.end - offset),
+            },
+            other => other,
+        })
+    }
+}
+
+impl<E: QueryExecutor> QueryExecutor for PreludeExecutor<E> {
+    fn execute(&self, data: &Value, query: &str) -> Result<Vec<Value>, AppError> {
+        if !self.variables.is_empty() {
+            return self.execute_inputs(std::slice::from_ref(data), query);
+        }
+        self.with_prelude(query, |program| self.executor.execute(data, program))
+    }
+
+    fn execute_inputs(&self, inputs: &[Value], query: &str) -> Result<Vec<Value>, AppError> {
+        self.execute_with_vars(inputs, query, &[])
+    }
+
+    fn execute_with_vars(
+        &self,
+        inputs: &[Value],
+        query: &str,
+        variables: &[(String, Value)],
+    ) -> Result<Vec<Value>, AppError> {
+        let variables = [self.variables.as_slice(), variables].concat();
+        self.with_prelude(query, |program| {
+            self.executor.execute_with_vars(inputs, program, &variables)
+        })
+    }
+
+    fn invalidate(&self) {
+        self.executor.invalidate();
+    }
+
+    fn cache_stats(&self) -> Option<CacheStats> {
+        self.executor.cache_stats()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::JaqQueryExecutor;
+    use serde_json::json;
+
+    #[test]
+    fn test_prelude_definitions_are_available() {
+        let executor = PreludeExecutor::new(
+            JaqQueryExecutor,
+            Some("def active: map(select(.active));".to_string()),
+        );
+        let data = json!([{"id": 1, "active": true}, {"id": 2, "active": false}]);
+
+        let result = executor.execute(&data, "active | map(.id)").unwrap();
+        assert_eq!(result, vec![json!([1])]);
+    }
+
+    #[test]
+    fn test_error_span_is_relative_to_query() {
+        let executor = PreludeExecutor::new(JaqQueryExecutor, Some("def f: 1;".to_string()));
+        let error = executor.execute(&json!({}), ".a | nosuch").unwrap_err();
+        assert_eq!(error.span(), Some(5..11));
+    }
+
+    #[test]
+    fn test_variables_are_available() {
+        let mut executor =
+            PreludeExecutor::new(JaqQueryExecutor, Some("def twice: . * 2;".to_string()));
+        executor.set_variables(vec![("n".to_string(), json!(21))]);
+        assert_eq!(
+            executor.execute(&json!(null), "$n | twice").unwrap(),
+            vec![json!(42)]
+        );
+    }
+
+    #[test]
+    fn test_without_prelude_delegates() {
+        let executor = PreludeExecutor::new(JaqQueryExecutor, None);
+        assert_eq!(executor.execute(&json!(1), ".").unwrap(), vec![json!(1)]);
+    }
+}
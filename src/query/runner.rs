@@ -0,0 +1,287 @@
+//! Running queries in the background
+//!
+//! A query over a large document can take seconds. Running it on a worker
+//! thread keeps the event loop drawing and reading keys meanwhile, so the
+//! previous result stays on screen and the query can be abandoned.
+
+use super::{CancelToken, JsonData, PrefixCache, QueryExecutor, QueryResult};
+use crate::app::AppError;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, TryRecvError};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Workers of abandoned runs, with when each was abandoned, until they stop
+static ABANDONED: Mutex<Vec<(Instant, Arc<AtomicU8>)>> = Mutex::new(Vec::new());
+
+/// Abandoned workers still evaluating this long after being cancelled are
+/// stuck in a loop that produces nothing, like `last(repeat(1))`
+pub const STUCK_AFTER: Duration = Duration::from_secs(2);
+
+/// How often a new run checks whether the abandoned ones have stopped
+const ABANDONED_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+const RUNNING: u8 = 0;
+const DONE: u8 = 1;
+const ABANDONED_RUN: u8 = 2;
+
+/// Query running on a worker thread
+///
+/// Dropping it abandons the query: its [`CancelToken`] is cancelled, so the
+/// worker stops at the next output and its result is thrown away. Until
+/// abandoned workers have stopped, new runs wait before evaluating, so
+/// editing an expensive query does not pile up workers. Workers that are
+/// [stuck](Self::stuck) cannot be stopped and are no longer waited for.
+#[derive(Debug)]
+pub struct QueryRun {
+    query: String,
+    generation: u64,
+    started: Instant,
+    result: Receiver<crate::Result<QueryResult>>,
+    token: CancelToken,
+    /// `RUNNING`, `DONE` or `ABANDONED_RUN`, shared with the worker
+    state: Arc<AtomicU8>,
+}
+
+/// Marks a run done when its worker ends, even by panicking
+struct Finished(Arc<AtomicU8>);
+
+impl Drop for Finished {
+    fn drop(&mut self) {
+        self.0.store(DONE, Ordering::SeqCst);
+    }
+}
+
+/// How long each abandoned worker that is still evaluating was abandoned for
+fn abandoned_for() -> Vec<Duration> {
+    let mut abandoned = ABANDONED.lock().unwrap_or_else(|e| e.into_inner());
+    abandoned.retain(|(_, state)| state.load(Ordering::SeqCst) == ABANDONED_RUN);
+    abandoned.iter().map(|(at, _)| at.elapsed()).collect()
+}
+
+impl QueryRun {
+    /// Starts running `query` over `data` on a thread of its own, from the
+    /// outputs of a query it extends when `prefixes` holds one
+    pub fn spawn<E>(data: &JsonData, executor: &E, prefixes: &PrefixCache, query: &str) -> Self
+    where
+        E: QueryExecutor + Clone + Send + 'static,
+    {
+        let (sender, result) = mpsc::channel();
+        let worker_data = data.clone();
+        let worker_executor = executor.clone();
+        let worker_prefixes = prefixes.clone();
+        let worker_query = query.to_string();
+        let token = CancelToken::new();
+        let worker_token = token.clone();
+        let state = Arc::new(AtomicU8::new(RUNNING));
+        let finished = Finished(state.clone());
+        thread::spawn(move || {
+            let _finished = finished;
+            while abandoned_for().iter().any(|&waited| waited < STUCK_AFTER) {
+                if worker_token.is_cancelled() {
+                    return;
+                }
+                thread::sleep(ABANDONED_POLL_INTERVAL);
+            }
+            let result = worker_token
+                .install(|| worker_prefixes.execute(&worker_data, &worker_executor, &worker_query));
+            // The receiver is gone once the run was abandoned
+            let _ = sender.send(result);
+        });
+        Self {
+            query: query.to_string(),
+            generation: data.generation(),
+            started: Instant::now(),
+            result,
+            token,
+            state,
+        }
+    }
+
+    /// Workers of abandoned runs still evaluating
+    pub fn abandoned() -> usize {
+        abandoned_for().len()
+    }
+
+    /// Abandoned workers still evaluating [`STUCK_AFTER`] after being
+    /// cancelled; they keep a core busy until the process exits
+    pub fn stuck() -> usize {
+        abandoned_for()
+            .iter()
+            .filter(|&&waited| waited >= STUCK_AFTER)
+            .count()
+    }
+
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    /// Generation of the data the query runs over
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// The result once the worker is done, without waiting
+    pub fn try_finish(&self) -> Option<crate::Result<QueryResult>> {
+        match self.result.try_recv() {
+            Ok(result) => Some(result),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => Some(Err(panicked())),
+        }
+    }
+
+    /// The result once the worker is done, waiting at most `timeout` for it
+    pub fn wait(&self, timeout: Duration) -> Option<crate::Result<QueryResult>> {
+        match self.result.recv_timeout(timeout) {
+            Ok(result) => Some(result),
+            Err(RecvTimeoutError::Timeout) => None,
+            Err(RecvTimeoutError::Disconnected) => Some(Err(panicked())),
+        }
+    }
+}
+
+fn panicked() -> AppError {
+    AppError::QueryExecution("query evaluation panicked".to_string())
+}
+
+impl Drop for QueryRun {
+    fn drop(&mut self) {
+        self.token.cancel();
+        if self
+            .state
+            .compare_exchange(RUNNING, ABANDONED_RUN, Ordering::SeqCst, Ordering::SeqCst)
+            .is_ok()
+        {
+            ABANDONED
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .push((Instant::now(), self.state.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::JaqQueryExecutor;
+    use serde_json::json;
+
+    fn finish(run: &QueryRun) -> crate::Result<QueryResult> {
+        loop {
+            if let Some(result) = run.try_finish() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn test_runs_query_on_worker() {
+        let data = JsonData::new(json!({"a": [1, 2]}));
+        let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), ".a[]");
+        assert_eq!(run.query(), ".a[]");
+        assert_eq!(run.generation(), data.generation());
+        assert_eq!(finish(&run).unwrap().values(), [json!(1), json!(2)]);
+
+        let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), ".a |");
+        assert!(finish(&run).is_err());
+    }
+
+    #[test]
+    fn test_wait_gives_up_after_timeout() {
+        let data = JsonData::new(json!(null));
+        let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), "1");
+        assert_eq!(
+            run.wait(Duration::from_secs(5)).unwrap().unwrap().values(),
+            &[json!(1)]
+        );
+
+        let run = QueryRun::spawn(
+            &data,
+            &JaqQueryExecutor,
+            &PrefixCache::default(),
+            "[range(1e12)]",
+        );
+        assert!(run.wait(Duration::from_millis(5)).is_none());
+    }
+
+    #[test]
+    fn test_cancelled_run_stops() {
+        let data = JsonData::new(json!(1));
+        let run = QueryRun::spawn(
+            &data,
+            &JaqQueryExecutor,
+            &PrefixCache::default(),
+            "repeat(.)",
+        );
+        thread::sleep(Duration::from_millis(5));
+        run.token.cancel();
+        assert!(matches!(
+            finish(&run),
+            Err(AppError::QueryExecution(message)) if message == "cancelled"
+        ));
+    }
+
+    #[test]
+    fn test_run_waits_for_abandoned_run() {
+        let data = JsonData::new(json!(null));
+        let abandoned = QueryRun::spawn(
+            &data,
+            &JaqQueryExecutor,
+            &PrefixCache::default(),
+            "[range(1e12)]",
+        );
+        thread::sleep(Duration::from_millis(5));
+        drop(abandoned);
+        let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), "1");
+        assert_eq!(finish(&run).unwrap().values(), &[json!(1)]);
+    }
+
+    #[test]
+    fn test_stuck_run_is_counted_and_not_waited_for() {
+        let data = JsonData::new(json!(null));
+        let stuck = QueryRun::spawn(
+            &data,
+            &JaqQueryExecutor,
+            &PrefixCache::default(),
+            "until(false; .)",
+        );
+        // Compiled and evaluating by then, past the checks between inputs
+        assert!(stuck.wait(Duration::from_millis(500)).is_none());
+        drop(stuck);
+        assert!(QueryRun::abandoned() >= 1);
+        thread::sleep(STUCK_AFTER);
+        assert!(QueryRun::stuck() >= 1);
+
+        let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), "1");
+        assert_eq!(
+            run.wait(Duration::from_secs(1)).unwrap().unwrap().values(),
+            &[json!(1)]
+        );
+    }
+
+    #[test]
+    fn test_cancelled_run_stops_without_outputs() {
+        let data = JsonData::new(json!(null));
+        for query in [
+            "[range(1e12)]",
+            "[limit(1000000000000; repeat(1))] | length",
+        ] {
+            let run = QueryRun::spawn(&data, &JaqQueryExecutor, &PrefixCache::default(), query);
+            thread::sleep(Duration::from_millis(5));
+            run.token.cancel();
+            let result = finish(&run);
+            assert!(
+                matches!(&result, Err(AppError::QueryExecution(message)) if message == "cancelled"),
+                "{}: {:?}",
+                query,
+                result
+            );
+        }
+    }
+}
@@ -0,0 +1,377 @@
+use super::JsonData;
+use super::lexer::{QueryTokenKind, tokenize_query};
+use crate::render::tree::field;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashSet};
+
+/// Suggestion score multiplier when every field in a query exists in the document
+const KNOWN_FIELDS_BOOST: f64 = 2.0;
+
+/// Multiplier applied once per field that the document does not contain
+const MISSING_FIELD_PENALTY: f64 = 0.25;
+
+/// Index of the object keys in a document
+#[derive(Debug, Clone, Default)]
+pub struct KeyIndex {
+    keys: HashSet<String>,
+    /// Generation of the data the index was built from
+    generation: u64,
+}
+
+impl KeyIndex {
+    /// Collects the keys of every object in `data`, at any depth
+    pub fn build(data: &Value) -> Self {
+        let mut index = Self::default();
+        index.extend(std::slice::from_ref(data));
+        index
+    }
+
+    /// Index of `data` at its current generation; only the top-level keys
+    /// while a lazily parsed document has not been parsed as a whole
+    pub fn for_data(data: &JsonData) -> Self {
+        let mut index = match data.unparsed() {
+            Some(document) => Self {
+                keys: document.keys().map(str::to_string).collect(),
+                generation: 0,
+            },
+            None => Self::build(data.get()),
+        };
+        index.set_generation(data.generation());
+        index
+    }
+
+    /// Adds the keys of `values`, e.g. records appended to a stream
+    pub fn extend(&mut self, values: &[Value]) {
+        let mut pending: Vec<&Value> = values.iter().collect();
+        while let Some(node) = pending.pop() {
+            match node {
+                Value::Object(map) => {
+                    for (key, value) in map {
+                        if !self.keys.contains(key) {
+                            self.keys.insert(key.clone());
+                        }
+                        pending.push(value);
+                    }
+                }
+                Value::Array(items) => pending.extend(items),
+                _ => {}
+            }
+        }
+    }
+
+    /// Marks the index as describing the data at `generation`
+    pub fn set_generation(&mut self, generation: u64) {
+        self.generation = generation;
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.keys.contains(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Score multiplier for a history entry: boosted when all of its `.field`
+    /// accesses exist in the document, demoted for each one that does not
+    pub fn relevance(&self, query: &str) -> f64 {
+        let mut fields = tokenize_query(query)
+            .into_iter()
+            .filter(|token| token.kind == QueryTokenKind::Field)
+            .map(|token| &token.text(query)[1..])
+            .peekable();
+        if fields.peek().is_none() {
+            return 1.0;
+        }
+        let missing = fields.filter(|field| !self.contains(field)).count();
+        if missing == 0 {
+            KNOWN_FIELDS_BOOST
+        } else {
+            MISSING_FIELD_PENALTY.powi(missing as i32)
+        }
+    }
+}
+
+/// Levels below the root searched for arrays of objects
+const OBJECT_ARRAY_DEPTH: usize = 4;
+
+/// Elements of each array read for their keys
+const OBJECT_ARRAY_SAMPLE: usize = 100;
+
+/// Array of objects in a document
+#[derive(Debug, Clone, PartialEq)]
+pub struct ObjectArray {
+    /// jq path of the array, with `[]` for arrays on the way, e.g.
+    /// `.teams[].members`
+    pub path: String,
+    /// Keys of its elements, sorted
+    pub keys: Vec<String>,
+}
+
+/// Arrays of objects in the top levels of `data`, in document order
+///
+/// Arrays reached through the elements of another array share a path and
+/// the keys of all of them; only the first elements of long arrays are read.
+pub fn object_arrays(data: &Value) -> Vec<ObjectArray> {
+    let mut found: Vec<(String, BTreeSet<String>)> = Vec::new();
+    collect_object_arrays(data, String::new(), 0, &mut found);
+    found
+        .into_iter()
+        .map(|(path, keys)| ObjectArray {
+            path: if path.is_empty() {
+                ".".to_string()
+            } else {
+                path
+            },
+            keys: keys.into_iter().collect(),
+        })
+        .collect()
+}
+
+fn collect_object_arrays(
+    value: &Value,
+    path: String,
+    depth: usize,
+    found: &mut Vec<(String, BTreeSet<String>)>,
+) {
+    if depth > OBJECT_ARRAY_DEPTH {
+        return;
+    }
+    match value {
+        Value::Object(map) => {
+            for (key, child) in map {
+                collect_object_arrays(child, format!("{}{}", path, field(key)), depth + 1, found);
+            }
+        }
+        Value::Array(items) => {
+            let sample = &items[..items.len().min(OBJECT_ARRAY_SAMPLE)];
+            let keys = sample
+                .iter()
+                .filter_map(Value::as_object)
+                .flat_map(|object| object.keys().cloned());
+            if sample.iter().any(Value::is_object) {
+                match found.iter_mut().find(|(p, _)| *p == path) {
+                    Some((_, known)) => known.extend(keys),
+                    None => found.push((path.clone(), keys.collect())),
+                }
+            }
+            let items_path = if path.is_empty() {
+                ".[]".to_string()
+            } else {
+                format!("{}[]", path)
+            };
+            for item in sample {
+                collect_object_arrays(item, items_path.clone(), depth + 1, found);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Levels below the root whose subtrees are kept in a [`SizeIndex`]
+const SIZE_INDEX_DEPTH: usize = 3;
+
+/// Largest children kept per node of a [`SizeIndex`]; the rest only count toward their parent
+const SIZE_INDEX_CHILDREN: usize = 20;
+
+/// Size of a subtree, in bytes and nodes
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeNode {
+    /// jq path of the subtree, e.g. `.items[3].tags`
+    pub path: String,
+    /// Length of the subtree as compact JSON
+    pub bytes: usize,
+    /// Values in the subtree, itself included
+    pub nodes: usize,
+    /// Largest subtrees first
+    pub children: Vec<SizeNode>,
+}
+
+/// Index of where a document is large
+///
+/// Only the largest subtrees of the top few levels are kept, so the index
+/// stays small however big the document is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeIndex {
+    pub root: SizeNode,
+}
+
+impl SizeIndex {
+    pub fn build(data: &Value) -> Self {
+        Self {
+            root: measure(data, String::new(), 0),
+        }
+    }
+
+    /// Kept subtrees in display order with their depth below the root
+    pub fn lines(&self) -> Vec<(usize, &SizeNode)> {
+        let mut lines = Vec::new();
+        let mut pending: Vec<(usize, &SizeNode)> =
+            self.root.children.iter().rev().map(|c| (0, c)).collect();
+        while let Some((depth, node)) = pending.pop() {
+            lines.push((depth, node));
+            pending.extend(node.children.iter().rev().map(|c| (depth + 1, c)));
+        }
+        lines
+    }
+}
+
+fn measure(value: &Value, path: String, depth: usize) -> SizeNode {
+    let keep = depth < SIZE_INDEX_DEPTH;
+    let mut children = Vec::new();
+    let (bytes, nodes) = match value {
+        Value::Object(map) => {
+            let mut bytes = 2 + map.len().saturating_sub(1);
+            let mut nodes = 1;
+            for (key, child) in map {
+                let child_path = if keep {
+                    format!("{}{}", path, field(key))
+                } else {
+                    String::new()
+                };
+                let child = measure(child, child_path, depth + 1);
+                bytes += serde_json::to_string(key).map_or(0, |k| k.len()) + 1 + child.bytes;
+                nodes += child.nodes;
+                if keep {
+                    children.push(child);
+                }
+            }
+            (bytes, nodes)
+        }
+        Value::Array(items) => {
+            let mut bytes = 2 + items.len().saturating_sub(1);
+            let mut nodes = 1;
+            for (i, child) in items.iter().enumerate() {
+                let child_path = if keep {
+                    format!("{}[{}]", path, i)
+                } else {
+                    String::new()
+                };
+                let child = measure(child, child_path, depth + 1);
+                bytes += child.bytes;
+                nodes += child.nodes;
+                if keep {
+                    children.push(child);
+                }
+            }
+            (bytes, nodes)
+        }
+        scalar => (serde_json::to_string(scalar).map_or(0, |s| s.len()), 1),
+    };
+    children.sort_by_key(|child| std::cmp::Reverse(child.bytes));
+    children.truncate(SIZE_INDEX_CHILDREN);
+    SizeNode {
+        path: if path.is_empty() {
+            ".".to_string()
+        } else {
+            path
+        },
+        bytes,
+        nodes,
+        children,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_key_index_collects_nested_keys() {
+        let index = KeyIndex::build(&json!({
+            "users": [{"name": "a", "address": {"city": "x"}}],
+            "count": 1
+        }));
+        assert_eq!(index.len(), 5);
+        assert!(index.contains("city"));
+        assert!(!index.contains("x"));
+    }
+
+    #[test]
+    fn test_object_arrays() {
+        let data = json!({
+            "users": [{"id": 1, "name": "a"}, {"id": 2, "team": "x"}],
+            "teams": [{"name": "x", "members": [{"user_id": 1}]}],
+            "tags": ["a", "b"]
+        });
+        let arrays: Vec<(String, Vec<String>)> = object_arrays(&data)
+            .into_iter()
+            .map(|array| (array.path, array.keys))
+            .collect();
+        assert_eq!(
+            arrays,
+            vec![
+                (
+                    ".teams".to_string(),
+                    vec!["members".to_string(), "name".to_string()]
+                ),
+                (".teams[].members".to_string(), vec!["user_id".to_string()]),
+                (
+                    ".users".to_string(),
+                    vec!["id".to_string(), "name".to_string(), "team".to_string()]
+                ),
+            ]
+        );
+        assert_eq!(object_arrays(&json!([{"a": 1}]))[0].path, ".");
+    }
+
+    #[test]
+    fn test_size_index() {
+        let data = json!({"big": {"list": [1, 2, 3], "s": "abc"}, "n": 1});
+        let index = SizeIndex::build(&data);
+        assert_eq!(
+            index.root.bytes,
+            serde_json::to_string(&data).unwrap().len()
+        );
+        assert_eq!(index.root.nodes, 8);
+
+        let paths: Vec<(usize, &str)> = index
+            .lines()
+            .iter()
+            .map(|(depth, node)| (*depth, node.path.as_str()))
+            .collect();
+        assert_eq!(
+            paths[..4],
+            [
+                (0, ".big"),
+                (1, ".big.list"),
+                (2, ".big.list[0]"),
+                (2, ".big.list[1]")
+            ]
+        );
+        assert_eq!(paths.last(), Some(&(0, ".n")));
+        // Below the kept depth only the totals remain
+        let deep = SizeIndex::build(&json!({"a": {"b": {"c": {"d": 1}}}}));
+        assert!(
+            deep.lines()
+                .iter()
+                .all(|(depth, _)| *depth < SIZE_INDEX_DEPTH)
+        );
+    }
+
+    #[test]
+    fn test_relevance() {
+        let index = KeyIndex::build(&json!({"users": [{"name": "a"}]}));
+        assert_eq!(index.relevance(".users[].name"), KNOWN_FIELDS_BOOST);
+        assert_eq!(index.relevance(".users[].email"), MISSING_FIELD_PENALTY);
+        assert_eq!(
+            index.relevance(".items[].email"),
+            MISSING_FIELD_PENALTY * MISSING_FIELD_PENALTY
+        );
+        // Nothing to check: neither boosted nor demoted
+        assert_eq!(index.relevance("length"), 1.0);
+        assert_eq!(
+            index.relevance(r#"select(.x == ".name")"#),
+            MISSING_FIELD_PENALTY
+        );
+    }
+}
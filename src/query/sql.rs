@@ -0,0 +1,486 @@
+//! Translating SQL-like queries to jq
+//!
+//! A small subset of SQL for people who know it better than jq:
+//!
+//! ```text
+//! SELECT name, age FROM .users WHERE age > 25 ORDER BY age DESC LIMIT 10
+//! ```
+//!
+//! becomes `[.users[] | select(.age > 25)] | sort_by(.age) | reverse | .[:10]
+//! | map({name: .name, age: .age})`. Columns are fields of the elements of
+//! the `FROM` array, with `.` for nested ones (`address.city`); `WHERE`
+//! supports comparisons, `AND`, `OR`, `NOT`, parentheses, `LIKE`, `IN` and
+//! `IS [NOT] NULL`. Keywords are not case sensitive.
+
+use crate::render::tree::field;
+
+/// Parsed SQL-like query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SqlQuery {
+    /// jq path and output key of each selected column; empty for `*`
+    pub columns: Vec<(String, String)>,
+    /// jq path of the array queried
+    pub from: String,
+    /// jq condition of `WHERE`
+    pub condition: Option<String>,
+    /// jq path sorted by, and whether the order is descending
+    pub order: Option<(String, bool)>,
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    /// A bare word: a keyword, a column or `true`/`false`/`null`
+    Word(String),
+    /// A column written in double quotes
+    Quoted(String),
+    /// A string literal in single quotes
+    Str(String),
+    Number(String),
+    Symbol(&'static str),
+}
+
+impl SqlQuery {
+    pub fn parse(sql: &str) -> Result<Self, String> {
+        Parser {
+            tokens: tokenize(sql)?,
+            position: 0,
+        }
+        .query()
+    }
+
+    /// The jq program returning the selected rows as one array
+    pub fn to_jq(&self) -> String {
+        let mut stages = vec![match &self.condition {
+            Some(condition) => format!("[{}[] | select({})]", self.from, condition),
+            None => self.from.clone(),
+        }];
+        if let Some((path, descending)) = &self.order {
+            stages.push(format!("sort_by({})", path));
+            if *descending {
+                stages.push("reverse".to_string());
+            }
+        }
+        if let Some(limit) = self.limit {
+            stages.push(format!(".[:{}]", limit));
+        }
+        if !self.columns.is_empty() {
+            let fields: Vec<String> = self
+                .columns
+                .iter()
+                .map(|(path, name)| format!("{}: {}", &field(name)[1..], path))
+                .collect();
+            stages.push(format!("map({{{}}})", fields.join(", ")));
+        }
+        stages.join(" | ")
+    }
+}
+
+/// Translates `sql` into a jq program, see [`SqlQuery`]
+pub fn sql_to_jq(sql: &str) -> Result<String, String> {
+    SqlQuery::parse(sql).map(|query| query.to_jq())
+}
+
+fn tokenize(sql: &str) -> Result<Vec<Token>, String> {
+    const SYMBOLS: [&str; 11] = ["<>", "!=", "<=", ">=", "=", "<", ">", "(", ")", ",", "*"];
+    let mut tokens = Vec::new();
+    let mut rest = sql.trim_start();
+    while let Some(c) = rest.chars().next() {
+        let (token, len) = if c == '\'' || c == '"' {
+            // A doubled quote stands for the quote itself
+            let mut text = String::new();
+            let mut chars = rest.char_indices().skip(1).peekable();
+            let end = loop {
+                match chars.next() {
+                    Some((i, q)) if q == c => {
+                        if chars.peek().is_some_and(|(_, next)| *next == c) {
+                            chars.next();
+                            text.push(c);
+                        } else {
+                            break i + 1;
+                        }
+                    }
+                    Some((_, other)) => text.push(other),
+                    None => {
+                        return Err(format!(
+                            "unterminated {}",
+                            if c == '\'' { "string" } else { "column name" }
+                        ));
+                    }
+                }
+            };
+            let token = if c == '\'' {
+                Token::Str(text)
+            } else {
+                Token::Quoted(text)
+            };
+            (token, end)
+        } else if let Some(symbol) = SYMBOLS.iter().find(|symbol| rest.starts_with(**symbol)) {
+            (Token::Symbol(symbol), symbol.len())
+        } else if c.is_ascii_digit()
+            || (c == '-' && rest[1..].starts_with(|c: char| c.is_ascii_digit()))
+        {
+            let len = 1 + rest[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E'))
+                .unwrap_or(rest.len() - 1);
+            (Token::Number(rest[..len].to_string()), len)
+        } else if c.is_alphanumeric() || matches!(c, '_' | '.' | '$') {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | '$' | '[' | ']')))
+                .unwrap_or(rest.len());
+            (Token::Word(rest[..len].to_string()), len)
+        } else {
+            return Err(format!("unexpected '{}'", c));
+        };
+        tokens.push(token);
+        rest = rest[len..].trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn query(mut self) -> Result<SqlQuery, String> {
+        self.expect_keyword("SELECT")?;
+        let columns = self.columns()?;
+        self.expect_keyword("FROM")?;
+        let from = match self.next() {
+            Some(Token::Word(word)) => path(&word),
+            Some(Token::Quoted(name)) => field(&name),
+            _ => return Err("FROM needs the path of an array, e.g. FROM .users".to_string()),
+        };
+        let condition = if self.keyword("WHERE") {
+            Some(self.or()?)
+        } else {
+            None
+        };
+        let order = if self.keyword("ORDER") {
+            self.expect_keyword("BY")?;
+            let column = self.column()?;
+            let descending = self.keyword("DESC");
+            if !descending {
+                self.keyword("ASC");
+            }
+            if self.peek() == Some(&Token::Symbol(",")) {
+                return Err("ORDER BY takes a single column".to_string());
+            }
+            Some((column, descending))
+        } else {
+            None
+        };
+        let limit = if self.keyword("LIMIT") {
+            match self.next() {
+                Some(Token::Number(n)) => Some(
+                    n.parse()
+                        .map_err(|_| format!("LIMIT needs a whole number, not {}", n))?,
+                ),
+                _ => return Err("LIMIT needs a number".to_string()),
+            }
+        } else {
+            None
+        };
+        match self.next() {
+            None => Ok(SqlQuery {
+                columns,
+                from,
+                condition,
+                order,
+                limit,
+            }),
+            Some(token) => Err(format!("unexpected {} at the end", describe(&token))),
+        }
+    }
+
+    fn columns(&mut self) -> Result<Vec<(String, String)>, String> {
+        if self.symbol("*") {
+            return Ok(Vec::new());
+        }
+        let mut columns = Vec::new();
+        loop {
+            let name = match self.peek() {
+                Some(Token::Word(word)) => word.rsplit('.').next().unwrap_or(word).to_string(),
+                Some(Token::Quoted(name)) => name.clone(),
+                _ => String::new(),
+            };
+            let column = self.column()?;
+            let name = if self.keyword("AS") {
+                match self.next() {
+                    Some(Token::Word(alias) | Token::Quoted(alias)) => alias,
+                    _ => return Err("AS needs a name".to_string()),
+                }
+            } else {
+                name
+            };
+            columns.push((column, name));
+            if !self.symbol(",") {
+                return Ok(columns);
+            }
+        }
+    }
+
+    /// A column as a jq path
+    fn column(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(word)) if !is_keyword(&word) => Ok(path(&word)),
+            Some(Token::Quoted(name)) => Ok(field(&name)),
+            Some(token) => Err(format!("expected a column, found {}", describe(&token))),
+            None => Err("expected a column".to_string()),
+        }
+    }
+
+    fn or(&mut self) -> Result<String, String> {
+        let mut condition = self.and()?;
+        while self.keyword("OR") {
+            condition = format!("{} or {}", condition, self.and()?);
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<String, String> {
+        let mut condition = self.not()?;
+        while self.keyword("AND") {
+            condition = format!("{} and {}", condition, self.not()?);
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<String, String> {
+        if self.keyword("NOT") {
+            return Ok(format!("({} | not)", self.not()?));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<String, String> {
+        if self.symbol("(") {
+            let condition = self.or()?;
+            if !self.symbol(")") {
+                return Err("missing )".to_string());
+            }
+            return Ok(format!("({})", condition));
+        }
+        let left = self.operand()?;
+        if self.keyword("IS") {
+            let negated = self.keyword("NOT");
+            self.expect_keyword("NULL")?;
+            return Ok(format!(
+                "{} {} null",
+                left,
+                if negated { "!=" } else { "==" }
+            ));
+        }
+        let negated = self.keyword("NOT");
+        if self.keyword("LIKE") {
+            let Some(Token::Str(pattern)) = self.next() else {
+                return Err("LIKE needs a string, e.g. LIKE 'a%'".to_string());
+            };
+            let test = format!("({} | test({}))", left, quote(&like_regex(&pattern)));
+            return Ok(if negated {
+                format!("({} | not)", test)
+            } else {
+                test
+            });
+        }
+        if self.keyword("IN") {
+            if !self.symbol("(") {
+                return Err("IN needs a list, e.g. IN (1, 2)".to_string());
+            }
+            let mut alternatives = Vec::new();
+            loop {
+                alternatives.push(format!("{} == {}", left, self.operand()?));
+                if !self.symbol(",") {
+                    break;
+                }
+            }
+            if !self.symbol(")") {
+                return Err("missing ) after the IN list".to_string());
+            }
+            let any = format!("({})", alternatives.join(" or "));
+            return Ok(if negated {
+                format!("({} | not)", any)
+            } else {
+                any
+            });
+        }
+        if negated {
+            return Err("NOT here must be followed by LIKE or IN".to_string());
+        }
+        let operator = match self.next() {
+            Some(Token::Symbol("=")) => "==",
+            Some(Token::Symbol("<>" | "!=")) => "!=",
+            Some(Token::Symbol(op @ ("<" | "<=" | ">" | ">="))) => op,
+            Some(token) => {
+                return Err(format!("expected a comparison, found {}", describe(&token)));
+            }
+            // A bare column tests its truthiness
+            None => return Ok(left),
+        };
+        Ok(format!("{} {} {}", left, operator, self.operand()?))
+    }
+
+    fn operand(&mut self) -> Result<String, String> {
+        match self.peek() {
+            Some(Token::Str(text)) => {
+                let text = quote(text);
+                self.position += 1;
+                Ok(text)
+            }
+            Some(Token::Number(n)) => {
+                let n = n.clone();
+                self.position += 1;
+                Ok(n)
+            }
+            Some(Token::Word(word))
+                if ["TRUE", "FALSE", "NULL"].contains(&word.to_uppercase().as_str()) =>
+            {
+                let word = word.to_lowercase();
+                self.position += 1;
+                Ok(word)
+            }
+            _ => self.column(),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += token.is_some() as usize;
+        token
+    }
+
+    /// Skips the keyword when it comes next
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found =
+            matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword));
+        self.position += found as usize;
+        found
+    }
+
+    fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.keyword(keyword) {
+            return Ok(());
+        }
+        Err(match self.peek() {
+            Some(token) => format!("expected {}, found {}", keyword, describe(token)),
+            None => format!("expected {}", keyword),
+        })
+    }
+
+    fn symbol(&mut self, symbol: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Symbol(s)) if *s == symbol);
+        self.position += found as usize;
+        found
+    }
+}
+
+/// A column or array as a jq path: `address.city` as `.address.city`;
+/// text starting with `.` is taken as jq already
+fn path(word: &str) -> String {
+    if word.starts_with('.') {
+        return word.to_string();
+    }
+    word.split('.').map(field).collect()
+}
+
+fn is_keyword(word: &str) -> bool {
+    [
+        "SELECT", "FROM", "WHERE", "ORDER", "BY", "ASC", "DESC", "LIMIT", "AND", "OR", "NOT", "AS",
+        "IS", "LIKE", "IN",
+    ]
+    .iter()
+    .any(|keyword| word.eq_ignore_ascii_case(keyword))
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Word(word) => word.clone(),
+        Token::Quoted(name) => format!("\"{}\"", name),
+        Token::Str(text) => format!("'{}'", text),
+        Token::Number(n) => n.clone(),
+        Token::Symbol(symbol) => symbol.to_string(),
+    }
+}
+
+fn quote(text: &str) -> String {
+    serde_json::to_string(text).unwrap_or_default()
+}
+
+/// A `LIKE` pattern as an anchored regular expression: `%` matches any
+/// text and `_` one character
+fn like_regex(pattern: &str) -> String {
+    let mut regex = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::{JaqQueryExecutor, QueryExecutor};
+    use serde_json::json;
+
+    #[test]
+    fn test_translates_select() {
+        assert_eq!(
+            sql_to_jq("SELECT name, age FROM .users WHERE age > 25 ORDER BY age DESC LIMIT 10")
+                .unwrap(),
+            "[.users[] | select(.age > 25)] | sort_by(.age) | reverse | .[:10] \
+             | map({name: .name, age: .age})"
+        );
+        assert_eq!(
+            sql_to_jq("select * from users where not (active = true or role in ('admin', 'ops'))")
+                .unwrap(),
+            "[.users[] | select(((.active == true or (.role == \"admin\" or .role == \"ops\")) | not))]"
+        );
+        assert_eq!(
+            sql_to_jq("SELECT address.city AS \"home town\" FROM . WHERE email IS NOT NULL")
+                .unwrap(),
+            "[.[] | select(.email != null)] | map({\"home town\": .address.city})"
+        );
+    }
+
+    #[test]
+    fn test_translation_runs() {
+        let data = json!({"users": [
+            {"name": "ann", "age": 31, "email": "ann@example.com"},
+            {"name": "bob", "age": 22, "email": null},
+            {"name": "amy", "age": 40, "email": "amy@example.com"}
+        ]});
+        let jq = sql_to_jq(
+            "SELECT name FROM users WHERE name LIKE 'a%' AND email IS NOT NULL ORDER BY age DESC LIMIT 1",
+        )
+        .unwrap();
+        assert_eq!(
+            JaqQueryExecutor.execute(&data, &jq).unwrap(),
+            vec![json!([{"name": "amy"}])]
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(sql_to_jq("SELECT name").unwrap_err(), "expected FROM");
+        assert_eq!(
+            sql_to_jq("SELECT name FROM users WHERE age >").unwrap_err(),
+            "expected a column"
+        );
+        assert_eq!(
+            sql_to_jq("SELECT name FROM users LIMIT 10 OFFSET 5").unwrap_err(),
+            "unexpected OFFSET at the end"
+        );
+        assert!(sql_to_jq("SELECT 'a FROM users").is_err());
+    }
+}
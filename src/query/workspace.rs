@@ -0,0 +1,142 @@
+//! Running one query over several files
+
+use super::{JsonData, QueryExecutor};
+use serde_json::Value;
+use std::num::NonZeroUsize;
+use std::thread;
+
+/// One file of a workspace
+#[derive(Debug)]
+pub struct WorkspaceFile {
+    pub path: String,
+    pub data: JsonData,
+}
+
+/// Summary of a query's result on one file
+#[derive(Debug, Clone, PartialEq)]
+pub struct FileSummary {
+    pub path: String,
+    /// Outputs other than `null`, so a missing key is not a match
+    pub matches: usize,
+    /// First such output
+    pub first: Option<Value>,
+    /// Why the query failed on this file
+    pub error: Option<String>,
+}
+
+/// Files the same query runs over
+#[derive(Debug, Default)]
+pub struct Workspace {
+    files: Vec<WorkspaceFile>,
+}
+
+impl Workspace {
+    pub fn new(files: Vec<WorkspaceFile>) -> Self {
+        Self { files }
+    }
+
+    pub fn files(&self) -> &[WorkspaceFile] {
+        &self.files
+    }
+
+    pub fn into_files(self) -> Vec<WorkspaceFile> {
+        self.files
+    }
+
+    /// Runs `query` on every file, spread over the available cores
+    ///
+    /// Summaries come back in file order whatever order the files finish in.
+    pub fn run<E: QueryExecutor + Sync>(&self, executor: &E, query: &str) -> Vec<FileSummary> {
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(self.files.len())
+            .max(1);
+        let chunk = self.files.len().div_ceil(workers).max(1);
+        thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .files
+                .chunks(chunk)
+                .map(|files| {
+                    scope.spawn(move || {
+                        files
+                            .iter()
+                            .map(|file| summarize(file, executor, query))
+                            .collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap_or_default())
+                .collect()
+        })
+    }
+}
+
+fn summarize<E: QueryExecutor>(file: &WorkspaceFile, executor: &E, query: &str) -> FileSummary {
+    let (matches, first, error) = match file.data.execute_query_with(executor, query) {
+        Ok(result) => {
+            let mut outputs = result.into_values().into_iter().filter(|v| !v.is_null());
+            let first = outputs.next();
+            (first.iter().count() + outputs.count(), first, None)
+        }
+        Err(e) => (0, None, Some(e.to_string())),
+    };
+    FileSummary {
+        path: file.path.clone(),
+        matches,
+        first,
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::JaqQueryExecutor;
+    use serde_json::json;
+
+    fn workspace() -> Workspace {
+        Workspace::new(
+            (0..5)
+                .map(|i| WorkspaceFile {
+                    path: format!("logs/{}.json", i),
+                    data: JsonData::new(json!({"ids": (0..i).collect::<Vec<_>>()})),
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn test_run_summarizes_every_file_in_order() {
+        let summaries = workspace().run(&JaqQueryExecutor, ".ids[] | select(. >= 2)");
+        let paths: Vec<&str> = summaries.iter().map(|s| s.path.as_str()).collect();
+        assert_eq!(
+            paths,
+            [
+                "logs/0.json",
+                "logs/1.json",
+                "logs/2.json",
+                "logs/3.json",
+                "logs/4.json"
+            ]
+        );
+        let matches: Vec<usize> = summaries.iter().map(|s| s.matches).collect();
+        assert_eq!(matches, [0, 0, 0, 1, 2]);
+        assert_eq!(summaries[4].first, Some(json!(2)));
+    }
+
+    #[test]
+    fn test_null_is_not_a_match_and_errors_are_per_file() {
+        let summaries = workspace().run(&JaqQueryExecutor, ".missing");
+        assert!(
+            summaries
+                .iter()
+                .all(|s| s.matches == 0 && s.error.is_none())
+        );
+
+        let summaries = workspace().run(&JaqQueryExecutor, "del(");
+        assert!(summaries.iter().all(|s| s.error.is_some()));
+        assert!(Workspace::default().run(&JaqQueryExecutor, ".").is_empty());
+    }
+}
@@ -0,0 +1,307 @@
+use super::table::Table;
+use serde_json::Value;
+
+/// Kind of chart for numeric results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ChartKind {
+    /// Counts of values falling into equal-width bins
+    Histogram,
+    /// Values in order, one column each
+    Sparkline,
+    /// Labelled counts as horizontal bars, largest first
+    Bar,
+}
+
+impl ChartKind {
+    /// Every chart, in the order they are cycled through
+    pub const ALL: [ChartKind; 3] = [ChartKind::Histogram, ChartKind::Sparkline, ChartKind::Bar];
+
+    pub fn title(self) -> &'static str {
+        match self {
+            ChartKind::Histogram => "Histogram",
+            ChartKind::Sparkline => "Sparkline",
+            ChartKind::Bar => "Bar chart",
+        }
+    }
+}
+
+/// Summary statistics of numbers
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+impl Stats {
+    /// `None` for no data
+    pub fn of(data: &[f64]) -> Option<Self> {
+        if data.is_empty() {
+            return None;
+        }
+        let min = data.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = data.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some(Self {
+            count: data.len(),
+            min,
+            max,
+            mean: data.iter().sum::<f64>() / data.len() as f64,
+        })
+    }
+}
+
+/// One bin of a histogram
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bin {
+    pub start: f64,
+    pub end: f64,
+    pub count: u64,
+}
+
+/// Keys giving the labels and values of a bar chart
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BarMapping {
+    pub label: String,
+    pub value: String,
+}
+
+/// Labelled values shown as a bar chart
+#[derive(Debug, Clone, PartialEq)]
+pub struct Counts {
+    /// What the labels are, e.g. the key they were read from
+    pub label: String,
+    /// What the values are
+    pub value: String,
+    /// Label and value pairs, largest value first
+    pub entries: Vec<(String, f64)>,
+}
+
+/// Labelled counts in a result
+///
+/// Recognizes an object of numbers (`{"a": 3, "b": 5}`) and a list of objects
+/// such as `{key, count}` from `group_by`. Without a `mapping` the labels
+/// come from the first key that is a string in every row (or the first key)
+/// and the values from the first other key that is a number in every row.
+pub fn counts(values: &[Value], mapping: Option<&BarMapping>) -> Option<Counts> {
+    if let ([Value::Object(object)], None) = (values, mapping) {
+        let entries = object
+            .iter()
+            .map(|(key, value)| Some((key.clone(), value.as_f64()?)))
+            .collect::<Option<Vec<_>>>()?;
+        return (!entries.is_empty()).then(|| Counts::new("key", "value", entries));
+    }
+
+    let table = Table::from_values(values)?;
+    let (label, value) = match mapping {
+        Some(mapping) => (mapping.label.as_str(), mapping.value.as_str()),
+        None => {
+            let all = |key: &String, test: fn(&Value) -> bool| {
+                table.rows.iter().all(|row| row.get(key).is_some_and(test))
+            };
+            let label = table
+                .keys
+                .iter()
+                .find(|key| all(key, Value::is_string))
+                .or(table.keys.first())?;
+            let value = table
+                .keys
+                .iter()
+                .find(|key| *key != label && all(key, Value::is_number))?;
+            (label.as_str(), value.as_str())
+        }
+    };
+    let entries: Vec<(String, f64)> = (0..table.rows.len())
+        .filter_map(|row| {
+            let number = match table.rows[row].get(value)? {
+                Value::Number(n) => n.as_f64()?,
+                Value::String(s) => s.trim().parse().ok()?,
+                _ => return None,
+            };
+            Some((table.cell(row, label), number))
+        })
+        .collect();
+    (!entries.is_empty()).then(|| Counts::new(label, value, entries))
+}
+
+impl Counts {
+    pub fn new(label: &str, value: &str, mut entries: Vec<(String, f64)>) -> Self {
+        entries.sort_by(|a, b| b.1.total_cmp(&a.1));
+        Self {
+            label: label.to_string(),
+            value: value.to_string(),
+            entries,
+        }
+    }
+}
+
+/// Numbers in a result: an array of numbers, or outputs that are all numbers
+pub fn numbers(values: &[Value]) -> Option<Vec<f64>> {
+    let items = match values {
+        [Value::Array(items)] => items.as_slice(),
+        _ => values,
+    };
+    if items.is_empty() {
+        return None;
+    }
+    items.iter().map(Value::as_f64).collect()
+}
+
+/// Numbers in a table column; numeric strings (as read from CSV) count, empty
+/// and `null` cells are skipped
+pub fn column_numbers(table: &Table, key: &str) -> Option<Vec<f64>> {
+    let mut data = Vec::new();
+    for row in &table.rows {
+        match row.get(key) {
+            None | Some(Value::Null) => {}
+            Some(Value::Number(n)) => data.push(n.as_f64()?),
+            Some(Value::String(s)) => data.push(s.trim().parse().ok()?),
+            Some(_) => return None,
+        }
+    }
+    (!data.is_empty()).then_some(data)
+}
+
+/// Bin count by Sturges' rule
+pub fn default_bins(count: usize) -> usize {
+    (count.max(1) as f64).log2().ceil() as usize + 1
+}
+
+/// Splits the range of `data` into `bins` equal-width bins and counts the
+/// values in each; the last bin includes the maximum
+pub fn histogram(data: &[f64], bins: usize) -> Vec<Bin> {
+    let Some(stats) = Stats::of(data) else {
+        return Vec::new();
+    };
+    let bins = if stats.min == stats.max {
+        1
+    } else {
+        bins.max(1)
+    };
+    let width = (stats.max - stats.min) / bins as f64;
+    let mut result: Vec<Bin> = (0..bins)
+        .map(|i| Bin {
+            start: stats.min + width * i as f64,
+            end: stats.min + width * (i + 1) as f64,
+            count: 0,
+        })
+        .collect();
+    for value in data {
+        let index = if width == 0.0 {
+            0
+        } else {
+            (((value - stats.min) / width) as usize).min(bins - 1)
+        };
+        result[index].count += 1;
+    }
+    result
+}
+
+/// Shrinks `data` to at most `width` points by averaging neighbours
+pub fn resample(data: &[f64], width: usize) -> Vec<f64> {
+    if width == 0 || data.len() <= width {
+        return data.to_vec();
+    }
+    let chunk = data.len().div_ceil(width);
+    data.chunks(chunk)
+        .map(|chunk| chunk.iter().sum::<f64>() / chunk.len() as f64)
+        .collect()
+}
+
+/// Short label for an axis or annotation: integers as-is, others to two decimals
+pub fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+/// Byte count with a binary unit, e.g. `512 B` or `1.5 MiB`
+pub fn format_bytes(bytes: usize) -> String {
+    const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_numbers() {
+        assert_eq!(numbers(&[json!([1, 2.5])]), Some(vec![1.0, 2.5]));
+        assert_eq!(numbers(&[json!(3), json!(4)]), Some(vec![3.0, 4.0]));
+        assert_eq!(numbers(&[json!([1, "2"])]), None);
+        assert_eq!(numbers(&[json!([])]), None);
+
+        let table = Table::from_values(&[json!([{"n": "1"}, {"n": 2}, {"m": 1}])]).unwrap();
+        assert_eq!(column_numbers(&table, "n"), Some(vec![1.0, 2.0]));
+        assert_eq!(column_numbers(&table, "x"), None);
+    }
+
+    #[test]
+    fn test_histogram_and_stats() {
+        let data = [1.0, 2.0, 2.0, 3.0, 9.0];
+        let stats = Stats::of(&data).unwrap();
+        assert_eq!((stats.min, stats.max, stats.mean), (1.0, 9.0, 3.4));
+
+        let bins = histogram(&data, 4);
+        let counts: Vec<u64> = bins.iter().map(|bin| bin.count).collect();
+        assert_eq!(counts, vec![3, 1, 0, 1]);
+        assert_eq!((bins[0].start, bins[3].end), (1.0, 9.0));
+
+        // A constant series is one bin
+        assert_eq!(histogram(&[5.0, 5.0], 4).len(), 1);
+        assert_eq!(default_bins(5), 4);
+    }
+
+    #[test]
+    fn test_counts_detection() {
+        let object = counts(&[json!({"a": 3, "b": 5})], None).unwrap();
+        assert_eq!(
+            object.entries,
+            vec![("b".to_string(), 5.0), ("a".to_string(), 3.0)]
+        );
+
+        let grouped = [json!([
+            {"key": "timeout", "count": 2, "first": null},
+            {"key": "refused", "count": 7, "first": "x"}
+        ])];
+        let detected = counts(&grouped, None).unwrap();
+        assert_eq!(
+            (detected.label.as_str(), detected.value.as_str()),
+            ("key", "count")
+        );
+        assert_eq!(detected.entries[0], ("refused".to_string(), 7.0));
+
+        let mapping = BarMapping {
+            label: "first".to_string(),
+            value: "count".to_string(),
+        };
+        let mapped = counts(&grouped, Some(&mapping)).unwrap();
+        assert_eq!(mapped.entries[1], ("null".to_string(), 2.0));
+
+        assert!(counts(&[json!({"a": "x"})], None).is_none());
+        assert!(counts(&[json!([{"a": "x"}])], None).is_none());
+    }
+
+    #[test]
+    fn test_resample_and_labels() {
+        assert_eq!(resample(&[1.0, 3.0, 5.0, 7.0, 9.0], 3), vec![2.0, 6.0, 9.0]);
+        assert_eq!(format_number(3.0), "3");
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_bytes(1536 * 1024), "1.5 MiB");
+        assert_eq!(format_number(2.345), "2.35");
+    }
+}
@@ -0,0 +1,162 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Color used for highlighting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RuleColor {
+    Red,
+    Green,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    Gray,
+    White,
+}
+
+/// Look of lines matching a rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RuleStyle {
+    pub color: Option<RuleColor>,
+    pub bold: bool,
+    pub dim: bool,
+    pub underline: bool,
+}
+
+/// Highlight rule from the config file
+///
+/// `key = "level", equals = "error", color = "red"` shows `"level": "error"`
+/// in red; without `equals` every value under a matching key is styled.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct HighlightRule {
+    /// Key pattern, where `*` matches any run of characters, e.g. `*_id`
+    pub key: String,
+    /// Only values equal to this one match
+    #[serde(default)]
+    pub equals: Option<Value>,
+    #[serde(default)]
+    pub color: Option<RuleColor>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub dim: bool,
+    #[serde(default)]
+    pub underline: bool,
+}
+
+impl HighlightRule {
+    pub fn style(&self) -> RuleStyle {
+        RuleStyle {
+            color: self.color,
+            bold: self.bold,
+            dim: self.dim,
+            underline: self.underline,
+        }
+    }
+}
+
+/// Rule highlighting table rows by a condition
+///
+/// `when = ".status >= 500", color = "red"` shows every table row whose
+/// object makes the jq predicate true in red.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RowRule {
+    /// jq predicate run on each row's object
+    pub when: String,
+    #[serde(default)]
+    pub color: Option<RuleColor>,
+    #[serde(default)]
+    pub bold: bool,
+    #[serde(default)]
+    pub dim: bool,
+    #[serde(default)]
+    pub underline: bool,
+}
+
+impl RowRule {
+    pub fn style(&self) -> RuleStyle {
+        RuleStyle {
+            color: self.color,
+            bold: self.bold,
+            dim: self.dim,
+            underline: self.underline,
+        }
+    }
+}
+
+/// List of highlight rules
+///
+/// Rules are tried in the order they are written and the first one matching
+/// the nearest key above a value, and its value when it has `equals`, wins.
+#[derive(Debug, Clone, Default)]
+pub struct HighlightRules {
+    rules: Vec<(glob::Pattern, Option<Value>, RuleStyle)>,
+}
+
+impl HighlightRules {
+    pub fn new(rules: &[HighlightRule]) -> Result<Self, String> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                glob::Pattern::new(&rule.key)
+                    .map(|pattern| (pattern, rule.equals.clone(), rule.style()))
+                    .map_err(|e| format!("invalid key pattern `{}`: {}", rule.key, e))
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The style of `value` under `key`
+    pub fn style(&self, key: &str, value: &Value) -> Option<RuleStyle> {
+        self.rules
+            .iter()
+            .find(|(pattern, equals, _)| {
+                pattern.matches(key) && equals.as_ref().is_none_or(|equals| equals == value)
+            })
+            .map(|(_, _, style)| *style)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn rule(key: &str, equals: Option<Value>, color: RuleColor) -> HighlightRule {
+        HighlightRule {
+            key: key.to_string(),
+            equals,
+            color: Some(color),
+            bold: false,
+            dim: false,
+            underline: false,
+        }
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let rules = HighlightRules::new(&[
+            rule("level", Some(json!("error")), RuleColor::Red),
+            rule("level", None, RuleColor::Gray),
+            rule("*_id", None, RuleColor::Cyan),
+        ])
+        .unwrap();
+        let color = |key, value| rules.style(key, &value).and_then(|style| style.color);
+        assert_eq!(color("level", json!("error")), Some(RuleColor::Red));
+        assert_eq!(color("level", json!("info")), Some(RuleColor::Gray));
+        assert_eq!(color("user_id", json!(7)), Some(RuleColor::Cyan));
+        assert_eq!(color("name", json!("error")), None);
+    }
+
+    #[test]
+    fn test_invalid_pattern() {
+        assert!(HighlightRules::new(&[rule("[", None, RuleColor::Red)]).is_err());
+    }
+}
@@ -0,0 +1,13 @@
+pub mod chart;
+pub mod highlight;
+pub mod numbers;
+pub mod row_filter;
+pub mod table;
+pub mod tree;
+
+pub use chart::{BarMapping, Bin, ChartKind, Counts, Stats};
+pub use highlight::{HighlightRule, HighlightRules, RowRule, RuleColor, RuleStyle};
+pub use numbers::{NumberFormat, NumberFormats};
+pub use row_filter::{RowCount, RowFilter};
+pub use table::{ColumnSpec, Table, TableLayout, TableQuery, TableSort};
+pub use tree::{NodeKind, NodeState, PathSegment, RenderTree, RenderedLine, jq_path};
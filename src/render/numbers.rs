@@ -0,0 +1,146 @@
+use super::chart::format_bytes;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// How a number is shown
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum NumberFormat {
+    /// A byte count shown as `1.5 MiB`
+    Bytes,
+    /// Milliseconds shown as seconds, `1250` as `1.25s`
+    Millis,
+    /// A fixed number of decimal places, written `decimals:2`
+    Decimals(usize),
+}
+
+impl NumberFormat {
+    /// `None` when the number can't be shown this way, e.g. negative bytes
+    pub fn apply(self, n: f64) -> Option<String> {
+        if !n.is_finite() {
+            return None;
+        }
+        match self {
+            Self::Bytes if n >= 0.0 && n.fract() == 0.0 => Some(format_bytes(n as usize)),
+            Self::Bytes => None,
+            Self::Millis => {
+                let seconds = format!("{:.3}", n / 1000.0);
+                let seconds = seconds.trim_end_matches('0').trim_end_matches('.');
+                Some(format!("{}s", seconds))
+            }
+            Self::Decimals(places) => Some(format!("{:.*}", places, n)),
+        }
+    }
+}
+
+impl TryFrom<String> for NumberFormat {
+    type Error = String;
+
+    fn try_from(spec: String) -> Result<Self, String> {
+        match spec.as_str() {
+            "bytes" => Ok(Self::Bytes),
+            "ms" => Ok(Self::Millis),
+            _ => spec
+                .strip_prefix("decimals:")
+                .and_then(|places| places.parse().ok())
+                .filter(|places| *places <= 20)
+                .map(Self::Decimals)
+                .ok_or_else(|| {
+                    format!(
+                        "unknown number format `{}` (expected `bytes`, `ms` or `decimals:N`)",
+                        spec
+                    )
+                }),
+        }
+    }
+}
+
+/// Number formats by key pattern
+///
+/// Patterns are matched against the nearest key above a number, so
+/// `latency_ms = "ms"` also covers the elements of a `latency_ms` array.
+/// `*` matches any run of characters; when several patterns match, the
+/// longest one wins, so `price` beats `*`.
+#[derive(Debug, Clone, Default)]
+pub struct NumberFormats {
+    rules: Vec<(glob::Pattern, NumberFormat)>,
+}
+
+impl NumberFormats {
+    pub fn new(rules: &BTreeMap<String, NumberFormat>) -> Result<Self, String> {
+        let mut rules = rules
+            .iter()
+            .map(|(pattern, format)| {
+                glob::Pattern::new(pattern)
+                    .map(|pattern| (pattern, *format))
+                    .map_err(|e| format!("invalid key pattern `{}`: {}", pattern, e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        rules.sort_by_key(|(pattern, _)| std::cmp::Reverse(pattern.as_str().len()));
+        Ok(Self { rules })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    /// The format of numbers under `key`
+    pub fn lookup(&self, key: &str) -> Option<NumberFormat> {
+        self.rules
+            .iter()
+            .find(|(pattern, _)| pattern.matches(key))
+            .map(|(_, format)| *format)
+    }
+
+    /// `number`, a JSON number under `key`, as the matching rule shows it
+    pub fn format(&self, key: &str, number: &str) -> Option<String> {
+        self.lookup(key)?.apply(number.parse().ok()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formats(rules: &[(&str, &str)]) -> NumberFormats {
+        let rules = rules
+            .iter()
+            .map(|(k, v)| {
+                (
+                    k.to_string(),
+                    NumberFormat::try_from(v.to_string()).unwrap(),
+                )
+            })
+            .collect();
+        NumberFormats::new(&rules).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        let formats = formats(&[
+            ("*_bytes", "bytes"),
+            ("*_ms", "ms"),
+            ("price", "decimals:2"),
+        ]);
+        assert_eq!(formats.format("body_bytes", "1536"), Some("1.5 KiB".into()));
+        assert_eq!(formats.format("latency_ms", "1250"), Some("1.25s".into()));
+        assert_eq!(formats.format("latency_ms", "3000"), Some("3s".into()));
+        assert_eq!(formats.format("price", "4.5"), Some("4.50".into()));
+        assert_eq!(formats.format("unit_price", "4.5"), None);
+        // Values the format can't show stay as they are
+        assert_eq!(formats.format("body_bytes", "-1"), None);
+    }
+
+    #[test]
+    fn test_longest_pattern_wins() {
+        let formats = formats(&[("*", "decimals:1"), ("price", "decimals:2")]);
+        assert_eq!(formats.lookup("price"), Some(NumberFormat::Decimals(2)));
+        assert_eq!(formats.lookup("total"), Some(NumberFormat::Decimals(1)));
+    }
+
+    #[test]
+    fn test_invalid_spec() {
+        assert!(NumberFormat::try_from("hex".to_string()).is_err());
+        assert!(NumberFormat::try_from("decimals:x".to_string()).is_err());
+    }
+}
@@ -0,0 +1,119 @@
+use regex::Regex;
+use serde_json::Value;
+
+/// Condition narrowing the rows of a result
+///
+/// Plain text matches rows containing it, ignoring case; text written as
+/// `/pattern/` is a regular expression. A row is an element of an array
+/// result or, when the query outputs several values, one of the outputs.
+/// String rows, such as log lines, are matched as they are and other rows
+/// by their compact JSON text.
+#[derive(Debug, Clone)]
+pub enum RowFilter {
+    /// Lowercased text to look for
+    Text(String),
+    Regex(Regex),
+}
+
+/// Row counts before and after filtering
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RowCount {
+    pub shown: usize,
+    pub total: usize,
+}
+
+impl RowFilter {
+    /// `None` for empty text, which keeps every row
+    pub fn parse(text: &str) -> Result<Option<Self>, String> {
+        if text.is_empty() {
+            return Ok(None);
+        }
+        match text
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(pattern) => Regex::new(pattern)
+                .map(|regex| Some(Self::Regex(regex)))
+                .map_err(|e| e.to_string()),
+            None => Ok(Some(Self::Text(text.to_lowercase()))),
+        }
+    }
+
+    pub fn matches(&self, row: &Value) -> bool {
+        let text = match row {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        match self {
+            Self::Text(needle) => text.to_lowercase().contains(needle),
+            Self::Regex(regex) => regex.is_match(&text),
+        }
+    }
+
+    /// The outputs with only their matching rows
+    ///
+    /// A single array output keeps its matching elements; several outputs
+    /// are the rows themselves. A single value that is not an array passes
+    /// through, as there is nothing to narrow.
+    pub fn apply(&self, values: Vec<Value>) -> (Vec<Value>, RowCount) {
+        match <[Value; 1]>::try_from(values) {
+            Ok([Value::Array(rows)]) => {
+                let total = rows.len();
+                let rows: Vec<Value> = rows.into_iter().filter(|row| self.matches(row)).collect();
+                let count = RowCount {
+                    shown: rows.len(),
+                    total,
+                };
+                (vec![Value::Array(rows)], count)
+            }
+            Ok([value]) => (vec![value], RowCount { shown: 1, total: 1 }),
+            Err(values) => {
+                let total = values.len();
+                let rows: Vec<Value> = values.into_iter().filter(|row| self.matches(row)).collect();
+                let count = RowCount {
+                    shown: rows.len(),
+                    total,
+                };
+                (rows, count)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_text_and_regex_filters() {
+        let logs = json!([
+            "INFO started",
+            "ERROR disk full",
+            {"level": "error", "msg": "timeout"},
+            {"level": "info", "msg": "ok"}
+        ]);
+        let text = RowFilter::parse("error").unwrap().unwrap();
+        let (kept, count) = text.apply(vec![logs.clone()]);
+        assert_eq!(
+            kept,
+            vec![json!(["ERROR disk full", {"level": "error", "msg": "timeout"}])]
+        );
+        assert_eq!(count, RowCount { shown: 2, total: 4 });
+
+        let regex = RowFilter::parse("/^[A-Z]+ /").unwrap().unwrap();
+        let (kept, _) = regex.apply(vec![logs]);
+        assert_eq!(kept, vec![json!(["INFO started", "ERROR disk full"])]);
+
+        assert!(RowFilter::parse("").unwrap().is_none());
+        assert!(RowFilter::parse("/(/").is_err());
+    }
+
+    #[test]
+    fn test_several_outputs_are_rows() {
+        let filter = RowFilter::parse("b").unwrap().unwrap();
+        let (kept, count) = filter.apply(vec![json!("a"), json!("b"), json!("ab")]);
+        assert_eq!(kept, vec![json!("b"), json!("ab")]);
+        assert_eq!(count, RowCount { shown: 2, total: 3 });
+    }
+}
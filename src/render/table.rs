@@ -0,0 +1,306 @@
+use super::tree::field;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Widest an automatically sized column grows
+pub const MAX_AUTO_WIDTH: u16 = 30;
+
+/// Narrowest a column can be resized to
+pub const MIN_WIDTH: u16 = 3;
+
+/// Settings of one table column
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ColumnSpec {
+    pub key: String,
+    pub visible: bool,
+    /// Fixed width in cells; `None` fits the content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub width: Option<u16>,
+}
+
+/// Selection, order and width of columns
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TableLayout {
+    pub columns: Vec<ColumnSpec>,
+}
+
+impl TableLayout {
+    /// Visible columns in display order
+    pub fn visible(&self) -> impl Iterator<Item = &ColumnSpec> {
+        self.columns.iter().filter(|column| column.visible)
+    }
+
+    /// Keeps the saved order for keys the table still has, drops the rest and
+    /// appends new keys as visible columns
+    pub fn reconcile(mut self, table: &Table) -> Self {
+        self.columns
+            .retain(|column| table.keys.contains(&column.key));
+        for key in &table.keys {
+            if !self.columns.iter().any(|column| &column.key == key) {
+                self.columns.push(ColumnSpec {
+                    key: key.clone(),
+                    visible: true,
+                    width: None,
+                });
+            }
+        }
+        self
+    }
+
+    pub fn toggle(&mut self, index: usize) {
+        if let Some(column) = self.columns.get_mut(index) {
+            column.visible = !column.visible;
+        }
+    }
+
+    /// Swaps column `index` with its neighbour, returning the column's new index
+    pub fn move_column(&mut self, index: usize, up: bool) -> usize {
+        let target = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&i| i < self.columns.len())
+        };
+        match target {
+            Some(target) if index < self.columns.len() => {
+                self.columns.swap(index, target);
+                target
+            }
+            _ => index,
+        }
+    }
+
+    /// Widens or narrows a column, starting from `current` when it is auto-sized
+    pub fn resize(&mut self, index: usize, delta: i32, current: u16) {
+        if let Some(column) = self.columns.get_mut(index) {
+            let width = column.width.unwrap_or(current) as i32 + delta;
+            column.width = Some(width.clamp(MIN_WIDTH as i32, u16::MAX as i32) as u16);
+        }
+    }
+}
+
+/// Sort order of a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSort {
+    pub key: String,
+    pub descending: bool,
+}
+
+/// Sort and filter of a table
+///
+/// Applied by running the jq from [`TableQuery::to_jq`] on the rows, so the
+/// same view can be reproduced outside rjq.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableQuery {
+    pub sort: Option<TableSort>,
+    /// Rows are kept when a visible cell contains this text, ignoring case
+    pub filter: String,
+}
+
+impl TableQuery {
+    pub fn is_empty(&self) -> bool {
+        self.sort.is_none() && self.filter.is_empty()
+    }
+
+    /// Sorts by `key` ascending, then descending, then not at all; another key
+    /// starts over ascending
+    pub fn cycle_sort(&mut self, key: &str) {
+        self.sort = match self.sort.take() {
+            Some(sort) if sort.key == key && !sort.descending => Some(TableSort {
+                descending: true,
+                ..sort
+            }),
+            Some(sort) if sort.key == key => None,
+            _ => Some(TableSort {
+                key: key.to_string(),
+                descending: false,
+            }),
+        };
+    }
+
+    /// jq that filters and sorts an array of rows, searching the `columns`;
+    /// `None` when the rows are shown as they are
+    pub fn to_jq(&self, columns: &[&str]) -> Option<String> {
+        let mut steps = Vec::new();
+        if !self.filter.is_empty() && !columns.is_empty() {
+            let cells: Vec<String> = columns.iter().map(|key| field(key)).collect();
+            let needle = serde_json::to_string(&self.filter.to_lowercase()).unwrap_or_default();
+            steps.push(format!(
+                "map(select(any({}; tostring | ascii_downcase | contains({}))))",
+                cells.join(", "),
+                needle
+            ));
+        }
+        if let Some(sort) = &self.sort {
+            steps.push(format!("sort_by({})", field(&sort.key)));
+            if sort.descending {
+                steps.push("reverse".to_string());
+            }
+        }
+        (!steps.is_empty()).then(|| steps.join(" | "))
+    }
+}
+
+/// Result that can be shown as a table (a list of objects)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Table {
+    /// Every key that appears in a row, in first-seen order
+    pub keys: Vec<String>,
+    pub rows: Vec<Map<String, Value>>,
+}
+
+impl Table {
+    /// A table from a list of objects, or from a single array of objects
+    ///
+    /// Returns `None` when any row is not an object or there are no rows.
+    pub fn from_values(values: &[Value]) -> Option<Self> {
+        let rows = match values {
+            [Value::Array(items)] => items.as_slice(),
+            _ => values,
+        };
+        let rows: Vec<Map<String, Value>> = rows
+            .iter()
+            .map(|row| row.as_object().cloned())
+            .collect::<Option<_>>()?;
+        if rows.is_empty() {
+            return None;
+        }
+        let mut keys: Vec<String> = Vec::new();
+        for row in &rows {
+            for key in row.keys() {
+                if !keys.contains(key) {
+                    keys.push(key.clone());
+                }
+            }
+        }
+        Some(Self { keys, rows })
+    }
+
+    /// Identifies the shape of the data: tables with the same set of keys
+    /// share a fingerprint, whatever their values
+    pub fn fingerprint(&self) -> String {
+        let mut keys: Vec<&str> = self.keys.iter().map(String::as_str).collect();
+        keys.sort_unstable();
+        crate::config::trust::content_hash(&keys.join("\n"))
+    }
+
+    /// All columns visible and sized to fit
+    pub fn default_layout(&self) -> TableLayout {
+        TableLayout::default().reconcile(self)
+    }
+
+    /// Cell text: strings as-is, other values as compact JSON, missing as empty
+    pub fn cell(&self, row: usize, key: &str) -> String {
+        match self.rows.get(row).and_then(|row| row.get(key)) {
+            None => String::new(),
+            Some(Value::String(s)) => s.clone(),
+            Some(other) => other.to_string(),
+        }
+    }
+
+    /// Width that fits the header and every cell, up to [`MAX_AUTO_WIDTH`]
+    pub fn auto_width(&self, key: &str) -> u16 {
+        let widest = (0..self.rows.len())
+            .map(|row| self.cell(row, key).chars().count())
+            .chain(std::iter::once(key.chars().count()))
+            .max()
+            .unwrap_or(0);
+        (widest as u16).clamp(MIN_WIDTH, MAX_AUTO_WIDTH)
+    }
+
+    /// Display width of a column under `spec`
+    pub fn width(&self, spec: &ColumnSpec) -> u16 {
+        spec.width.unwrap_or_else(|| self.auto_width(&spec.key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn table() -> Table {
+        Table::from_values(&[json!([
+            {"id": 1, "name": "alice", "tags": ["a"]},
+            {"id": 2, "email": "bob@example.com"}
+        ])])
+        .unwrap()
+    }
+
+    #[test]
+    fn test_from_values() {
+        let table = table();
+        assert_eq!(table.keys, vec!["id", "name", "tags", "email"]);
+        assert_eq!(table.cell(0, "name"), "alice");
+        assert_eq!(table.cell(0, "tags"), "[\"a\"]");
+        assert_eq!(table.cell(1, "name"), "");
+
+        let outputs = [json!({"a": 1}), json!({"a": 2})];
+        assert_eq!(Table::from_values(&outputs).unwrap().rows.len(), 2);
+        assert!(Table::from_values(&[json!([1, 2])]).is_none());
+        assert!(Table::from_values(&[json!([])]).is_none());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_values_and_order() {
+        let other =
+            Table::from_values(&[json!({"email": "x", "tags": [], "name": "y", "id": 9})]).unwrap();
+        assert_eq!(table().fingerprint(), other.fingerprint());
+        let different = Table::from_values(&[json!({"id": 1})]).unwrap();
+        assert_ne!(table().fingerprint(), different.fingerprint());
+    }
+
+    #[test]
+    fn test_layout_editing() {
+        let table = table();
+        let mut layout = table.default_layout();
+        layout.toggle(1);
+        assert_eq!(layout.move_column(3, true), 2);
+        assert_eq!(layout.move_column(0, true), 0);
+        layout.resize(0, -10, table.auto_width("id"));
+        let visible: Vec<&str> = layout.visible().map(|c| c.key.as_str()).collect();
+        assert_eq!(visible, vec!["id", "email", "tags"]);
+        assert_eq!(layout.columns[0].width, Some(MIN_WIDTH));
+    }
+
+    #[test]
+    fn test_table_query_to_jq() {
+        let mut query = TableQuery::default();
+        assert_eq!(query.to_jq(&["id"]), None);
+
+        query.cycle_sort("first name");
+        assert_eq!(query.to_jq(&[]).unwrap(), "sort_by(.\"first name\")");
+        query.cycle_sort("first name");
+        query.filter = "Ali".to_string();
+        assert_eq!(
+            query.to_jq(&["id", "name"]).unwrap(),
+            "map(select(any(.id, .name; tostring | ascii_downcase | contains(\"ali\")))) \
+             | sort_by(.\"first name\") | reverse"
+        );
+        query.cycle_sort("first name");
+        assert!(query.sort.is_none());
+        query.cycle_sort("id");
+        assert_eq!(query.sort.as_ref().map(|s| s.key.as_str()), Some("id"));
+    }
+
+    #[test]
+    fn test_reconcile_keeps_saved_order() {
+        let saved = TableLayout {
+            columns: vec![
+                ColumnSpec {
+                    key: "name".to_string(),
+                    visible: true,
+                    width: Some(8),
+                },
+                ColumnSpec {
+                    key: "gone".to_string(),
+                    visible: true,
+                    width: None,
+                },
+            ],
+        };
+        let layout = saved.reconcile(&table());
+        let keys: Vec<&str> = layout.columns.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(keys, vec!["name", "id", "tags", "email"]);
+        assert_eq!(layout.columns[0].width, Some(8));
+    }
+}
@@ -0,0 +1,819 @@
+use super::{HighlightRules, NumberFormats, RuleStyle};
+use crate::query::QueryResult;
+use serde_json::Value;
+
+/// Indentation per nesting level, matching `serde_json::to_string_pretty`
+const INDENT: &str = "  ";
+
+/// One element of a path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Null,
+    Bool,
+    Number,
+    String,
+    Array,
+    Object,
+}
+
+/// Display state of a node
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeState {
+    /// A container shown on one line as `{…}` / `[…]`
+    pub folded: bool,
+    /// A string shown cut off at [`RenderTree::truncate_strings`]'s limit
+    pub truncated: bool,
+    /// Marked by a search
+    pub highlighted: bool,
+    /// A container that fits [`RenderTree::set_line_width`]'s width, shown
+    /// whole on one line
+    pub inline: bool,
+}
+
+/// One value in a result
+#[derive(Debug, Clone)]
+pub struct Node {
+    pub path: Vec<PathSegment>,
+    pub kind: NodeKind,
+    pub parent: Option<usize>,
+    /// Number of direct children of a container
+    pub len: usize,
+    /// First line of the node in the unfolded text
+    pub first_line: usize,
+    /// Last line of the node in the unfolded text; the closing bracket of a container
+    pub last_line: usize,
+    pub state: NodeState,
+}
+
+impl Node {
+    /// Only non-empty containers span more than one line and can be folded
+    pub fn is_foldable(&self) -> bool {
+        self.first_line != self.last_line
+    }
+}
+
+/// One line of the unfolded text
+#[derive(Debug, Clone)]
+struct SourceLine {
+    node: usize,
+    depth: usize,
+    /// `"key": ` when the node is an object member
+    key: Option<String>,
+    /// Scalar as JSON, an opening bracket, or a closing bracket
+    body: String,
+    /// A number as [`RenderTree::format_numbers`] shows it
+    formatted: Option<String>,
+    /// Set by [`RenderTree::apply_highlight_rules`]
+    style: Option<RuleStyle>,
+    comma: bool,
+    closing: bool,
+}
+
+impl SourceLine {
+    fn shown(&self) -> &str {
+        self.formatted.as_deref().unwrap_or(&self.body)
+    }
+}
+
+/// A rendered line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RenderedLine {
+    pub text: String,
+    /// Innermost node on the line; the container itself for a closing bracket
+    pub node: usize,
+    pub highlighted: bool,
+    /// Style of the highlight rule matching a scalar on its own line
+    pub style: Option<RuleStyle>,
+}
+
+/// Structured display model of a query result
+///
+/// Built once per execution. Line rendering, search, folding and path lookup
+/// all work on the nodes, so nothing re-parses pretty-printed text. With no
+/// node folded or truncated and no line width set, the text is exactly
+/// `serde_json::to_string_pretty`.
+#[derive(Debug, Clone)]
+pub struct RenderTree {
+    nodes: Vec<Node>,
+    source: Vec<SourceLine>,
+    rendered: Vec<RenderedLine>,
+    truncate_at: Option<usize>,
+    line_width: Option<usize>,
+}
+
+impl RenderTree {
+    pub fn from_value(value: &Value) -> Self {
+        let mut tree = Self::empty();
+        tree.push_value(value, Vec::new(), None, None, 0, false);
+        tree.relayout();
+        tree
+    }
+
+    /// Several outputs are shown as one array, like [`QueryResult::format_pretty`]
+    pub fn from_result(result: &QueryResult) -> Self {
+        match result {
+            QueryResult::Single(value) => Self::from_value(value),
+            QueryResult::Partial { values, .. } if values.len() == 1 => {
+                Self::from_value(&values[0])
+            }
+            QueryResult::Multiple(values) | QueryResult::Partial { values, .. } => {
+                let mut tree = Self::empty();
+                tree.push_array(values, Vec::new(), None, None, 0, false);
+                tree.relayout();
+                tree
+            }
+            QueryResult::Empty => Self::from_value(&Value::Null),
+        }
+    }
+
+    fn empty() -> Self {
+        Self {
+            nodes: Vec::new(),
+            source: Vec::new(),
+            rendered: Vec::new(),
+            truncate_at: None,
+            line_width: None,
+        }
+    }
+
+    fn push_node(
+        &mut self,
+        path: Vec<PathSegment>,
+        kind: NodeKind,
+        parent: Option<usize>,
+        len: usize,
+    ) -> usize {
+        let line = self.source.len();
+        self.nodes.push(Node {
+            path,
+            kind,
+            parent,
+            len,
+            first_line: line,
+            last_line: line,
+            state: NodeState::default(),
+        });
+        self.nodes.len() - 1
+    }
+
+    fn push_line(&mut self, node: usize, depth: usize, key: Option<&str>, body: String) {
+        self.source.push(SourceLine {
+            node,
+            depth,
+            key: key.map(|key| format!("{}: ", quote(key))),
+            body,
+            formatted: None,
+            style: None,
+            comma: false,
+            closing: false,
+        });
+    }
+
+    /// Adds `value` and its descendants; `comma` is set when a sibling follows
+    fn push_value(
+        &mut self,
+        value: &Value,
+        path: Vec<PathSegment>,
+        parent: Option<usize>,
+        key: Option<&str>,
+        depth: usize,
+        comma: bool,
+    ) {
+        match value {
+            Value::Array(items) => self.push_array(items, path, parent, key, depth, comma),
+            Value::Object(map) => {
+                let node = self.push_node(path.clone(), NodeKind::Object, parent, map.len());
+                if map.is_empty() {
+                    self.push_line(node, depth, key, "{}".to_string());
+                } else {
+                    self.push_line(node, depth, key, "{".to_string());
+                    for (i, (child_key, child)) in map.iter().enumerate() {
+                        let mut child_path = path.clone();
+                        child_path.push(PathSegment::Key(child_key.clone()));
+                        let comma = i + 1 < map.len();
+                        self.push_value(
+                            child,
+                            child_path,
+                            Some(node),
+                            Some(child_key),
+                            depth + 1,
+                            comma,
+                        );
+                    }
+                    self.close(node, depth, "}");
+                }
+            }
+            scalar => {
+                let kind = match scalar {
+                    Value::Null => NodeKind::Null,
+                    Value::Bool(_) => NodeKind::Bool,
+                    Value::Number(_) => NodeKind::Number,
+                    _ => NodeKind::String,
+                };
+                let node = self.push_node(path, kind, parent, 0);
+                let body = serde_json::to_string(scalar).unwrap_or_default();
+                self.push_line(node, depth, key, body);
+            }
+        }
+        if let Some(line) = self.source.last_mut() {
+            line.comma = comma;
+        }
+    }
+
+    fn push_array(
+        &mut self,
+        items: &[Value],
+        path: Vec<PathSegment>,
+        parent: Option<usize>,
+        key: Option<&str>,
+        depth: usize,
+        comma: bool,
+    ) {
+        let node = self.push_node(path.clone(), NodeKind::Array, parent, items.len());
+        if items.is_empty() {
+            self.push_line(node, depth, key, "[]".to_string());
+        } else {
+            self.push_line(node, depth, key, "[".to_string());
+            for (i, item) in items.iter().enumerate() {
+                let mut child_path = path.clone();
+                child_path.push(PathSegment::Index(i));
+                self.push_value(
+                    item,
+                    child_path,
+                    Some(node),
+                    None,
+                    depth + 1,
+                    i + 1 < items.len(),
+                );
+            }
+            self.close(node, depth, "]");
+        }
+        if let Some(line) = self.source.last_mut() {
+            line.comma = comma;
+        }
+    }
+
+    fn close(&mut self, node: usize, depth: usize, bracket: &str) {
+        self.nodes[node].last_line = self.source.len();
+        self.push_line(node, depth, None, bracket.to_string());
+        if let Some(line) = self.source.last_mut() {
+            line.closing = true;
+        }
+    }
+
+    /// Recomputes the visible lines after a display state changed
+    fn relayout(&mut self) {
+        for node in &mut self.nodes {
+            node.state.inline = false;
+        }
+        let mut rendered = Vec::with_capacity(self.source.len());
+        let mut i = 0;
+        while i < self.source.len() {
+            let line = &self.source[i];
+            let node = &self.nodes[line.node];
+            let mut text = INDENT.repeat(line.depth);
+            if let Some(key) = &line.key {
+                text.push_str(key);
+            }
+            let mut comma = line.comma;
+            let inline = self.line_width.and_then(|width| {
+                let comma = self.source[node.last_line].comma as usize;
+                let budget = width.checked_sub(text.chars().count() + comma)?;
+                self.inline_text(line.node, budget)
+            });
+            if let Some(inline) = inline.filter(|_| !line.closing) {
+                let (first, last) = (node.first_line, node.last_line);
+                let highlighted = self.source[first..=last]
+                    .iter()
+                    .any(|line| self.nodes[line.node].state.highlighted);
+                text.push_str(&inline);
+                if self.source[last].comma {
+                    text.push(',');
+                }
+                rendered.push(RenderedLine {
+                    text,
+                    node: line.node,
+                    highlighted,
+                    style: None,
+                });
+                self.nodes[self.source[i].node].state.inline = true;
+                i = last + 1;
+                continue;
+            }
+            if node.state.folded && !line.closing {
+                text.push_str(match node.kind {
+                    NodeKind::Object => "{…}",
+                    _ => "[…]",
+                });
+                comma = self.source[node.last_line].comma;
+                i = node.last_line;
+            } else if node.state.truncated {
+                text.push_str(&truncate(
+                    &line.body,
+                    self.truncate_at.unwrap_or(usize::MAX),
+                ));
+            } else {
+                text.push_str(line.shown());
+            }
+            if comma {
+                text.push(',');
+            }
+            rendered.push(RenderedLine {
+                text,
+                node: line.node,
+                highlighted: node.state.highlighted,
+                style: line.style,
+            });
+            i += 1;
+        }
+        self.rendered = rendered;
+    }
+
+    /// An unfolded container written on one line, e.g. `{"a": 1, "b": [2]}`,
+    /// when that takes at most `budget` characters
+    fn inline_text(&self, id: usize, budget: usize) -> Option<String> {
+        let node = &self.nodes[id];
+        // Every line adds at least a character
+        if !node.is_foldable() || node.state.folded || node.last_line - node.first_line >= budget {
+            return None;
+        }
+        let mut text = String::new();
+        let mut j = node.first_line;
+        while j <= node.last_line {
+            let line = &self.source[j];
+            let inner = &self.nodes[line.node];
+            if j > node.first_line
+                && let Some(key) = &line.key
+            {
+                text.push_str(key);
+            }
+            let mut comma = line.comma;
+            if inner.state.folded && !line.closing && j > node.first_line {
+                text.push_str(match inner.kind {
+                    NodeKind::Object => "{…}",
+                    _ => "[…]",
+                });
+                comma = self.source[inner.last_line].comma;
+                j = inner.last_line;
+            } else if inner.state.truncated {
+                text.push_str(&truncate(
+                    &line.body,
+                    self.truncate_at.unwrap_or(usize::MAX),
+                ));
+            } else {
+                text.push_str(line.shown());
+            }
+            if comma && j < node.last_line {
+                text.push_str(", ");
+            }
+            if text.chars().count() > budget {
+                return None;
+            }
+            j += 1;
+        }
+        Some(text)
+    }
+
+    /// Shows containers that fit in `width` characters, indentation
+    /// included, on one line; `None` gives every value a line of its own
+    pub fn set_line_width(&mut self, width: Option<usize>) {
+        self.line_width = width;
+        self.relayout();
+    }
+
+    pub fn line_width(&self) -> Option<usize> {
+        self.line_width
+    }
+
+    pub fn nodes(&self) -> &[Node] {
+        &self.nodes
+    }
+
+    pub fn node(&self, id: usize) -> Option<&Node> {
+        self.nodes.get(id)
+    }
+
+    /// The visible lines, with folded containers collapsed
+    pub fn lines(&self) -> &[RenderedLine] {
+        &self.rendered
+    }
+
+    pub fn line_count(&self) -> usize {
+        self.rendered.len()
+    }
+
+    pub fn to_text(&self) -> String {
+        self.rendered
+            .iter()
+            .map(|line| line.text.as_str())
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Node shown on visible line `line`
+    pub fn node_at_line(&self, line: usize) -> Option<usize> {
+        self.rendered.get(line).map(|line| line.node)
+    }
+
+    /// Visible line a node starts on, or `None` while a folded ancestor hides it
+    ///
+    /// A value inside a container shown on one line is on that line.
+    pub fn line_of(&self, id: usize) -> Option<usize> {
+        if self.ancestors(id).any(|a| self.nodes[a].state.folded) {
+            return None;
+        }
+        let shown = self
+            .ancestors(id)
+            .filter(|&a| self.nodes[a].state.inline)
+            .last()
+            .unwrap_or(id);
+        self.rendered.iter().position(|line| line.node == shown)
+    }
+
+    fn ancestors(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        std::iter::successors(self.nodes.get(id).and_then(|n| n.parent), |&a| {
+            self.nodes[a].parent
+        })
+    }
+
+    /// jq path of a node, e.g. `.users[0]."first name"`
+    pub fn path_string(&self, id: usize) -> String {
+        self.nodes
+            .get(id)
+            .map_or_else(String::new, |node| jq_path(&node.path))
+    }
+
+    /// The value of a scalar node; `None` for containers
+    pub fn scalar(&self, id: usize) -> Option<Value> {
+        let node = self.nodes.get(id)?;
+        if matches!(node.kind, NodeKind::Array | NodeKind::Object) {
+            return None;
+        }
+        serde_json::from_str(&self.source[node.first_line].body).ok()
+    }
+
+    /// Node whose [`path_string`](Self::path_string) is `path`
+    pub fn find_path(&self, path: &str) -> Option<usize> {
+        let path = path.trim();
+        (0..self.nodes.len()).find(|&id| self.path_string(id) == path)
+    }
+
+    /// Unfolds every ancestor of the node so it is visible, returning its line
+    pub fn reveal(&mut self, id: usize) -> Option<usize> {
+        let ancestors: Vec<usize> = self.ancestors(id).collect();
+        if ancestors.iter().any(|&a| self.nodes[a].state.folded) {
+            for a in ancestors {
+                self.nodes[a].state.folded = false;
+            }
+            self.relayout();
+        }
+        self.line_of(id)
+    }
+
+    /// Folds or unfolds a container; returns `false` for nodes that cannot fold
+    pub fn toggle_fold(&mut self, id: usize) -> bool {
+        match self.nodes.get_mut(id) {
+            Some(node) if node.is_foldable() => {
+                node.state.folded = !node.state.folded;
+                self.relayout();
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Toggles the innermost foldable node on visible line `line`
+    pub fn toggle_fold_at_line(&mut self, line: usize) -> Option<usize> {
+        let id = self.node_at_line(line)?;
+        let target = std::iter::once(id)
+            .chain(self.ancestors(id))
+            .find(|&n| self.nodes[n].is_foldable())?;
+        self.toggle_fold(target);
+        Some(target)
+    }
+
+    /// Nodes whose key or scalar value contains `needle`, in document order
+    pub fn search(&self, needle: &str) -> Vec<usize> {
+        if needle.is_empty() {
+            return Vec::new();
+        }
+        let mut matches: Vec<usize> = self
+            .source
+            .iter()
+            .filter(|line| !line.closing)
+            .filter(|line| {
+                let scalar = line.body != "{" && line.body != "[";
+                line.key.as_deref().is_some_and(|key| key.contains(needle))
+                    || (scalar && line.body.contains(needle))
+            })
+            .map(|line| line.node)
+            .collect();
+        matches.dedup();
+        matches
+    }
+
+    /// Highlights exactly `ids`, clearing earlier highlights
+    pub fn highlight(&mut self, ids: &[usize]) {
+        for node in &mut self.nodes {
+            node.state.highlighted = false;
+        }
+        for &id in ids {
+            if let Some(node) = self.nodes.get_mut(id) {
+                node.state.highlighted = true;
+            }
+        }
+        self.relayout();
+    }
+
+    /// Cuts strings longer than `max_chars` characters; `None` shows them whole
+    pub fn truncate_strings(&mut self, max_chars: Option<usize>) {
+        self.truncate_at = max_chars;
+        for line in &self.source {
+            if self.nodes[line.node].kind == NodeKind::String {
+                // The body is quoted, so two characters are not content
+                let len = line.body.chars().count().saturating_sub(2);
+                self.nodes[line.node].state.truncated = max_chars.is_some_and(|max| len > max);
+            }
+        }
+        self.relayout();
+    }
+
+    /// Styles scalars by the first rule matching the nearest key above them
+    /// and their value
+    pub fn apply_highlight_rules(&mut self, rules: &HighlightRules) {
+        for line in &mut self.source {
+            let node = &self.nodes[line.node];
+            line.style = None;
+            if matches!(node.kind, NodeKind::Array | NodeKind::Object) || rules.is_empty() {
+                continue;
+            }
+            let Some(key) = nearest_key(node) else {
+                continue;
+            };
+            if let Ok(value) = serde_json::from_str(&line.body) {
+                line.style = rules.style(key, &value);
+            }
+        }
+        self.relayout();
+    }
+
+    /// Shows numbers through the rule for the nearest key above them;
+    /// search and paths still see the JSON value
+    pub fn format_numbers(&mut self, formats: &NumberFormats) {
+        for line in &mut self.source {
+            let node = &self.nodes[line.node];
+            line.formatted = None;
+            if node.kind != NodeKind::Number || formats.is_empty() {
+                continue;
+            }
+            line.formatted = nearest_key(node).and_then(|key| formats.format(key, &line.body));
+        }
+        self.relayout();
+    }
+}
+
+/// jq path of `segments`, `.` when there are none
+pub fn jq_path(segments: &[PathSegment]) -> String {
+    let mut path = String::new();
+    for segment in segments {
+        match segment {
+            PathSegment::Key(key) => path.push_str(&field(key)),
+            PathSegment::Index(index) => path.push_str(&format!("[{}]", index)),
+        }
+    }
+    if !path.starts_with('.') {
+        path.insert(0, '.');
+    }
+    path
+}
+
+/// The key of `node`, or of the closest object member holding it
+fn nearest_key(node: &Node) -> Option<&str> {
+    node.path.iter().rev().find_map(|segment| match segment {
+        PathSegment::Key(key) => Some(key.as_str()),
+        PathSegment::Index(_) => None,
+    })
+}
+
+/// Keeps the first `max` characters of a quoted string, ending it with `…"`
+fn truncate(quoted: &str, max: usize) -> String {
+    let inner = &quoted[1..quoted.len() - 1];
+    let mut cut: String = inner.chars().take(max).collect();
+    // Don't leave half of an escape sequence before the closing quote
+    if cut.chars().rev().take_while(|&c| c == '\\').count() % 2 == 1 {
+        cut.pop();
+    }
+    format!("\"{}…\"", cut)
+}
+
+fn quote(key: &str) -> String {
+    serde_json::to_string(key).unwrap_or_default()
+}
+
+/// jq filter reading the field `key`: `.name` or `."first name"`
+pub(crate) fn field(key: &str) -> String {
+    if is_identifier(key) {
+        format!(".{}", key)
+    } else {
+        format!(".{}", quote(key))
+    }
+}
+
+/// Keys that jq accepts after a bare `.`
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::NumberFormat;
+    use serde_json::json;
+
+    fn sample() -> RenderTree {
+        RenderTree::from_value(&json!({
+            "users": [{"name": "alice", "tags": ["a", "b"]}, {"name": "bob", "tags": []}],
+            "first name": "x"
+        }))
+    }
+
+    #[test]
+    fn test_text_matches_to_string_pretty() {
+        let value = json!({"a": [1, {"b": null}, [], {}], "c": "d\n\"e\"", "f": 1.5});
+        assert_eq!(
+            RenderTree::from_value(&value).to_text(),
+            serde_json::to_string_pretty(&value).unwrap()
+        );
+        let result = QueryResult::Multiple(vec![json!(1), json!({"x": true})]);
+        assert_eq!(
+            RenderTree::from_result(&result).to_text(),
+            result.format_pretty()
+        );
+        assert_eq!(
+            RenderTree::from_result(&QueryResult::Empty).to_text(),
+            "null"
+        );
+    }
+
+    #[test]
+    fn test_paths() {
+        let tree = sample();
+        let id = tree.find_path(".users[1].name").unwrap();
+        assert_eq!(
+            tree.lines()[tree.line_of(id).unwrap()].text,
+            "      \"name\": \"bob\","
+        );
+        assert_eq!(tree.path_string(0), ".");
+        let quoted = tree.find_path(".\"first name\"").unwrap();
+        assert_eq!(
+            tree.nodes()[quoted].path,
+            vec![PathSegment::Key("first name".into())]
+        );
+        assert_eq!(tree.find_path(".missing"), None);
+    }
+
+    #[test]
+    fn test_folding() {
+        let mut tree = sample();
+        let users = tree.find_path(".users").unwrap();
+        assert!(tree.toggle_fold(users));
+        assert_eq!(
+            tree.to_text(),
+            "{\n  \"first name\": \"x\",\n  \"users\": […]\n}"
+        );
+        let name = tree.find_path(".users[0].name").unwrap();
+        assert_eq!(tree.line_of(name), None);
+
+        // Revealing a hidden node unfolds its ancestors
+        let line = tree.reveal(name).unwrap();
+        assert_eq!(tree.node_at_line(line), Some(name));
+        assert!(!tree.nodes()[users].state.folded);
+
+        // A scalar line folds its container; empty containers don't fold
+        assert_eq!(tree.toggle_fold_at_line(line), tree.find_path(".users[0]"));
+        assert!(!tree.toggle_fold(tree.find_path(".users[1].tags").unwrap()));
+    }
+
+    #[test]
+    fn test_search_and_highlight() {
+        let mut tree = sample();
+        let matches = tree.search("name");
+        assert_eq!(matches.len(), 3);
+        tree.highlight(&matches[..1]);
+        let highlighted: Vec<&str> = tree
+            .lines()
+            .iter()
+            .filter(|line| line.highlighted)
+            .map(|line| line.text.as_str())
+            .collect();
+        assert_eq!(highlighted, vec!["  \"first name\": \"x\","]);
+        assert!(tree.search("").is_empty());
+    }
+
+    #[test]
+    fn test_line_width_keeps_short_containers_on_one_line() {
+        let mut tree = sample();
+        tree.set_line_width(Some(42));
+        assert_eq!(
+            tree.to_text(),
+            "{\n  \"first name\": \"x\",\n  \"users\": [\n    {\"name\": \"alice\", \"tags\": [\"a\", \"b\"]},\n    {\"name\": \"bob\", \"tags\": []}\n  ]\n}"
+        );
+        let tag = tree.find_path(".users[0].tags[1]").unwrap();
+        assert_eq!(tree.line_of(tag), Some(3));
+        assert!(
+            tree.nodes()[tree.find_path(".users[0]").unwrap()]
+                .state
+                .inline
+        );
+
+        // Folding a container on one line folds the whole line
+        assert_eq!(tree.toggle_fold_at_line(3), tree.find_path(".users[0]"));
+        assert_eq!(tree.lines()[3].text, "    {…},");
+
+        tree.set_line_width(Some(200));
+        assert_eq!(tree.line_count(), 1);
+        tree.set_line_width(None);
+        tree.toggle_fold_at_line(3);
+        assert_eq!(
+            tree.to_text(),
+            serde_json::to_string_pretty(&json!({
+                "users": [{"name": "alice", "tags": ["a", "b"]}, {"name": "bob", "tags": []}],
+                "first name": "x"
+            }))
+            .unwrap()
+        );
+    }
+
+    #[test]
+    fn test_format_numbers() {
+        let rules = [
+            ("*_bytes".to_string(), NumberFormat::Bytes),
+            ("*_ms".to_string(), NumberFormat::Millis),
+        ]
+        .into_iter()
+        .collect();
+        let mut tree = RenderTree::from_value(
+            &json!({"size_bytes": 2048, "latency_ms": [1500, 20], "count": 3}),
+        );
+        tree.format_numbers(&NumberFormats::new(&rules).unwrap());
+        assert_eq!(
+            tree.to_text(),
+            "{\n  \"count\": 3,\n  \"latency_ms\": [\n    1.5s,\n    0.02s\n  ],\n  \"size_bytes\": 2.0 KiB\n}"
+        );
+        // Search still matches the value itself
+        assert_eq!(tree.search("2048").len(), 1);
+        tree.format_numbers(&NumberFormats::default());
+        assert!(tree.to_text().contains("\"size_bytes\": 2048\n"));
+    }
+
+    #[test]
+    fn test_highlight_rules() {
+        let rules = HighlightRules::new(&[
+            toml::from_str(
+                r#"key = "level"
+equals = "error"
+color = "red""#,
+            )
+            .unwrap(),
+            toml::from_str("key = \"*_id\"\ndim = true").unwrap(),
+        ])
+        .unwrap();
+        let mut tree = RenderTree::from_value(&json!([
+            {"level": "error", "user_id": 7},
+            {"level": "info", "tags": {"a": 1}},
+        ]));
+        tree.apply_highlight_rules(&rules);
+        let styled: Vec<(&str, bool)> = tree
+            .lines()
+            .iter()
+            .filter_map(|line| Some((line.text.trim(), line.style?.dim)))
+            .collect();
+        assert_eq!(
+            styled,
+            [("\"level\": \"error\",", false), ("\"user_id\": 7", true)]
+        );
+    }
+
+    #[test]
+    fn test_truncate_strings() {
+        let mut tree = RenderTree::from_value(&json!(["abcdef", "ab\\cd", "ok"]));
+        tree.truncate_strings(Some(3));
+        assert_eq!(tree.to_text(), "[\n  \"abc…\",\n  \"ab…\",\n  \"ok\"\n]");
+        assert!(tree.nodes()[1].state.truncated);
+        tree.truncate_strings(None);
+        assert_eq!(
+            tree.to_text(),
+            "[\n  \"abcdef\",\n  \"ab\\\\cd\",\n  \"ok\"\n]"
+        );
+    }
+}
@@ -0,0 +1,7 @@
+pub mod replay;
+pub mod store;
+pub mod tee;
+
+pub use replay::Replay;
+pub use store::SessionStore;
+pub use tee::{SessionEntry, TeeLog};
@@ -0,0 +1,100 @@
+use super::tee::SessionEntry;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Steps through the queries recorded in a session log
+#[derive(Debug)]
+pub struct Replay {
+    queries: Vec<String>,
+    position: usize,
+    interval: Option<Duration>,
+    last_step: Instant,
+}
+
+impl Replay {
+    pub fn new(queries: Vec<String>, interval: Option<Duration>) -> Self {
+        Self {
+            queries,
+            position: 0,
+            interval,
+            last_step: Instant::now(),
+        }
+    }
+
+    /// Loads the queries from a log written by `--tee`
+    pub fn load(path: impl AsRef<Path>, interval: Option<Duration>) -> std::io::Result<Self> {
+        let content = std::fs::read_to_string(path)?;
+        let queries = content
+            .lines()
+            .filter_map(SessionEntry::from_json_line)
+            .map(|entry| entry.query)
+            .collect();
+        Ok(Self::new(queries, interval))
+    }
+
+    /// Returns the next recorded query, or `None` once all have been replayed
+    pub fn next_query(&mut self) -> Option<&str> {
+        let query = self.queries.get(self.position)?;
+        self.position += 1;
+        self.last_step = Instant::now();
+        Some(query)
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.position >= self.queries.len()
+    }
+
+    /// (replayed, total)
+    pub fn progress(&self) -> (usize, usize) {
+        (self.position, self.queries.len())
+    }
+
+    /// Time left until the next automatic step, if replay is timed
+    pub fn time_until_next(&self) -> Option<Duration> {
+        if self.is_finished() {
+            return None;
+        }
+        self.interval
+            .map(|interval| interval.saturating_sub(self.last_step.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_steps_through_queries() {
+        let mut replay = Replay::new(vec![".a".to_string(), ".b".to_string()], None);
+        assert_eq!(replay.progress(), (0, 2));
+        assert_eq!(replay.next_query(), Some(".a"));
+        assert_eq!(replay.next_query(), Some(".b"));
+        assert!(replay.is_finished());
+        assert_eq!(replay.next_query(), None);
+        assert_eq!(replay.progress(), (2, 2));
+    }
+
+    #[test]
+    fn test_untimed_replay_has_no_deadline() {
+        let replay = Replay::new(vec![".a".to_string()], None);
+        assert_eq!(replay.time_until_next(), None);
+
+        let replay = Replay::new(vec![".a".to_string()], Some(Duration::from_secs(60)));
+        assert!(replay.time_until_next().unwrap() > Duration::from_secs(59));
+    }
+
+    #[test]
+    fn test_load_skips_malformed_lines() {
+        let path = std::env::temp_dir().join(format!("rjq_replay_test_{}.log", std::process::id()));
+        std::fs::write(
+            &path,
+            "{\"timestamp\":1,\"query\":\".a\",\"result\":\"1\",\"truncated\":false}\nnot json\n",
+        )
+        .unwrap();
+
+        let replay = Replay::load(&path, None).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(replay.progress(), (0, 1));
+    }
+}
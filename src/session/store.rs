@@ -0,0 +1,87 @@
+use crate::render::TableLayout;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoreData {
+    /// Table layouts keyed by [`Table::fingerprint`](crate::render::Table::fingerprint)
+    #[serde(default)]
+    tables: BTreeMap<String, TableLayout>,
+}
+
+/// View settings kept across sessions
+///
+/// Kept as JSON in `~/.config/rjq/session.json`. Changes are made in memory;
+/// [`SessionStore::contents`] gives what to write back.
+#[derive(Debug, Default)]
+pub struct SessionStore {
+    file: Option<PathBuf>,
+    data: StoreData,
+}
+
+impl SessionStore {
+    pub fn load_default() -> Self {
+        match crate::config::user_config_dir() {
+            Some(dir) => Self::load(dir.join("session.json")),
+            None => Self::default(),
+        }
+    }
+
+    /// Loads a store backed by `file`; a missing or unreadable file yields an empty store
+    pub fn load(file: PathBuf) -> Self {
+        let data = std::fs::read_to_string(&file)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self {
+            file: Some(file),
+            data,
+        }
+    }
+
+    /// File the store is saved to; `None` for a store that lives only in memory
+    pub fn path(&self) -> Option<&Path> {
+        self.file.as_deref()
+    }
+
+    pub fn table_layout(&self, fingerprint: &str) -> Option<&TableLayout> {
+        self.data.tables.get(fingerprint)
+    }
+
+    pub fn set_table_layout(&mut self, fingerprint: String, layout: TableLayout) {
+        self.data.tables.insert(fingerprint, layout);
+    }
+
+    /// Serialized store, as written to [`path`](Self::path)
+    pub fn contents(&self) -> String {
+        serde_json::to_string_pretty(&self.data).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::Table;
+    use serde_json::json;
+
+    #[test]
+    fn test_layout_round_trip() {
+        let file = std::env::temp_dir().join(format!(
+            "rjq_session_store_test_{}.json",
+            std::process::id()
+        ));
+        let table = Table::from_values(&[json!({"id": 1, "name": "a"})]).unwrap();
+        let mut layout = table.default_layout();
+        layout.toggle(0);
+
+        let mut store = SessionStore::load(file.clone());
+        assert!(store.table_layout(&table.fingerprint()).is_none());
+        store.set_table_layout(table.fingerprint(), layout.clone());
+        std::fs::write(&file, store.contents()).unwrap();
+
+        let reloaded = SessionStore::load(file.clone());
+        std::fs::remove_file(&file).ok();
+        assert_eq!(reloaded.table_layout(&table.fingerprint()), Some(&layout));
+    }
+}
@@ -0,0 +1,192 @@
+use crate::clock::{Clock, SystemClock};
+use crate::history::Redactor;
+use serde_json::{Value, json};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::UNIX_EPOCH;
+
+/// One entry of the session log
+///
+/// Serialized as one JSON object per line so the log can be both read by
+/// humans and replayed later.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionEntry {
+    /// Seconds since the Unix epoch
+    pub timestamp: u64,
+    pub query: String,
+    pub result: String,
+    /// Whether `result` was cut off at the configured limit
+    pub truncated: bool,
+}
+
+impl SessionEntry {
+    pub fn to_json(&self) -> Value {
+        json!({
+            "timestamp": self.timestamp,
+            "query": self.query,
+            "result": self.result,
+            "truncated": self.truncated,
+        })
+    }
+
+    /// Parses one log line; returns `None` for blank or malformed lines
+    pub fn from_json_line(line: &str) -> Option<Self> {
+        let value: Value = serde_json::from_str(line).ok()?;
+        Some(Self {
+            timestamp: value.get("timestamp")?.as_u64()?,
+            query: value.get("query")?.as_str()?.to_string(),
+            result: value
+                .get("result")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+            truncated: value
+                .get("truncated")
+                .and_then(Value::as_bool)
+                .unwrap_or(false),
+        })
+    }
+}
+
+/// Appends every executed query and its result to an audit log
+#[derive(Debug)]
+pub struct TeeLog {
+    file: File,
+    result_limit: Option<usize>,
+    redactor: Redactor,
+    clock: Arc<dyn Clock>,
+}
+
+impl TeeLog {
+    /// Opens `path` for appending, creating it if necessary
+    pub fn open(path: impl AsRef<Path>, result_limit: Option<usize>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            file,
+            result_limit,
+            redactor: Redactor::default(),
+            clock: Arc::new(SystemClock),
+        })
+    }
+
+    /// Applies the same secret masking as the query history
+    pub fn set_redactor(&mut self, redactor: Redactor) {
+        self.redactor = redactor;
+    }
+
+    /// Timestamps entries with `clock` instead of the system time
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = clock;
+    }
+
+    /// Appends `query` and `result`; the kept and total lines of the result
+    /// when it was cut at the limit
+    pub fn record(&mut self, query: &str, result: &str) -> std::io::Result<Option<(usize, usize)>> {
+        let Some(query) = self.redactor.apply(query) else {
+            return Ok(None);
+        };
+        let result = self.redactor.mask(result);
+        let timestamp = self
+            .clock
+            .now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        let (kept, truncated) = truncate(&result, self.result_limit);
+        let entry = SessionEntry {
+            timestamp,
+            query,
+            result: kept.to_string(),
+            truncated,
+        };
+        writeln!(self.file, "{}", entry.to_json())?;
+        self.file.flush()?;
+        Ok(truncated.then(|| (kept.lines().count(), result.lines().count())))
+    }
+}
+
+/// Cuts `text` to at most `limit` bytes on a character boundary
+fn truncate(text: &str, limit: Option<usize>) -> (&str, bool) {
+    match limit {
+        Some(limit) if text.len() > limit => {
+            let mut end = limit;
+            while !text.is_char_boundary(end) {
+                end -= 1;
+            }
+            (&text[..end], true)
+        }
+        _ => (text, false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("abcdef", None), ("abcdef", false));
+        assert_eq!(truncate("abcdef", Some(10)), ("abcdef", false));
+        assert_eq!(truncate("abcdef", Some(3)), ("abc", true));
+        // マルチバイト文字の途中では切らない
+        assert_eq!(truncate("あい", Some(4)), ("あ", true));
+    }
+
+    #[test]
+    fn test_record_applies_redaction() {
+        use crate::history::RedactMode;
+
+        let path =
+            std::env::temp_dir().join(format!("rjq_tee_redact_test_{}.log", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let patterns = ["tok_[0-9]+".to_string()];
+        let mut log = TeeLog::open(&path, None).unwrap();
+        log.set_redactor(Redactor::new(&patterns, RedactMode::Mask).unwrap());
+        log.record(r#".k == "tok_123""#, r#""tok_123""#).unwrap();
+        log.set_redactor(Redactor::new(&patterns, RedactMode::Skip).unwrap());
+        log.record(r#".k == "tok_456""#, "true").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        assert!(!content.contains("tok_"));
+        let entries: Vec<SessionEntry> = content
+            .lines()
+            .filter_map(SessionEntry::from_json_line)
+            .collect();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].query, r#".k == "***""#);
+        assert_eq!(entries[0].result, r#""***""#);
+    }
+
+    #[test]
+    fn test_record_appends_json_lines() {
+        let path = std::env::temp_dir().join(format!("rjq_tee_test_{}.log", std::process::id()));
+        std::fs::remove_file(&path).ok();
+
+        let clock = crate::clock::MockClock::default();
+        clock.advance(std::time::Duration::from_secs(1_700_000_000));
+        let mut log = TeeLog::open(&path, Some(5)).unwrap();
+        log.set_clock(Arc::new(clock));
+        assert_eq!(log.record(".name", "\"Alice\"").unwrap(), Some((1, 1)));
+        assert_eq!(log.record(".age", "30").unwrap(), None);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).ok();
+
+        let entries: Vec<SessionEntry> = content
+            .lines()
+            .filter_map(SessionEntry::from_json_line)
+            .collect();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].timestamp, 1_700_000_000);
+        assert_eq!(entries[0].query, ".name");
+        assert_eq!(entries[0].result, "\"Alic");
+        assert!(entries[0].truncated);
+        assert_eq!(entries[1].result, "30");
+        assert!(!entries[1].truncated);
+    }
+}
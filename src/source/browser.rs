@@ -0,0 +1,126 @@
+//! Directory listings for picking an input file
+
+use super::compression;
+use crate::input::InputFormat;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+/// Most bytes read from a file for its preview
+const PREVIEW_BYTES: u64 = 16 * 1024;
+
+/// One entry of a directory listing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub name: String,
+    pub is_dir: bool,
+}
+
+/// Subdirectories and the files rjq can read in `dir`, each group sorted by
+/// name, after `..` unless `dir` is the root; hidden entries are skipped
+pub fn list(dir: &Path) -> io::Result<Vec<DirEntry>> {
+    let mut entries: Vec<DirEntry> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_str()?.to_string();
+            let is_dir = entry.path().is_dir();
+            let inner = compression::inner_path(Path::new(&name));
+            let readable = is_dir || InputFormat::from_extension(&inner).is_some();
+            (readable && !name.starts_with('.')).then_some(DirEntry { name, is_dir })
+        })
+        .collect();
+    entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+    if dir.parent().is_some() {
+        entries.insert(
+            0,
+            DirEntry {
+                name: "..".to_string(),
+                is_dir: true,
+            },
+        );
+    }
+    Ok(entries)
+}
+
+/// How well `query` matches `candidate` as a case-insensitive subsequence
+///
+/// `None` when some character is missing. Consecutive characters and
+/// characters starting a word (after `_`, `-`, `.` or `/`) score higher.
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for wanted in query.to_lowercase().chars() {
+        let found = position + candidate[position..].iter().position(|&c| c == wanted)?;
+        score += 1;
+        if previous.is_some_and(|p| p + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || matches!(candidate[found - 1], '_' | '-' | '.' | '/' | ' ') {
+            score += 3;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    // Prefer shorter names among equal matches
+    Some(score * 100 - candidate.len() as i64)
+}
+
+/// Entries matching `query`, best first; all of them in order for an empty query
+pub fn filter<'a>(entries: &'a [DirEntry], query: &str) -> Vec<&'a DirEntry> {
+    if query.is_empty() {
+        return entries.iter().collect();
+    }
+    let mut scored: Vec<(i64, &DirEntry)> = entries
+        .iter()
+        .filter_map(|entry| Some((fuzzy_score(query, &entry.name)?, entry)))
+        .collect();
+    scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+    scored.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// First `lines` lines of the file at `path`, for a preview
+pub fn preview(path: &Path, lines: usize) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    compression::open(path)?
+        .take(PREVIEW_BYTES)
+        .read_to_end(&mut bytes)?;
+    let text = String::from_utf8_lossy(&bytes);
+    Ok(text.lines().take(lines).collect::<Vec<_>>().join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_score() {
+        assert!(fuzzy_score("dpl", "deploy.yaml").is_some());
+        assert!(fuzzy_score("xyz", "deploy.yaml").is_none());
+        // Consecutive and word-start matches win
+        assert!(fuzzy_score("log", "logs.json") > fuzzy_score("log", "l_o_g.json"));
+        assert!(fuzzy_score("uj", "users.json") > fuzzy_score("uj", "hugejson"));
+    }
+
+    #[test]
+    fn test_list_and_filter() {
+        let dir = std::env::temp_dir().join(format!("rjq_browser_list_{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested")).unwrap();
+        for name in ["users.json", "deploy.yaml.gz", "notes.txt", ".hidden.json"] {
+            fs::write(dir.join(name), "{}\n[]\n").unwrap();
+        }
+
+        let entries = list(&dir).unwrap();
+        let names: Vec<&str> = entries.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["..", "nested", "deploy.yaml.gz", "users.json"]);
+
+        let matched: Vec<&str> = filter(&entries, "US")
+            .iter()
+            .map(|e| e.name.as_str())
+            .collect();
+        assert_eq!(matched, ["users.json"]);
+        assert_eq!(preview(&dir.join("users.json"), 1).unwrap(), "{}");
+        fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,129 @@
+//! Decompressing compressed input
+
+use crate::app::AppError;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+/// Compression of the input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Recognizes the magic bytes at the start of `bytes`
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        match bytes {
+            [0x1f, 0x8b, ..] => Some(Compression::Gzip),
+            [0x28, 0xb5, 0x2f, 0xfd, ..] => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The compression a `.gz` or `.zst` extension stands for
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "gz" | "gzip" => Some(Compression::Gzip),
+            "zst" | "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+}
+
+/// `path` without a compression extension, so `logs.ndjson.gz` is read as NDJSON
+pub fn inner_path(path: &Path) -> PathBuf {
+    match Compression::from_path(path) {
+        Some(_) => path.with_extension(""),
+        None => path.to_path_buf(),
+    }
+}
+
+/// Opens `path`, decompressing as it is read when its extension says it is compressed
+pub fn open(path: &Path) -> io::Result<Box<dyn Read>> {
+    let file = File::open(path)?;
+    Ok(match Compression::from_path(path) {
+        Some(Compression::Gzip) => Box::new(flate2::read::MultiGzDecoder::new(file)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(file)?),
+        None => Box::new(file),
+    })
+}
+
+/// Decompresses `bytes` when they start with a gzip or zstd header, and
+/// returns them unchanged otherwise
+pub fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>, AppError> {
+    let Some(compression) = Compression::detect(&bytes) else {
+        return Ok(bytes);
+    };
+    let mut output = Vec::new();
+    let result = match compression {
+        // Concatenated members, as written by `gzip -c a >> b`, are read as one stream
+        Compression::Gzip => flate2::read::MultiGzDecoder::new(bytes.as_slice())
+            .read_to_end(&mut output)
+            .map(drop),
+        Compression::Zstd => zstd::stream::copy_decode(bytes.as_slice(), &mut output),
+    };
+    result.map_err(|e| AppError::InputParse {
+        format: compression.name(),
+        message: e.to_string(),
+    })?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        encoder.write_all(data).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn test_decompress_by_magic_bytes() {
+        let json = b"{\"a\": 1}".to_vec();
+        assert_eq!(decompress(gzip(&json)).unwrap(), json);
+        assert_eq!(
+            decompress(zstd::encode_all(json.as_slice(), 0).unwrap()).unwrap(),
+            json
+        );
+        // Plain input passes through
+        assert_eq!(decompress(json.clone()).unwrap(), json);
+
+        let mut concatenated = gzip(b"1\n");
+        concatenated.extend(gzip(b"2\n"));
+        assert_eq!(decompress(concatenated).unwrap(), b"1\n2\n");
+    }
+
+    #[test]
+    fn test_truncated_input_is_a_parse_error() {
+        let mut truncated = gzip(b"{\"a\": 1}");
+        truncated.truncate(12);
+        let error = decompress(truncated).unwrap_err();
+        assert!(
+            error.to_string().starts_with("gzip parsing error"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn test_inner_path() {
+        assert_eq!(
+            inner_path(Path::new("logs/app.ndjson.gz")),
+            Path::new("logs/app.ndjson")
+        );
+        assert_eq!(inner_path(Path::new("a.json.zst")), Path::new("a.json"));
+        assert_eq!(inner_path(Path::new("a.json")), Path::new("a.json"));
+    }
+}
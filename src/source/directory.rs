@@ -0,0 +1,138 @@
+//! Directory input
+//!
+//! A directory given as the input is read as one array with an object per
+//! matching file, `{"filename": "a.json", "content": …}`, so a query can
+//! look across all of them.
+
+use super::progress::LoadProgress;
+use super::{DataSource, LoadOptions};
+use crate::app::{AppError, AppWarning};
+use crate::query::JsonData;
+use serde_json::{Value, json};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// Files read from a directory unless `--include` says otherwise
+pub const DEFAULT_INCLUDE: &str = "*.json";
+
+/// Reads every file in `dir` whose name matches the include pattern, in
+/// path order; subdirectories are descended into with `options.recursive`
+pub fn load(
+    dir: &str,
+    options: &LoadOptions,
+    warnings: &mut Vec<AppWarning>,
+    progress: &LoadProgress,
+) -> Result<JsonData, AppError> {
+    let pattern = options.include.as_deref().unwrap_or(DEFAULT_INCLUDE);
+    let include = glob::Pattern::new(pattern).map_err(|e| AppError::Config {
+        path: "--include".to_string(),
+        message: e.to_string(),
+    })?;
+    let root = Path::new(dir);
+    let mut paths = Vec::new();
+    collect(root, &include, options.recursive, &mut paths).map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => AppError::FileNotFound(dir.to_string()),
+        _ => AppError::Io(e),
+    })?;
+    if paths.is_empty() {
+        return Err(AppError::FileNotFound(
+            root.join(pattern).display().to_string(),
+        ));
+    }
+    let files = paths
+        .into_iter()
+        .map(|path| {
+            let data = DataSource::File(path.display().to_string())
+                .load_reporting(options, warnings, progress)?;
+            Ok(json!({
+                "filename": filename(root, &path),
+                "content": Arc::unwrap_or_clone(data.shared()),
+            }))
+        })
+        .collect::<Result<Vec<_>, AppError>>()?;
+    Ok(JsonData::new(Value::Array(files)))
+}
+
+/// Matching files under `dir`, sorted so the order doesn't depend on the
+/// file system; symlinked directories are not followed
+fn collect(
+    dir: &Path,
+    include: &glob::Pattern,
+    recursive: bool,
+    paths: &mut Vec<PathBuf>,
+) -> io::Result<()> {
+    let mut entries = std::fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            if recursive {
+                collect(&path, include, recursive, paths)?;
+            }
+        } else if path.is_file() && include.matches(&entry.file_name().to_string_lossy()) {
+            paths.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// `path` relative to the directory, with `/` separators on every platform
+fn filename(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_directory() {
+        let dir = std::env::temp_dir().join(format!("rjq_source_directory_{}", std::process::id()));
+        std::fs::remove_dir_all(&dir).ok();
+        std::fs::create_dir_all(dir.join("nested")).unwrap();
+        std::fs::write(dir.join("b.json"), "{\"n\": 2}").unwrap();
+        std::fs::write(dir.join("a.json"), "{\"n\": 1}").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not json").unwrap();
+        std::fs::write(dir.join("nested").join("c.json"), "[3]").unwrap();
+        let path = dir.display().to_string();
+        let read =
+            |options: &LoadOptions| load(&path, options, &mut Vec::new(), &LoadProgress::default());
+
+        let data = read(&LoadOptions::default()).unwrap();
+        assert_eq!(
+            data.get(),
+            &json!([
+                {"filename": "a.json", "content": {"n": 1}},
+                {"filename": "b.json", "content": {"n": 2}},
+            ])
+        );
+
+        let options = LoadOptions {
+            recursive: true,
+            include: Some("[ac].json".to_string()),
+            ..Default::default()
+        };
+        let data = read(&options).unwrap();
+        assert_eq!(
+            data.get(),
+            &json!([
+                {"filename": "a.json", "content": {"n": 1}},
+                {"filename": "nested/c.json", "content": [3]},
+            ])
+        );
+
+        let options = LoadOptions {
+            include: Some("*.yaml".to_string()),
+            ..Default::default()
+        };
+        assert!(matches!(read(&options), Err(AppError::FileNotFound(_))));
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
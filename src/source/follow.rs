@@ -0,0 +1,130 @@
+//! Reading input as it grows (`--follow`)
+//!
+//! Records are read on a background thread, so the TUI keeps running while
+//! the producer on the other end of the pipe is still writing.
+
+use serde_json::Value;
+use std::io::BufRead;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+
+/// Result of reading one line
+#[derive(Debug)]
+enum Line {
+    Record(Value),
+    /// 1-based line number and why it is not JSON
+    Invalid(usize, String),
+}
+
+/// Records received since the last call
+#[derive(Debug, Default, PartialEq)]
+pub struct Received {
+    pub records: Vec<Value>,
+    /// Lines that were not JSON, with their 1-based line numbers
+    pub invalid: Vec<(usize, String)>,
+}
+
+/// Background thread reading NDJSON as it arrives
+#[derive(Debug)]
+pub struct Follower {
+    lines: Receiver<Line>,
+    closed: bool,
+    /// Records received so far
+    count: usize,
+}
+
+impl Follower {
+    /// Starts reading `reader` line by line; blank lines are skipped
+    pub fn spawn<R: BufRead + Send + 'static>(reader: R) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for (index, line) in reader.lines().enumerate() {
+                let Ok(line) = line else { break };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let parsed = match serde_json::from_str(&line) {
+                    Ok(value) => Line::Record(value),
+                    Err(e) => Line::Invalid(index + 1, e.to_string()),
+                };
+                if sender.send(parsed).is_err() {
+                    break;
+                }
+            }
+        });
+        Self {
+            lines,
+            closed: false,
+            count: 0,
+        }
+    }
+
+    /// Everything that arrived since the last call, without waiting
+    pub fn receive(&mut self) -> Received {
+        let mut received = Received::default();
+        loop {
+            match self.lines.try_recv() {
+                Ok(Line::Record(value)) => received.records.push(value),
+                Ok(Line::Invalid(line, message)) => received.invalid.push((line, message)),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.closed = true;
+                    break;
+                }
+            }
+        }
+        self.count += received.records.len();
+        received
+    }
+
+    /// The writer closed its end and every line has been received
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::{BufReader, Write};
+    use std::os::unix::net::UnixStream;
+    use std::time::{Duration, Instant};
+
+    fn receive_until(follower: &mut Follower, done: impl Fn(&Follower) -> bool) -> Received {
+        let started = Instant::now();
+        let mut all = Received::default();
+        while !done(follower) {
+            assert!(started.elapsed() < Duration::from_secs(5), "timed out");
+            let received = follower.receive();
+            all.records.extend(received.records);
+            all.invalid.extend(received.invalid);
+            thread::sleep(Duration::from_millis(10));
+        }
+        all
+    }
+
+    #[test]
+    fn test_records_arrive_while_the_writer_is_open() {
+        let (mut writer, reader) = UnixStream::pair().unwrap();
+        let mut follower = Follower::spawn(BufReader::new(reader));
+        assert_eq!(follower.receive(), Received::default());
+
+        writeln!(writer, "{{\"n\": 1}}\n\n{{\"n\": 2}}").unwrap();
+        let received = receive_until(&mut follower, |f| f.count() == 2);
+        assert_eq!(received.records, vec![json!({"n": 1}), json!({"n": 2})]);
+        assert!(!follower.is_closed());
+
+        writeln!(writer, "oops\n[3]").unwrap();
+        drop(writer);
+        let received = receive_until(&mut follower, Follower::is_closed);
+        assert_eq!(received.records, vec![json!([3])]);
+        assert_eq!(received.invalid.len(), 1);
+        assert_eq!(received.invalid[0].0, 4);
+        assert_eq!(follower.count(), 3);
+    }
+}
@@ -0,0 +1,494 @@
+//! Fetching from HTTP(S)
+
+use super::progress::LoadProgress;
+use crate::app::AppError;
+use crate::input::InputFormat;
+use base64::Engine;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::io::Read;
+use std::time::Duration;
+
+/// Formats rjq reads, most preferred first
+const ACCEPT: &str = "application/json, application/x-ndjson;q=0.9, application/yaml;q=0.8, text/csv;q=0.8, */*;q=0.5";
+
+/// Most redirects followed before giving up
+const MAX_REDIRECTS: usize = 5;
+
+/// Fetched body
+#[derive(Debug)]
+pub struct Response {
+    pub body: Vec<u8>,
+    /// Format named by the `Content-Type` header, if it names one
+    pub format: Option<InputFormat>,
+}
+
+pub fn is_url(arg: &str) -> bool {
+    arg.starts_with("http://") || arg.starts_with("https://")
+}
+
+/// Credentials for a host
+///
+/// Set in the user config's `[credentials."host"]` sections and sent only to
+/// that host, not after a redirect to another one, e.g. `bearer = "${API_TOKEN}"`.
+#[derive(Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Credentials {
+    /// Sent as `Authorization: Bearer <token>`
+    pub bearer: Option<String>,
+    /// With `password`, sent as `Authorization: Basic …`
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Any other headers, e.g. `{ "X-Api-Key" = "…" }`
+    pub headers: BTreeMap<String, String>,
+}
+
+/// Keeps secrets out of logs and panic messages
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hidden = |value: &Option<String>| value.as_ref().map(|_| "***");
+        f.debug_struct("Credentials")
+            .field("bearer", &hidden(&self.bearer))
+            .field("username", &self.username)
+            .field("password", &hidden(&self.password))
+            .field("headers", &self.headers.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Credentials {
+    /// The request headers these credentials add
+    pub fn headers(&self) -> Vec<(String, String)> {
+        let mut headers = Vec::new();
+        if let Some(token) = &self.bearer {
+            headers.push(("Authorization".to_string(), format!("Bearer {}", token)));
+        } else if let Some(username) = &self.username {
+            let pair = format!("{}:{}", username, self.password.as_deref().unwrap_or(""));
+            let encoded = base64::engine::general_purpose::STANDARD.encode(pair);
+            headers.push(("Authorization".to_string(), format!("Basic {}", encoded)));
+        }
+        headers.extend(
+            self.headers
+                .iter()
+                .map(|(name, value)| (name.clone(), value.clone())),
+        );
+        headers
+    }
+}
+
+/// The credentials for `url`'s host: a section named `host:port` wins over
+/// one named after the host alone
+pub fn credentials_for<'a>(
+    url: &str,
+    credentials: &'a BTreeMap<String, Credentials>,
+) -> Option<&'a Credentials> {
+    let authority = authority(url);
+    let host = match authority.rsplit_once(':') {
+        // Not the colons inside an IPv6 address
+        Some((host, port)) if !port.contains(']') => host,
+        _ => authority,
+    };
+    credentials.get(authority).or_else(|| credentials.get(host))
+}
+
+/// `scheme://host[:port]` of `url`, lowercased, to tell whether a redirect
+/// leaves the server the request was meant for
+fn origin(url: &str) -> String {
+    let scheme = url.split_once("://").map_or("", |(scheme, _)| scheme);
+    format!("{}://{}", scheme, authority(url)).to_ascii_lowercase()
+}
+
+/// The URL a `Location` header sends a request for `base` to
+fn resolve_location(base: &str, location: &str) -> String {
+    if location.contains("://") {
+        return location.to_string();
+    }
+    let scheme = base.split_once("://").map_or("http", |(scheme, _)| scheme);
+    if let Some(rest) = location.strip_prefix("//") {
+        return format!("{}://{}", scheme, rest);
+    }
+    let root = format!("{}://{}", scheme, authority_with_user(base));
+    if location.starts_with('/') {
+        return format!("{}{}", root, location);
+    }
+    let path = url_path(base);
+    let dir = path.rfind('/').map_or("/", |end| &path[..=end]);
+    format!("{}{}{}", root, dir, location)
+}
+
+/// `[user@]host[:port]` of `url`
+fn authority_with_user(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    rest.split(['/', '?', '#']).next().unwrap_or(rest)
+}
+
+/// `host[:port]` of `url`, without any `user@` part
+fn authority(url: &str) -> &str {
+    let authority = authority_with_user(url);
+    authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host)
+}
+
+/// A `--header` value, `Name: value`
+pub fn parse_header(value: &str) -> Result<(String, String), String> {
+    match value.split_once(':') {
+        Some((name, value)) if !name.trim().is_empty() && !name.contains(char::is_whitespace) => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err("expected a header such as `Authorization: Bearer TOKEN`".to_string()),
+    }
+}
+
+/// Path of `url` without the query string, to guess the format from its extension
+pub fn url_path(url: &str) -> &str {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let path = rest.find('/').map_or("", |start| &rest[start..]);
+    path.split(['?', '#']).next().unwrap_or(path)
+}
+
+/// Fetches `url` with a GET request, giving up after `timeout`
+///
+/// Fails on an error status and on a body that is not data, such as an HTML
+/// login page.
+pub fn fetch(url: &str, timeout: Duration) -> Result<Response, AppError> {
+    fetch_reporting(url, timeout, &[], &LoadProgress::default())
+}
+
+/// Like [`fetch`], sending `headers` with the request and counting the bytes
+/// of the body in `progress` against its `Content-Length`
+///
+/// Redirects are followed by hand so that `headers`, which may hold
+/// credentials, are dropped once a redirect leaves the original server.
+pub fn fetch_reporting(
+    url: &str,
+    timeout: Duration,
+    headers: &[(String, String)],
+    progress: &LoadProgress,
+) -> Result<Response, AppError> {
+    let error = |message: String| AppError::Fetch {
+        url: url.to_string(),
+        message,
+    };
+    let agent = ureq::AgentBuilder::new()
+        .timeout(timeout)
+        .redirects(0)
+        .build();
+    let mut location = url.to_string();
+    let mut headers = headers;
+    let mut redirects = 0;
+    let response = loop {
+        let mut request = agent.get(&location).set("Accept", ACCEPT);
+        // Later headers replace earlier ones of the same name
+        for (name, value) in headers {
+            request = request.set(name, value);
+        }
+        let response = request.call().map_err(|e| match e {
+            ureq::Error::Status(code, response) => {
+                error(format!("HTTP {} {}", code, response.status_text()))
+            }
+            ureq::Error::Transport(transport) => error(transport.to_string()),
+        })?;
+        if !(300..400).contains(&response.status()) {
+            break response;
+        }
+        let Some(next) = response.header("Location") else {
+            return Err(error(format!(
+                "HTTP {} {} without a Location",
+                response.status(),
+                response.status_text()
+            )));
+        };
+        if redirects == MAX_REDIRECTS {
+            return Err(error(format!("more than {} redirects", MAX_REDIRECTS)));
+        }
+        redirects += 1;
+        let next = resolve_location(&location, next);
+        if origin(&next) != origin(&location) {
+            headers = &[];
+        }
+        location = next;
+    };
+
+    let content_type = response.header("Content-Type").unwrap_or("").to_string();
+    let format = content_type_format(&content_type)
+        .map_err(|()| error(format!("unexpected content type `{}`", content_type)))?;
+    let length = response
+        .header("Content-Length")
+        .and_then(|length| length.parse().ok());
+    progress.start(format!("Downloading {}", url), length);
+    let mut body = Vec::new();
+    progress
+        .reader(response.into_reader())
+        .read_to_end(&mut body)
+        .map_err(|e| error(e.to_string()))?;
+    Ok(Response { body, format })
+}
+
+/// Input format for a `Content-Type`; `Err` for types that are not data
+fn content_type_format(content_type: &str) -> Result<Option<InputFormat>, ()> {
+    let mime = content_type
+        .split(';')
+        .next()
+        .unwrap_or("")
+        .trim()
+        .to_ascii_lowercase();
+    match mime.as_str() {
+        "application/x-ndjson"
+        | "application/ndjson"
+        | "application/jsonl"
+        | "application/x-jsonlines" => Ok(Some(InputFormat::Ndjson)),
+        "application/json5" => Ok(Some(InputFormat::Json5)),
+        "application/json" | "text/json" => Ok(Some(InputFormat::Json)),
+        mime if mime.ends_with("+json") => Ok(Some(InputFormat::Json)),
+        "application/yaml" | "application/x-yaml" | "text/yaml" | "text/x-yaml" => {
+            Ok(Some(InputFormat::Yaml))
+        }
+        "text/csv" => Ok(Some(InputFormat::Csv)),
+        "text/tab-separated-values" => Ok(Some(InputFormat::Tsv)),
+        // Raw file hosts often send data without a specific type
+        "" | "text/plain" | "application/octet-stream" => Ok(None),
+        // Compressed files are unpacked before parsing
+        "application/gzip" | "application/x-gzip" | "application/zstd" => Ok(None),
+        _ => Err(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers one request with `response` after `delay`, returning the URL to fetch
+    fn serve(response: &'static str, delay: Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let _ = stream.read(&mut request);
+            thread::sleep(delay);
+            let _ = stream.write_all(response.as_bytes());
+        });
+        format!("http://{}/items.yaml?page=1", address)
+    }
+
+    #[test]
+    fn test_url_path() {
+        assert_eq!(url_path("https://example.com/a/b.csv?x=1#top"), "/a/b.csv");
+        assert_eq!(url_path("https://example.com"), "");
+        assert!(is_url("http://localhost:8080"));
+        assert!(!is_url("data/http.json"));
+    }
+
+    #[test]
+    fn test_content_types() {
+        assert_eq!(
+            content_type_format("application/json; charset=utf-8"),
+            Ok(Some(InputFormat::Json))
+        );
+        assert_eq!(
+            content_type_format("application/problem+json"),
+            Ok(Some(InputFormat::Json))
+        );
+        assert_eq!(
+            content_type_format("application/x-ndjson"),
+            Ok(Some(InputFormat::Ndjson))
+        );
+        assert_eq!(content_type_format("text/plain"), Ok(None));
+        assert_eq!(content_type_format("text/html"), Err(()));
+    }
+
+    #[test]
+    fn test_fetch_reads_the_body_and_its_format() {
+        let url = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 8\r\n\r\n{\"a\": 1}",
+            Duration::ZERO,
+        );
+        let response = fetch(&url, Duration::from_secs(5)).unwrap();
+        assert_eq!(response.body, b"{\"a\": 1}");
+        assert_eq!(response.format, Some(InputFormat::Json));
+    }
+
+    #[test]
+    fn test_fetch_rejects_errors_and_pages() {
+        let url = serve(
+            "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+            Duration::ZERO,
+        );
+        let error = fetch(&url, Duration::from_secs(5)).unwrap_err();
+        assert!(
+            error.to_string().ends_with("HTTP 404 Not Found"),
+            "{}",
+            error
+        );
+
+        let url = serve(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 6\r\n\r\n<html>",
+            Duration::ZERO,
+        );
+        let error = fetch(&url, Duration::from_secs(5)).unwrap_err();
+        assert!(error.to_string().contains("text/html"), "{}", error);
+    }
+
+    #[test]
+    fn test_credentials() {
+        let bearer = Credentials {
+            bearer: Some("t0k".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            bearer.headers(),
+            [("Authorization".to_string(), "Bearer t0k".to_string())]
+        );
+        let basic = Credentials {
+            username: Some("user".to_string()),
+            password: Some("pass".to_string()),
+            headers: BTreeMap::from([("X-Team".to_string(), "core".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            basic.headers(),
+            [
+                (
+                    "Authorization".to_string(),
+                    "Basic dXNlcjpwYXNz".to_string()
+                ),
+                ("X-Team".to_string(), "core".to_string()),
+            ]
+        );
+        assert!(!format!("{:?}", basic).contains("pass\""));
+
+        let credentials = BTreeMap::from([
+            ("api.example.com".to_string(), bearer.clone()),
+            ("localhost:8080".to_string(), basic.clone()),
+        ]);
+        let lookup = |url| credentials_for(url, &credentials);
+        assert_eq!(lookup("https://api.example.com/v1/items"), Some(&bearer));
+        assert_eq!(lookup("https://me@api.example.com:443/"), Some(&bearer));
+        assert_eq!(lookup("http://localhost:8080/a.json"), Some(&basic));
+        assert_eq!(lookup("http://localhost:9090/a.json"), None);
+        assert_eq!(lookup("https://example.com/?api.example.com"), None);
+    }
+
+    #[test]
+    fn test_parse_header() {
+        assert_eq!(
+            parse_header("X-Api-Key: abc:def"),
+            Ok(("X-Api-Key".to_string(), "abc:def".to_string()))
+        );
+        assert!(parse_header("no colon").is_err());
+        assert!(parse_header(": value").is_err());
+    }
+
+    #[test]
+    fn test_fetch_sends_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/a.json", listener.local_addr().unwrap());
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = [0; 1024];
+            let len = stream.read(&mut request).unwrap();
+            let _ = stream.write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}");
+            String::from_utf8_lossy(&request[..len]).to_lowercase()
+        });
+        let headers = [("Authorization".to_string(), "Bearer t0k".to_string())];
+        fetch_reporting(
+            &url,
+            Duration::from_secs(5),
+            &headers,
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        let request = server.join().unwrap();
+        assert!(request.contains("authorization: bearer t0k"), "{}", request);
+    }
+
+    #[test]
+    fn test_resolve_location() {
+        let base = "https://me@api.example.com/v1/items?page=2";
+        assert_eq!(
+            resolve_location(base, "http://cdn.example.com/a"),
+            "http://cdn.example.com/a"
+        );
+        assert_eq!(
+            resolve_location(base, "//cdn.example.com/a"),
+            "https://cdn.example.com/a"
+        );
+        assert_eq!(
+            resolve_location(base, "/v2/items"),
+            "https://me@api.example.com/v2/items"
+        );
+        assert_eq!(
+            resolve_location(base, "more"),
+            "https://me@api.example.com/v1/more"
+        );
+        assert_eq!(origin(base), "https://api.example.com");
+        assert_ne!(origin(base), origin("http://api.example.com/v1"));
+    }
+
+    #[test]
+    fn test_redirect_to_another_host_drops_headers() {
+        // Answers each connection with the next response, returning the requests
+        fn serve_all(
+            listener: TcpListener,
+            responses: Vec<String>,
+        ) -> thread::JoinHandle<Vec<String>> {
+            thread::spawn(move || {
+                responses
+                    .into_iter()
+                    .map(|response| {
+                        let (mut stream, _) = listener.accept().unwrap();
+                        let mut request = [0; 1024];
+                        let len = stream.read(&mut request).unwrap();
+                        let _ = stream.write_all(response.as_bytes());
+                        String::from_utf8_lossy(&request[..len]).to_lowercase()
+                    })
+                    .collect()
+            })
+        }
+        let redirect = |location: &str| {
+            format!(
+                "HTTP/1.1 302 Found\r\nLocation: {}\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+                location
+            )
+        };
+        let first = TcpListener::bind("127.0.0.1:0").unwrap();
+        let second = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/a.json", first.local_addr().unwrap());
+        let other = format!("http://{}/b.json", second.local_addr().unwrap());
+        let first = serve_all(first, vec![redirect("/moved.json"), redirect(&other)]);
+        let second = serve_all(
+            second,
+            vec!["HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\n{}".to_string()],
+        );
+
+        let headers = [("X-Api-Key".to_string(), "s3cret".to_string())];
+        let response = fetch_reporting(
+            &url,
+            Duration::from_secs(5),
+            &headers,
+            &LoadProgress::default(),
+        )
+        .unwrap();
+        assert_eq!(response.body, b"{}");
+
+        let first = first.join().unwrap();
+        // A redirect on the same server keeps the headers
+        assert!(first[1].starts_with("get /moved.json"), "{}", first[1]);
+        assert!(first[1].contains("x-api-key: s3cret"), "{}", first[1]);
+        let second = second.join().unwrap();
+        assert!(second[0].starts_with("get /b.json"), "{}", second[0]);
+        assert!(!second[0].contains("s3cret"), "{}", second[0]);
+    }
+
+    #[test]
+    fn test_fetch_times_out() {
+        let url = serve("HTTP/1.1 200 OK\r\n\r\n", Duration::from_secs(2));
+        let error = fetch(&url, Duration::from_millis(100)).unwrap_err();
+        assert_eq!(error.code(), "fetch");
+        assert!(error.hint().unwrap().contains("--fetch-timeout"));
+    }
+}
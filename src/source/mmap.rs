@@ -0,0 +1,89 @@
+//! Reading large files through a memory map
+//!
+//! Reading a multi-gigabyte file into a buffer and then parsing it needs the
+//! file's size in memory twice over before the document exists. Above
+//! [`MMAP_THRESHOLD`] the file is mapped instead and parsed straight from the
+//! mapping, whose pages the kernel can drop again once they have been read.
+
+use memmap2::Mmap;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+
+/// Files at least this large are mapped rather than read into memory
+pub const MMAP_THRESHOLD: u64 = 64 * 1024 * 1024;
+
+/// Read-only mapping
+#[derive(Debug)]
+pub struct MappedFile {
+    map: Mmap,
+}
+
+impl MappedFile {
+    /// Maps `path` when it is at least `threshold` bytes long, `None` otherwise
+    pub fn open_if_larger(path: &Path, threshold: u64) -> io::Result<Option<Self>> {
+        let file = File::open(path)?;
+        let len = file.metadata()?.len();
+        if len < threshold || len == 0 {
+            return Ok(None);
+        }
+        // SAFETY: the mapping is read-only and only lives while the file is
+        // parsed; a file truncated by another process during that time is
+        // outside what rjq can guard against, as with `less` or `jq`.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(Some(Self { map }))
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.map
+    }
+
+    /// The contents as text when they are valid UTF-8, without copying them
+    pub fn text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.map).ok()
+    }
+
+    /// The mapping as text that can outlive this borrow, e.g. in a
+    /// [`LazyDocument`](crate::query::LazyDocument); `Err` gives it back when
+    /// it is not UTF-8
+    pub fn into_text(self) -> Result<MappedText, Self> {
+        match self.text() {
+            Some(_) => Ok(MappedText(self)),
+            None => Err(self),
+        }
+    }
+}
+
+/// Mapping validated as UTF-8
+#[derive(Debug)]
+pub struct MappedText(MappedFile);
+
+impl AsRef<str> for MappedText {
+    fn as_ref(&self) -> &str {
+        // SAFETY: `into_text` checked that the bytes are UTF-8, and the
+        // mapping is read-only
+        unsafe { std::str::from_utf8_unchecked(self.0.bytes()) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_large_files_are_mapped() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_mmap_events_{}.json", std::process::id()));
+        std::fs::write(&path, b"{\"a\": [1, 2]}").unwrap();
+
+        assert!(MappedFile::open_if_larger(&path, 1024).unwrap().is_none());
+        let mapped = MappedFile::open_if_larger(&path, 4).unwrap().unwrap();
+        assert_eq!(mapped.text(), Some("{\"a\": [1, 2]}"));
+
+        std::fs::write(&path, b"\xff\xfe").unwrap();
+        let mapped = MappedFile::open_if_larger(&path, 0).unwrap().unwrap();
+        assert_eq!(mapped.bytes(), b"\xff\xfe");
+        assert_eq!(mapped.text(), None);
+        std::fs::remove_file(&path).ok();
+    }
+}
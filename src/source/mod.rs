@@ -0,0 +1,395 @@
+//! Where input data comes from
+//!
+//! Files and URLs are read the same way, so the TUI can read them again on reload.
+
+pub mod browser;
+pub mod compression;
+pub mod directory;
+pub mod follow;
+pub mod http;
+pub mod mmap;
+pub mod progress;
+pub mod watch;
+
+use crate::app::{AppError, AppWarning};
+use crate::input::{self, CsvOptions, InputFormat};
+use crate::query::{JsonData, LazyDocument};
+use http::Credentials;
+use mmap::MappedFile;
+use progress::LoadProgress;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
+use std::path::Path;
+use std::time::Duration;
+
+/// How long a URL may take to answer unless `--fetch-timeout` says otherwise
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// JSON files at least this large open before they are parsed as a whole
+pub const LAZY_THRESHOLD: u64 = 256 * 1024 * 1024;
+
+/// Where the data comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DataSource {
+    Stdin,
+    File(String),
+    /// A directory whose files are read as an array of `{filename, content}`
+    Directory(String),
+    /// An `http://` or `https://` URL, fetched with a GET request
+    Url(String),
+}
+
+impl DataSource {
+    /// A command-line argument: a URL when it starts with `http://` or
+    /// `https://`, a directory when one exists at that path
+    pub fn from_arg(arg: &str) -> Self {
+        if http::is_url(arg) {
+            DataSource::Url(arg.to_string())
+        } else if Path::new(arg).is_dir() {
+            DataSource::Directory(arg.to_string())
+        } else {
+            DataSource::File(arg.to_string())
+        }
+    }
+
+    /// Name used in messages, e.g. for invalid UTF-8
+    pub fn name(&self) -> &str {
+        match self {
+            DataSource::Stdin => "stdin",
+            DataSource::File(path) | DataSource::Directory(path) | DataSource::Url(path) => path,
+        }
+    }
+
+    /// Reads and parses a file or URL; stdin is read once up front by the caller
+    pub fn load(
+        &self,
+        options: &LoadOptions,
+        warnings: &mut Vec<AppWarning>,
+    ) -> Result<JsonData, AppError> {
+        self.load_reporting(options, warnings, &LoadProgress::default())
+    }
+
+    /// Like [`load`](Self::load), reporting each stage and the bytes read to `progress`
+    pub fn load_reporting(
+        &self,
+        options: &LoadOptions,
+        warnings: &mut Vec<AppWarning>,
+        progress: &LoadProgress,
+    ) -> Result<JsonData, AppError> {
+        match self {
+            DataSource::Stdin => Err(AppError::Io(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "stdin cannot be read again",
+            ))),
+            DataSource::File(path) => {
+                let not_found = |e: io::Error| match e.kind() {
+                    io::ErrorKind::NotFound => AppError::FileNotFound(path.clone()),
+                    _ => AppError::Io(e),
+                };
+                let format_path = compression::inner_path(Path::new(path));
+                let format = options.format_for(Some(&format_path));
+                // Large plain UTF-8 files are parsed in place; anything else is copied
+                let mapped = MappedFile::open_if_larger(Path::new(path), options.mmap_threshold)
+                    .map_err(not_found)?
+                    .filter(|mapped| compression::Compression::detect(mapped.bytes()).is_none());
+                if let Some(mapped) = mapped {
+                    let len = mapped.bytes().len() as u64;
+                    if format == InputFormat::Json
+                        && len >= options.lazy_threshold
+                        && !options.slurp
+                    {
+                        if let Ok(text) = mapped.into_text() {
+                            progress.start(format!("Indexing {}", path), None);
+                            return Ok(JsonData::lazy(LazyDocument::index(text)?));
+                        }
+                    } else if let Some(text) = mapped.text() {
+                        progress.start(format!("Parsing {}", path), None);
+                        return options.parse(text, format);
+                    }
+                }
+                let file = std::fs::File::open(path).map_err(not_found)?;
+                let len = file.metadata().map(|metadata| metadata.len()).ok();
+                progress.start(format!("Reading {}", path), len);
+                let mut bytes = Vec::with_capacity(len.unwrap_or(0) as usize);
+                progress
+                    .reader(file)
+                    .read_to_end(&mut bytes)
+                    .map_err(not_found)?;
+                progress.start(format!("Parsing {}", path), None);
+                let text = decode(compression::decompress(bytes)?, path, warnings);
+                options.parse(&text, format)
+            }
+            DataSource::Directory(dir) => directory::load(dir, options, warnings, progress),
+            DataSource::Url(url) => {
+                progress.start(format!("Fetching {}", url), None);
+                let headers = options.request_headers(url);
+                let response =
+                    http::fetch_reporting(url, options.fetch_timeout, &headers, progress)?;
+                // The content type says more about the body than the URL does
+                let selected = options.format.or(response.format);
+                let url_path = compression::inner_path(Path::new(http::url_path(url)));
+                let format = options.relax(InputFormat::resolve(selected, Some(&url_path)));
+                progress.start(format!("Parsing {}", url), None);
+                let text = decode(compression::decompress(response.body)?, url, warnings);
+                options.parse(&text, format)
+            }
+        }
+    }
+}
+
+/// How the input is read, as given on the command line
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadOptions {
+    /// Format selected on the command line; guessed per source when `None`
+    pub format: Option<InputFormat>,
+    /// Read JSON as JSON5
+    pub lenient: bool,
+    /// CSV/TSV field delimiter overriding the format's own
+    pub delimiter: Option<u8>,
+    /// CSV/TSV input starts with a header row
+    pub headers: bool,
+    pub fetch_timeout: Duration,
+    /// Files at least this many bytes are memory-mapped instead of read
+    pub mmap_threshold: u64,
+    /// JSON files at least this many bytes are parsed lazily, see [`LazyDocument`]
+    pub lazy_threshold: u64,
+    /// Collect every document of the input into one array, like `jq --slurp`
+    pub slurp: bool,
+    /// Read the subdirectories of a directory input too
+    pub recursive: bool,
+    /// Names of the files read from a directory input;
+    /// [`directory::DEFAULT_INCLUDE`] when `None`
+    pub include: Option<String>,
+    /// Pattern of the files queried together with `--glob`
+    pub glob: Option<String>,
+    /// Headers sent with every URL request, from `--header`
+    pub http_headers: Vec<(String, String)>,
+    /// Credentials by host, from the config's `[credentials]` sections
+    pub credentials: BTreeMap<String, Credentials>,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            format: None,
+            lenient: false,
+            delimiter: None,
+            headers: true,
+            fetch_timeout: DEFAULT_FETCH_TIMEOUT,
+            mmap_threshold: mmap::MMAP_THRESHOLD,
+            lazy_threshold: LAZY_THRESHOLD,
+            slurp: false,
+            recursive: false,
+            include: None,
+            glob: None,
+            http_headers: Vec::new(),
+            credentials: BTreeMap::new(),
+        }
+    }
+}
+
+impl LoadOptions {
+    /// The selected format, or the one implied by the extension of `path`;
+    /// `lenient` relaxes JSON to JSON5
+    pub fn format_for(&self, path: Option<&Path>) -> InputFormat {
+        self.relax(InputFormat::resolve(self.format, path))
+    }
+
+    /// Headers for a request to `url`: the credentials configured for its
+    /// host, then `--header`s, which replace them
+    pub fn request_headers(&self, url: &str) -> Vec<(String, String)> {
+        let mut headers = http::credentials_for(url, &self.credentials)
+            .map(Credentials::headers)
+            .unwrap_or_default();
+        headers.extend(self.http_headers.iter().cloned());
+        headers
+    }
+
+    /// CSV/TSV settings for `format`, with the overrides applied
+    pub fn csv_options(&self, format: InputFormat) -> CsvOptions {
+        let mut options = format.csv_options();
+        if let Some(delimiter) = self.delimiter {
+            options.delimiter = delimiter;
+        }
+        options.headers = self.headers;
+        options
+    }
+
+    /// Parses `text` read from any source in `format`
+    pub fn parse(&self, text: &str, format: InputFormat) -> Result<JsonData, AppError> {
+        if self.slurp {
+            return input::slurp(text, format, &self.csv_options(format)).map(JsonData::new);
+        }
+        input::load(text, format, &self.csv_options(format))
+    }
+
+    fn relax(&self, format: InputFormat) -> InputFormat {
+        if self.lenient {
+            format.lenient()
+        } else {
+            format
+        }
+    }
+}
+
+/// Decodes input as UTF-8, replacing invalid sequences and recording a warning
+pub fn decode(bytes: Vec<u8>, source: &str, warnings: &mut Vec<AppWarning>) -> String {
+    match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e) => {
+            warnings.push(AppWarning::LossyDecode {
+                source: source.to_string(),
+            });
+            String::from_utf8_lossy(e.as_bytes()).into_owned()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ContentGenerator;
+    use serde_json::json;
+
+    #[test]
+    fn test_source_from_arg() {
+        assert_eq!(
+            DataSource::from_arg("https://api.example.com/items"),
+            DataSource::Url("https://api.example.com/items".to_string())
+        );
+        assert_eq!(
+            DataSource::from_arg("http.json"),
+            DataSource::File("http.json".to_string())
+        );
+        assert_eq!(
+            DataSource::from_arg(&std::env::temp_dir().display().to_string()),
+            DataSource::Directory(std::env::temp_dir().display().to_string())
+        );
+        assert_eq!(DataSource::Stdin.name(), "stdin");
+    }
+
+    #[test]
+    fn test_load_options_pick_the_format() {
+        let options = LoadOptions {
+            lenient: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            options.format_for(Some(Path::new("a.json"))),
+            InputFormat::Json5
+        );
+        assert_eq!(
+            options.format_for(Some(Path::new("a.yaml"))),
+            InputFormat::Yaml
+        );
+
+        let options = LoadOptions {
+            delimiter: Some(b';'),
+            headers: false,
+            ..Default::default()
+        };
+        let data = options.parse("1;a\n", InputFormat::Csv).unwrap();
+        assert_eq!(data.get(), &json!([["1", "a"]]));
+    }
+
+    #[test]
+    fn test_request_headers() {
+        let options = LoadOptions {
+            http_headers: vec![("Authorization".to_string(), "Bearer cli".to_string())],
+            credentials: BTreeMap::from([(
+                "api.example.com".to_string(),
+                Credentials {
+                    bearer: Some("config".to_string()),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        // `--header` comes last, so it replaces the configured header
+        assert_eq!(
+            options.request_headers("https://api.example.com/items"),
+            [
+                ("Authorization".to_string(), "Bearer config".to_string()),
+                ("Authorization".to_string(), "Bearer cli".to_string()),
+            ]
+        );
+        assert_eq!(
+            options.request_headers("https://other.example.com/items"),
+            [("Authorization".to_string(), "Bearer cli".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_load_compressed_file() {
+        use std::io::Write;
+        let path = std::env::temp_dir().join(format!(
+            "rjq_source_events_{}.ndjson.gz",
+            std::process::id()
+        ));
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(b"{\"n\": 1}\n{\"n\": 2}\n").unwrap();
+        std::fs::write(&path, encoder.finish().unwrap()).unwrap();
+
+        let source = DataSource::File(path.display().to_string());
+        let data = source
+            .load(&LoadOptions::default(), &mut Vec::new())
+            .unwrap();
+        // The extension inside `.gz` picks the format
+        assert!(data.is_stream());
+        assert_eq!(data.inputs(), &[json!({"n": 1}), json!({"n": 2})]);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_load_mapped_file() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_source_mapped_{}.ndjson", std::process::id()));
+        std::fs::write(&path, "{\"n\": 1}\n{\"n\": 2}\n").unwrap();
+        let options = LoadOptions {
+            mmap_threshold: 1,
+            ..Default::default()
+        };
+
+        let source = DataSource::File(path.display().to_string());
+        let data = source.load(&options, &mut Vec::new()).unwrap();
+        assert_eq!(data.inputs(), &[json!({"n": 1}), json!({"n": 2})]);
+
+        // Invalid UTF-8 still falls back to the lossy copy
+        std::fs::write(&path, b"[\"\xff\"]").unwrap();
+        let mut warnings = Vec::new();
+        let data = source.load(&options, &mut warnings).unwrap();
+        assert_eq!(data.inputs(), &[json!(["\u{fffd}"])]);
+        assert_eq!(warnings.len(), 1);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_large_json_opens_before_it_is_parsed() {
+        let path =
+            std::env::temp_dir().join(format!("rjq_source_lazy_{}.json", std::process::id()));
+        let big: Vec<u32> = (0..500).collect();
+        std::fs::write(&path, json!({"big": big, "name": "x"}).to_string()).unwrap();
+        let options = LoadOptions {
+            mmap_threshold: 1,
+            lazy_threshold: 1,
+            ..Default::default()
+        };
+        let source = DataSource::File(path.display().to_string());
+        let data = source.load(&options, &mut Vec::new()).unwrap();
+        assert!(data.unparsed().is_some());
+
+        let mut app = crate::App::from_data(data);
+        let outline = app.generate_current_content();
+        assert!(outline.contains("not parsed yet"), "{}", outline);
+        app.set_input(".big | length");
+        assert_eq!(app.current_values().unwrap(), vec![json!(500)]);
+        assert!(app.data().unparsed().is_some());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_stdin_cannot_be_loaded_again() {
+        let result = DataSource::Stdin.load(&LoadOptions::default(), &mut Vec::new());
+        assert!(matches!(result, Err(AppError::Io(_))));
+    }
+}
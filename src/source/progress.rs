@@ -0,0 +1,114 @@
+//! Loading progress
+//!
+//! Reading a big file or a slow URL can take a while before the TUI has
+//! anything to draw. Loading reports what it is doing and how many bytes it
+//! has read, so the terminal can show that instead of staying blank.
+
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Progress of a load underway
+///
+/// Clones share the same progress, so a loader thread can report to the
+/// thread that draws it.
+#[derive(Debug, Clone, Default)]
+pub struct LoadProgress {
+    inner: Arc<Shared>,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    stage: Mutex<String>,
+    read: AtomicU64,
+    /// Expected bytes, 0 when unknown
+    total: AtomicU64,
+}
+
+/// Progress at one point in time
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoadStatus {
+    /// What is being done, e.g. `Reading data.json`
+    pub stage: String,
+    pub read: u64,
+    pub total: Option<u64>,
+}
+
+impl LoadProgress {
+    /// Starts a new stage, expecting `total` bytes when known
+    pub fn start(&self, stage: impl Into<String>, total: Option<u64>) {
+        *self.inner.stage.lock().unwrap_or_else(|e| e.into_inner()) = stage.into();
+        self.inner.read.store(0, Ordering::Relaxed);
+        self.inner
+            .total
+            .store(total.unwrap_or(0), Ordering::Relaxed);
+    }
+
+    pub fn add(&self, bytes: u64) {
+        self.inner.read.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn status(&self) -> LoadStatus {
+        LoadStatus {
+            stage: self
+                .inner
+                .stage
+                .lock()
+                .unwrap_or_else(|e| e.into_inner())
+                .clone(),
+            read: self.inner.read.load(Ordering::Relaxed),
+            total: Some(self.inner.total.load(Ordering::Relaxed)).filter(|&total| total > 0),
+        }
+    }
+
+    /// Wraps `reader` so the bytes read through it count toward the stage
+    pub fn reader<R: Read>(&self, reader: R) -> ProgressReader<R> {
+        ProgressReader {
+            inner: reader,
+            progress: self.clone(),
+        }
+    }
+}
+
+/// `Read` counting the bytes read into a [`LoadProgress`]
+#[derive(Debug)]
+pub struct ProgressReader<R> {
+    inner: R,
+    progress: LoadProgress,
+}
+
+impl<R: Read> Read for ProgressReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.add(n as u64);
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reader_counts_bytes() {
+        let progress = LoadProgress::default();
+        progress.start("Reading stdin", None);
+        let mut text = String::new();
+        progress
+            .reader("hello".as_bytes())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert_eq!(
+            progress.status(),
+            LoadStatus {
+                stage: "Reading stdin".to_string(),
+                read: 5,
+                total: None,
+            }
+        );
+
+        progress.start("Parsing stdin", Some(5));
+        let status = progress.status();
+        assert_eq!((status.read, status.total), (0, Some(5)));
+    }
+}
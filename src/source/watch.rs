@@ -0,0 +1,124 @@
+//! Watching the input file for changes (`--watch`)
+
+use crate::app::AppError;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::time::Duration;
+
+/// How often the event loop looks for changes while no key is pressed
+pub const WATCH_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Watches a file for changes
+///
+/// The directory is watched rather than the file, so editors that save by
+/// writing a new file and renaming it over the old one are noticed too.
+pub struct FileWatcher {
+    path: PathBuf,
+    events: Receiver<notify::Result<Event>>,
+    _watcher: RecommendedWatcher,
+}
+
+impl std::fmt::Debug for FileWatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FileWatcher")
+            .field("path", &self.path)
+            .finish_non_exhaustive()
+    }
+}
+
+impl FileWatcher {
+    pub fn new(path: impl AsRef<Path>) -> Result<Self, AppError> {
+        let path = path.as_ref();
+        let absolute = std::fs::canonicalize(path).map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => AppError::FileNotFound(path.display().to_string()),
+            _ => AppError::Io(e),
+        })?;
+        let dir = absolute.parent().unwrap_or(Path::new("/")).to_path_buf();
+        let (sender, events) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender).map_err(watch_error)?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .map_err(watch_error)?;
+        Ok(Self {
+            path: absolute,
+            events,
+            _watcher: watcher,
+        })
+    }
+
+    /// The watched file, as an absolute path
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Whether the file was written, created or replaced since the last call
+    ///
+    /// Every pending event is consumed, so a burst of writes from one save
+    /// counts as one change.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        for event in self.events.try_iter().flatten() {
+            let touches_file = event.paths.contains(&self.path);
+            changed |=
+                touches_file && matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_));
+        }
+        changed
+    }
+}
+
+fn watch_error(error: notify::Error) -> AppError {
+    AppError::Io(io::Error::other(error.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    fn wait_for_change(watcher: &FileWatcher) -> bool {
+        let started = Instant::now();
+        while started.elapsed() < Duration::from_secs(5) {
+            if watcher.changed() {
+                return true;
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+        false
+    }
+
+    #[test]
+    fn test_notices_writes_to_the_watched_file_only() {
+        let dir = std::env::temp_dir().join(format!("rjq-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("data.json");
+        std::fs::write(&file, "{\"a\":1}").unwrap();
+
+        let watcher = FileWatcher::new(&file).unwrap();
+        assert!(!watcher.changed());
+
+        std::fs::write(dir.join("other.json"), "{}").unwrap();
+        std::thread::sleep(Duration::from_millis(300));
+        assert!(!watcher.changed());
+
+        std::fs::write(&file, "{\"a\":2}").unwrap();
+        assert!(wait_for_change(&watcher));
+
+        // Saving through a rename, as many editors do
+        let temp = dir.join("data.json.tmp");
+        std::fs::write(&temp, "{\"a\":3}").unwrap();
+        std::fs::rename(&temp, &file).unwrap();
+        assert!(wait_for_change(&watcher));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_missing_file() {
+        assert!(matches!(
+            FileWatcher::new("/nonexistent/rjq/data.json"),
+            Err(AppError::FileNotFound(_))
+        ));
+    }
+}
@@ -1,117 +1,1476 @@
-use super::events::{get_action, update};
+use super::events::{ActionContext, get_action, update_with_context};
+use super::handler::{DefaultEventHandler, EventHandler};
+use super::inspector;
 use super::syntax::SyntaxHighlighter;
-use crate::app::App;
+use crate::app::{
+    App, AppError, BrowserState, ExecutionPolicy, FrequencyState, JOIN_PARTS, JoinState,
+    ManualState, PinnedResult,
+};
+use crate::history::{Fix, UsageStats};
+use crate::query::docs::BuiltinDoc;
+use crate::query::{FileSummary, SizeIndex};
+use crate::render::chart::{self, ChartKind, Counts, Stats};
+use crate::render::{ColumnSpec, RenderTree, RuleColor, RuleStyle, Table, TableLayout, TableQuery};
+use crate::source::watch::WATCH_POLL_INTERVAL;
 use crossterm::event::{self, Event, KeyEvent};
 use ratatui::{
     Frame, Terminal,
     backend::Backend,
     buffer::Buffer,
     layout::{Constraint, Direction, Layout, Rect},
-    style::{Color, Style},
+    style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Paragraph, Widget},
+    widgets::{
+        Bar, BarChart, BarGroup, Block, Borders, Clear, List, ListItem, Paragraph, Row, Sparkline,
+        Table as TableWidget, Tabs, Widget, Wrap,
+    },
 };
+use std::ops::Range;
+use std::time::Duration;
+
+/// A background query shows the spinner once it has run this long
+pub(crate) const SPINNER_DELAY: Duration = Duration::from_millis(200);
+
+/// Time between two frames of the spinner
+pub(crate) const SPINNER_INTERVAL: Duration = Duration::from_millis(80);
+
+/// How often the event loop looks for a finished background query
+const QUERY_POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Frames the spinner cycles through
+pub(crate) const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Longest query shown in a tab title before it is cut off
+const TAB_TITLE_WIDTH: usize = 20;
+
+/// Widest the documentation popup grows
+const DOC_POPUP_WIDTH: u16 = 60;
+
+/// Width of the manual's function list
+const MANUAL_LIST_WIDTH: u16 = 24;
+
+/// Width of the table view's column picker
+const COLUMN_PICKER_WIDTH: u16 = 60;
+
+/// Number of string literal picker entries shown at once
+const PICKER_HEIGHT: usize = 8;
+
+/// Smallest terminal the views are laid out for; below it only a notice
+/// asking for a larger window is drawn
+pub const MIN_WIDTH: u16 = 40;
+pub const MIN_HEIGHT: u16 = 6;
 
 impl App {
     pub fn run<B: Backend>(&mut self, terminal: &mut Terminal<B>) -> crate::Result<()> {
+        self.run_with(terminal, &DefaultEventHandler)
+    }
+
+    /// Runs the event loop, passing every terminal event through `handler`
+    pub fn run_with<B: Backend, H: EventHandler + ?Sized>(
+        &mut self,
+        terminal: &mut Terminal<B>,
+        handler: &H,
+    ) -> crate::Result<()> {
+        if let Ok(size) = terminal.size() {
+            self.resize(size.height);
+        }
         while !self.should_exit() {
+            self.run_due_query();
+            self.poll_query();
+            self.poll_export();
+            self.refresh_query_tokens();
             terminal.draw(|frame| self.draw(frame))?;
-            if let Event::Key(key_event) = event::read()? {
-                self.handle_events(key_event)?;
+
+            if let Some(timeout) = self.time_until_replay_step()
+                && !event::poll(timeout)?
+            {
+                let effects = self.advance_replay();
+                self.run_effects(effects);
+                continue;
+            }
+            // The spinner turns, the export progress advances and a finished
+            // query is shown while no key arrives
+            if (self.running_query().is_some() || self.export_progress().is_some())
+                && !event::poll(QUERY_POLL_INTERVAL)?
+            {
+                continue;
+            }
+            // A debounced edit runs once no key arrives before it is due
+            if let Some(timeout) = self.time_until_execution()
+                && !event::poll(timeout)?
+            {
+                continue;
+            }
+            if self.has_live_input() && !self.wait_for_input()? {
+                continue;
             }
+            let event = event::read()?;
+            if let Event::Resize(_, height) = event {
+                self.resize(height);
+            }
+            let context = ActionContext::from_event(&event);
+            let effects = handler.update_app(self, handler.handle_event(event), context);
+            self.run_effects(effects);
+        }
+        // Quitting mid-export would leave a truncated file behind
+        while self.export_progress().is_some() && !self.poll_export() {
+            std::thread::sleep(QUERY_POLL_INTERVAL);
         }
         Ok(())
     }
 
+    /// Waits for a terminal event, picking up changes to the input meanwhile;
+    /// `false` once the input changed, so the loop redraws
+    fn wait_for_input(&mut self) -> crate::Result<bool> {
+        loop {
+            if event::poll(WATCH_POLL_INTERVAL)? {
+                return Ok(true);
+            }
+            if self.poll_live_input() {
+                return Ok(false);
+            }
+        }
+    }
+
     fn draw(&self, frame: &mut Frame) {
         frame.render_widget(self, frame.area());
-        frame.set_cursor_position(((self.prompt().len() + self.input().len()) as u16, 0));
+        if !fits(frame.area()) {
+            return;
+        }
+        frame.set_cursor_position((
+            (self.prompt().len() + self.input()[..self.cursor()].chars().count()) as u16,
+            self.tab_bar_height(),
+        ));
+    }
+
+    /// The tab bar is only shown once a second tab has been opened
+    fn tab_bar_height(&self) -> u16 {
+        if self.tab_queries().len() > 1 { 1 } else { 0 }
     }
 
     pub fn handle_events(&mut self, key_event: KeyEvent) -> crate::Result<()> {
-        let action = get_action(key_event);
-        update(self, action);
+        let context = ActionContext::from_key(&key_event);
+        let effects = update_with_context(self, get_action(key_event), context);
+        self.run_effects(effects);
         Ok(())
     }
 
-    fn render_input_with_suggestion(&self, area: Rect, buf: &mut Buffer) {
+    fn render_input_with_suggestion(
+        &self,
+        error_span: Option<&Range<usize>>,
+        area: Rect,
+        buf: &mut Buffer,
+    ) {
         let prompt = self.prompt();
         let input = self.input();
 
-        // 最適候補を取得
-        let suggestion = self.get_best_suggestion();
+        // スライダーモードでは選択中のリテラルを反転表示
+        if let Some(range) = self.slider_literal() {
+            let line = Line::from(vec![
+                Span::raw(format!("{}{}", prompt, &input[..range.start])),
+                Span::styled(
+                    &input[range.clone()],
+                    Style::default().fg(Color::Black).bg(Color::Cyan),
+                ),
+                Span::raw(&input[range.end..]),
+            ]);
+            Paragraph::new(line).render(area, buf);
+            return;
+        }
+
+        // エラー箇所を赤い下線で表示
+        if let Some(span) = error_span {
+            let line = Line::from(vec![
+                Span::raw(format!("{}{}", prompt, &input[..span.start])),
+                Span::styled(
+                    &input[span.clone()],
+                    Style::default()
+                        .fg(Color::Red)
+                        .add_modifier(Modifier::UNDERLINED),
+                ),
+                Span::raw(&input[span.end..]),
+            ]);
+            Paragraph::new(line).render(area, buf);
+            return;
+        }
+
+        // 最適候補を取得（カーソルが末尾にあるときのみ）
+        let suggestion = if self.cursor() == input.len() {
+            self.get_best_suggestion()
+        } else {
+            None
+        };
 
-        if let Some(candidate) = suggestion.as_ref().and_then(|c| c.strip_prefix(input)) {
-            // 入力済み部分 + 候補部分の表示
-            // 通常色で入力部分
-            let input_text = format!("{}{}", prompt, input);
-            let input_span = Span::styled(input_text, Style::default());
+        // 括弧を深さごとに色分けした入力部分
+        let mut spans = vec![Span::raw(prompt)];
+        spans.extend(SyntaxHighlighter::new().highlight_query_tokens(input, &self.query_tokens()));
 
-            // グレー色で候補部分
-            let suggestion_span = Span::styled(candidate, Style::default().fg(Color::DarkGray));
+        // 候補がある場合はグレー色で候補部分を続ける
+        if let Some(suggestion) = &suggestion
+            && let Some(candidate) = suggestion.strip_prefix(input)
+        {
+            spans.push(Span::styled(
+                candidate,
+                Style::default().fg(Color::DarkGray),
+            ));
+            // Cancelled before, so accepting it would likely stall again
+            if self.is_slow_query(suggestion) {
+                spans.push(Span::styled("  (slow)", Style::default().fg(Color::Yellow)));
+            }
+        }
 
-            let line = Line::from(vec![input_span, suggestion_span]);
-            let paragraph = Paragraph::new(line);
-            paragraph.render(area, buf);
-            return;
+        Paragraph::new(Line::from(spans)).render(area, buf);
+    }
+}
+
+/// Draws a caret under the prompt pointing at the error
+fn render_caret(prompt: &str, input: &str, span: &Range<usize>, area: Rect, buf: &mut Buffer) {
+    let column = prompt.chars().count() + input[..span.start].chars().count();
+    // An empty span (e.g. unexpected end of input) still gets a single caret
+    let width = input[span.clone()].chars().count().max(1);
+    let caret = format!("{}^{}", " ".repeat(column), "~".repeat(width - 1));
+    Paragraph::new(Span::styled(caret, Style::default().fg(Color::Red))).render(area, buf);
+}
+
+/// Draws the error message and its hint
+fn render_error(error: &AppError, fix: Option<Fix>, area: Rect, buf: &mut Buffer) {
+    let mut lines = vec![Line::from(Span::styled(
+        format!("Error: {}", error),
+        Style::default().fg(Color::Red),
+    ))];
+    if let Some(hint) = error.hint() {
+        lines.push(Line::from(Span::styled(
+            format!("hint: {}", hint),
+            Style::default().fg(Color::Yellow),
+        )));
+    }
+    if let Some(fix) = fix {
+        lines.push(Line::from(Span::styled(
+            format!(
+                "fixed before: `{}` failed like this and `{}` worked",
+                fix.failed, fix.fixed
+            ),
+            Style::default().fg(Color::Green),
+        )));
+    }
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .render(area, buf);
+}
+
+/// Draws the tab bar
+fn render_tab_bar(app: &App, area: Rect, buf: &mut Buffer) {
+    let sources = app.tab_sources();
+    let titles = app.tab_queries().into_iter().enumerate().map(|(i, query)| {
+        let query = if query.is_empty() { "." } else { query };
+        let title = if query.chars().count() > TAB_TITLE_WIDTH {
+            let cut: String = query.chars().take(TAB_TITLE_WIDTH - 1).collect();
+            format!("{}…", cut)
+        } else {
+            query.to_string()
+        };
+        match sources.get(i).copied().flatten() {
+            Some(file) => format!("{}: {} {}", i + 1, file, title),
+            None => format!("{}: {}", i + 1, title),
+        }
+    });
+    Tabs::new(titles)
+        .select(app.active_tab())
+        .style(Style::default().fg(Color::DarkGray))
+        .highlight_style(Style::default().fg(Color::Cyan))
+        .render(area, buf);
+}
+
+/// Draws the result tree from the scroll position
+fn render_document(tree: &RenderTree, scroll_offset: usize, area: Rect, buf: &mut Buffer) {
+    // JSONにシンタックスハイライトを適用し、検索に一致した行は背景色で示す
+    let highlighter = SyntaxHighlighter::new();
+    let highlighted_lines: Vec<Line> = tree
+        .lines()
+        .iter()
+        .skip(scroll_offset)
+        .take(area.height as usize)
+        .map(|line| {
+            let mut highlighted = highlighter.highlight_line(&line.text);
+            if let Some(style) = line.style {
+                let style = rule_style(style);
+                for span in &mut highlighted.spans {
+                    span.style = span.style.patch(style);
+                }
+            }
+            if line.highlighted {
+                highlighted.patch_style(Style::default().bg(Color::DarkGray))
+            } else {
+                highlighted
+            }
+        })
+        .collect();
+
+    Paragraph::new(highlighted_lines).render(area, buf);
+}
+
+/// The terminal style of a highlight rule from the config
+fn rule_style(style: RuleStyle) -> Style {
+    let mut result = Style::default();
+    if let Some(color) = style.color {
+        result = result.fg(match color {
+            RuleColor::Red => Color::Red,
+            RuleColor::Green => Color::Green,
+            RuleColor::Yellow => Color::Yellow,
+            RuleColor::Blue => Color::Blue,
+            RuleColor::Magenta => Color::Magenta,
+            RuleColor::Cyan => Color::Cyan,
+            RuleColor::Gray => Color::Gray,
+            RuleColor::White => Color::White,
+        });
+    }
+    for (enabled, modifier) in [
+        (style.bold, Modifier::BOLD),
+        (style.dim, Modifier::DIM),
+        (style.underline, Modifier::UNDERLINED),
+    ] {
+        if enabled {
+            result = result.add_modifier(modifier);
+        }
+    }
+    result
+}
+
+/// Draws a list of objects as a table
+///
+/// The sorted column is marked with an arrow and the selected one is shown
+/// reversed. A quick filter, while typed or applied, gets a line above the table.
+fn render_table(app: &App, table: &Table, layout: &TableLayout, area: Rect, buf: &mut Buffer) {
+    let query = app.table_query();
+    let mut area = area;
+    if app.table_filter_editing() || !query.filter.is_empty() {
+        render_table_filter(app, query, table.rows.len(), area, buf);
+        area.y += 1;
+        area.height = area.height.saturating_sub(1);
+    }
+
+    let columns: Vec<&ColumnSpec> = layout.visible().collect();
+    let selected = app.table_column().min(columns.len().saturating_sub(1));
+    let header = Row::new(columns.iter().enumerate().map(|(i, column)| {
+        let arrow = match &query.sort {
+            Some(sort) if sort.key == column.key && sort.descending => " ▼",
+            Some(sort) if sort.key == column.key => " ▲",
+            _ => "",
+        };
+        let style = if i == selected {
+            Style::default().add_modifier(Modifier::REVERSED)
+        } else {
+            Style::default()
+        };
+        Span::styled(format!("{}{}", column.key, arrow), style)
+    }))
+    .style(
+        Style::default()
+            .fg(Color::Blue)
+            .add_modifier(Modifier::BOLD),
+    );
+    let first = app.scroll_offset().min(table.rows.len());
+    let last = (first + area.height as usize).min(table.rows.len());
+    let styles = app.row_styles(table, layout);
+    let rows = (first..last).map(|row| {
+        let cells = Row::new(columns.iter().map(|column| table.cell(row, &column.key)));
+        match styles.get(row).copied().flatten() {
+            Some(style) => cells.style(rule_style(style)),
+            None => cells,
+        }
+    });
+    let widths = columns
+        .iter()
+        .map(|column| Constraint::Length(table.width(column)));
+    Widget::render(TableWidget::new(rows, widths).header(header), area, buf);
+}
+
+/// Draws the table's filter text and the number of matching rows
+fn render_table_filter(app: &App, query: &TableQuery, rows: usize, area: Rect, buf: &mut Buffer) {
+    let cursor = if app.table_filter_editing() {
+        "█"
+    } else {
+        ""
+    };
+    let line = Line::from(vec![
+        Span::styled("filter: ", Style::default().fg(Color::Cyan)),
+        Span::raw(format!("{}{}", query.filter, cursor)),
+        Span::styled(
+            format!("  ({} rows)", rows),
+            Style::default().fg(Color::DarkGray),
+        ),
+    ]);
+    Paragraph::new(line).render(Rect { height: 1, ..area }, buf);
+}
+
+/// Draws the JSON view's row filter and the rows left
+fn render_row_filter(app: &App, area: Rect, buf: &mut Buffer) {
+    let cursor = if app.row_filter_editing() { "█" } else { "" };
+    let status = match (app.row_filter_error(), app.row_count()) {
+        (Some(error), _) => Span::styled(format!("  ({})", error), Style::default().fg(Color::Red)),
+        (None, Some(count)) => Span::styled(
+            format!("  ({} of {} rows)", count.shown, count.total),
+            Style::default().fg(Color::DarkGray),
+        ),
+        (None, None) => Span::raw(""),
+    };
+    let line = Line::from(vec![
+        Span::styled("rows: ", Style::default().fg(Color::Cyan)),
+        Span::raw(format!("{}{}", app.row_filter(), cursor)),
+        status,
+    ]);
+    Paragraph::new(line).render(Rect { height: 1, ..area }, buf);
+}
+
+/// Draws the error of a query that stopped partway
+fn render_runtime_error(outputs: usize, error: &str, area: Rect, buf: &mut Buffer) {
+    let line = Line::from(Span::styled(
+        format!(
+            "error after {} output{}: {}",
+            outputs,
+            if outputs == 1 { "" } else { "s" },
+            error
+        ),
+        Style::default().fg(Color::Red),
+    ));
+    Paragraph::new(line).render(Rect { height: 1, ..area }, buf);
+}
+
+/// Draws the SQL mode's input and whether it translates
+fn render_sql(app: &App, sql: &str, area: Rect, buf: &mut Buffer) {
+    let status = match app.sql_error() {
+        Some(error) => Span::styled(format!("  ({})", error), Style::default().fg(Color::Red)),
+        None => Span::styled(
+            "  (jq in the query line · Enter/Esc back to jq)",
+            Style::default().fg(Color::DarkGray),
+        ),
+    };
+    let line = Line::from(vec![
+        Span::styled("SQL: ", Style::default().fg(Color::Magenta)),
+        Span::raw(format!("{}█", sql)),
+        status,
+    ]);
+    Paragraph::new(line).render(Rect { height: 1, ..area }, buf);
+}
+
+/// Draws subtree sizes as nested horizontal bars
+///
+/// Every bar is scaled to the whole document, so a child's bar sits inside
+/// its parent's length.
+fn render_size_view(index: &SizeIndex, scroll_offset: usize, area: Rect, buf: &mut Buffer) {
+    let total = index.root.bytes.max(1);
+    // Nested entries are labelled relative to their parent, e.g. `[0]` under `.items`
+    let mut parents: Vec<&str> = Vec::new();
+    let lines: Vec<(usize, String, usize)> = index
+        .lines()
+        .into_iter()
+        .map(|(depth, node)| {
+            parents.truncate(depth);
+            let relative = parents
+                .last()
+                .and_then(|parent| node.path.strip_prefix(parent))
+                .unwrap_or(&node.path);
+            let label = format!("{}{}", "  ".repeat(depth), relative);
+            parents.push(&node.path);
+            (depth, label, node.bytes)
+        })
+        .collect();
+    let label_width = lines
+        .iter()
+        .map(|(_, label, _)| label.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(area.width as usize / 3);
+    // "100.0% 1023.9 KiB" after the bar
+    let bar_width = (area.width as usize)
+        .saturating_sub(label_width + 20)
+        .max(1);
+    let colors = [Color::Cyan, Color::Blue, Color::Magenta];
+
+    let rendered: Vec<Line> = lines
+        .iter()
+        .skip(scroll_offset)
+        .take(area.height as usize)
+        .map(|(depth, label, bytes)| {
+            let label: String = label.chars().take(label_width).collect();
+            let share = *bytes as f64 / total as f64;
+            let filled = ((share * bar_width as f64).round() as usize).min(bar_width);
+            Line::from(vec![
+                Span::raw(format!("{:<width$} ", label, width = label_width)),
+                Span::styled(
+                    "█".repeat(filled),
+                    Style::default().fg(colors[depth % colors.len()]),
+                ),
+                Span::raw(" ".repeat(bar_width - filled)),
+                Span::styled(
+                    format!(" {:>5.1}% {}", share * 100.0, chart::format_bytes(*bytes)),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ])
+        })
+        .collect();
+    Paragraph::new(rendered).render(area, buf);
+}
+
+/// Draws each workspace file's result on one line
+///
+/// Each row has the file, its match count and the first match; the list
+/// scrolls to keep the selected row in view.
+fn render_workspace(summaries: &[FileSummary], selected: usize, area: Rect, buf: &mut Buffer) {
+    let matched = summaries.iter().filter(|s| s.matches > 0).count();
+    let mut lines = vec![Line::styled(
+        format!(
+            "{} files, {} with matches  [↑/↓ select, F10 open]",
+            summaries.len(),
+            matched
+        ),
+        Style::default().fg(Color::DarkGray),
+    )];
+    let path_width = summaries
+        .iter()
+        .map(|s| s.path.chars().count())
+        .max()
+        .unwrap_or(0)
+        .min(area.width as usize / 2);
+    let rows = (area.height as usize).saturating_sub(1);
+    let offset = (selected + 1).saturating_sub(rows);
+    lines.extend(
+        summaries
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(rows)
+            .map(|(i, summary)| {
+                let path: String = summary.path.chars().take(path_width).collect();
+                let path_style = if i == selected {
+                    Style::default().add_modifier(Modifier::REVERSED)
+                } else {
+                    Style::default()
+                };
+                let (count, detail) = match (&summary.error, &summary.first) {
+                    (Some(error), _) => (
+                        Span::styled("  err ", Style::default().fg(Color::Red)),
+                        Span::styled(error.clone(), Style::default().fg(Color::Red)),
+                    ),
+                    (None, first) => (
+                        Span::styled(
+                            format!(" {:>4} ", summary.matches),
+                            Style::default().fg(if summary.matches > 0 {
+                                Color::Green
+                            } else {
+                                Color::DarkGray
+                            }),
+                        ),
+                        Span::raw(first.as_ref().map(|v| v.to_string()).unwrap_or_default()),
+                    ),
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:<width$}", path, width = path_width), path_style),
+                    count,
+                    detail,
+                ])
+            }),
+    );
+    Paragraph::new(lines).render(area, buf);
+}
+
+/// Draws labelled values as horizontal bars, largest first
+fn render_bar_chart(counts: &Counts, area: Rect, buf: &mut Buffer) {
+    let shown = counts
+        .entries
+        .len()
+        .min(area.height.saturating_sub(2) as usize);
+    let mut block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(" {} by {} ", counts.value, counts.label));
+    if shown < counts.entries.len() {
+        block = block.title_bottom(format!(" top {} of {} ", shown, counts.entries.len()));
+    }
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    // Bars are scaled to the largest value; negative values draw as empty
+    let max = counts.entries.first().map_or(0.0, |(_, v)| *v).max(0.0);
+    let bars: Vec<Bar> = counts.entries[..shown]
+        .iter()
+        .map(|(label, value)| {
+            let scaled = if max > 0.0 {
+                (value.max(0.0) / max * 1000.0).round() as u64
+            } else {
+                0
+            };
+            Bar::default()
+                .value(scaled)
+                .text_value(chart::format_number(*value))
+                .label(Line::from(label.as_str()))
+        })
+        .collect();
+    BarChart::default()
+        .direction(Direction::Horizontal)
+        .data(BarGroup::default().bars(&bars))
+        .max(1000)
+        .bar_width(1)
+        .bar_gap(0)
+        .bar_style(Style::default().fg(Color::Cyan))
+        .value_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+        .render(inner, buf);
+}
+
+/// Draws the selected chart; draws nothing and returns `false` when the result no longer fits it
+fn render_chart(app: &App, kind: ChartKind, area: Rect, buf: &mut Buffer) -> bool {
+    if kind == ChartKind::Bar {
+        let Some(counts) = app.bar_data() else {
+            return false;
+        };
+        render_bar_chart(&counts, area, buf);
+    } else {
+        let Some((label, data)) = app.chart_data() else {
+            return false;
+        };
+        render_distribution(kind, &label, &data, area, buf);
+    }
+    true
+}
+
+/// Draws the counts of a field's values as a bar chart over the result
+fn render_frequency(frequency: &FrequencyState, area: Rect, buf: &mut Buffer) {
+    let popup = Rect {
+        x: area.x + area.width / 10,
+        y: area.y + area.height / 10,
+        width: area.width - area.width / 5,
+        height: area.height - area.height / 5,
+    };
+    Clear.render(popup, buf);
+    render_bar_chart(&frequency.counts, popup, buf);
+    // On the right of the bottom border, clear of the "top N of M" title
+    let hint = " Enter insert query · Esc/Alt+C close ";
+    let width = hint.chars().count() as u16;
+    if popup.height > 2 && popup.width > width * 2 {
+        let line = Line::styled(hint, Style::default().fg(Color::DarkGray));
+        Paragraph::new(line).render(
+            Rect {
+                x: popup.x + popup.width - 1 - width,
+                y: popup.y + popup.height - 1,
+                width,
+                height: 1,
+            },
+            buf,
+        );
+    }
+}
+
+/// Draws the join's array and key fields, the candidates and the generated expression
+fn render_join(app: &App, join: &JoinState, area: Rect, buf: &mut Buffer) {
+    let height = (JOIN_PARTS.len() as u16 + 6).min(area.height);
+    let popup = Rect {
+        x: area.x + area.width / 10,
+        y: area.y + (area.height - height) / 2,
+        width: area.width - area.width / 5,
+        height,
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(" Join two arrays ")
+        .title_bottom(" ↑/↓ part · Tab suggest · Enter insert · Esc close ");
+    let inner = block.inner(popup);
+    Clear.render(popup, buf);
+    block.render(popup, buf);
+
+    let spec = &join.spec;
+    let values = [&spec.left, &spec.left_key, &spec.right, &spec.right_key];
+    let mut lines: Vec<Line> = JOIN_PARTS
+        .iter()
+        .zip(values)
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let focused = i == join.focus;
+            let cursor = if focused { "█" } else { "" };
+            let label_style = if focused {
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::Cyan)
+            };
+            Line::from(vec![
+                Span::styled(format!("{:<12}", label), label_style),
+                Span::raw(format!("{}{}", value, cursor)),
+            ])
+        })
+        .collect();
+    lines.push(Line::styled(
+        format!("  {}", app.join_candidates().join("  ")),
+        Style::default().fg(Color::DarkGray),
+    ));
+    lines.push(Line::raw(""));
+    if spec.is_complete() {
+        lines.push(Line::styled(
+            spec.to_jq(),
+            Style::default().fg(Color::Green),
+        ));
+    }
+    Paragraph::new(lines)
+        .wrap(Wrap { trim: false })
+        .render(inner, buf);
+}
+
+/// Draws a distribution of numbers as a chart with its count, min, max and mean
+fn render_distribution(kind: ChartKind, label: &str, data: &[f64], area: Rect, buf: &mut Buffer) {
+    let Some(stats) = Stats::of(data) else {
+        return;
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(" {} of {} ", kind.title(), label))
+        .title_bottom(format!(
+            " n={} min={} max={} mean={} ",
+            stats.count,
+            chart::format_number(stats.min),
+            chart::format_number(stats.max),
+            chart::format_number(stats.mean)
+        ));
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    if kind == ChartKind::Histogram {
+        // Narrow terminals get fewer, at least two cells wide, bins
+        let bins = chart::default_bins(data.len()).min((inner.width / 3).max(1) as usize);
+        let bins = chart::histogram(data, bins);
+        let bar_width = (inner.width / bins.len() as u16).saturating_sub(1).max(1);
+        let bars: Vec<Bar> = bins
+            .iter()
+            .map(|bin| {
+                Bar::default()
+                    .value(bin.count)
+                    .label(Line::from(chart::format_number(bin.start)))
+            })
+            .collect();
+        BarChart::default()
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(bar_width)
+            .bar_gap(1)
+            .bar_style(Style::default().fg(Color::Cyan))
+            .value_style(Style::default().fg(Color::Black).bg(Color::Cyan))
+            .render(inner, buf);
+    } else {
+        // Scale to 0..=100 so fractions and negative values still show
+        let range = stats.max - stats.min;
+        let points: Vec<u64> = chart::resample(data, inner.width as usize)
+            .iter()
+            .map(|v| {
+                if range == 0.0 {
+                    1
+                } else {
+                    ((v - stats.min) / range * 100.0).round() as u64
+                }
+            })
+            .collect();
+        Sparkline::default()
+            .data(&points)
+            .style(Style::default().fg(Color::Cyan))
+            .render(inner, buf);
+    }
+}
+
+/// Draws the popup choosing the table's visible columns, their order and widths
+fn render_column_picker(
+    table: &Table,
+    layout: &TableLayout,
+    selected: usize,
+    area: Rect,
+    buf: &mut Buffer,
+) {
+    let items: Vec<ListItem> = layout
+        .columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let mark = if column.visible { "[x]" } else { "[ ]" };
+            let width = match column.width {
+                Some(width) => width.to_string(),
+                None => format!("auto ({})", table.auto_width(&column.key)),
+            };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Span::styled(
+                format!("{} {}  {}", mark, column.key, width),
+                style,
+            ))
+        })
+        .collect();
+    let popup = Rect {
+        x: area.x,
+        y: area.y,
+        width: area.width.min(COLUMN_PICKER_WIDTH),
+        height: (items.len() as u16 + 2).min(area.height),
+    };
+    Clear.render(popup, buf);
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray))
+                .title(" Columns ")
+                .title_bottom(" Space show · Shift+↑↓ move · +/- width "),
+        )
+        .render(popup, buf);
+}
+
+/// Draws the pinned result as a read-only pane
+fn render_pinned(pinned: &PinnedResult, scroll_offset: usize, area: Rect, buf: &mut Buffer) {
+    let block = Block::default()
+        .borders(Borders::LEFT)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(Span::styled(
+            format!(" pinned: {} ", pinned.query),
+            Style::default().fg(Color::Cyan),
+        ));
+    let inner = block.inner(area);
+    block.render(area, buf);
+    render_document(&pinned.document, scroll_offset, inner, buf);
+}
+
+/// Shows candidate string literals in a popup below the prompt
+fn render_picker(app: &App, candidates: &[String], area: Rect, buf: &mut Buffer) {
+    let Some(completion) = app.completion() else {
+        return;
+    };
+    let selected = app.picker_index().min(candidates.len() - 1);
+    // Keep the selection visible when the list is longer than the popup
+    let first = selected.saturating_sub(PICKER_HEIGHT - 1);
+    let items: Vec<ListItem> = candidates
+        .iter()
+        .enumerate()
+        .skip(first)
+        .take(PICKER_HEIGHT)
+        .map(|(i, value)| {
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            ListItem::new(Span::styled(value.as_str(), style))
+        })
+        .collect();
+
+    let column = (app.prompt().len() + app.input()[..completion.start].chars().count()) as u16;
+    let width = candidates
+        .iter()
+        .map(|c| c.chars().count())
+        .max()
+        .unwrap_or(0) as u16
+        + 2;
+    let popup = Rect {
+        x: area.x + column.min(area.width.saturating_sub(width)),
+        y: area.y,
+        width: width.min(area.width),
+        height: (items.len() as u16 + 2).min(area.height),
+    };
+    Clear.render(popup, buf);
+    List::new(items)
+        .block(
+            Block::default()
+                .borders(Borders::ALL)
+                .border_style(Style::default().fg(Color::DarkGray)),
+        )
+        .render(popup, buf);
+}
+
+/// Draws the documentation of the builtin under the cursor
+fn render_doc(app: &App, start: usize, doc: &BuiltinDoc, area: Rect, buf: &mut Buffer) {
+    let width = DOC_POPUP_WIDTH.min(area.width);
+    let inner = width.saturating_sub(2).max(1) as usize;
+    let rows: usize = [doc.signature, doc.summary, "", doc.example]
+        .iter()
+        .map(|line| wrapped_rows(line, inner))
+        .sum();
+    let text = vec![
+        Line::from(Span::styled(
+            doc.signature,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(doc.summary),
+        Line::from(""),
+        Line::from(Span::styled(doc.example, Style::default().fg(Color::Green))),
+    ];
+    let paragraph = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+        Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::DarkGray))
+            .title(format!(" {} · {} ", doc.name, doc.category)),
+    );
+    let height = (rows as u16 + 2).min(area.height);
+
+    let column = (app.prompt().len() + app.input()[..start].chars().count()) as u16;
+    let popup = Rect {
+        x: area.x + column.min(area.width.saturating_sub(width)),
+        y: area.y,
+        width,
+        height,
+    };
+    Clear.render(popup, buf);
+    paragraph.render(popup, buf);
+}
+
+/// Draws the builtin manual: functions by category and the selected one's description
+fn render_manual(app: &App, manual: &ManualState, area: Rect, buf: &mut Buffer) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(" Manual  search: {}_ ", manual.search))
+        .title_bottom(" type to search · ↑/↓ select · Esc/F2 close ");
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let entries = app.manual_entries();
+    if entries.is_empty() {
+        Paragraph::new("No matching builtins").render(inner, buf);
+        return;
+    }
+    let selected = manual.selected.min(entries.len() - 1);
+
+    let mut items = Vec::new();
+    let mut selected_row = 0;
+    let mut category = "";
+    for (i, doc) in entries.iter().enumerate() {
+        if doc.category != category {
+            category = doc.category;
+            items.push(ListItem::new(Span::styled(
+                category,
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::BOLD),
+            )));
         }
+        let style = if i == selected {
+            selected_row = items.len();
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+        items.push(ListItem::new(Span::styled(
+            format!("  {}", doc.name),
+            style,
+        )));
+    }
+
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Length(MANUAL_LIST_WIDTH), Constraint::Min(0)])
+        .split(inner);
+    // Keep the selection visible
+    let first = selected_row.saturating_sub((panes[0].height as usize).saturating_sub(1));
+    List::new(items.into_iter().skip(first).collect::<Vec<_>>()).render(panes[0], buf);
+
+    let doc = entries[selected];
+    Paragraph::new(vec![
+        Line::from(Span::styled(
+            doc.signature,
+            Style::default().add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(doc.summary),
+        Line::from(""),
+        Line::from("Example:"),
+        Line::from(Span::styled(doc.example, Style::default().fg(Color::Green))),
+    ])
+    .wrap(Wrap { trim: false })
+    .block(Block::default().borders(Borders::LEFT))
+    .render(panes[1], buf);
+}
+
+/// Draws the list of cached earlier-stage outputs
+fn render_cache_inspector(app: &App, selected: usize, area: Rect, buf: &mut Buffer) {
+    let stats = app.cache_stats();
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(
+            " Cache  {} hits · {} misses ",
+            stats.hits, stats.misses
+        ))
+        .title_bottom(" ↑/↓ select · p pin · d forget · c forget unpinned · Esc/F12 close ");
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let entries = app.cache_entries();
+    if entries.is_empty() {
+        Paragraph::new("No stored query outputs").render(inner, buf);
+        return;
+    }
+    let selected = selected.min(entries.len() - 1);
+    let header = Row::new(["", "hits", "outputs", "size", "age", "query"])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+    let rows = entries.iter().enumerate().map(|(i, entry)| {
+        let style = if i == selected {
+            Style::default().fg(Color::Black).bg(Color::Cyan)
+        } else {
+            Style::default()
+        };
+        Row::new([
+            if entry.pinned { "*" } else { "" }.to_string(),
+            entry.hits.to_string(),
+            entry.outputs.to_string(),
+            chart::format_bytes(entry.bytes),
+            format!("{}s", entry.age.as_secs()),
+            entry.query.clone(),
+        ])
+        .style(style)
+    });
+    let widths = [
+        Constraint::Length(1),
+        Constraint::Length(6),
+        Constraint::Length(8),
+        Constraint::Length(10),
+        Constraint::Length(7),
+        Constraint::Min(0),
+    ];
+    Widget::render(TableWidget::new(rows, widths).header(header), inner, buf);
+}
+
+/// Draws the usage log summary
+fn render_usage_stats(stats: &UsageStats, scroll: usize, area: Rect, buf: &mut Buffer) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(" Usage ")
+        .title_bottom(" ↑/↓ scroll · Esc/Alt+U close ");
+    let mut lines = vec![Line::from(stats.summary())];
+    for (title, rows) in stats.sections() {
+        lines.push(Line::default());
+        lines.push(Line::from(Span::styled(
+            title,
+            Style::default().add_modifier(Modifier::BOLD),
+        )));
+        lines.extend(rows.into_iter().map(|(figure, text)| {
+            Line::from(vec![
+                Span::styled(format!("{:>8}  ", figure), Style::default().fg(Color::Cyan)),
+                Span::raw(text.to_string()),
+            ])
+        }));
+    }
+    let lines: Vec<Line> = lines.into_iter().skip(scroll).collect();
+    Paragraph::new(lines).block(block).render(area, buf);
+}
+
+/// Draws the file browser in two columns, the list and a preview
+fn render_browser(app: &App, browser: &BrowserState, area: Rect, buf: &mut Buffer) {
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(
+            " Open {}  filter: {}_ ",
+            browser.dir.display(),
+            browser.search
+        ))
+        .title_bottom(" type to filter · Enter open · Backspace up · Esc close ");
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let entries = app.browser_entries();
+    if entries.is_empty() {
+        Paragraph::new("No matching files").render(inner, buf);
+        return;
+    }
+    let selected = browser.selected.min(entries.len() - 1);
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(inner);
+
+    // Keep the selection visible
+    let first = selected.saturating_sub((panes[0].height as usize).saturating_sub(1));
+    let items: Vec<ListItem> = entries
+        .iter()
+        .enumerate()
+        .skip(first)
+        .map(|(i, entry)| {
+            let (name, color) = if entry.is_dir {
+                (format!("{}/", entry.name), Color::Blue)
+            } else {
+                (entry.name.clone(), Color::Reset)
+            };
+            let style = if i == selected {
+                Style::default().fg(Color::Black).bg(Color::Cyan)
+            } else {
+                Style::default().fg(color)
+            };
+            ListItem::new(Span::styled(name, style))
+        })
+        .collect();
+    List::new(items).render(panes[0], buf);
 
-        // 候補がない場合は通常表示
-        let prompt_text = format!("{}{}", prompt, input);
-        let paragraph = Paragraph::new(prompt_text);
-        paragraph.render(area, buf);
+    let preview = app
+        .browser_preview(panes[1].height as usize)
+        .unwrap_or_default();
+    Paragraph::new(preview)
+        .style(Style::default().fg(Color::DarkGray))
+        .block(Block::default().borders(Borders::LEFT))
+        .render(panes[1], buf);
+}
+
+/// Rows `text` takes when word-wrapped to `width` columns
+fn wrapped_rows(text: &str, width: usize) -> usize {
+    let mut rows = 1;
+    let mut column = 0;
+    for word in text.split(' ') {
+        let len = word.chars().count();
+        let needed = if column == 0 { len } else { column + 1 + len };
+        if needed <= width {
+            column = needed;
+        } else {
+            rows += (column > 0) as usize + len.saturating_sub(1) / width;
+            column = if len == 0 { 0 } else { (len - 1) % width + 1 };
+        }
     }
+    rows
+}
+
+/// Draws the replay progress and the latest warning in the status area
+fn render_status(app: &App, area: Rect, buf: &mut Buffer) {
+    let mut spans = Vec::new();
+    if let Some((replayed, total)) = app.replay_progress() {
+        spans.push(Span::styled(
+            format!("[replay {}/{} - Ctrl+N next] ", replayed, total),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if app.slider_active() {
+        spans.push(Span::styled(
+            "[slider ←/→ select, +/- adjust, Enter done] ",
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if app.workspace_len() > 0 && !app.workspace_view() {
+        spans.push(Span::styled(
+            format!(
+                "[file {}/{} - F10 summary] ",
+                app.workspace_file() + 1,
+                app.workspace_len()
+            ),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if let Some((count, open)) = app.follow_progress() {
+        let label = if open {
+            "following stdin"
+        } else {
+            "stdin closed"
+        };
+        spans.push(Span::styled(
+            format!("[{}: {} records] ", label, count),
+            Style::default().fg(Color::Cyan),
+        ));
+    }
+
+    if let Some((_, elapsed)) = app.running_query().filter(|(_, e)| *e >= SPINNER_DELAY) {
+        let frame = elapsed.as_millis() / SPINNER_INTERVAL.as_millis();
+        spans.push(Span::styled(
+            format!(
+                "[{} running {:.1}s - press Esc to cancel] ",
+                SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()],
+                elapsed.as_secs_f64()
+            ),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    if let Some((path, written)) = app.export_progress() {
+        spans.push(Span::styled(
+            format!("[exporting {}: {}] ", path, chart::format_bytes(written)),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    if let Some(construct) = app.unconfirmed_query() {
+        spans.push(Span::styled(
+            format!("[{} is slow on large input - Enter/F5 runs it] ", construct),
+            Style::default().fg(Color::Yellow),
+        ));
+    }
+
+    match app.execution_policy() {
+        ExecutionPolicy::Immediate => {}
+        policy => spans.push(Span::styled(
+            match (policy, app.query_pending()) {
+                (ExecutionPolicy::Manual, true) => "[run: manual - Enter/F5 runs *] ".to_string(),
+                (ExecutionPolicy::Manual, false) => "[run: manual - Enter/F5 runs] ".to_string(),
+                (policy, true) => format!("[run: {} *] ", policy),
+                (policy, false) => format!("[run: {}] ", policy),
+            },
+            Style::default().fg(Color::Cyan),
+        )),
+    }
+
+    if let Some((name, filter)) = app.active_display_filter() {
+        match app.display_filter_error() {
+            Some(error) => spans.push(Span::styled(
+                format!("[view {} failed: {}] ", name, error),
+                Style::default().fg(Color::Red),
+            )),
+            None if name == filter => spans.push(Span::styled(
+                format!("[view: {}] ", filter),
+                Style::default().fg(Color::Cyan),
+            )),
+            None => spans.push(Span::styled(
+                format!("[view {}: {}] ", name, filter),
+                Style::default().fg(Color::Cyan),
+            )),
+        }
+    }
+
+    if let Some(stats) = app.query_cache_stats() {
+        spans.push(Span::styled(
+            format!(
+                "[cache {}% hit · {}/{} · {}] ",
+                stats
+                    .hit_rate()
+                    .map_or(0, |rate| (rate * 100.0).round() as usize),
+                stats.entries,
+                stats.max_entries,
+                chart::format_bytes(stats.bytes)
+            ),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+
+    if let Some(message) = app.status_message() {
+        spans.push(Span::styled(
+            format!("{}  ", message),
+            Style::default().fg(Color::Green),
+        ));
+    }
+
+    let warnings = app.warnings();
+    if let Some(latest) = warnings.last() {
+        let text = if warnings.len() > 1 {
+            format!(
+                "Warning ({} pending): {}  [Ctrl+D to dismiss]",
+                warnings.len(),
+                latest
+            )
+        } else {
+            format!("Warning: {}  [Ctrl+D to dismiss]", latest)
+        };
+        spans.push(Span::styled(text, Style::default().fg(Color::Yellow)));
+    }
+
+    Paragraph::new(Line::from(spans)).render(area, buf);
+}
+
+/// Whether `area` is large enough for the views, see [`MIN_WIDTH`]
+fn fits(area: Rect) -> bool {
+    area.width >= MIN_WIDTH && area.height >= MIN_HEIGHT
+}
+
+/// Shown while waiting for a result
+fn render_awaiting(area: Rect, buf: &mut Buffer) {
+    Paragraph::new(Span::styled(
+        "running… (Esc to cancel)",
+        Style::default().fg(Color::DarkGray),
+    ))
+    .render(area, buf);
+}
+
+/// Draws a notice when the terminal is too small
+fn render_too_small(area: Rect, buf: &mut Buffer) {
+    let notice = format!(
+        "window too small (need ≥ {}x{}, have {}x{})",
+        MIN_WIDTH, MIN_HEIGHT, area.width, area.height
+    );
+    Paragraph::new(Span::styled(notice, Style::default().fg(Color::Yellow)))
+        .wrap(Wrap { trim: true })
+        .render(area, buf);
 }
 
 impl Widget for &App {
     fn render(self, area: Rect, buf: &mut Buffer) {
+        if !fits(area) {
+            render_too_small(area, buf);
+            return;
+        }
+        let has_status = !self.warnings().is_empty()
+            || self.replay_progress().is_some()
+            || self.slider_active()
+            || self.active_display_filter().is_some()
+            || self.execution_policy() != ExecutionPolicy::Immediate
+            || self
+                .running_query()
+                .is_some_and(|(_, elapsed)| elapsed >= SPINNER_DELAY)
+            || self.export_progress().is_some()
+            || self.query_cache_stats().is_some()
+            || self.status_message().is_some();
+        let status_height = if has_status { 1 } else { 0 };
+
+        // Running the query here would block until the worker delivers it
+        let result = (!self.awaiting_result()).then(|| self.current_result());
+        // The span points into the query that ran, which an edit may have changed since
+        let error_span = match result.as_deref() {
+            Some(Err(error)) if !self.input().is_empty() && !self.query_pending() => error
+                .span()
+                .filter(|span| self.input().is_char_boundary(span.start))
+                .filter(|span| self.input().is_char_boundary(span.end)),
+            _ => None,
+        };
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .constraints([
+                Constraint::Length(self.tab_bar_height()),
+                Constraint::Length(1),
+                Constraint::Length(if error_span.is_some() { 1 } else { 0 }),
+                Constraint::Min(0),
+                Constraint::Length(status_height),
+            ])
             .split(area);
+        let (tab_area, prompt_area, caret_area, body_area, status_area) =
+            (chunks[0], chunks[1], chunks[2], chunks[3], chunks[4]);
+        if tab_area.height > 0 {
+            render_tab_bar(self, tab_area, buf);
+        }
+
+        render_status(self, status_area, buf);
 
         // プロンプト行を候補付きで描画
-        self.render_input_with_suggestion(chunks[0], buf);
+        self.render_input_with_suggestion(error_span.as_ref(), prompt_area, buf);
+        if let Some(span) = &error_span {
+            render_caret(self.prompt(), self.input(), span, caret_area, buf);
+        }
+
+        if let Some(manual) = self.manual() {
+            render_manual(self, manual, body_area, buf);
+            return;
+        }
 
-        if let Some(error) = self.last_error() {
-            let error_text = format!("Error: {}", error);
-            let error_paragraph = Paragraph::new(error_text);
-            error_paragraph.render(chunks[1], buf);
+        if let Some(browser) = self.browser() {
+            render_browser(self, browser, body_area, buf);
+            return;
+        }
+
+        if let Some(selected) = self.cache_inspector() {
+            render_cache_inspector(self, selected, body_area, buf);
+            return;
+        }
+
+        if let Some((stats, scroll)) = self.usage_stats() {
+            render_usage_stats(stats, scroll, body_area, buf);
+            return;
+        }
+
+        let Some(result) = result else {
+            render_awaiting(body_area, buf);
+            return;
+        };
+
+        let mut result_area = body_area;
+        if let Some(sql) = self.sql() {
+            render_sql(self, sql, result_area, buf);
+            result_area.y += 1;
+            result_area.height = result_area.height.saturating_sub(1);
+        }
+        if let Some(pinned) = self.pinned() {
+            let panes = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+                .split(result_area);
+            result_area = panes[0];
+            render_pinned(pinned, self.pinned_scroll_offset(), panes[1], buf);
+        }
+
+        let table = self.table_mode().then(|| self.current_table()).flatten();
+        let workspace = self
+            .workspace_view()
+            .then(|| self.workspace_summary())
+            .flatten();
+        if let Some(summaries) = workspace {
+            render_workspace(&summaries, self.workspace_file(), result_area, buf);
+        } else if self.size_view() {
+            render_size_view(self.size_index(), self.scroll_offset(), result_area, buf);
+        } else if let Some(error) = self.last_error() {
+            render_error(error, self.suggested_fix(), result_area, buf);
+        } else if let Some(kind) = self.chart()
+            && render_chart(self, kind, result_area, buf)
+        {
+            // A chart that no longer fits the result falls through to the usual view
+        } else if let Some((table, layout)) = &table {
+            render_table(self, table, layout, result_area, buf);
+            if let Some(selected) = self.column_picker() {
+                render_column_picker(table, layout, selected, result_area, buf);
+            }
         } else {
-            let result_text = match self.execute_current_query() {
-                Ok(result) => result.format_pretty(),
-                Err(_) => {
-                    if self.input().is_empty() {
-                        serde_json::to_string_pretty(self.data().get())
-                            .unwrap_or_else(|_| "Error formatting JSON".to_string())
-                    } else {
-                        "".to_string()
+            if self.row_filter_editing() || !self.row_filter().is_empty() {
+                render_row_filter(self, result_area, buf);
+                result_area.y += 1;
+                result_area.height = result_area.height.saturating_sub(1);
+            }
+            if let Some((outputs, error)) = self.runtime_error() {
+                render_runtime_error(outputs, &error, result_area, buf);
+                result_area.y += 1;
+                result_area.height = result_area.height.saturating_sub(1);
+            }
+            self.fit_document(result_area.width as usize);
+            match self.document_for(Some(&*result)) {
+                // Use current scroll offset as-is (bounds are enforced during scroll operations)
+                Some(tree) => render_document(&tree, self.scroll_offset(), result_area, buf),
+                None => {
+                    if let Err(error) = &*result {
+                        render_error(error, self.suggested_fix(), result_area, buf);
                     }
                 }
-            };
+            }
+        }
 
-            // Apply scrolling by skipping lines based on scroll_offset
-            let lines: Vec<&str> = result_text.lines().collect();
-            let available_height = chunks[1].height as usize;
-
-            // Use current scroll offset as-is (bounds are enforced during scroll operations)
-            let scroll_offset = self.scroll_offset();
-            let visible_lines: Vec<&str> = lines
-                .iter()
-                .skip(scroll_offset)
-                .take(available_height)
-                .copied()
-                .collect();
-
-            // JSONにシンタックスハイライトを適用
-            let highlighter = SyntaxHighlighter::new();
-            let highlighted_lines: Vec<Line> = visible_lines
-                .iter()
-                .map(|line| highlighter.highlight_line(line))
-                .collect();
-
-            let json_paragraph = Paragraph::new(highlighted_lines);
-            json_paragraph.render(chunks[1], buf);
+        let candidates = self.picker_candidates();
+        if let Some(inspector) = self.value_inspector() {
+            inspector::render_value_inspector(inspector, result_area, buf);
+        } else if let Some(frequency) = self.frequency() {
+            render_frequency(frequency, result_area, buf);
+        } else if let Some(join) = self.join() {
+            render_join(self, join, result_area, buf);
+        } else if let Some((range, doc)) = self.doc_popup() {
+            render_doc(self, range.start, doc, body_area, buf);
+        } else if !candidates.is_empty() {
+            render_picker(self, &candidates, body_area, buf);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wrapped_rows() {
+        assert_eq!(wrapped_rows("", 10), 1);
+        assert_eq!(wrapped_rows("abc def", 10), 1);
+        assert_eq!(wrapped_rows("abc def ghi", 7), 2);
+        assert_eq!(wrapped_rows("abcdefghijkl", 5), 3);
+    }
+
+    #[test]
+    fn test_caret_points_at_error_span() {
+        let area = Rect::new(0, 0, 20, 1);
+        let mut buf = Buffer::empty(area);
+        render_caret("> ", ".a | ]x", &(5..6), area, &mut buf);
+        let line: String = (0..area.width)
+            .map(|x| buf[(x, 0)].symbol().to_string())
+            .collect();
+        assert_eq!(line.trim_end(), "       ^");
+
+        let mut buf = Buffer::empty(area);
+        render_caret("> ", "foo(", &(0..3), area, &mut buf);
+        let line: String = (0..area.width)
+            .map(|x| buf[(x, 0)].symbol().to_string())
+            .collect();
+        assert_eq!(line.trim_end(), "  ^~~");
+    }
+}
@@ -1,76 +1,751 @@
-use crate::app::App;
-use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use crate::app::{App, Effect, ExecutionPolicy};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers, MouseEventKind};
 
+/// Lines scrolled per notch of the mouse wheel
+pub const MOUSE_SCROLL_LINES: usize = 3;
+
+/// Editing of the query line
 #[derive(Debug, Clone, PartialEq)]
-pub enum Action {
-    Quit,
+#[non_exhaustive]
+pub enum EditAction {
     Input(char),
     Backspace,
-    Clear,
+    /// Records the query in the history and clears the line (Enter); runs
+    /// it and keeps the line when edits do not run the query by themselves
+    Submit,
+    MoveLeft,
+    MoveRight,
+    Format,
+    /// Expands an alias, accepts a picker entry or the suggestion (Tab)
+    Complete,
+    /// Inserts text at the cursor as-is, without auto-closing brackets
+    Paste(String),
+}
+
+/// View operations
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum ViewAction {
     ScrollUp,
     ScrollDown,
-    Tab,
+    PageUp,
+    PageDown,
+    ScrollPinnedUp,
+    ScrollPinnedDown,
+    NewTab,
+    NextTab,
+    PrevTab,
+    TogglePin,
+    ToggleSyncScroll,
+    /// Folds the container on the top visible line of the result
+    ToggleFold,
+    /// Switches to the next configured display filter
+    CycleDisplayFilter,
+    /// Keeps arrays and objects that fit the view's width on one line (Alt+W)
+    ToggleCompactLayout,
+    /// Shows a list of objects as a table
+    ToggleTable,
+    /// Opens or closes the column picker of the table view
+    ColumnPicker,
+    /// Selects the table column to the left
+    TableColumnLeft,
+    /// Selects the table column to the right
+    TableColumnRight,
+    /// Sorts the table by the selected column
+    SortTable,
+    /// Starts or stops typing the table's quick filter
+    TableFilter,
+    /// Draws numeric results as a histogram, then a sparkline, then as usual
+    CycleChart,
+    /// Shows how much of the document each subtree takes up
+    ToggleSizeView,
+    /// Opens the file selected in the workspace summary, or goes back to the summary
+    ToggleWorkspace,
+    /// Opens or closes the browser for picking another input file
+    ToggleBrowser,
+    ToggleSlider,
+    ShowDoc,
+    ShowManual,
+    /// Opens or closes the debug view of the stored query outputs (F12)
+    ToggleCacheInspector,
+    /// Decodes a base64 string in view and previews it in a popup (Alt+I)
+    InspectValue,
+    /// Counts the values of the focused field in a panel (Alt+C)
+    FieldFrequency,
+    /// Opens or closes the dialog building a join of two arrays (Alt+J)
+    Join,
+    /// Starts or stops typing SQL translated to jq (Alt+S)
+    SqlMode,
+    /// Opens or closes the summary of the local usage log (Alt+U)
+    UsageStats,
+    DismissWarning,
+}
+
+/// Application-wide operations
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum SystemAction {
+    Quit,
+    ReplayNext,
+    ExportCommand,
+    ExportHtml,
+    NextBookmark,
+    /// Read the input file or URL again
+    Reload,
+    /// Switches between running the query on every edit, after a pause and on Enter
+    CycleExecutionPolicy,
+    /// Runs the query now and records it, whatever the execution policy
+    /// (F5 outside the table view)
+    RunQuery,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum Action {
+    Edit(EditAction),
+    View(ViewAction),
+    System(SystemAction),
     None,
 }
 
+impl From<EditAction> for Action {
+    fn from(action: EditAction) -> Self {
+        Action::Edit(action)
+    }
+}
+
+impl From<ViewAction> for Action {
+    fn from(action: ViewAction) -> Self {
+        Action::View(action)
+    }
+}
+
+impl From<SystemAction> for Action {
+    fn from(action: SystemAction) -> Self {
+        Action::System(action)
+    }
+}
+
+impl Action {
+    /// Whether applying the action several times differs from applying it once
+    /// in a useful way; toggles and commands ignore the repeat count
+    pub fn is_repeatable(&self) -> bool {
+        matches!(
+            self,
+            Action::Edit(
+                EditAction::Input(_)
+                    | EditAction::Backspace
+                    | EditAction::MoveLeft
+                    | EditAction::MoveRight
+            ) | Action::View(
+                ViewAction::ScrollUp
+                    | ViewAction::ScrollDown
+                    | ViewAction::PageUp
+                    | ViewAction::PageDown
+                    | ViewAction::ScrollPinnedUp
+                    | ViewAction::ScrollPinnedDown
+            )
+        )
+    }
+}
+
+/// Input context accompanying an action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ActionContext {
+    /// Modifier keys held when the action was triggered
+    pub modifiers: KeyModifiers,
+    /// How many times a repeatable action is applied
+    pub repeat: usize,
+}
+
+impl Default for ActionContext {
+    fn default() -> Self {
+        Self {
+            modifiers: KeyModifiers::NONE,
+            repeat: 1,
+        }
+    }
+}
+
+impl ActionContext {
+    pub fn from_key(key_event: &KeyEvent) -> Self {
+        Self {
+            modifiers: key_event.modifiers,
+            ..Self::default()
+        }
+    }
+
+    /// Context of any terminal event; a wheel notch scrolls several lines
+    pub fn from_event(event: &Event) -> Self {
+        match event {
+            Event::Key(key_event) => Self::from_key(key_event),
+            Event::Mouse(mouse) => Self {
+                modifiers: mouse.modifiers,
+                repeat: match mouse.kind {
+                    MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => MOUSE_SCROLL_LINES,
+                    _ => 1,
+                },
+            },
+            _ => Self::default(),
+        }
+    }
+
+    pub fn repeated(repeat: usize) -> Self {
+        Self {
+            repeat,
+            ..Self::default()
+        }
+    }
+}
+
 pub fn get_action(key_event: KeyEvent) -> Action {
+    let ctrl = key_event.modifiers.contains(KeyModifiers::CONTROL);
     match key_event.code {
-        KeyCode::Esc => Action::Quit,
-        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::CONTROL) => Action::Quit,
-        KeyCode::Up => Action::ScrollUp,
-        KeyCode::Down => Action::ScrollDown,
-        KeyCode::Char(c) => {
-            if c == '\n' {
-                Action::Clear
-            } else {
-                Action::Input(c)
-            }
+        KeyCode::Esc => SystemAction::Quit.into(),
+        KeyCode::Char('c') if ctrl => SystemAction::Quit.into(),
+        KeyCode::Char('d') if ctrl => ViewAction::DismissWarning.into(),
+        KeyCode::Char('n') if ctrl => SystemAction::ReplayNext.into(),
+        KeyCode::Char('e') if ctrl => SystemAction::ExportCommand.into(),
+        KeyCode::Char('s') if ctrl => SystemAction::ExportHtml.into(),
+        KeyCode::Char('b') if ctrl => SystemAction::NextBookmark.into(),
+        KeyCode::Char('r') if ctrl => SystemAction::Reload.into(),
+        KeyCode::Char('p') if ctrl => ViewAction::TogglePin.into(),
+        KeyCode::Char('f') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            EditAction::Format.into()
+        }
+        KeyCode::Char('o') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::ToggleBrowser.into()
+        }
+        KeyCode::Char('w') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::ToggleCompactLayout.into()
+        }
+        KeyCode::Char('i') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::InspectValue.into()
+        }
+        KeyCode::Char('c') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::FieldFrequency.into()
+        }
+        KeyCode::Char('j') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::Join.into()
+        }
+        KeyCode::Char('s') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::SqlMode.into()
         }
-        KeyCode::Backspace => Action::Backspace,
-        KeyCode::Enter => Action::Clear,
-        KeyCode::Tab => Action::Tab,
+        KeyCode::Char('u') if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::UsageStats.into()
+        }
+        KeyCode::Char('g') if ctrl => ViewAction::ToggleSlider.into(),
+        KeyCode::Char('y') if ctrl => ViewAction::ToggleSyncScroll.into(),
+        KeyCode::Char('o') if ctrl => ViewAction::ToggleFold.into(),
+        KeyCode::Up if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+            ViewAction::ScrollPinnedUp.into()
+        }
+        KeyCode::Down if key_event.modifiers.contains(KeyModifiers::SHIFT) => {
+            ViewAction::ScrollPinnedDown.into()
+        }
+        KeyCode::Char('t') if ctrl => ViewAction::NewTab.into(),
+        KeyCode::PageDown if ctrl => ViewAction::NextTab.into(),
+        KeyCode::PageUp if ctrl => ViewAction::PrevTab.into(),
+        KeyCode::PageDown => ViewAction::PageDown.into(),
+        KeyCode::PageUp => ViewAction::PageUp.into(),
+        KeyCode::F(1) => ViewAction::ShowDoc.into(),
+        KeyCode::F(2) => ViewAction::ShowManual.into(),
+        KeyCode::F(3) => ViewAction::CycleDisplayFilter.into(),
+        KeyCode::F(4) => ViewAction::ToggleTable.into(),
+        KeyCode::F(5) => ViewAction::ColumnPicker.into(),
+        KeyCode::F(6) => ViewAction::SortTable.into(),
+        KeyCode::F(7) => ViewAction::TableFilter.into(),
+        KeyCode::F(8) => ViewAction::CycleChart.into(),
+        KeyCode::F(9) => ViewAction::ToggleSizeView.into(),
+        KeyCode::F(10) => ViewAction::ToggleWorkspace.into(),
+        KeyCode::F(11) => SystemAction::CycleExecutionPolicy.into(),
+        KeyCode::F(12) => ViewAction::ToggleCacheInspector.into(),
+        KeyCode::Left if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::TableColumnLeft.into()
+        }
+        KeyCode::Right if key_event.modifiers.contains(KeyModifiers::ALT) => {
+            ViewAction::TableColumnRight.into()
+        }
+        KeyCode::Left => EditAction::MoveLeft.into(),
+        KeyCode::Right => EditAction::MoveRight.into(),
+        KeyCode::Up => ViewAction::ScrollUp.into(),
+        KeyCode::Down => ViewAction::ScrollDown.into(),
+        KeyCode::Char('\n') | KeyCode::Enter => EditAction::Submit.into(),
+        KeyCode::Char(c) => EditAction::Input(c).into(),
+        KeyCode::Backspace => EditAction::Backspace.into(),
+        KeyCode::Tab => EditAction::Complete.into(),
         _ => Action::None,
     }
 }
 
-pub fn update(app: &mut App, action: Action) {
+/// Maps any terminal event: keys through [`get_action`], bracketed paste to
+/// [`EditAction::Paste`] and the mouse wheel to scrolling. Focus changes and
+/// resizes do nothing by default.
+pub fn get_event_action(event: Event) -> Action {
+    match event {
+        Event::Key(key_event) => get_action(key_event),
+        Event::Paste(text) => EditAction::Paste(text).into(),
+        Event::Mouse(mouse) => match mouse.kind {
+            MouseEventKind::ScrollUp => ViewAction::ScrollUp.into(),
+            MouseEventKind::ScrollDown => ViewAction::ScrollDown.into(),
+            _ => Action::None,
+        },
+        Event::FocusGained | Event::FocusLost | Event::Resize(..) => Action::None,
+    }
+}
+
+/// Handles keys that have a different meaning while slider mode is on
+///
+/// Returns `true` when the action was consumed. Holding Alt steps by ten.
+fn update_slider(app: &mut App, action: &Action, context: ActionContext) -> bool {
+    let step = if context.modifiers.contains(KeyModifiers::ALT) {
+        10
+    } else {
+        1
+    };
+    match action {
+        Action::Edit(EditAction::Input('+' | '=')) => app.adjust_literal(step),
+        Action::Edit(EditAction::Input('-' | '_')) => app.adjust_literal(-step),
+        Action::Edit(EditAction::MoveLeft) => app.select_literal(false),
+        Action::Edit(EditAction::MoveRight) => app.select_literal(true),
+        // Esc and Enter leave slider mode instead of quitting or clearing
+        Action::System(SystemAction::Quit) | Action::Edit(EditAction::Submit) => {
+            app.toggle_slider()
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the builtin manual is open: typing searches, the
+/// arrows move the selection, and Esc or F2 close it
+///
+/// Returns `true` when the action was consumed.
+fn update_manual(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.manual_search_push(*c),
+        Action::Edit(EditAction::Paste(text)) => {
+            text.chars().for_each(|c| app.manual_search_push(c))
+        }
+        Action::Edit(EditAction::Backspace) => app.manual_search_pop(),
+        Action::View(ViewAction::ScrollUp) => app.move_manual(false),
+        Action::View(ViewAction::ScrollDown) => app.move_manual(true),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::ShowManual) => {
+            app.toggle_manual()
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the cache inspector is open: the arrows select, `p`
+/// pins, `d` forgets the selected entry, `c` forgets every unpinned one and
+/// Esc or F12 close it
+///
+/// Returns `true` when the action was consumed.
+fn update_cache_inspector(app: &mut App, action: &Action) -> bool {
     match action {
-        Action::Quit => app.set_exit(true),
-        Action::Input(c) => {
-            app.push_char(c);
+        Action::Edit(EditAction::Input('p')) => app.pin_cache_entry(),
+        Action::Edit(EditAction::Input('d')) => app.remove_cache_entry(),
+        Action::Edit(EditAction::Input('c')) => app.clear_cache(),
+        Action::View(ViewAction::ScrollUp) => app.move_cache_inspector(false),
+        Action::View(ViewAction::ScrollDown) => app.move_cache_inspector(true),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::ToggleCacheInspector) => {
+            app.toggle_cache_inspector()
+        }
+        // Typing would edit the query hidden behind the inspector
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the usage summary is open: the arrows scroll it, Esc
+/// or Alt+U close it
+///
+/// Returns `true` when the action was consumed.
+fn update_usage_stats(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::View(ViewAction::ScrollUp) => app.scroll_usage_stats(false),
+        Action::View(ViewAction::ScrollDown) => app.scroll_usage_stats(true),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::UsageStats) => {
+            app.toggle_usage_stats()
+        }
+        // Typing would edit the query hidden behind the summary
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the value inspector is open: the arrows and Page
+/// keys scroll the preview, Esc or Alt+I close it
+///
+/// Returns `true` when the action was consumed.
+fn update_value_inspector(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::View(ViewAction::ScrollUp) => app.scroll_value_inspector(false, 1),
+        Action::View(ViewAction::ScrollDown) => app.scroll_value_inspector(true, 1),
+        Action::View(ViewAction::PageUp) => app.scroll_value_inspector(false, app.page_height()),
+        Action::View(ViewAction::PageDown) => app.scroll_value_inspector(true, app.page_height()),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::InspectValue) => {
+            app.toggle_value_inspector()
+        }
+        // Typing would edit the query hidden behind the popup
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the frequency panel is open: Enter replaces the query
+/// with the one computing the counts, Esc or Alt+C close it
+///
+/// Returns `true` when the action was consumed.
+fn update_frequency(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Submit) => app.insert_frequency_query(),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::FieldFrequency) => {
+            app.toggle_frequency()
+        }
+        // Typing would edit the query hidden behind the panel
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the join dialog is open: typing edits the focused
+/// part, the arrows move between parts, Tab cycles through the arrays or
+/// keys found in the document, Enter inserts the join and Esc or Alt+J close
+///
+/// Returns `true` when the action was consumed.
+fn update_join(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.join_push(*c),
+        Action::Edit(EditAction::Paste(text)) => text.chars().for_each(|c| app.join_push(c)),
+        Action::Edit(EditAction::Backspace) => app.join_pop(),
+        Action::Edit(EditAction::Complete) => app.complete_join_part(),
+        Action::Edit(EditAction::Submit) => app.apply_join(),
+        Action::View(ViewAction::ScrollUp) => app.move_join_focus(false),
+        Action::View(ViewAction::ScrollDown) => app.move_join_focus(true),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::Join) => app.toggle_join(),
+        // Other edits would change the query hidden behind the dialog
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys in the SQL mode: typing edits the SQL, and Enter, Esc or
+/// Alt+S go back to jq keeping the translated query
+///
+/// Returns `true` when the action was consumed.
+fn update_sql(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.sql_push(*c),
+        Action::Edit(EditAction::Paste(text)) => text.chars().for_each(|c| app.sql_push(c)),
+        Action::Edit(EditAction::Backspace) => app.sql_pop(),
+        Action::Edit(EditAction::Submit)
+        | Action::System(SystemAction::Quit)
+        | Action::View(ViewAction::SqlMode) => app.toggle_sql(),
+        // Other edits would change the query behind the SQL
+        Action::Edit(_) => {}
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the file browser is open: typing filters, the arrows
+/// select, Enter opens, Backspace on an empty filter goes up a directory and
+/// Esc or Alt+O close it
+///
+/// Returns `true` when the action was consumed.
+fn update_browser(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.browser_search_push(*c),
+        Action::Edit(EditAction::Paste(text)) => {
+            text.chars().for_each(|c| app.browser_search_push(c))
+        }
+        Action::Edit(EditAction::Backspace) => app.browser_search_pop(),
+        Action::Edit(EditAction::Submit) => app.browser_open(),
+        Action::View(ViewAction::ScrollUp) => app.move_browser(false),
+        Action::View(ViewAction::ScrollDown) => app.move_browser(true),
+        Action::System(SystemAction::Quit) | Action::View(ViewAction::ToggleBrowser) => {
+            app.toggle_browser()
+        }
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the column picker is open: the arrows select,
+/// Shift+arrows reorder, Space shows or hides and `+`/`-` resize; Enter, Esc
+/// or F5 close it and save the layout
+///
+/// Returns `true` when the action was consumed.
+fn update_column_picker(app: &mut App, action: &Action, effects: &mut Vec<Effect>) -> bool {
+    match action {
+        Action::View(ViewAction::ScrollUp) => app.select_column(false),
+        Action::View(ViewAction::ScrollDown) => app.select_column(true),
+        Action::View(ViewAction::ScrollPinnedUp) => app.move_column(true),
+        Action::View(ViewAction::ScrollPinnedDown) => app.move_column(false),
+        Action::Edit(EditAction::Input(' ')) => app.toggle_column(),
+        Action::Edit(EditAction::Input('+' | '=')) => app.resize_column(1),
+        Action::Edit(EditAction::Input('-' | '_')) => app.resize_column(-1),
+        Action::System(SystemAction::Quit)
+        | Action::Edit(EditAction::Submit)
+        | Action::View(ViewAction::ColumnPicker) => effects.extend(app.toggle_column_picker()),
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the table's quick filter is edited: typing filters the
+/// rows, Enter or F7 keep the filter and Esc clears it
+///
+/// Returns `true` when the action was consumed.
+fn update_table_filter(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.table_filter_push(*c),
+        Action::Edit(EditAction::Paste(text)) => {
+            text.chars().for_each(|c| app.table_filter_push(c))
+        }
+        Action::Edit(EditAction::Backspace) => app.table_filter_pop(),
+        Action::Edit(EditAction::Submit) | Action::View(ViewAction::TableFilter) => {
+            app.toggle_table_filter()
+        }
+        Action::System(SystemAction::Quit) => app.clear_table_filter(),
+        _ => return false,
+    }
+    true
+}
+
+/// Handles keys while the row filter of the JSON view is edited, like
+/// [`update_table_filter`]
+///
+/// Returns `true` when the action was consumed.
+fn update_row_filter(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::Edit(EditAction::Input(c)) => app.row_filter_push(*c),
+        Action::Edit(EditAction::Paste(text)) => text.chars().for_each(|c| app.row_filter_push(c)),
+        Action::Edit(EditAction::Backspace) => app.row_filter_pop(),
+        Action::Edit(EditAction::Submit) | Action::View(ViewAction::TableFilter) => {
+            app.toggle_row_filter()
+        }
+        Action::System(SystemAction::Quit) => app.clear_row_filter(),
+        _ => return false,
+    }
+    true
+}
+
+/// Handles navigation of the string literal picker while it is shown
+fn update_picker(app: &mut App, action: &Action) -> bool {
+    match action {
+        Action::View(ViewAction::ScrollUp) => app.move_picker(false),
+        Action::View(ViewAction::ScrollDown) => app.move_picker(true),
+        Action::Edit(EditAction::Complete) => return app.accept_picker(),
+        _ => return false,
+    }
+    true
+}
+
+pub fn update(app: &mut App, action: impl Into<Action>) -> Vec<Effect> {
+    update_with_context(app, action.into(), ActionContext::default())
+}
+
+/// Applies `action`, repeating it `context.repeat` times when that makes sense
+///
+/// Only in-memory state changes here; file writes and logging are returned
+/// as effects for the caller to run.
+pub fn update_with_context(app: &mut App, action: Action, context: ActionContext) -> Vec<Effect> {
+    if action != Action::None {
+        app.clear_status_message();
+        if action != Action::View(ViewAction::ShowDoc) {
+            app.close_doc();
+        }
+    }
+    let times = if action.is_repeatable() {
+        context.repeat.max(1)
+    } else {
+        1
+    };
+    let mut effects = Vec::new();
+    for _ in 0..times {
+        apply(app, &action, context, &mut effects);
+    }
+    effects
+}
+
+fn apply(app: &mut App, action: &Action, context: ActionContext, effects: &mut Vec<Effect>) {
+    if app.manual().is_some() && update_manual(app, action) {
+        return;
+    }
+    if app.browser().is_some() && update_browser(app, action) {
+        return;
+    }
+    if app.cache_inspector().is_some() && update_cache_inspector(app, action) {
+        return;
+    }
+    if app.usage_stats().is_some() && update_usage_stats(app, action) {
+        return;
+    }
+    if app.value_inspector().is_some() && update_value_inspector(app, action) {
+        return;
+    }
+    if app.frequency().is_some() && update_frequency(app, action) {
+        return;
+    }
+    if app.join().is_some() && update_join(app, action) {
+        return;
+    }
+    if app.column_picker().is_some() && update_column_picker(app, action, effects) {
+        return;
+    }
+    if app.table_filter_editing() && update_table_filter(app, action) {
+        return;
+    }
+    if app.row_filter_editing() && update_row_filter(app, action) {
+        return;
+    }
+    if app.sql().is_some() && update_sql(app, action) {
+        return;
+    }
+    if app.slider_active() && update_slider(app, action, context) {
+        return;
+    }
+    if !app.picker_candidates().is_empty() && update_picker(app, action) {
+        return;
+    }
+    match action {
+        Action::Edit(action) => apply_edit(app, action, effects),
+        Action::View(action) => apply_view(app, action, effects),
+        Action::System(action) => apply_system(app, action, effects),
+        Action::None => {
+            // Do nothing for undefined keys
+        }
+    }
+}
+
+fn apply_edit(app: &mut App, action: &EditAction, effects: &mut Vec<Effect>) {
+    match action {
+        EditAction::Input(c) => {
+            app.type_char(*c);
+            app.reset_picker();
             app.reset_scroll();
         }
-        Action::Backspace => {
-            if !app.input().is_empty() {
-                app.pop_char();
-            }
+        EditAction::Backspace => {
+            app.delete_char();
+            app.reset_picker();
             app.reset_scroll();
         }
-        Action::Clear => {
-            if !app.input().trim().is_empty() {
-                // Record in history when executing a query
-                app.record_query(app.input().to_string());
-            }
+        EditAction::Submit
+            if app.execution_policy() == ExecutionPolicy::Immediate
+                && app.unconfirmed_query().is_none() =>
+        {
+            record_input(app, effects);
             app.clear_input();
             app.reset_scroll();
         }
-        Action::ScrollUp => app.scroll_up(),
-        Action::ScrollDown => app.scroll_down(),
-        Action::Tab => {
-            // Handle when the TAB key is pressed
-            if let Some(suggestion) = app.get_best_suggestion() {
+        // Enter is how a held-back or expensive query runs, so the line stays for the next edit
+        EditAction::Submit => apply_system(app, &SystemAction::RunQuery, effects),
+        EditAction::MoveLeft => app.move_cursor_left(),
+        EditAction::MoveRight => app.move_cursor_right(),
+        EditAction::Format => app.format_query(),
+        EditAction::Paste(text) => {
+            app.paste(text);
+            app.reset_picker();
+            app.reset_scroll();
+        }
+        EditAction::Complete => {
+            if app.expand_alias() {
+                app.reset_scroll();
+            } else if let Some(suggestion) = app.get_best_suggestion() {
                 app.apply_suggestion(suggestion);
             }
         }
-        Action::None => {
-            // Do nothing for undefined keys
+    }
+}
+
+fn apply_view(app: &mut App, action: &ViewAction, effects: &mut Vec<Effect>) {
+    match action {
+        ViewAction::ScrollUp if app.workspace_view() => app.select_workspace_file(false),
+        ViewAction::ScrollDown if app.workspace_view() => app.select_workspace_file(true),
+        ViewAction::ScrollUp => app.scroll_up(),
+        ViewAction::ScrollDown => app.scroll_down(),
+        ViewAction::PageUp => app.scroll_up_by(app.page_height()),
+        ViewAction::PageDown => app.scroll_down_by(app.page_height()),
+        ViewAction::ScrollPinnedUp => app.scroll_pinned_up(),
+        ViewAction::ScrollPinnedDown => app.scroll_pinned_down(),
+        ViewAction::NewTab => app.new_tab(),
+        ViewAction::NextTab => app.switch_tab(true),
+        ViewAction::PrevTab => app.switch_tab(false),
+        ViewAction::TogglePin => app.toggle_pin(),
+        ViewAction::ToggleSyncScroll => app.toggle_sync_scroll(),
+        ViewAction::ToggleFold => app.toggle_fold(),
+        ViewAction::CycleDisplayFilter => app.cycle_display_filter(),
+        ViewAction::ToggleTable => app.toggle_table(),
+        // Outside the table view F5 runs the query, as in SQL consoles
+        ViewAction::ColumnPicker if !app.table_mode() => {
+            apply_system(app, &SystemAction::RunQuery, effects)
+        }
+        ViewAction::ColumnPicker => effects.extend(app.toggle_column_picker()),
+        ViewAction::TableColumnLeft => app.select_table_column(false),
+        ViewAction::TableColumnRight => app.select_table_column(true),
+        ViewAction::SortTable => app.cycle_table_sort(),
+        // Outside the table view the same key filters the rows of the JSON view
+        ViewAction::TableFilter if app.table_mode() => app.toggle_table_filter(),
+        ViewAction::TableFilter => app.toggle_row_filter(),
+        ViewAction::CycleChart => app.cycle_chart(),
+        ViewAction::ToggleSizeView => app.toggle_size_view(),
+        ViewAction::ToggleWorkspace => app.toggle_workspace_view(),
+        ViewAction::ToggleBrowser => app.toggle_browser(),
+        ViewAction::ToggleCompactLayout => app.toggle_compact_layout(),
+        ViewAction::ToggleSlider => app.toggle_slider(),
+        ViewAction::ShowDoc => app.toggle_doc(),
+        ViewAction::ShowManual => app.toggle_manual(),
+        ViewAction::ToggleCacheInspector => app.toggle_cache_inspector(),
+        ViewAction::UsageStats => app.toggle_usage_stats(),
+        ViewAction::InspectValue => app.toggle_value_inspector(),
+        ViewAction::FieldFrequency => app.toggle_frequency(),
+        ViewAction::Join => app.toggle_join(),
+        ViewAction::SqlMode => app.toggle_sql(),
+        ViewAction::DismissWarning => app.dismiss_warning(),
+    }
+}
+
+fn apply_system(app: &mut App, action: &SystemAction, effects: &mut Vec<Effect>) {
+    match action {
+        // Esc abandons a query still running in the background before it quits
+        SystemAction::Quit if app.running_query().is_some() => app.cancel_query(),
+        SystemAction::Quit => app.set_exit(true),
+        SystemAction::ReplayNext => effects.extend(app.advance_replay()),
+        SystemAction::ExportCommand => effects.push(app.export_command()),
+        SystemAction::ExportHtml => effects.extend(app.export_html()),
+        SystemAction::NextBookmark => app.next_bookmark(),
+        SystemAction::Reload => effects.push(Effect::Reload),
+        SystemAction::CycleExecutionPolicy => app.cycle_execution_policy(),
+        SystemAction::RunQuery => {
+            record_input(app, effects);
+            app.confirm_query();
+            app.reset_scroll();
         }
     }
 }
 
+/// Records the query in the history and the tee log, unless it is blank
+fn record_input(app: &mut App, effects: &mut Vec<Effect>) {
+    if !app.input().trim().is_empty() {
+        app.record_query(app.input().to_string());
+        effects.push(Effect::LogQuery(app.input().to_string()));
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::JsonData;
+    use crate::app::ContentGenerator;
     use crossterm::event::{KeyCode, KeyModifiers};
 
     #[test]
@@ -79,43 +754,595 @@ mod tests {
             KeyCode::Esc,
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::Quit);
+        assert_eq!(action, Action::System(SystemAction::Quit));
 
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Char('a'),
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::Input('a'));
+        assert_eq!(action, Action::Edit(EditAction::Input('a')));
 
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Backspace,
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::Backspace);
+        assert_eq!(action, Action::Edit(EditAction::Backspace));
 
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Up,
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::ScrollUp);
+        assert_eq!(action, Action::View(ViewAction::ScrollUp));
 
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Down,
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::ScrollDown);
+        assert_eq!(action, Action::View(ViewAction::ScrollDown));
 
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Tab,
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::Tab);
+        assert_eq!(action, Action::Edit(EditAction::Complete));
 
         // Test space character input
         let action = get_action(crossterm::event::KeyEvent::new(
             KeyCode::Char(' '),
             KeyModifiers::NONE,
         ));
-        assert_eq!(action, Action::Input(' '));
+        assert_eq!(action, Action::Edit(EditAction::Input(' ')));
+
+        let action = get_action(crossterm::event::KeyEvent::new(
+            KeyCode::Char('d'),
+            KeyModifiers::CONTROL,
+        ));
+        assert_eq!(action, Action::View(ViewAction::DismissWarning));
+
+        let action = get_action(crossterm::event::KeyEvent::new(
+            KeyCode::Char('t'),
+            KeyModifiers::CONTROL,
+        ));
+        assert_eq!(action, Action::View(ViewAction::NewTab));
+
+        let action = get_action(crossterm::event::KeyEvent::new(
+            KeyCode::PageDown,
+            KeyModifiers::CONTROL,
+        ));
+        assert_eq!(action, Action::View(ViewAction::NextTab));
+
+        let action = get_action(crossterm::event::KeyEvent::new(
+            KeyCode::PageUp,
+            KeyModifiers::CONTROL,
+        ));
+        assert_eq!(action, Action::View(ViewAction::PrevTab));
+
+        let action = get_action(crossterm::event::KeyEvent::new(
+            KeyCode::Char('f'),
+            KeyModifiers::ALT,
+        ));
+        assert_eq!(action, Action::Edit(EditAction::Format));
+    }
+
+    #[test]
+    fn test_repeat_count_and_modifiers() {
+        let config = crate::app::AppConfig::with_visible_height(3);
+        let mut app = App::with_config(serde_json::json!([1, 2, 3, 4, 5, 6, 7, 8]), config);
+        update_with_context(
+            &mut app,
+            ViewAction::ScrollDown.into(),
+            ActionContext::repeated(4),
+        );
+        assert_eq!(app.scroll_offset(), 4);
+        update(&mut app, ViewAction::PageUp);
+        assert_eq!(app.scroll_offset(), 1);
+
+        // Toggles ignore the repeat count
+        app.set_input(".[0:2]");
+        update_with_context(
+            &mut app,
+            ViewAction::ToggleSlider.into(),
+            ActionContext::repeated(2),
+        );
+        assert!(app.slider_active());
+
+        let alt = ActionContext::from_key(&KeyEvent::new(KeyCode::Char('+'), KeyModifiers::ALT));
+        update_with_context(&mut app, EditAction::Input('+').into(), alt);
+        assert_eq!(app.input(), ".[0:12]");
+    }
+
+    #[test]
+    fn test_update_returns_effects_instead_of_writing() {
+        let mut app = App::new(serde_json::json!({"a": 1}));
+        app.set_input(".a");
+        let effects = update(&mut app, SystemAction::ExportCommand);
+        let [Effect::WriteFile { path, contents, .. }] = effects.as_slice() else {
+            panic!("expected one file write, got {effects:?}");
+        };
+        assert_eq!(path, crate::app::DEFAULT_EXPORT_PATH);
+        assert_eq!(*contents, app.command_export().shell_script());
+        // The status line only changes once the effect has run
+        assert!(app.status_message().is_none());
+
+        assert_eq!(
+            update(&mut app, EditAction::Submit),
+            vec![Effect::LogQuery(".a".to_string())]
+        );
+        assert_eq!(app.input(), "");
+        assert!(update(&mut app, EditAction::Submit).is_empty());
+        assert!(update(&mut app, EditAction::Input('.')).is_empty());
+    }
+
+    #[test]
+    fn test_enter_runs_query_in_manual_mode() {
+        let mut app = App::new(serde_json::json!({"a": 1}));
+        update(&mut app, SystemAction::CycleExecutionPolicy);
+        update(&mut app, SystemAction::CycleExecutionPolicy);
+        assert_eq!(app.execution_policy(), ExecutionPolicy::Manual);
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, EditAction::Input('a'));
+        assert!(app.query_pending());
+
+        assert_eq!(
+            update(&mut app, EditAction::Submit),
+            vec![Effect::LogQuery(".a".to_string())]
+        );
+        assert_eq!(app.input(), ".a");
+        assert_eq!(app.current_values().unwrap(), vec![serde_json::json!(1)]);
+
+        // F5 runs the query too, unless it opens the table view's column picker
+        update(&mut app, EditAction::Backspace);
+        let f5 = get_action(KeyEvent::new(KeyCode::F(5), KeyModifiers::NONE));
+        assert_eq!(
+            update(&mut app, f5),
+            vec![Effect::LogQuery(".".to_string())]
+        );
+        assert!(!app.query_pending());
+    }
+
+    #[test]
+    fn test_pinned_result_survives_new_query() {
+        let mut app = App::new(serde_json::json!({"a": 1, "b": 2}));
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, EditAction::Input('a'));
+        update(&mut app, ViewAction::TogglePin);
+        update(&mut app, EditAction::Submit);
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, EditAction::Input('b'));
+
+        let pinned = app.pinned().expect("result should be pinned");
+        assert_eq!(pinned.query, ".a");
+        assert_eq!(pinned.result.format_pretty(), "1");
+
+        update(&mut app, ViewAction::TogglePin);
+        assert!(app.pinned().is_none());
+    }
+
+    #[test]
+    fn test_sync_scroll_aligns_pinned_pane() {
+        let config = crate::app::AppConfig::with_visible_height(2);
+        let mut app = App::with_config(serde_json::json!([1, 2, 3, 4, 5, 6]), config);
+        update(&mut app, ViewAction::TogglePin);
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, ViewAction::ScrollDown);
+
+        // Independent scrolling leaves the pinned pane where it was
+        assert_eq!(app.pinned_scroll_offset(), 0);
+        update(&mut app, ViewAction::ScrollPinnedDown);
+        assert_eq!(app.pinned_scroll_offset(), 1);
+
+        update(&mut app, ViewAction::ToggleSyncScroll);
+        assert!(app.sync_scroll());
+        assert_eq!(app.pinned_scroll_offset(), app.scroll_offset());
+    }
+
+    #[test]
+    fn test_slider_adjusts_literal() {
+        let mut app = App::new(serde_json::json!([10, 20, 30]));
+        app.set_input("map(select(. > 15)) | .[0:2]");
+        update(&mut app, ViewAction::ToggleSlider);
+        assert!(app.slider_active());
+
+        // Starts on the last literal; Left moves to the threshold
+        update(&mut app, EditAction::MoveLeft);
+        update(&mut app, EditAction::MoveLeft);
+        update(&mut app, EditAction::Input('+'));
+        update(&mut app, EditAction::Input('+'));
+        assert_eq!(app.input(), "map(select(. > 17)) | .[0:2]");
+        update(&mut app, EditAction::Input('-'));
+        assert_eq!(app.input(), "map(select(. > 16)) | .[0:2]");
+
+        // Esc leaves slider mode without quitting
+        update(&mut app, SystemAction::Quit);
+        assert!(!app.slider_active());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_esc_cancels_running_query() {
+        let mut app = App::new(serde_json::json!({"a": 1}));
+        app.enable_background_execution();
+        app.set_input(".a");
+        app.current_values().unwrap();
+        update(&mut app, EditAction::Input('b'));
+        app.poll_query();
+        assert!(app.running_query().is_some());
+
+        update(&mut app, SystemAction::Quit);
+        assert!(!app.should_exit());
+        assert!(app.running_query().is_none());
+        assert_eq!(app.query(), ".a");
+        update(&mut app, SystemAction::Quit);
+        assert!(app.should_exit());
+    }
+
+    #[test]
+    fn test_string_literal_picker() {
+        let mut app = App::new(serde_json::json!([
+            {"status": "active"},
+            {"status": "archived"},
+            {"status": "banned"}
+        ]));
+        app.set_input(r#".[] | select(.status == ""#);
+        update(&mut app, EditAction::Input('a'));
+        assert_eq!(app.picker_candidates(), vec!["active", "archived"]);
+
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.input(), r#".[] | select(.status == "archived""#);
+        assert!(app.picker_candidates().is_empty());
+
+        // The values follow the data, and only so many are offered
+        app.replace_data(serde_json::Value::Array(
+            (0..500)
+                .map(|i| serde_json::json!({"status": format!("s{}", i)}))
+                .collect(),
+        ));
+        app.set_input(r#".[] | select(.status == "s"#);
+        assert_eq!(app.picker_candidates().len(), 100);
+        assert_eq!(app.picker_candidates()[0], "s0");
+    }
+
+    #[test]
+    fn test_table_quick_filter_keys() {
+        let mut app = App::new(serde_json::json!([{"name": "ann"}, {"name": "bob"}]));
+        update(&mut app, ViewAction::ToggleTable);
+        update(&mut app, ViewAction::TableFilter);
+        update(&mut app, EditAction::Input('b'));
+        assert_eq!(app.input(), "");
+        assert_eq!(app.current_table().unwrap().0.rows.len(), 1);
+
+        // Enter keeps the filter, Esc while editing clears it instead of quitting
+        update(&mut app, EditAction::Submit);
+        assert!(!app.table_filter_editing());
+        assert_eq!(app.table_query().filter, "b");
+        update(&mut app, ViewAction::TableFilter);
+        update(&mut app, SystemAction::Quit);
+        assert!(!app.should_exit());
+        assert!(app.table_query().is_empty());
+    }
+
+    #[test]
+    fn test_row_filter_keys() {
+        let mut app = App::new(serde_json::json!(["GET /a 200", "GET /b 500"]));
+        update(&mut app, ViewAction::TableFilter);
+        assert!(app.row_filter_editing());
+        update(&mut app, EditAction::Paste("500".to_string()));
+        assert_eq!(app.input(), "");
+        assert_eq!(app.row_filter(), "500");
+
+        update(&mut app, EditAction::Submit);
+        assert!(!app.row_filter_editing());
+        update(&mut app, ViewAction::TableFilter);
+        update(&mut app, SystemAction::Quit);
+        assert!(!app.should_exit());
+        assert_eq!(app.row_filter(), "");
+    }
+
+    #[test]
+    fn test_next_stage_completion() {
+        let mut app = App::new(serde_json::json!({"users": [{"name": "a"}]}));
+        app.record_query(".users[] | .name".to_string());
+        app.record_query(".users[] | select(.name) | length".to_string());
+        app.record_query(".users[] | .name".to_string());
+
+        for c in ".users[] |".chars() {
+            update(&mut app, EditAction::Input(c));
+        }
+        assert_eq!(app.picker_candidates(), vec![".name", "select(.name)"]);
+
+        update(&mut app, EditAction::Input(' '));
+        update(&mut app, EditAction::Input('s'));
+        assert_eq!(app.picker_candidates(), vec!["select(.name)"]);
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.input(), ".users[] | select(.name)");
+
+        app.set_input(".users[]|");
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.input(), ".users[]| .name");
+    }
+
+    #[test]
+    fn test_fix_of_earlier_failure_is_suggested() {
+        let mut app = App::new(serde_json::json!({
+            "users": [{"name": "a"}],
+            "orders": [{"id": 1}]
+        }));
+        app.set_input(".users | lenght");
+        assert!(app.current_values().is_err());
+        assert!(app.suggested_fix().is_none());
+        app.poll_query();
+
+        app.set_input(".users | length");
+        update(&mut app, EditAction::Submit);
+
+        app.set_input(".orders | lenght");
+        let fix = app.suggested_fix().unwrap();
+        assert_eq!(
+            (fix.failed.as_str(), fix.fixed.as_str()),
+            (".users | lenght", ".users | length")
+        );
+    }
+
+    #[test]
+    fn test_alias_expansion() {
+        let mut app = App::new(serde_json::json!([1, 2]));
+        app.set_aliases(
+            [
+                ("sel".to_string(), "select($0)".to_string()),
+                ("te".to_string(), "to_entries".to_string()),
+            ]
+            .into(),
+        );
+        for c in ".[] | sel".chars() {
+            update(&mut app, EditAction::Input(c));
+        }
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.input(), ".[] | select()");
+        assert_eq!(app.cursor(), ".[] | select(".len());
+
+        update(&mut app, EditAction::Input('.'));
+        assert_eq!(app.input(), ".[] | select(.)");
+
+        // Not inside strings or after a dot
+        for input in [r#""te"#, ".te"] {
+            app.set_input(input);
+            update(&mut app, EditAction::Complete);
+            assert_eq!(app.input(), input);
+        }
+        app.set_input("te");
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.input(), "to_entries");
+    }
+
+    #[test]
+    fn test_doc_popup_for_builtin_under_cursor() {
+        let mut app = App::new(serde_json::json!([]));
+        app.set_input("group_by(.a) | .x");
+        update(&mut app, ViewAction::ShowDoc);
+        assert!(app.doc_popup().is_none());
+        assert!(app.status_message().is_some());
+
+        for _ in 0.."(.a) | .x".len() {
+            update(&mut app, EditAction::MoveLeft);
+        }
+        update(&mut app, ViewAction::ShowDoc);
+        let (range, doc) = app.doc_popup().unwrap();
+        assert_eq!(range, 0..8);
+        assert_eq!(doc.name, "group_by");
+
+        // Any other key closes the popup
+        update(&mut app, EditAction::MoveRight);
+        assert!(app.doc_popup().is_none());
+    }
+
+    #[test]
+    fn test_manual_search_and_navigation() {
+        let mut app = App::new(serde_json::json!([]));
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, ViewAction::ShowManual);
+        assert!(app.manual().is_some());
+
+        for c in "entries".chars() {
+            update(&mut app, EditAction::Input(c));
+        }
+        // Typing goes to the search, not the query
+        assert_eq!(app.input(), ".");
+        assert_eq!(app.manual_entries().len(), 3);
+
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, ViewAction::ScrollDown);
+        assert_eq!(app.manual().unwrap().selected, 2);
+
+        update(&mut app, EditAction::Backspace);
+        assert_eq!(app.manual().unwrap().search, "entrie");
+        assert_eq!(app.manual().unwrap().selected, 0);
+
+        update(&mut app, SystemAction::Quit);
+        assert!(app.manual().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_cache_inspector_keys() {
+        let mut app = App::new(serde_json::json!({"a": [1]}));
+        app.set_input(".a");
+        app.current_values().unwrap();
+        let f12 = KeyEvent::new(KeyCode::F(12), KeyModifiers::NONE);
+        assert_eq!(get_action(f12), ViewAction::ToggleCacheInspector.into());
+
+        update(&mut app, ViewAction::ToggleCacheInspector);
+        update(&mut app, EditAction::Input('p'));
+        assert!(app.cache_entries()[0].pinned);
+        // Typing does not reach the query behind the inspector
+        update(&mut app, EditAction::Input('x'));
+        update(&mut app, EditAction::Backspace);
+        assert_eq!(app.input(), ".a");
+
+        update(&mut app, SystemAction::Quit);
+        assert!(app.cache_inspector().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_value_inspector_keys() {
+        let alt_i = KeyEvent::new(KeyCode::Char('i'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_i), ViewAction::InspectValue.into());
+
+        let mut app = App::new(serde_json::json!({"blob": "AAECAwQFBgcICQoLDA0ODxAREhP7_w"}));
+        update(&mut app, ViewAction::InspectValue);
+        assert_eq!(app.value_inspector().unwrap().bytes.len(), 22);
+        update(&mut app, ViewAction::ScrollDown);
+        assert_eq!(app.value_inspector().unwrap().scroll, 1);
+        update(&mut app, EditAction::Input('x'));
+        assert_eq!(app.input(), "");
+
+        update(&mut app, SystemAction::Quit);
+        assert!(app.value_inspector().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_usage_stats_keys() {
+        let alt_u = KeyEvent::new(KeyCode::Char('u'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_u), ViewAction::UsageStats.into());
+
+        let path = std::env::temp_dir().join(format!(
+            "rjq_events_usage_stats_{}.jsonl",
+            std::process::id()
+        ));
+        std::fs::write(&path, "{\"timestamp\": 1, \"query\": \".a\"}\n").unwrap();
+        let mut app = App::new(serde_json::json!({"a": 1}));
+        app.set_usage_log(crate::history::UsageLog::new(path.clone()));
+        update(&mut app, ViewAction::UsageStats);
+        assert_eq!(app.usage_stats().unwrap().0.runs, 1);
+        update(&mut app, EditAction::Input('x'));
+        assert_eq!(app.input(), "");
+        update(&mut app, SystemAction::Quit);
+        assert!(app.usage_stats().is_none());
+        assert!(!app.should_exit());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_frequency_keys() {
+        let alt_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_c), ViewAction::FieldFrequency.into());
+
+        let mut app = App::new(serde_json::json!([{"level": "info"}, {"level": "error"}]));
+        update(&mut app, ViewAction::FieldFrequency);
+        assert_eq!(app.frequency().unwrap().counts.entries.len(), 2);
+        update(&mut app, EditAction::Input('x'));
+        assert_eq!(app.input(), "");
+        update(&mut app, EditAction::Submit);
+        assert!(app.frequency().is_none());
+        assert!(app.input().starts_with("group_by(.level)"));
+
+        update(&mut app, ViewAction::FieldFrequency);
+        update(&mut app, SystemAction::Quit);
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_sql_mode_keys() {
+        let alt_s = KeyEvent::new(KeyCode::Char('s'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_s), ViewAction::SqlMode.into());
+
+        let mut app = App::new(serde_json::json!({"a": [{"k": 1}]}));
+        update(&mut app, ViewAction::SqlMode);
+        update(&mut app, EditAction::Paste("select k from a".to_string()));
+        assert_eq!(app.sql(), Some("select k from a"));
+        assert_eq!(app.input(), ".a | map({k: .k})");
+        update(&mut app, EditAction::Submit);
+        assert!(app.sql().is_none());
+        assert_eq!(app.input(), ".a | map({k: .k})");
+
+        update(&mut app, ViewAction::SqlMode);
+        update(&mut app, SystemAction::Quit);
+        assert!(app.sql().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_join_keys() {
+        let alt_j = KeyEvent::new(KeyCode::Char('j'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_j), ViewAction::Join.into());
+
+        let mut app = App::new(serde_json::json!({"a": [{"k": 1}], "b": [{"k": 1, "v": 2}]}));
+        update(&mut app, ViewAction::Join);
+        update(&mut app, EditAction::Input('k'));
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, ViewAction::ScrollDown);
+        update(&mut app, EditAction::Complete);
+        assert_eq!(app.join().unwrap().spec.right_key, "k");
+        assert_eq!(app.input(), "");
+        update(&mut app, EditAction::Submit);
+        assert!(app.join().is_none());
+        assert!(app.input().starts_with("(reduce .b[] as $row"));
+
+        update(&mut app, ViewAction::Join);
+        update(&mut app, SystemAction::Quit);
+        assert!(app.join().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_browser_keys() {
+        let alt_o = KeyEvent::new(KeyCode::Char('o'), KeyModifiers::ALT);
+        assert_eq!(get_action(alt_o), ViewAction::ToggleBrowser.into());
+
+        let mut app = App::new(serde_json::json!([]));
+        update(&mut app, ViewAction::ToggleBrowser);
+        assert!(app.browser().is_some());
+        update(&mut app, EditAction::Input('x'));
+        // Typing filters the listing, not the query
+        assert_eq!(app.input(), "");
+        assert_eq!(app.browser().unwrap().search, "x");
+
+        update(&mut app, SystemAction::Quit);
+        assert!(app.browser().is_none());
+        assert!(!app.should_exit());
+    }
+
+    #[test]
+    fn test_tabs_keep_separate_queries() {
+        let mut app = App::new(serde_json::json!({"a": 1, "b": 2}));
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, EditAction::Input('a'));
+        update(&mut app, ViewAction::NewTab);
+        assert_eq!(app.input(), "");
+        update(&mut app, EditAction::Input('.'));
+        update(&mut app, EditAction::Input('b'));
+
+        assert_eq!(app.tab_queries(), vec![".a", ".b"]);
+        update(&mut app, ViewAction::PrevTab);
+        assert_eq!(app.input(), ".a");
+        assert_eq!(app.active_tab(), 0);
+    }
+
+    #[test]
+    fn test_each_file_has_its_own_tab() {
+        let mut app = App::new(serde_json::json!({"name": "a"}));
+        app.set_source_path(Some("data/a.json".to_string()));
+        app.open_document(
+            Some("b.json".to_string()),
+            JsonData::new(serde_json::json!({"name": "b"})),
+        );
+        assert_eq!(app.tab_sources(), vec![Some("a.json"), Some("b.json")]);
+        update(&mut app, EditAction::Input('.'));
+
+        update(&mut app, ViewAction::NextTab);
+        assert_eq!(app.data().get(), &serde_json::json!({"name": "b"}));
+        assert_eq!(app.generate_current_content(), "{\n  \"name\": \"b\"\n}");
+        // A new tab queries the same file as the tab it was opened from
+        update(&mut app, ViewAction::NewTab);
+        assert_eq!(app.data().get(), &serde_json::json!({"name": "b"}));
+
+        update(&mut app, ViewAction::NextTab);
+        assert_eq!(app.input(), ".");
+        assert_eq!(app.data().get(), &serde_json::json!({"name": "a"}));
+        assert_eq!(app.command_export().jq_command(), "jq . data/a.json");
     }
 }
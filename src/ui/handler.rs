@@ -1,28 +1,40 @@
-use crate::app::App;
-use crate::ui::events::Action;
-use crossterm::event::KeyEvent;
+use crate::app::{App, Effect};
+use crate::ui::events::{Action, ActionContext, get_action, get_event_action, update_with_context};
+use crossterm::event::{Event, KeyEvent};
 
+/// Turns terminal events into actions and applies them to the app
+///
+/// Every method has a default; override `handle_key_event` to rebind keys or
+/// `handle_event` to react to mouse, paste, focus and resize events.
 pub trait EventHandler {
-    fn handle_key_event(&self, key_event: KeyEvent) -> Action;
-    fn update_app(&self, app: &mut App, action: Action);
-}
-
-pub struct DefaultEventHandler;
-
-impl EventHandler for DefaultEventHandler {
     fn handle_key_event(&self, key_event: KeyEvent) -> Action {
-        crate::ui::events::get_action(key_event)
+        get_action(key_event)
+    }
+
+    /// Maps any terminal event; key events go through `handle_key_event`
+    fn handle_event(&self, event: Event) -> Action {
+        match event {
+            Event::Key(key_event) => self.handle_key_event(key_event),
+            other => get_event_action(other),
+        }
     }
 
-    fn update_app(&self, app: &mut App, action: Action) {
-        crate::ui::events::update(app, action);
+    /// Applies `action` and returns the effects to run; `context` carries the
+    /// modifiers and repeat count
+    fn update_app(&self, app: &mut App, action: Action, context: ActionContext) -> Vec<Effect> {
+        update_with_context(app, action, context)
     }
 }
 
+pub struct DefaultEventHandler;
+
+impl EventHandler for DefaultEventHandler {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crossterm::event::{KeyCode, KeyModifiers};
+    use crate::ui::EditAction;
+    use crossterm::event::{KeyCode, KeyModifiers, MouseEvent, MouseEventKind};
     use serde_json::json;
 
     #[test]
@@ -31,7 +43,7 @@ mod tests {
         let key_event = crossterm::event::KeyEvent::new(KeyCode::Char('a'), KeyModifiers::NONE);
 
         let action = handler.handle_key_event(key_event);
-        assert_eq!(action, Action::Input('a'));
+        assert_eq!(action, Action::Edit(EditAction::Input('a')));
     }
 
     #[test]
@@ -39,7 +51,72 @@ mod tests {
         let handler = DefaultEventHandler;
         let mut app = App::new(json!({"test": "data"}));
 
-        handler.update_app(&mut app, Action::Input('a'));
+        handler.update_app(
+            &mut app,
+            EditAction::Input('a').into(),
+            ActionContext::default(),
+        );
         assert_eq!(app.input(), "a");
     }
+
+    #[test]
+    fn test_default_event_mapping() {
+        let handler = DefaultEventHandler;
+        assert_eq!(
+            handler.handle_event(Event::Paste(".a |\n.b".to_string())),
+            Action::Edit(EditAction::Paste(".a |\n.b".to_string()))
+        );
+        assert_eq!(handler.handle_event(Event::Resize(80, 24)), Action::None);
+        assert_eq!(handler.handle_event(Event::FocusLost), Action::None);
+
+        let wheel = Event::Mouse(MouseEvent {
+            kind: MouseEventKind::ScrollDown,
+            column: 0,
+            row: 0,
+            modifiers: KeyModifiers::NONE,
+        });
+        assert_eq!(ActionContext::from_event(&wheel).repeat, 3);
+        assert_eq!(
+            handler.handle_event(wheel),
+            Action::View(crate::ui::ViewAction::ScrollDown)
+        );
+
+        let mut app = App::new(json!(null));
+        let paste = Event::Paste(".a |\n.b".to_string());
+        handler.update_app(
+            &mut app,
+            handler.handle_event(paste),
+            ActionContext::default(),
+        );
+        assert_eq!(app.input(), ".a | .b");
+    }
+
+    #[test]
+    fn test_custom_handler_observes_other_events() {
+        struct QuitOnFocusLost;
+        impl EventHandler for QuitOnFocusLost {
+            fn handle_event(&self, event: Event) -> Action {
+                match event {
+                    Event::FocusLost => crate::ui::SystemAction::Quit.into(),
+                    other => get_event_action(other),
+                }
+            }
+        }
+
+        let handler = QuitOnFocusLost;
+        let mut app = App::new(json!(null));
+        let key = Event::Key(KeyEvent::new(KeyCode::Char('x'), KeyModifiers::NONE));
+        handler.update_app(
+            &mut app,
+            handler.handle_event(key),
+            ActionContext::default(),
+        );
+        assert_eq!(app.input(), "x");
+        handler.update_app(
+            &mut app,
+            handler.handle_event(Event::FocusLost),
+            ActionContext::default(),
+        );
+        assert!(app.should_exit());
+    }
 }
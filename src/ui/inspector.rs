@@ -0,0 +1,186 @@
+//! Value inspector
+//!
+//! Long base64 strings in a result, such as embedded files or encoded
+//! payloads, are unreadable as they are. The inspector decodes one and shows
+//! it in a popup, as text when it is readable and as a hexdump otherwise.
+
+use crate::app::ValueInspectorState;
+use crate::render::chart;
+use base64::Engine;
+use base64::alphabet;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Style},
+    text::Line,
+    widgets::{Block, Borders, Clear, Paragraph, Widget},
+};
+
+/// Shorter strings are too likely to be ordinary words or identifiers
+pub const MIN_BASE64_LEN: usize = 24;
+
+/// Bytes per hexdump line
+const HEX_WIDTH: usize = 16;
+
+/// Share of the result area the popup covers, in percent
+const POPUP_PERCENT: u16 = 80;
+
+const PADDING_INDIFFERENT: GeneralPurposeConfig =
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent);
+const STANDARD: GeneralPurpose = GeneralPurpose::new(&alphabet::STANDARD, PADDING_INDIFFERENT);
+const URL_SAFE: GeneralPurpose = GeneralPurpose::new(&alphabet::URL_SAFE, PADDING_INDIFFERENT);
+
+/// Whether `s` looks like base64 and decodes as such
+///
+/// Hex digests and lowercase identifiers use only base64 characters too, so
+/// both upper and lower case letters are required.
+pub fn looks_like_base64(s: &str) -> bool {
+    s.len() >= MIN_BASE64_LEN
+        && s.bytes().any(|b| b.is_ascii_uppercase())
+        && s.bytes().any(|b| b.is_ascii_lowercase())
+        && decode_base64(s).is_some()
+}
+
+/// Decodes standard or URL-safe base64, with or without padding
+pub fn decode_base64(s: &str) -> Option<Vec<u8>> {
+    let url_safe = s.contains(['-', '_']);
+    if url_safe && s.contains(['+', '/']) {
+        return None;
+    }
+    let engine = if url_safe { &URL_SAFE } else { &STANDARD };
+    engine.decode(s).ok()
+}
+
+/// Whether decoded bytes read as text: UTF-8 without control characters
+/// other than line breaks and tabs
+pub fn is_text(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+    })
+}
+
+/// One line of a hexdump: offset, up to 16 bytes in hex and as ASCII
+pub fn hexdump_line(bytes: &[u8], offset: usize) -> String {
+    let hex: Vec<String> = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    let ascii: String = bytes
+        .iter()
+        .map(|&b| {
+            if b.is_ascii_graphic() || b == b' ' {
+                b as char
+            } else {
+                '.'
+            }
+        })
+        .collect();
+    format!(
+        "{:08x}  {:<width$}  |{}|",
+        offset,
+        hex.join(" "),
+        ascii,
+        width = HEX_WIDTH * 3 - 1
+    )
+}
+
+/// Lines of the preview from `first` on, at most `count` of them
+pub fn preview_lines(bytes: &[u8], first: usize, count: usize) -> Vec<String> {
+    if is_text(bytes) {
+        let text = String::from_utf8_lossy(bytes);
+        text.lines()
+            .skip(first)
+            .take(count)
+            .map(str::to_string)
+            .collect()
+    } else {
+        bytes
+            .chunks(HEX_WIDTH)
+            .enumerate()
+            .skip(first)
+            .take(count)
+            .map(|(i, chunk)| hexdump_line(chunk, i * HEX_WIDTH))
+            .collect()
+    }
+}
+
+/// Number of lines in the preview, for scrolling
+pub fn preview_len(bytes: &[u8]) -> usize {
+    if is_text(bytes) {
+        String::from_utf8_lossy(bytes).lines().count()
+    } else {
+        bytes.len().div_ceil(HEX_WIDTH)
+    }
+}
+
+/// Draws the decoded value in a popup over the result area
+pub fn render_value_inspector(inspector: &ValueInspectorState, area: Rect, buf: &mut Buffer) {
+    let width = area.width * POPUP_PERCENT / 100;
+    let height = area.height * POPUP_PERCENT / 100;
+    let popup = Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    };
+    let kind = if is_text(&inspector.bytes) {
+        "text"
+    } else {
+        "binary"
+    };
+    let block = Block::default()
+        .borders(Borders::ALL)
+        .border_style(Style::default().fg(Color::DarkGray))
+        .title(format!(
+            " base64 {} · {} {} ",
+            inspector.path,
+            chart::format_bytes(inspector.bytes.len()),
+            kind
+        ))
+        .title_bottom(" ↑/↓ scroll · Esc/Alt+I close ");
+    let inner = block.inner(popup);
+    Clear.render(popup, buf);
+    block.render(popup, buf);
+    let lines: Vec<Line> = preview_lines(&inspector.bytes, inspector.scroll, inner.height as usize)
+        .into_iter()
+        .map(Line::from)
+        .collect();
+    Paragraph::new(lines).render(inner, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detects_base64() {
+        // "Hello, value inspector!" and some binary bytes
+        assert!(looks_like_base64("SGVsbG8sIHZhbHVlIGluc3BlY3RvciE="));
+        assert!(looks_like_base64("SGVsbG8sIHZhbHVlIGluc3BlY3RvciE"));
+        assert!(looks_like_base64("AAECAwQFBgcICQoLDA0ODxAREhP7_w"));
+        // Too short, a hex digest, prose and mixed alphabets
+        assert!(!looks_like_base64("SGVsbG8="));
+        assert!(!looks_like_base64("9f86d081884c7d659a2feaa0c55ad015"));
+        assert!(!looks_like_base64("Hello there, this is plain text"));
+        assert!(!looks_like_base64("AAECAwQFBgcICQoLDA0ODxAREhP7_w+/"));
+    }
+
+    #[test]
+    fn test_preview() {
+        let text = decode_base64("bGluZSBvbmUKbGluZSB0d28K").unwrap();
+        assert!(is_text(&text));
+        assert_eq!(preview_lines(&text, 1, 5), ["line two"]);
+        assert_eq!(preview_len(&text), 2);
+
+        let binary: Vec<u8> = (0..20).collect();
+        assert!(!is_text(&binary));
+        assert_eq!(preview_len(&binary), 2);
+        assert_eq!(
+            preview_lines(&binary, 1, 5),
+            [format!("00000010  10 11 12 13{}  |....|", " ".repeat(36))]
+        );
+        assert_eq!(
+            hexdump_line(b"Hi!\x00", 0),
+            format!("00000000  48 69 21 00{}  |Hi!.|", " ".repeat(36))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+//! Loading screen
+//!
+//! Input is read before the TUI starts. While a big file or a slow URL
+//! loads, a spinner line on the terminal says what is being read and how
+//! far along it is, so the screen does not just stay blank.
+
+use super::app::{SPINNER_DELAY, SPINNER_FRAMES, SPINNER_INTERVAL};
+use crate::render::chart::format_bytes;
+use crate::source::progress::{LoadProgress, LoadStatus};
+use std::io::Write;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Runs `load` on a worker thread, drawing its progress on `output` once it
+/// takes longer than [`SPINNER_DELAY`]
+///
+/// The line is erased again when loading is done, so whatever is drawn next
+/// starts on a clean line.
+pub fn load_with_progress<T, F>(output: &mut dyn Write, progress: &LoadProgress, load: F) -> T
+where
+    F: FnOnce() -> T + Send,
+    T: Send,
+{
+    let started = Instant::now();
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        let worker = scope.spawn(move || {
+            let _ = sender.send(load());
+        });
+        let mut shown = false;
+        let result = loop {
+            match receiver.recv_timeout(SPINNER_INTERVAL) {
+                Ok(result) => break result,
+                Err(RecvTimeoutError::Timeout) => {
+                    let elapsed = started.elapsed();
+                    if elapsed >= SPINNER_DELAY {
+                        // Drawing is best effort; loading goes on without it
+                        let line = loading_line(&progress.status(), elapsed);
+                        let _ = write!(output, "\r\x1b[2K{}", line);
+                        let _ = output.flush();
+                        shown = true;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => match worker.join() {
+                    Err(panic) => std::panic::resume_unwind(panic),
+                    Ok(()) => unreachable!("the worker sends its result before finishing"),
+                },
+            }
+        };
+        if shown {
+            let _ = write!(output, "\r\x1b[2K");
+            let _ = output.flush();
+        }
+        result
+    })
+}
+
+/// The spinner line for `status` after loading for `elapsed`, e.g.
+/// `⠋ Reading data.json  12.0 MiB / 48.0 MiB (25%)  1.2s`
+pub fn loading_line(status: &LoadStatus, elapsed: Duration) -> String {
+    let frame = elapsed.as_millis() / SPINNER_INTERVAL.as_millis();
+    let size = match status.total {
+        Some(total) => format!(
+            "  {} / {} ({}%)",
+            format_bytes(status.read as usize),
+            format_bytes(total as usize),
+            status.read.min(total) * 100 / total
+        ),
+        None if status.read > 0 => format!("  {}", format_bytes(status.read as usize)),
+        None => String::new(),
+    };
+    format!(
+        "{} {}{}  {:.1}s",
+        SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()],
+        status.stage,
+        size,
+        elapsed.as_secs_f64()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loading_line() {
+        let status = LoadStatus {
+            stage: "Reading data.json".to_string(),
+            read: 12 * 1024 * 1024,
+            total: Some(48 * 1024 * 1024),
+        };
+        assert_eq!(
+            loading_line(&status, Duration::from_millis(1200)),
+            "⠴ Reading data.json  12.0 MiB / 48.0 MiB (25%)  1.2s"
+        );
+        let status = LoadStatus {
+            stage: "Parsing stdin".to_string(),
+            read: 0,
+            total: None,
+        };
+        assert_eq!(
+            loading_line(&status, Duration::ZERO),
+            "⠋ Parsing stdin  0.0s"
+        );
+    }
+
+    #[test]
+    fn test_quick_loads_draw_nothing() {
+        let mut output = Vec::new();
+        let value = load_with_progress(&mut output, &LoadProgress::default(), || 42);
+        assert_eq!(value, 42);
+        assert!(output.is_empty());
+
+        let progress = LoadProgress::default();
+        progress.start("Fetching https://example.com", None);
+        let value = load_with_progress(&mut output, &progress, || {
+            thread::sleep(SPINNER_DELAY + SPINNER_INTERVAL * 2);
+            "done"
+        });
+        assert_eq!(value, "done");
+        let drawn = String::from_utf8(output).unwrap();
+        assert!(drawn.contains("Fetching https://example.com"), "{}", drawn);
+        assert!(drawn.ends_with("\r\x1b[2K"));
+    }
+}
@@ -1,10 +1,15 @@
 pub mod app;
 pub mod events;
 pub mod handler;
+pub mod inspector;
+pub mod loading;
 pub mod syntax;
 pub mod terminal;
 
-pub use events::{Action, get_action, update};
+pub use events::{
+    Action, ActionContext, EditAction, MOUSE_SCROLL_LINES, SystemAction, ViewAction, get_action,
+    get_event_action, update, update_with_context,
+};
 pub use handler::{DefaultEventHandler, EventHandler};
 pub use syntax::SyntaxHighlighter;
-pub use terminal::restore_terminal;
+pub use terminal::{enter_in_place, open_tui_output, restore_in_place, restore_terminal};
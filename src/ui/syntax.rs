@@ -1,6 +1,10 @@
+use crate::query::lexer::{QueryToken, QueryTokenKind, tokenize_query};
 use ratatui::style::{Color, Style};
 use ratatui::text::{Line, Span};
 
+/// クエリ行の括弧にネストの深さごとに使う色
+const BRACKET_COLORS: [Color; 4] = [Color::Yellow, Color::Magenta, Color::Cyan, Color::Green];
+
 /// JSONのトークンタイプ
 #[derive(Debug, Clone, PartialEq)]
 pub enum TokenType {
@@ -14,6 +18,22 @@ pub enum TokenType {
     Default,     // その他
 }
 
+impl TokenType {
+    /// 端末の外で描画するとき（HTML 出力など）に使う CSS クラス
+    pub fn css_class(&self) -> &'static str {
+        match self {
+            TokenType::String => "tok-string",
+            TokenType::Number => "tok-number",
+            TokenType::Boolean => "tok-boolean",
+            TokenType::Null => "tok-null",
+            TokenType::Key => "tok-key",
+            TokenType::Bracket => "tok-bracket",
+            TokenType::Punctuation => "tok-punct",
+            TokenType::Default => "tok-default",
+        }
+    }
+}
+
 /// トークン情報
 #[derive(Debug, Clone)]
 pub struct Token {
@@ -246,6 +266,49 @@ impl SyntaxHighlighter {
     pub fn highlight_line<'a>(&self, input: &'a str) -> Line<'a> {
         Line::from(self.highlight(input))
     }
+
+    /// クエリの括弧をネストの深さごとに色分けする（レインボーブラケット）
+    ///
+    /// 対応しない閉じ括弧は赤で表示する。文字列リテラル中の括弧は文字列トークンの一部で、
+    /// 既定のスタイルで表示する
+    pub fn highlight_query<'a>(&self, query: &'a str) -> Vec<Span<'a>> {
+        self.highlight_query_tokens(query, &tokenize_query(query))
+    }
+
+    /// インクリメンタルトークナイザのトークンを使う [`highlight_query`](Self::highlight_query)
+    pub fn highlight_query_tokens<'a>(
+        &self,
+        query: &'a str,
+        tokens: &[QueryToken],
+    ) -> Vec<Span<'a>> {
+        let mut spans = Vec::new();
+        let mut depth = 0usize;
+        let mut last_end = 0;
+
+        for token in tokens {
+            let style = match token.kind {
+                QueryTokenKind::Open => {
+                    depth += 1;
+                    Style::default().fg(BRACKET_COLORS[(depth - 1) % BRACKET_COLORS.len()])
+                }
+                QueryTokenKind::Close if depth == 0 => Style::default().fg(Color::Red),
+                QueryTokenKind::Close => {
+                    depth -= 1;
+                    Style::default().fg(BRACKET_COLORS[depth % BRACKET_COLORS.len()])
+                }
+                _ => continue,
+            };
+            if token.start > last_end {
+                spans.push(Span::raw(&query[last_end..token.start]));
+            }
+            spans.push(Span::styled(token.text(query), style));
+            last_end = token.end;
+        }
+        if last_end < query.len() {
+            spans.push(Span::raw(&query[last_end..]));
+        }
+        spans
+    }
 }
 
 #[cfg(test)]
@@ -405,6 +468,31 @@ mod tests {
         assert!(!spans.is_empty());
     }
 
+    #[test]
+    fn test_highlight_query_bracket_depth() {
+        let highlighter = SyntaxHighlighter::new();
+        let spans = highlighter.highlight_query(r#"map(.a[0]) | "(" )"#);
+        let colored: Vec<(&str, Option<Color>)> = spans
+            .iter()
+            .filter(|span| span.style.fg.is_some())
+            .map(|span| (span.content.as_ref(), span.style.fg))
+            .collect();
+
+        assert_eq!(
+            colored,
+            vec![
+                ("(", Some(BRACKET_COLORS[0])),
+                ("[", Some(BRACKET_COLORS[1])),
+                ("]", Some(BRACKET_COLORS[1])),
+                (")", Some(BRACKET_COLORS[0])),
+                (")", Some(Color::Red)),
+            ]
+        );
+        // The spans still reproduce the query text
+        let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+        assert_eq!(text, r#"map(.a[0]) | "(" )"#);
+    }
+
     #[test]
     fn test_highlight_line() {
         let highlighter = SyntaxHighlighter::new();
@@ -1,18 +1,77 @@
 use crossterm::{
+    cursor::MoveTo,
     execute,
     terminal::{LeaveAlternateScreen, disable_raw_mode},
 };
 use ratatui::{Terminal, backend::Backend};
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
 
-pub fn restore_terminal<B: Backend + std::io::Write>(
+fn open_dev_tty() -> io::Result<File> {
+    OpenOptions::new().read(true).write(true).open("/dev/tty")
+}
+
+/// Where the TUI is drawn, or `None` when there is no terminal to run it on
+///
+/// Keys are read from the controlling terminal when stdin is a pipe, so
+/// `curl … | rjq` works as long as one exists. The screen goes to stderr,
+/// or straight to the terminal when stderr is redirected.
+pub fn open_tui_output() -> Option<Box<dyn Write>> {
+    let stdin_is_tty = atty::is(atty::Stream::Stdin);
+    if !stdin_is_tty && open_dev_tty().is_err() {
+        return None;
+    }
+    if atty::is(atty::Stream::Stderr) {
+        return Some(Box::new(io::stderr()));
+    }
+    open_dev_tty()
+        .ok()
+        .map(|tty| Box::new(tty) as Box<dyn Write>)
+}
+
+pub fn restore_terminal<B: Backend + Write>(
     terminal: &mut Terminal<B>,
-) -> std::result::Result<(), std::io::Error> {
+) -> std::result::Result<(), io::Error> {
     execute!(
         terminal.backend_mut(),
         LeaveAlternateScreen,
-        crossterm::event::DisableMouseCapture
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::event::DisableFocusChange
     )?;
     disable_raw_mode()?;
     terminal.show_cursor()?;
     Ok(())
 }
+
+/// Clears the normal screen buffer for a TUI drawn without the alternate
+/// screen, scrolling what was on it into the scrollback first
+pub fn enter_in_place<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+) -> std::result::Result<(), io::Error> {
+    let height = terminal.size()?.height;
+    terminal
+        .backend_mut()
+        .write_all("\n".repeat(usize::from(height)).as_bytes())?;
+    terminal.clear()
+}
+
+/// Like [`restore_terminal`] for a TUI drawn with [`enter_in_place`]: the
+/// last frame stays on screen, and so in the scrollback, with the cursor on
+/// the line below it
+pub fn restore_in_place<B: Backend + Write>(
+    terminal: &mut Terminal<B>,
+) -> std::result::Result<(), io::Error> {
+    let height = terminal.size()?.height;
+    execute!(
+        terminal.backend_mut(),
+        crossterm::event::DisableMouseCapture,
+        crossterm::event::DisableBracketedPaste,
+        crossterm::event::DisableFocusChange,
+        MoveTo(0, height.saturating_sub(1))
+    )?;
+    disable_raw_mode()?;
+    terminal.show_cursor()?;
+    terminal.backend_mut().write_all(b"\n")?;
+    Write::flush(terminal.backend_mut())
+}
@@ -0,0 +1,54 @@
+//! Inputs found by `fuzz/fuzz_targets/query_execution.rs` that once crashed
+
+use rjq::query::executor::MAX_QUERY_LEN;
+use rjq::{AppError, JsonData};
+use serde_json::json;
+
+fn run(query: &str) -> rjq::Result<String> {
+    let data = JsonData::new(json!({"a": [1, {"b": "c"}]}));
+    data.execute_query(query)
+        .map(|result| result.format_pretty())
+}
+
+#[test]
+fn test_deep_nesting_does_not_overflow() {
+    let depth = 1000;
+    for (open, close) in [("(", ")"), ("[", "]")] {
+        let query = format!("{}.{}", open.repeat(depth), close.repeat(depth));
+        assert!(run(&query).is_ok(), "{open}{close} nested {depth} deep");
+    }
+    let negations = format!("{}1", "-".repeat(depth));
+    assert!(run(&negations).is_ok());
+
+    let ifs = "if true then ".repeat(500) + "." + &" else . end".repeat(500);
+    assert!(run(&ifs).is_ok());
+
+    let interpolations = "\"\\(".repeat(1000) + "." + &")\"".repeat(1000);
+    assert!(run(&interpolations).is_ok());
+}
+
+#[test]
+fn test_long_chains_do_not_overflow() {
+    for separator in [" | ", " + ", ", "] {
+        let query = vec!["1"; 2000].join(separator);
+        assert!(run(&query).is_ok(), "chain joined by {separator:?}");
+    }
+}
+
+#[test]
+fn test_overlong_query_is_rejected() {
+    let query = ".".repeat(MAX_QUERY_LEN + 1);
+    match run(&query) {
+        Err(AppError::QueryCompile { message, .. }) => assert!(message.contains("too long")),
+        other => panic!("expected a compile error, got {other:?}"),
+    }
+}
+
+#[test]
+fn test_truncated_queries_are_errors() {
+    for query in [
+        ".[", "\"\\(", "\"abc", "{a:", ".a as", "reduce", "@", "$", "...",
+    ] {
+        assert!(run(query).is_err(), "{query:?} should not compile");
+    }
+}
@@ -124,3 +124,245 @@ fn test_query_history_ranking() {
     let suggested = suggestion.unwrap();
     assert!(suggested == ".test" || suggested == ".testing");
 }
+
+#[test]
+fn test_suggestions_prefer_keys_in_document() {
+    let mut app = App::new(json!({"users": [{"name": "Alice"}]}));
+
+    // 他のファイル用のクエリをより多く実行していても、現在の文書に合う候補を優先する
+    for _ in 0..3 {
+        app.record_query(".user_ids[0]".to_string());
+    }
+    app.record_query(".users[0].name".to_string());
+
+    app.set_input(".us");
+    assert_eq!(app.get_best_suggestion().as_deref(), Some(".users[0].name"));
+}
+
+#[test]
+fn test_replacing_data_refreshes_derived_state() {
+    let mut app = App::new(json!({"users": [{"name": "Alice"}]}));
+    for _ in 0..3 {
+        app.record_query(".users[0].name".to_string());
+    }
+    app.record_query(".user_ids[0]".to_string());
+
+    app.replace_data(json!({"user_ids": [7]}));
+    assert_eq!(app.data().generation(), 1);
+    app.set_input(".us");
+    assert_eq!(app.get_best_suggestion().as_deref(), Some(".user_ids[0]"));
+
+    app.merge_data(json!({"ok": true}));
+    assert_eq!(app.data().generation(), 2);
+    app.set_input("");
+    assert_eq!(
+        app.current_values().unwrap(),
+        vec![json!({"user_ids": [7], "ok": true})]
+    );
+}
+
+#[test]
+fn test_tee_log_records_executed_queries() {
+    use rjq::session::{SessionEntry, TeeLog};
+    use rjq::{EditAction, update};
+
+    let path = std::env::temp_dir().join(format!("rjq_integration_tee_{}.log", std::process::id()));
+    std::fs::remove_file(&path).ok();
+
+    let mut app = App::new(json!({"name": "Alice"}));
+    app.set_tee_log(TeeLog::open(&path, None).unwrap());
+    for c in ".name".chars() {
+        update(&mut app, EditAction::Input(c));
+    }
+    let effects = update(&mut app, EditAction::Submit);
+    app.run_effects(effects);
+
+    let content = std::fs::read_to_string(&path).unwrap();
+    std::fs::remove_file(&path).ok();
+
+    let entry = SessionEntry::from_json_line(content.lines().next().unwrap()).unwrap();
+    assert_eq!(entry.query, ".name");
+    assert_eq!(entry.result, "\"Alice\"");
+}
+
+#[test]
+fn test_command_export_uses_source_path() {
+    let mut app = App::new(json!({"users": []}));
+    app.set_source_path(Some("data.json".to_string()));
+    for c in ".users | length".chars() {
+        app.push_char(c);
+    }
+
+    let export = app.command_export();
+    assert_eq!(export.jq_command(), "jq '.users | length' data.json");
+}
+
+#[test]
+fn test_bookmarks_and_prelude() {
+    use rjq::{SystemAction, update};
+
+    let mut app = App::new(json!({"users": [{"name": "Alice", "active": true}]}));
+    app.set_prelude(Some("def active: .users[] | select(.active);".to_string()));
+    app.set_bookmarks(vec![("names".to_string(), "active | .name".to_string())]);
+
+    update(&mut app, SystemAction::NextBookmark);
+    assert_eq!(app.input(), "active | .name");
+    assert_eq!(app.current_values().unwrap(), vec![json!("Alice")]);
+}
+
+#[test]
+fn test_document_folding_and_goto_path() {
+    use rjq::{ViewAction, update};
+
+    let mut app = App::new(json!({"a": {"x": 1, "y": 2}, "b": [true, false]}));
+    assert_eq!(app.document().unwrap().line_count(), 10);
+
+    // Ctrl+O folds the container on the top visible line
+    update(&mut app, ViewAction::ToggleFold);
+    assert_eq!(app.document().unwrap().to_text(), "{…}");
+
+    assert!(app.goto_path(".b[1]"));
+    assert_eq!(app.scroll_offset(), 7);
+    assert!(!app.goto_path(".missing"));
+
+    assert_eq!(app.highlight_matches("y"), 1);
+    let tree = app.document().unwrap();
+    assert_eq!(
+        tree.lines().iter().filter(|line| line.highlighted).count(),
+        1
+    );
+    drop(tree);
+
+    // A new query starts from a fresh tree
+    app.set_input(".a");
+    assert_eq!(
+        app.document().unwrap().to_text(),
+        "{\n  \"x\": 1,\n  \"y\": 2\n}"
+    );
+    app.set_input(".[");
+    assert!(app.document().is_none());
+}
+
+#[test]
+fn test_resize_keeps_scroll_within_the_result() {
+    use rjq::{ViewAction, update};
+
+    let mut app = App::new(json!((0..30).collect::<Vec<_>>()));
+    app.resize(12);
+    for _ in 0..40 {
+        update(&mut app, ViewAction::ScrollDown);
+    }
+    // 32 lines, 11 of them in view below the query line
+    assert_eq!(app.scroll_offset(), 21);
+
+    app.resize(30);
+    assert_eq!(app.scroll_offset(), 3);
+    app.resize(1);
+    update(&mut app, ViewAction::ScrollDown);
+    assert_eq!(app.scroll_offset(), 4);
+}
+
+#[test]
+fn test_compact_layout_keeps_short_containers_on_one_line() {
+    use rjq::{ViewAction, update};
+
+    let mut app = App::new(json!({"a": {"x": 1, "y": 2}, "b": [true, false]}));
+    update(&mut app, ViewAction::ToggleCompactLayout);
+    assert!(app.compact_layout());
+    assert_eq!(
+        app.document().unwrap().to_text(),
+        "{\"a\": {\"x\": 1, \"y\": 2}, \"b\": [true, false]}"
+    );
+
+    // The layout carries over to the next query's tree
+    app.set_input(".b");
+    assert_eq!(app.document().unwrap().to_text(), "[true, false]");
+    update(&mut app, ViewAction::ToggleCompactLayout);
+    assert_eq!(app.document().unwrap().line_count(), 4);
+}
+
+#[test]
+fn test_display_filters_apply_after_the_query() {
+    use rjq::app::ContentGenerator;
+    use rjq::{ViewAction, update};
+
+    let mut app =
+        App::new(json!({"items": [{"id": 1, "metadata": {}}, {"id": 2, "metadata": {}}]}));
+    app.set_display_filters(vec![
+        ("no_meta".to_string(), "del(.metadata)".to_string()),
+        ("broken".to_string(), "del(".to_string()),
+    ]);
+    app.set_input(".items[]");
+
+    update(&mut app, ViewAction::CycleDisplayFilter);
+    assert_eq!(
+        app.active_display_filter(),
+        Some(("no_meta", "del(.metadata)"))
+    );
+    assert_eq!(
+        app.generate_current_content(),
+        "[\n  {\n    \"id\": 1\n  },\n  {\n    \"id\": 2\n  }\n]"
+    );
+    // Only the view changes, not what the query produces
+    assert_eq!(app.current_values().unwrap().len(), 2);
+    assert!(app.current_values().unwrap()[0].get("metadata").is_some());
+
+    // A failing display filter falls back to the raw result
+    update(&mut app, ViewAction::CycleDisplayFilter);
+    assert!(app.display_filter_error().is_some());
+    assert!(app.generate_current_content().contains("metadata"));
+
+    update(&mut app, ViewAction::CycleDisplayFilter);
+    assert_eq!(app.active_display_filter(), None);
+
+    app.activate_display_filter(".id");
+    app.set_input("");
+    assert_eq!(app.generate_current_content(), "null");
+}
+
+#[test]
+fn test_ndjson_inputs_behave_like_jq() {
+    use rjq::JsonData;
+    use rjq::input::{self, CsvOptions, InputFormat};
+
+    let text = "{\"level\": \"info\"}\n{\"level\": \"error\"}\n";
+    let data = input::load(text, InputFormat::Ndjson, &CsvOptions::default()).unwrap();
+    let mut app = App::from_data(data);
+
+    app.set_input(".level");
+    assert_eq!(
+        app.current_values().unwrap(),
+        vec![json!("info"), json!("error")]
+    );
+    app.set_input("[., inputs] | map(.level)");
+    assert_eq!(
+        app.current_values().unwrap(),
+        vec![json!(["info", "error"])]
+    );
+
+    // A plain JSON array is one input
+    let app = App::from_data(JsonData::new(json!([1, 2])));
+    assert_eq!(app.current_values().unwrap(), vec![json!([1, 2])]);
+}
+
+#[test]
+fn test_slurp_collects_every_document() {
+    use rjq::JsonData;
+    use rjq::input::InputFormat;
+    use rjq::source::LoadOptions;
+
+    let options = LoadOptions {
+        slurp: true,
+        ..Default::default()
+    };
+    let first = options
+        .parse("{\"n\": 1}\n{\"n\": 2}", InputFormat::Json)
+        .unwrap();
+    let second = options.parse("{\"n\": 3}\n", InputFormat::Ndjson).unwrap();
+    let mut app = App::from_data(JsonData::slurp([first, second]));
+
+    app.set_input("map(.n) | add");
+    assert_eq!(app.current_values().unwrap(), vec![json!(6)]);
+    app.set_input("length");
+    assert_eq!(app.current_values().unwrap(), vec![json!(3)]);
+}
@@ -0,0 +1,110 @@
+use proptest::prelude::*;
+use rjq::query::format::format_query;
+use rjq::query::lexer::tokenize_query;
+use rjq::ui::SyntaxHighlighter;
+use rjq::{QueryResult, RenderTree};
+use serde_json::Value;
+
+/// 任意のJSON値（浮動小数点数を含む）
+fn arb_json() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>()
+            .prop_filter("JSON has no NaN or infinity", |f| f.is_finite())
+            .prop_map(Value::from),
+        any::<String>().prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 32, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::btree_map(any::<String>(), inner, 0..6)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+/// jqらしい記号を多めに含むクエリ
+fn arb_query() -> impl Strategy<Value = String> {
+    prop::collection::vec(
+        prop_oneof![
+            Just(".".to_string()),
+            Just("|".to_string()),
+            Just(" ".to_string()),
+            Just("\"".to_string()),
+            Just("\\(".to_string()),
+            prop::sample::select(vec!["(", ")", "[", "]", "{", "}", ",", ":", ";", "?"])
+                .prop_map(str::to_string),
+            prop::sample::select(vec!["==", "//", "-", "+", "$x", "@csv", "if", "end", "a1"])
+                .prop_map(str::to_string),
+            any::<char>().prop_map(String::from),
+        ],
+        0..24,
+    )
+    .prop_map(|parts| parts.concat())
+}
+
+proptest! {
+    #[test]
+    fn json_tokens_round_trip(input in any::<String>()) {
+        let tokens = SyntaxHighlighter::new().tokenize(&input);
+        let mut rebuilt = String::new();
+        let mut last_end = 0;
+        for token in &tokens {
+            let gap = &input[last_end..token.start];
+            prop_assert!(gap.chars().all(|c| matches!(c, ' ' | '\t' | '\n' | '\r')));
+            prop_assert_eq!(&input[token.start..token.end], token.text.as_str());
+            rebuilt.push_str(gap);
+            rebuilt.push_str(&token.text);
+            last_end = token.end;
+        }
+        rebuilt.push_str(&input[last_end..]);
+        prop_assert!(input[last_end..].trim().is_empty());
+        prop_assert_eq!(rebuilt, input);
+    }
+
+    #[test]
+    fn query_tokens_round_trip(query in arb_query()) {
+        let mut last_end = 0;
+        for token in tokenize_query(&query) {
+            prop_assert!(token.start >= last_end && token.end > token.start);
+            prop_assert!(query[last_end..token.start].trim().is_empty());
+            last_end = token.end;
+        }
+        prop_assert!(query[last_end..].trim().is_empty());
+    }
+
+    #[test]
+    fn highlighted_query_preserves_text(query in any::<String>()) {
+        let spans = SyntaxHighlighter::new().highlight_query(&query);
+        let text: String = spans.iter().map(|span| span.content.as_ref()).collect();
+        prop_assert_eq!(text, query);
+    }
+
+    #[test]
+    fn format_query_is_idempotent(query in arb_query()) {
+        let once = format_query(&query);
+        prop_assert_eq!(format_query(&once), once);
+    }
+
+    #[test]
+    fn formatted_json_reparses(values in prop::collection::vec(arb_json(), 0..4)) {
+        let result = match values.len() {
+            0 => QueryResult::Empty,
+            1 => QueryResult::Single(values[0].clone()),
+            _ => QueryResult::Multiple(values.clone()),
+        };
+        let parsed: Result<Value, _> = serde_json::from_str(&result.format_pretty());
+        prop_assert!(parsed.is_ok());
+    }
+
+    #[test]
+    fn render_tree_matches_pretty_printing(value in arb_json()) {
+        let tree = RenderTree::from_value(&value);
+        prop_assert_eq!(tree.to_text(), serde_json::to_string_pretty(&value).unwrap());
+        for id in 0..tree.nodes().len() {
+            prop_assert_eq!(tree.find_path(&tree.path_string(id)), Some(id));
+        }
+    }
+}
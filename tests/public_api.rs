@@ -0,0 +1,38 @@
+//! Compatibility tests of the public API
+//!
+//! Uses the crate only the way a downstream user can, so accidental breaking
+//! changes to the prelude show up as compile errors here.
+
+use rjq::prelude::*;
+use serde_json::json;
+
+#[test]
+fn test_prelude_builds_and_queries() {
+    let mut config = AppConfig::default();
+    config.prompt = "jq> ";
+    let app = AppBuilder::new(json!({"items": [1, 2]}))
+        .with_config(config)
+        .with_cache()
+        .build();
+    assert_eq!(app.prompt(), "jq> ");
+
+    let result: Result<QueryResult> =
+        JsonData::new(json!({"items": [1, 2]})).execute_query(".items[]");
+    match result.unwrap() {
+        QueryResult::Multiple(values) => assert_eq!(values, vec![json!(1), json!(2)]),
+        // non_exhaustive: downstream matches need a wildcard arm
+        other => panic!("unexpected result {other:?}"),
+    }
+}
+
+#[test]
+fn test_errors_and_actions() {
+    let error = JaqQueryExecutor.execute(&json!(null), ".[").unwrap_err();
+    assert!(matches!(error, AppError::QueryCompile { .. }));
+
+    let esc = crossterm::event::KeyEvent::from(crossterm::event::KeyCode::Esc);
+    assert_eq!(
+        DefaultEventHandler.handle_key_event(esc),
+        Action::System(SystemAction::Quit)
+    );
+}
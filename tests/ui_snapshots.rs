@@ -0,0 +1,192 @@
+//! Snapshot tests of the main screens
+//!
+//! Review changes with `cargo insta review`, or rerun with `INSTA_UPDATE=always`
+//! to accept them.
+
+use ratatui::{Terminal, backend::TestBackend};
+use rjq::{App, JsonData};
+use serde_json::json;
+
+const WIDTH: u16 = 48;
+const HEIGHT: u16 = 12;
+
+fn sample_app() -> App {
+    App::new(json!({
+        "users": [
+            {"name": "alice", "status": "active"},
+            {"name": "bob", "status": "banned"}
+        ]
+    }))
+}
+
+fn render(app: &mut App) -> TestBackend {
+    app.refresh_query_tokens();
+    let mut terminal = Terminal::new(TestBackend::new(WIDTH, HEIGHT)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(&*app, frame.area()))
+        .unwrap();
+    terminal.backend().clone()
+}
+
+#[test]
+fn snapshot_initial_view() {
+    let mut app = sample_app();
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_window_too_small() {
+    let mut app = sample_app();
+    app.refresh_query_tokens();
+    let mut terminal = Terminal::new(TestBackend::new(24, 4)).unwrap();
+    terminal
+        .draw(|frame| frame.render_widget(&app, frame.area()))
+        .unwrap();
+    insta::assert_snapshot!(terminal.backend().clone());
+}
+
+#[test]
+fn snapshot_query_result() {
+    let mut app = sample_app();
+    app.set_input(".users[0]");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_error_view() {
+    let mut app = sample_app();
+    app.set_input(".users | ]");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_runtime_error_after_outputs() {
+    let mut app = sample_app();
+    app.set_input(".users[].name, error(\"boom\")");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_suggestion_ghost_text() {
+    let mut app = sample_app();
+    app.record_query(".users[].name".to_string());
+    app.set_input(".us");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_pinned_split() {
+    let mut app = sample_app();
+    app.set_input(".users[0].name");
+    app.toggle_pin();
+    app.set_input(".users[1].name");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_string_picker() {
+    let mut app = sample_app();
+    app.set_input(".users[] | select(.status == \"");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_tab_bar() {
+    let mut app = sample_app();
+    app.set_input(".users");
+    app.new_tab();
+    app.set_input(".users | length");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_file_tabs() {
+    let mut app = sample_app();
+    app.set_source_path(Some("users.json".to_string()));
+    app.open_document(Some("empty.json".to_string()), JsonData::new(json!({})));
+    app.set_input(".users | length");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_workspace_summary() {
+    use rjq::query::{Workspace, WorkspaceFile};
+    let files = [
+        ("logs/api.json", json!({"level": "error", "code": 502})),
+        ("logs/db.json", json!({"level": "info"})),
+        ("logs/web.json", json!([1])),
+    ]
+    .into_iter()
+    .map(|(path, value)| WorkspaceFile {
+        path: path.to_string(),
+        data: JsonData::new(value),
+    })
+    .collect();
+    let mut app = App::new(json!(null));
+    app.set_workspace(Workspace::new(files));
+    app.set_input("select(.level == \"error\") | .code");
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_doc_popup() {
+    let mut app = sample_app();
+    app.set_input(".users | group_by(.status)");
+    for _ in 0.."(.status)".len() {
+        app.move_cursor_left();
+    }
+    app.toggle_doc();
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_manual() {
+    let mut app = sample_app();
+    app.toggle_manual();
+    for c in "sort".chars() {
+        app.manual_search_push(c);
+    }
+    app.move_manual(true);
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_table_view() {
+    let mut app = sample_app();
+    app.set_input(".users");
+    app.toggle_table();
+    insta::assert_snapshot!(render(&mut app));
+
+    app.toggle_column_picker();
+    app.toggle_column();
+    insta::assert_snapshot!("table_view_column_picker", render(&mut app));
+}
+
+#[test]
+fn snapshot_histogram() {
+    let mut app = App::new(json!({"latency": [12, 15, 15, 18, 20, 21, 40, 95]}));
+    app.set_input(".latency");
+    app.cycle_chart();
+    insta::assert_snapshot!(render(&mut app));
+
+    app.cycle_chart();
+    insta::assert_snapshot!("sparkline", render(&mut app));
+}
+
+#[test]
+fn snapshot_bar_chart() {
+    let mut app = App::new(json!({"errors": {"timeout": 12, "refused": 30, "reset": 4}}));
+    app.set_input(".errors");
+    app.cycle_chart();
+    insta::assert_snapshot!(render(&mut app));
+}
+
+#[test]
+fn snapshot_size_view() {
+    let mut app = App::new(json!({
+        "items": [{"id": 1, "body": "a fairly long text value"}, {"id": 2}],
+        "meta": {"page": 1}
+    }));
+    app.toggle_size_view();
+    insta::assert_snapshot!(render(&mut app));
+}